@@ -0,0 +1,58 @@
+// Run-time:
+//   env-var: YKD_LOG_IR=hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     i=4, sum=22
+//     yk-tracing: stop-tracing
+//     --- Begin hir ---
+//     ...
+//     %{{_}}: ptr = alloca 16, {{_}}
+//     ...
+//     --- End hir ---
+//     i=3, sum=18
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     i=2, sum=14
+//     i=1, sum=10
+//     yk-execution: deoptimise {"trid": "0", "gidx": "{{_}}"}
+//     exit
+
+// Check that stack allocations made inside traced code work.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+__attribute__((noinline)) void fill(int *a, int n) {
+  for (int j = 0; j < 4; j++)
+    a[j] = j + n;
+}
+
+__attribute__((noinline)) int sum(int n) {
+  int a[4];
+  fill(a, n);
+  return a[0] + a[1] + a[2] + a[3];
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    fprintf(stderr, "i=%d, sum=%d\n", i, sum(i));
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
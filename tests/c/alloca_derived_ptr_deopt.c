@@ -0,0 +1,61 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O1
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     i=4, f=10
+//     yk-tracing: stop-tracing
+//     i=3, f=8
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     i=2, f=6
+//     yk-execution: deoptimise ...
+//     n=1
+//     i=1, f=4
+//     exit
+
+// Check that a pointer derived from a stack allocation made inside traced code,
+// and which is live across a failing guard, is relocated to point into the
+// reconstructed stack allocation on deopt.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+__attribute__((noinline)) void fill(int *a, int n) {
+  for (int j = 0; j < 4; j++)
+    a[j] = j + n;
+}
+
+__attribute__((noinline)) int get(int *p) { return *p; }
+
+__attribute__((noinline)) int f(int n) {
+  int a[4];
+  fill(a, n);
+  int *p = &a[2];
+  if (n == 1)
+    fprintf(stderr, "n=1\n");
+  return get(p) + a[0];
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    fprintf(stderr, "i=%d, f=%d\n", i, f(i));
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
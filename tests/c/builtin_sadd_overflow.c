@@ -0,0 +1,61 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O0
+// Run-time:
+//   env-var: YKD_LOG_IR=aot,hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     i=4 1 -2147483647
+//     yk-tracing: stop-tracing
+//     --- Begin aot ---
+//     ...
+//     %{{s}}: {0: i32, 32: i1} = call llvm.sadd.with.overflow.i32(%{{_}}, %{{_}})...
+//     ...
+//     --- End aot ---
+//     --- Begin hir ---
+//     ...
+//     %{{_}}: i32 = add %{{_}}, 2147483645i32
+//     ...
+//     %{{_}}: i1 = overflow sadd %{{_}}, 2147483645i32
+//     ...
+//     --- End hir ---
+//     i=3 1 -2147483648
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     i=2 0 2147483647
+//     i=1 0 2147483646
+//     i=0 0 2147483645
+//     yk-execution: deoptimise ...
+//     exit
+
+// Check that `{iN, i1}` aggregates returned by overflow intrinsics are
+// scalarised.
+
+#include <assert.h>
+#include <limits.h>
+#include <stdbool.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i >= 0) {
+    yk_mt_control_point(mt, &loc);
+    int res;
+    bool overflow = __builtin_sadd_overflow(INT_MAX - 2, i, &res);
+    fprintf(stderr, "i=%d %d %d\n", i, overflow, res);
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
@@ -1,4 +1,3 @@
-// ignore-if: true # not yet implemented in j2
 // Compiler:
 //   env-var: YKB_EXTRA_CC_FLAGS=-O0
 // Run-time:
@@ -11,7 +10,7 @@
 //     yk-tracing: stop-tracing
 //     --- Begin hir ---
 //     ...
-//     %{{19}}: {0: i32, 32: i1} = call @llvm.umul.with.overflow.i32...
+//     %{{_}}: i1 = overflow umul ...
 //     ...
 //     --- End hir ---
 //     1 0
@@ -21,8 +20,8 @@
 //     yk-execution: deoptimise ...
 //     exit
 
-// Test that we can deal with GEPOperands as in
-// `load ptr getelementptr inbounds ([34 x i16], ptr @a, i64 0, i64 1)`
+// Check that `llvm.umul.with.overflow` intrinsics become `overflow`
+// instructions.
 
 #include <assert.h>
 #include <stdio.h>
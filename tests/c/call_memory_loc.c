@@ -0,0 +1,88 @@
+// ## Needs ykllvm to serialise the argmem/inaccessiblemem function flags.
+// ignore-if: true
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O1
+// Run-time:
+//   env-var: YKD_LOG_IR=aot,hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: 8
+//     yk-tracing: stop-tracing
+//     ...
+//     --- Begin aot ---
+//     ...
+//     #[yk_outline, memory(inaccessiblemem: readwrite)]
+//     func churn(...
+//     ...
+//     #[yk_outline, memory(argmem: write)]
+//     func set(...
+//     ...
+//     --- End aot ---
+//     --- Begin hir ---
+//     ...
+//     %{{a}}: i32 = load %{{_}}
+//     ...
+//     call %{{_}}(%{{_}}, %{{a}}) ; @set
+//     ...
+//     %{{_}}: ptr = call %{{_}}(%{{_}}) ; @churn
+//     ...
+//     %{{_}}: i32 = add %{{a}}, %{{a}}
+//     ...
+//     --- End hir ---
+//     3: 6
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: 4
+//     1: 2
+//     yk-execution: deoptimise ...
+//     exit
+
+// Check that calls to functions that can only write to memory reachable from
+// their arguments (`set`) or to memory inaccessible to the caller (`churn`) do
+// not force the second load of `g` in `step` to be reloaded.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int g;
+
+__attribute__((yk_outline, noinline)) void set(int *p, int v) { *p = v; }
+
+__attribute__((yk_outline, noinline)) void *churn(size_t n) {
+  return malloc(n);
+}
+
+// `optnone` stops LLVM from forwarding the first load of `g` to the second
+// itself.
+__attribute__((noinline, optnone)) int step(void) {
+  int x;
+  int a = g;
+  set(&x, a);
+  churn(8);
+  int b = g;
+  return a + b;
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    g = i;
+    fprintf(stderr, "%d: %d\n", i, step());
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
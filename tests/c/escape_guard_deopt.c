@@ -0,0 +1,63 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O1
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: 40
+//     yk-tracing: stop-tracing
+//     3: 30
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: 20
+//     yk-execution: deoptimise ...
+//     deopt: 10
+//     ...
+//     exit
+
+// Check that an object allocated with `malloc`, which is referenced only by a
+// guard, is correctly materialised when that guard fails.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+__attribute__((noinline)) int *box(int v) {
+  int *p = malloc(sizeof(int));
+  *p = v;
+  return p;
+}
+
+__attribute__((noinline)) void show(int i, int *p) {
+  fprintf(stderr, "%d: %d\n", i, *p);
+}
+
+__attribute__((noinline)) void show_deopt(int *p) {
+  fprintf(stderr, "deopt: %d\n", *p);
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    // The boxes are deliberately leaked: `free` would cause them to escape.
+    int *b = box(i * 10);
+    if (i == 1)
+      show_deopt(b);
+    else
+      show(i, b);
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
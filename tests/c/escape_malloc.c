@@ -0,0 +1,70 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O1
+// Run-time:
+//   env-var: YKD_LOG_IR=aot,hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: 6
+//     yk-tracing: stop-tracing
+//     ...
+//     --- Begin aot ---
+//     ...
+//     #[allocator]
+//     func malloc(...
+//     ...
+//     --- End aot ---
+//     --- Begin hir ---
+//     ...
+//     %{{_}}: ptr = call %{{_}}(%{{_}}) ; @malloc
+//     ...
+//     %{{r}}: i32 = 6
+//     ...
+//     %{{_}}: i32 = call %{{_}}(%{{_}}, %{{_}}, %{{_}}, %{{r}}) ; @fprintf
+//     ...
+//     --- End hir ---
+//     3: 6
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: 6
+//     1: 6
+//     yk-execution: deoptimise ...
+//     exit
+
+// Check that an object allocated with `malloc` that does not escape the trace
+// is virtualised: the load in `unbox` is replaced by the value stored in `box`,
+// allowing `unbox(box(3)) * 2` to be constant folded.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+__attribute__((noinline)) int *box(int v) {
+  int *p = malloc(sizeof(int));
+  *p = v;
+  return p;
+}
+
+__attribute__((noinline)) int unbox(int *p) { return *p; }
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    // The boxes are deliberately leaked: `free` would cause them to escape.
+    fprintf(stderr, "%d: %d\n", i, unbox(box(3)) * 2);
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
@@ -0,0 +1,61 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-ffp-contract=on
+// Run-time:
+//   env-var: YKD_LOG_IR=aot,hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: -13.794400
+//     yk-tracing: stop-tracing
+//     --- Begin aot ---
+//     ...
+//     %{{_}}: double = call llvm.fmuladd.f64(%{{_}}, %{{_}}, %{{_}})...
+//     ...
+//     --- End aot ---
+//     --- Begin hir ---
+//     ...
+//     %{{a}}: double = fmul %{{_}}, %{{_}}
+//     %{{_}}: double = fadd %{{a}}, %{{_}}
+//     ...
+//     --- End hir ---
+//     3: -6.794400
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: -1.794400
+//     1: 1.205600
+//     yk-execution: deoptimise {"trid": "0", "gidx": "0"}
+
+// Check that `llvm.fmuladd` (which clang emits for `x * y + z` when
+// floating point contraction is enabled) is lowered to a multiply and an add,
+// rather than a call to libm's `fma`.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  double d = 0.84;
+  double e = 1.5;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(d);
+  NOOPT_VAL(e);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    double x = d + i;
+    double y = d - i;
+    fprintf(stderr, "%d: %f\n", i, x * y + e);
+    i--;
+  }
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
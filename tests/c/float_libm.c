@@ -0,0 +1,84 @@
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-fno-math-errno
+//   env-var: YKB_EXTRA_LD_FLAGS=-lm
+// Run-time:
+//   env-var: YKD_LOG_IR=hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: sqrt 2.200000 fabs 3.160000 ceil -3.000000 trunc -3.000000 copysign -4.840000
+//     4: fmod 0.340000 round -3.000000 fma -13.794400 fmin -3.160000 fmax 4.840000 pow 10.648000
+//     yk-tracing: stop-tracing
+//     --- Begin hir ---
+//     ...
+//     %{{_}}: double = sqrt %{{_}}
+//     ...
+//     %{{_}}: double = fabs %{{_}}
+//     ...
+//     %{{_}}: double = ceil %{{_}}
+//     ...
+//     %{{_}}: double = ftrunc %{{_}}
+//     ...
+//     %{{_}}: double = copysign %{{_}}, %{{_}}
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}, %{{_}}) ; @fmod
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}) ; @round
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}, %{{_}}, %{{_}}) ; @fma
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}, %{{_}}) ; @fmin
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}, %{{_}}) ; @fmax
+//     ...
+//     %{{_}}: double = call %{{_}}(%{{_}}, %{{_}}) ; @pow
+//     ...
+//     --- End hir ---
+//     3: sqrt 1.959592 fabs 2.160000 ceil -2.000000 trunc -2.000000 copysign -3.840000
+//     3: fmod 0.840000 round -2.000000 fma -6.794400 fmin -2.160000 fmax 3.840000 pow 7.524832
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: sqrt 1.685230 fabs 1.160000 ceil -1.000000 trunc -1.000000 copysign -2.840000
+//     2: fmod 1.340000 round -1.000000 fma -1.794400 fmin -1.160000 fmax 2.840000 pow 4.786053
+//     1: sqrt 1.356466 fabs 0.160000 ceil -0.000000 trunc -0.000000 copysign -1.840000
+//     1: fmod 0.340000 round -0.000000 fma 1.205600 fmin -0.160000 fmax 1.840000 pow 2.495897
+//     yk-execution: deoptimise {"trid": "0", "gidx": "0"}
+
+// Check that floating point remainder and libm functions work. `-fno-math-errno` means that clang
+// turns these into LLVM intrinsics (or `frem`) rather than plain calls.
+
+#include <assert.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  double d = 0.84;
+  double e = 1.5;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(d);
+  NOOPT_VAL(e);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    double x = d + i;
+    double y = d - i;
+    fprintf(stderr, "%d: sqrt %f fabs %f ceil %f trunc %f copysign %f\n", i,
+            sqrt(x), fabs(y), ceil(y), trunc(y), copysign(x, y));
+    fprintf(stderr, "%d: fmod %f round %f fma %f fmin %f fmax %f pow %f\n", i,
+            fmod(x, e), round(y), fma(x, y, e), fmin(x, y), fmax(x, y),
+            pow(x, e));
+    i--;
+  }
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
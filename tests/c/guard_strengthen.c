@@ -0,0 +1,51 @@
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   stdout:
+//     0: both 5
+//     1: both 5
+//     2: both 5
+//     3: both 5
+//     4: last
+//     5: neither
+//     6: neither
+//     7: neither
+//     exit
+
+// Check that when the guard for `i < n` is strengthened to check `i + 1 < n`,
+// a failure of the strengthened guard when `i < n` holds resumes execution on
+// the `i < n` path.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 0;
+  int n = 5;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  NOOPT_VAL(n);
+  while (i < 8) {
+    yk_mt_control_point(mt, &loc);
+    if (i < n) {
+      if (i + 1 < n)
+        fprintf(stdout, "%d: both %d\n", i, n);
+      else
+        fprintf(stdout, "%d: last\n", i);
+    } else {
+      fprintf(stdout, "%d: neither\n", i);
+    }
+    i++;
+  }
+  fprintf(stdout, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
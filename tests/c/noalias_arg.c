@@ -0,0 +1,79 @@
+// ## Needs ykllvm to serialise noalias arguments.
+// ignore-if: true
+// Compiler:
+//   env-var: YKB_EXTRA_CC_FLAGS=-O1
+// Run-time:
+//   env-var: YKD_LOG_IR=aot,hir
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     4: 8
+//     yk-tracing: stop-tracing
+//     ...
+//     --- Begin aot ---
+//     ...
+//     func step(...
+//     ...
+//     %{{_}}: ptr = arg(0) noalias
+//     ...
+//     --- End aot ---
+//     --- Begin hir ---
+//     ...
+//     %{{p}}: ptr = noalias %{{_}}
+//     ...
+//     %{{a}}: i32 = load %{{p}}
+//     ...
+//     store %{{a}}, %{{_}}
+//     ...
+//     %{{_}}: i32 = add %{{a}}, %{{a}}
+//     ...
+//     noalias_end %{{p}}
+//     ...
+//     --- End hir ---
+//     3: 6
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     2: 4
+//     1: 2
+//     yk-execution: deoptimise ...
+//     exit
+
+// Check that, within a call to `step`, the store through `q` does not force the
+// second load through the `restrict` (i.e. `noalias`) pointer `p` to be reloaded.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int g, h;
+
+// `optnone` stops LLVM from forwarding the first load of `*p` to the second
+// itself.
+__attribute__((noinline, optnone)) int step(int *restrict p, int *q) {
+  int a = *p;
+  *q = a;
+  int b = *p;
+  return a + b;
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 4;
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    g = i;
+    fprintf(stderr, "%d: %d\n", i, step(&g, &h));
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
@@ -0,0 +1,21 @@
+// Run-time:
+//   env-var: YKD_OPT_PASSES=KnownBits,Foo
+//   stderr:
+//     Invalid YKD_OPT_PASSES value 'Foo': must be one of KnownBits, ...
+
+// Check that an invalid `YKD_OPT_PASSES` value is reported when the
+// meta-tracer is created, rather than when the first trace is compiled.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+
+int main(int argc, char **argv) {
+  char *err_msg = NULL;
+  YkMT *mt = yk_mt_new(&err_msg);
+  assert(mt == NULL);
+  fprintf(stderr, "%s\n", err_msg);
+  free(err_msg);
+  return (EXIT_SUCCESS);
+}
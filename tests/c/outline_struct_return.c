@@ -0,0 +1,63 @@
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=2
+//   stderr:
+//     ...
+//     yk-warning: trace-compilation-aborted: outlined call returning aggregate...
+//     ...
+//   stdout:
+//     i=6 a=21 b=42
+//     i=5 a=15 b=30
+//     i=4 a=10 b=20
+//     i=3 a=6 b=12
+//     i=2 a=3 b=6
+//     i=1 a=1 b=2
+//     exit
+
+// Check that a trace containing an outlined call returning a first-class
+// aggregate (here `{i64, i64}`) is aborted, rather than reading every field
+// from the first return register.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+struct pair {
+  long a;
+  long b;
+};
+
+struct pair foo(long i) {
+  struct pair p = {0, 0};
+  // the loop ensures this function is outlined.
+  while (i > 0) {
+    p.a += i;
+    p.b += 2 * i;
+    i--;
+  }
+  return p;
+}
+
+int main(int argc, char **argv) {
+  YkMTConfig *config = yk_mt_config_new(NULL);
+  yk_mt_config_hot_threshold_set(config, 0);
+  yk_mt_config_trace_failure_threshold_set(config, 1);
+  YkMT *mt = yk_mt_new_with_config(config, NULL);
+  YkLocation loc = yk_location_new();
+
+  int i = 6;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    struct pair p = foo(i);
+    fprintf(stdout, "i=%d a=%ld b=%ld\n", i, p.a, p.b);
+    i--;
+  }
+  fprintf(stdout, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
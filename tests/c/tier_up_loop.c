@@ -0,0 +1,51 @@
+// Run-time:
+//   env-var: YK_RECOMPILE_THRESHOLD=3
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     i=20
+//     yk-tracing: stop-tracing
+//     i=19
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     i=18
+//     i=17
+//     i=16
+//     yk-execution: deoptimise {"trid": "0", "gidx": "{{_}}"}
+//     ...
+//     yk-tracing: trace-recompiled {"trid": "1"}
+//     ...
+//     yk-execution: enter-jit-code {"trid": "1"}
+//     ...
+//     i=1
+//     exit
+
+// Check that a looping baseline trace counts its iterations towards being
+// recompiled: the thread leaves the loop once the recompile threshold is
+// reached, and then executes the recompiled trace.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+
+  int i = 20;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    fprintf(stderr, "i=%d\n", i);
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
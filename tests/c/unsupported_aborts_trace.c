@@ -0,0 +1,49 @@
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=2
+//   stderr:
+//     yk-warning: trace-compilation-aborted: 128-bit integer...
+//     yk-warning: trace-compilation-aborted: 128-bit integer...
+//   stdout:
+//     i=6
+//     i=5
+//     i=4
+//     i=3
+//     i=2
+//     i=1
+//     exit
+
+// Check that a trace containing something the JIT does not support is aborted
+// (rather than crashing the interpreter), and that once the trace failure
+// threshold is reached, the location is no longer traced.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+int main(int argc, char **argv) {
+  YkMTConfig *config = yk_mt_config_new(NULL);
+  yk_mt_config_hot_threshold_set(config, 0);
+  yk_mt_config_trace_failure_threshold_set(config, 1);
+  YkMT *mt = yk_mt_new_with_config(config, NULL);
+  YkLocation loc = yk_location_new();
+
+  __int128 x = 1;
+  int i = 6;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(x);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    fprintf(stdout, "i=%d\n", i);
+    x = x * 3 + i;
+    i--;
+  }
+  assert(x == 2734);
+  fprintf(stdout, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
@@ -0,0 +1,54 @@
+// Run-time:
+//   env-var: YKD_SERIALISE_COMPILATION=1
+//   env-var: YKD_LOG=4
+//   stderr:
+//     yk-tracing: start-tracing
+//     i=5
+//     yk-tracing: stop-tracing
+//     i=4
+//     yk-execution: enter-jit-code {"trid": "0"}
+//     i=3
+//     i=2
+//     fire
+//     yk-execution: deoptimise {"trid": "0", "gidx": "{{_}}"}
+//     ...
+//     exit
+
+// Check that a thread executing a looping trace leaves it at the loop's
+// back-edge when a watchpoint the trace depends on is fired.
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <yk.h>
+#include <yk_testing.h>
+
+__attribute__((noinline)) void f(YkMT *mt, YkWatch w, int i) {
+  if (i == 2) {
+    fprintf(stderr, "fire\n");
+    yk_watch_fire(mt, w);
+  }
+}
+
+int main(int argc, char **argv) {
+  YkMT *mt = yk_mt_new(NULL);
+  yk_mt_hot_threshold_set(mt, 0);
+  YkLocation loc = yk_location_new();
+  YkWatch w = yk_watch_new(mt);
+
+  int i = 5;
+  NOOPT_VAL(loc);
+  NOOPT_VAL(i);
+  while (i > 0) {
+    yk_mt_control_point(mt, &loc);
+    yk_watch_depend(w);
+    fprintf(stderr, "i=%d\n", i);
+    f(mt, w, i);
+    i--;
+  }
+  fprintf(stderr, "exit\n");
+  yk_location_drop(loc);
+  yk_mt_shutdown(mt);
+  return (EXIT_SUCCESS);
+}
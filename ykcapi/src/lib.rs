@@ -15,32 +15,166 @@
 #![allow(clippy::missing_safety_doc)]
 
 use setjmp::{jmp_buf, longjmp};
-#[cfg(feature = "ykd")]
-use std::ffi::CStr;
 use std::{
-    ffi::{CString, c_char, c_int},
+    error::Error,
+    ffi::{CStr, CString, c_char, c_int},
     mem::forget,
     os::raw::c_void,
+    path::PathBuf,
     ptr,
     sync::Arc,
+    time::Duration,
+};
+use ykrt::{
+    Event, HotThreshold, Location, MT, MTConfig, MTThread, StackMapIdx, StatsSnapshot, WatchId,
 };
-use ykrt::{HotThreshold, Location, MT, MTThread, StackMapIdx};
+
+/// If `err_msg` is null, panic with `e`; otherwise store a `malloc`ed copy of `e`'s message in
+/// `*err_msg`.
+unsafe fn report_err(e: Box<dyn Error>, err_msg: *mut *const c_char) {
+    if err_msg.is_null() {
+        panic!("{}", e);
+    }
+    let s = CString::new(e.to_string()).unwrap();
+    let b = s.to_bytes_with_nul();
+    let buf = unsafe { libc::malloc(b.len()) as *mut i8 };
+    unsafe {
+        buf.copy_from(b.as_ptr() as *const i8, b.len());
+    }
+    unsafe { *err_msg = buf };
+}
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn yk_mt_new(err_msg: *mut *const c_char) -> *const MT {
     match MT::new() {
         Ok(mt) => Arc::into_raw(mt),
         Err(e) => {
-            if err_msg.is_null() {
-                panic!("{}", e);
-            }
-            let s = CString::new(e.to_string()).unwrap();
-            let b = s.to_bytes_with_nul();
-            let buf = unsafe { libc::malloc(b.len()) as *mut i8 };
-            unsafe {
-                buf.copy_from(b.as_ptr() as *const i8, b.len());
-            }
-            unsafe { *err_msg = buf };
+            unsafe { report_err(e, err_msg) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create a new MT configuration whose defaults are taken from the environment.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_new(err_msg: *mut *const c_char) -> *mut MTConfig {
+    match MTConfig::from_env() {
+        Ok(config) => Box::into_raw(Box::new(config)),
+        Err(e) => {
+            unsafe { report_err(e, err_msg) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Drop an MT configuration that will not be passed to [yk_mt_new_with_config].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_drop(config: *mut MTConfig) {
+    drop(unsafe { Box::from_raw(config) });
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_hot_threshold_set(
+    config: *mut MTConfig,
+    hot_threshold: HotThreshold,
+) {
+    unsafe { &mut *config }.hot_threshold(hot_threshold);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_sidetrace_threshold_set(
+    config: *mut MTConfig,
+    sidetrace_threshold: HotThreshold,
+) {
+    unsafe { &mut *config }.sidetrace_threshold(sidetrace_threshold);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_recompile_threshold_set(
+    config: *mut MTConfig,
+    recompile_threshold: HotThreshold,
+) {
+    unsafe { &mut *config }.recompile_threshold(recompile_threshold);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_trace_failure_threshold_set(
+    config: *mut MTConfig,
+    trace_failure_threshold: u16,
+) {
+    unsafe { &mut *config }.trace_failure_threshold(trace_failure_threshold);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_opt_level_set(config: *mut MTConfig, opt_level: u8) {
+    unsafe { &mut *config }.opt_level(opt_level);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_jobs_set(config: *mut MTConfig, jobs: usize) {
+    unsafe { &mut *config }.jobs(jobs);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_jit_enabled_set(config: *mut MTConfig, jit_enabled: bool) {
+    unsafe { &mut *config }.jit_enabled(jit_enabled);
+}
+
+/// Set the log path: if `path` is null, logging goes to stderr.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_log_path_set(config: *mut MTConfig, path: *const c_char) {
+    let path = (!path.is_null()).then(|| {
+        PathBuf::from(
+            unsafe { CStr::from_ptr(path) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    });
+    unsafe { &mut *config }.log_path(path);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_log_level_set(config: *mut MTConfig, level: u8) {
+    unsafe { &mut *config }.log_level(level);
+}
+
+/// Set the stats output path: if `path` is null, statistics collection is disabled.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_stats_path_set(config: *mut MTConfig, path: *const c_char) {
+    let path = (!path.is_null()).then(|| {
+        unsafe { CStr::from_ptr(path) }
+            .to_string_lossy()
+            .into_owned()
+    });
+    unsafe { &mut *config }.stats_path(path);
+}
+
+/// Set the interval, in milliseconds, at which unreachable compiled traces are freed. 0 disables
+/// periodic collection.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_trace_collection_interval_set(
+    config: *mut MTConfig,
+    ms: u64,
+) {
+    unsafe { &mut *config }.trace_collection_interval((ms > 0).then(|| Duration::from_millis(ms)));
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_config_code_cache_limit_set(config: *mut MTConfig, limit: usize) {
+    unsafe { &mut *config }.code_cache_limit(limit);
+}
+
+/// Create a new MT instance from `config`, which is consumed by this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_new_with_config(
+    config: *mut MTConfig,
+    err_msg: *mut *const c_char,
+) -> *const MT {
+    let config = unsafe { Box::from_raw(config) };
+    match MT::with_config(*config) {
+        Ok(mt) => Arc::into_raw(mt),
+        Err(e) => {
+            unsafe { report_err(e, err_msg) };
             ptr::null_mut()
         }
     }
@@ -141,6 +275,206 @@ pub unsafe extern "C" fn yk_mt_sidetrace_threshold_set(mt: *const MT, hot_thresh
     forget(arc);
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_jit_enabled_set(mt: *const MT, jit_enabled: bool) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    arc.set_jit_enabled(jit_enabled);
+    forget(arc);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_jit_enabled_get(mt: *const MT) -> bool {
+    let arc = unsafe { Arc::from_raw(mt) };
+    let jit_enabled = arc.jit_enabled();
+    forget(arc);
+    jit_enabled
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_execute_compiled_traces_set(mt: *const MT, execute: bool) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    arc.set_execute_compiled_traces(execute);
+    forget(arc);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_code_cache_limit_set(mt: *const MT, limit: usize) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    arc.set_code_cache_limit(limit);
+    forget(arc);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_collect_traces(mt: *const MT) -> usize {
+    let arc = unsafe { Arc::from_raw(mt) };
+    let n = arc.collect_traces();
+    forget(arc);
+    n
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_invalidate_all(mt: *const MT) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    arc.invalidate_all();
+    forget(arc);
+}
+
+/// The C representation of a [StatsSnapshot]: see `YkStats` in `yk.h`.
+#[repr(C)]
+pub struct YkStats {
+    traces_recorded_ok: u64,
+    traces_recorded_err: u64,
+    traces_compiled_ok: u64,
+    traces_compiled_err: u64,
+    trace_executions: u64,
+    trace_evictions: u64,
+    guards_compiled: u64,
+    guards_removed: u64,
+    duration_tracing_ns: u64,
+    duration_compiling_ns: u64,
+    duration_deopting_ns: u64,
+    duration_jit_executing_ns: u64,
+    duration_outside_yk_ns: u64,
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_stats(mt: *const MT, stats: *mut YkStats) {
+    fn ns(d: Duration) -> u64 {
+        u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)
+    }
+    let mt = unsafe { &*mt };
+    let StatsSnapshot {
+        traces_recorded_ok,
+        traces_recorded_err,
+        traces_compiled_ok,
+        traces_compiled_err,
+        trace_executions,
+        trace_evictions,
+        guards_compiled,
+        guards_removed,
+        duration_tracing,
+        duration_compiling,
+        duration_deopting,
+        duration_jit_executing,
+        duration_outside_yk,
+    } = mt.stats_snapshot();
+    unsafe {
+        stats.write(YkStats {
+            traces_recorded_ok,
+            traces_recorded_err,
+            traces_compiled_ok,
+            traces_compiled_err,
+            trace_executions,
+            trace_evictions,
+            guards_compiled,
+            guards_removed,
+            duration_tracing_ns: ns(duration_tracing),
+            duration_compiling_ns: ns(duration_compiling),
+            duration_deopting_ns: ns(duration_deopting),
+            duration_jit_executing_ns: ns(duration_jit_executing),
+            duration_outside_yk_ns: ns(duration_outside_yk),
+        });
+    }
+}
+
+/// The C representation of an [Event]: see `YkEvent` in `yk.h`.
+#[repr(C)]
+pub struct YkEvent {
+    kind: u32,
+    trid: u64,
+    parent_trid: u64,
+    gid: usize,
+    reason: u32,
+    debug_str: *const c_char,
+    msg: *const c_char,
+}
+
+/// The user data passed to `yk_mt_event_callback_set`. It is up to the user to ensure that it is
+/// safe to use from any thread.
+struct EventUserData(*mut c_void);
+
+unsafe impl Send for EventUserData {}
+unsafe impl Sync for EventUserData {}
+
+impl EventUserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Convert `e` into a [YkEvent] and pass it to `f`. Events which have no C representation are
+/// ignored.
+fn with_c_event<F: FnOnce(&YkEvent)>(e: &Event, f: F) {
+    let (kind, trid, parent_trid, gid, reason, debug_str, msg) = match e {
+        Event::StartTracing { trid, debug_str } => (0, *trid, 0, 0, 0, debug_str.as_deref(), None),
+        Event::StartSideTracing {
+            trid,
+            parent_trid,
+            gid,
+            debug_str,
+        } => (1, *trid, *parent_trid, *gid, 0, debug_str.as_deref(), None),
+        Event::StopTracing { trid, debug_str } => (2, *trid, 0, 0, 0, debug_str.as_deref(), None),
+        Event::AbortTracing {
+            trid,
+            debug_str,
+            reason,
+        } => (3, *trid, 0, 0, *reason as u32, debug_str.as_deref(), None),
+        Event::CompileOk { trid } => (4, *trid, 0, 0, 0, None, None),
+        Event::CompileErr { trid, kind, msg } => {
+            (5, *trid, 0, 0, *kind as u32, None, Some(msg.as_str()))
+        }
+        Event::ExecuteTrace { trid, debug_str } => (6, *trid, 0, 0, 0, debug_str.as_deref(), None),
+        Event::GuardFailure { trid, gid } => (7, *trid, 0, *gid, 0, None, None),
+        Event::Deopt { trid } => (8, *trid, 0, 0, 0, None, None),
+        Event::TraceReturn { trid } => (9, *trid, 0, 0, 0, None, None),
+        _ => return,
+    };
+    // Strings containing NUL bytes can't be represented in C, so we pass `NULL` instead.
+    let debug_str = debug_str.and_then(|x| CString::new(x).ok());
+    let msg = msg.and_then(|x| CString::new(x).ok());
+    f(&YkEvent {
+        kind,
+        trid,
+        parent_trid,
+        gid,
+        reason,
+        debug_str: debug_str.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+        msg: msg.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+    });
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_mt_event_callback_set(
+    mt: *const MT,
+    cb: Option<unsafe extern "C" fn(*const YkEvent, *mut c_void)>,
+    user_data: *mut c_void,
+) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    let handler = cb.map(|cb| {
+        let user_data = EventUserData(user_data);
+        Box::new(move |e: &Event| {
+            with_c_event(e, |ce| unsafe { cb(ce, user_data.get()) });
+        }) as Box<dyn Fn(&Event) + Send + Sync>
+    });
+    arc.set_event_handler(handler);
+    forget(arc);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_watch_new(mt: *const MT) -> WatchId {
+    let arc = unsafe { Arc::from_raw(mt) };
+    let watch = arc.watch_new();
+    forget(arc);
+    watch
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_watch_fire(mt: *const MT, watch: WatchId) {
+    let arc = unsafe { Arc::from_raw(mt) };
+    arc.watch_fire(watch);
+    forget(arc);
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn yk_location_new() -> Location {
     Location::new()
@@ -162,6 +496,30 @@ pub unsafe extern "C" fn yk_location_set_debug_str(loc: *mut Location, s: *const
     loc.set_hl_debug_str(s);
 }
 
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_location_set_hot_threshold(
+    loc: *mut Location,
+    hot_threshold: HotThreshold,
+) {
+    let loc = unsafe { &*loc };
+    assert!(!loc.is_null());
+    loc.set_hot_threshold(hot_threshold);
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_location_set_dont_trace(loc: *mut Location) {
+    let loc = unsafe { &*loc };
+    assert!(!loc.is_null());
+    loc.set_dont_trace();
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn yk_location_reset(loc: *mut Location) {
+    let loc = unsafe { &*loc };
+    assert!(!loc.is_null());
+    loc.reset();
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn yk_location_null() -> Location {
     Location::null()
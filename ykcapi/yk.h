@@ -31,6 +31,9 @@ typedef uint32_t YkHotThreshold;
 
 typedef struct YkMT YkMT;
 
+// A watchpoint: see `yk_watch_new`.
+typedef uint64_t YkWatch;
+
 // Create a new `YkMT` instance. If this fails then:
 //   * If `err_msg` is `NULL`, this function will abort.
 //   * If `err_msg` is not `NULL`:
@@ -40,6 +43,64 @@ typedef struct YkMT YkMT;
 //       2. `yk_mt_new` will return `NULL`.
 YkMT *yk_mt_new(char **err_msg);
 
+// The configuration of a `YkMT` instance, built up with the
+// `yk_mt_config_*_set` functions and then passed to `yk_mt_new_with_config`.
+typedef struct YkMTConfig YkMTConfig;
+
+// Create a new `YkMTConfig` whose values are taken from the `YK_*` / `YKD_*`
+// environment variables where they are set (and yk's defaults otherwise).
+// Errors are reported as for `yk_mt_new`.
+YkMTConfig *yk_mt_config_new(char **err_msg);
+
+// Free a `YkMTConfig` that will not be passed to `yk_mt_new_with_config`.
+void yk_mt_config_drop(YkMTConfig *);
+
+// Override the equivalent of `YK_HOT_THRESHOLD`.
+void yk_mt_config_hot_threshold_set(YkMTConfig *, YkHotThreshold);
+
+// Override the equivalent of `YK_SIDETRACE_THRESHOLD`.
+void yk_mt_config_sidetrace_threshold_set(YkMTConfig *, YkHotThreshold);
+
+// Override the equivalent of `YK_RECOMPILE_THRESHOLD`.
+void yk_mt_config_recompile_threshold_set(YkMTConfig *, YkHotThreshold);
+
+// Set how often tracing or compiling a location can fail before yk gives up
+// on it. Must be >= 1.
+void yk_mt_config_trace_failure_threshold_set(YkMTConfig *, uint16_t);
+
+// Override the equivalent of `YKD_OPT`.
+void yk_mt_config_opt_level_set(YkMTConfig *, uint8_t);
+
+// Override the equivalent of `YK_JOBS`.
+void yk_mt_config_jobs_set(YkMTConfig *, size_t);
+
+// Override the equivalent of `YK_JITC=none`.
+void yk_mt_config_jit_enabled_set(YkMTConfig *, bool);
+
+// Set the path yk logs to. If `NULL`, yk logs to stderr.
+void yk_mt_config_log_path_set(YkMTConfig *, const char *);
+
+// Set the level of yk's logging (see `YKD_LOG`).
+void yk_mt_config_log_level_set(YkMTConfig *, uint8_t);
+
+// Set the path statistics are written to at shutdown (see `YKD_LOG_STATS`).
+// If `NULL`, statistics are not collected.
+void yk_mt_config_stats_path_set(YkMTConfig *, const char *);
+
+// Set the interval, in milliseconds, at which a background thread frees
+// compiled traces which can no longer be executed (see
+// `yk_mt_collect_traces`). 0 (the default) disables periodic collection.
+void yk_mt_config_trace_collection_interval_set(YkMTConfig *, uint64_t);
+
+// Set the maximum number of bytes of machine code compiled traces may occupy
+// (see `yk_mt_code_cache_limit_set`). 0 (the default) means unlimited.
+void yk_mt_config_code_cache_limit_set(YkMTConfig *, size_t);
+
+// Create a new `YkMT` instance from a `YkMTConfig`. The `YkMTConfig` is
+// consumed by this call and must not be used afterwards, whether or not this
+// call succeeds. Errors are reported as for `yk_mt_new`.
+YkMT *yk_mt_new_with_config(YkMTConfig *, char **err_msg);
+
 // Shutdown this MT instance. Will panic if an error is detected when doing so.
 // This function can be called more than once, but only the first call will
 // have observable behaviour.
@@ -58,6 +119,132 @@ void yk_mt_hot_threshold_set(YkMT *, YkHotThreshold);
 // Set the threshold at which guard failures are considered hot.
 void yk_mt_sidetrace_threshold_set(YkMT *, YkHotThreshold);
 
+// Enable or disable JIT compilation at run-time. While disabled, locations do
+// not count towards hotness and no new tracing is started. If the calling
+// thread is recording a trace, that trace is abandoned immediately; other
+// threads abandon theirs at their next control point. Already compiled traces
+// continue to be executed unless `yk_mt_execute_compiled_traces_set` is used
+// to prevent this.
+void yk_mt_jit_enabled_set(YkMT *, bool);
+
+// Returns `true` if JIT compilation is enabled.
+bool yk_mt_jit_enabled_get(YkMT *);
+
+// Set whether control points enter already compiled traces. Defaults to
+// `true`.
+void yk_mt_execute_compiled_traces_set(YkMT *, bool);
+
+// Set the maximum number of bytes of machine code compiled traces may occupy,
+// where 0 means unlimited. When the limit is exceeded, the least recently
+// executed traces, and their side-traces, are evicted: the locations they were
+// compiled for start counting towards hotness again.
+void yk_mt_code_cache_limit_set(YkMT *, size_t);
+
+// Free compiled traces which are no longer reachable from any location, and
+// return how many were freed. If any thread is executing a compiled trace when
+// this is called, nothing is freed.
+size_t yk_mt_collect_traces(YkMT *);
+
+// Invalidate every compiled trace, and every trace currently being recorded or
+// compiled: all locations with compiled traces start counting towards hotness
+// again. This should be called when the interpreter's code changes in a way
+// that could make existing traces incorrect. A thread executing a trace when
+// this is called leaves the trace the next time one of its guards fails, the
+// trace returns, or it reaches the end of a looping trace. Invalidated traces
+// are freed once no thread is executing a compiled trace.
+void yk_mt_invalidate_all(YkMT *);
+
+// A snapshot of yk's statistics (see `yk_mt_stats`). The meaning of each field
+// is as for the `YKD_LOG_STATS` output of the same name. Durations are in
+// nanoseconds and only include time up to each thread's most recent change of
+// state (e.g. starting or stopping tracing).
+typedef struct {
+  uint64_t traces_recorded_ok;
+  uint64_t traces_recorded_err;
+  uint64_t traces_compiled_ok;
+  uint64_t traces_compiled_err;
+  uint64_t trace_executions;
+  uint64_t trace_evictions;
+  uint64_t guards_compiled;
+  uint64_t guards_removed;
+  uint64_t duration_tracing_ns;
+  uint64_t duration_compiling_ns;
+  uint64_t duration_deopting_ns;
+  uint64_t duration_jit_executing_ns;
+  uint64_t duration_outside_yk_ns;
+} YkStats;
+
+// Write a snapshot of yk's statistics to `stats`. This is cheap enough to be
+// called frequently (e.g. from an interpreter's introspection functions) and
+// does not require `YKD_LOG_STATS` to be set.
+void yk_mt_stats(YkMT *, YkStats *stats);
+
+// The kinds of lifecycle event passed to a `YkEventCallback`.
+typedef enum {
+  // Tracing started at a location.
+  YK_EVENT_START_TRACING = 0,
+  // Tracing started after guard `gid` in trace `parent_trid` failed.
+  YK_EVENT_START_SIDE_TRACING = 1,
+  // Tracing stopped successfully and the trace was queued for compilation.
+  YK_EVENT_STOP_TRACING = 2,
+  // Tracing was aborted for the `YkAbortKind` in `reason`.
+  YK_EVENT_ABORT_TRACING = 3,
+  // A trace was compiled successfully.
+  YK_EVENT_COMPILE_OK = 4,
+  // A trace failed to compile for the `YkCompilationErrorKind` in `reason`.
+  // `msg` describes the error.
+  YK_EVENT_COMPILE_ERR = 5,
+  // A thread started executing a trace.
+  YK_EVENT_EXECUTE_TRACE = 6,
+  // Guard `gid` in a trace failed.
+  YK_EVENT_GUARD_FAILURE = 7,
+  // A thread executing a trace deoptimised back to the interpreter.
+  YK_EVENT_DEOPT = 8,
+  // A thread executing a trace returned from the function containing the
+  // trace's location.
+  YK_EVENT_TRACE_RETURN = 9,
+} YkEventKind;
+
+// Why tracing was aborted (see `YK_EVENT_ABORT_TRACING`).
+typedef enum {
+  YK_ABORT_BACK_INTO_EXECUTION = 0,
+  YK_ABORT_JIT_DISABLED = 1,
+  YK_ABORT_LONGJMP_ENCOUNTERED = 2,
+  YK_ABORT_UNROLLED_INNER_LOOP = 3,
+  YK_ABORT_RECORDER_ERROR = 4,
+} YkAbortKind;
+
+// Why compilation failed (see `YK_EVENT_COMPILE_ERR`).
+typedef enum {
+  YK_COMPILATION_ERROR_GENERAL = 0,
+  YK_COMPILATION_ERROR_INTERNAL = 1,
+  YK_COMPILATION_ERROR_LIMIT_EXCEEDED = 2,
+  YK_COMPILATION_ERROR_RESOURCE_EXHAUSTED = 3,
+} YkCompilationErrorKind;
+
+// A lifecycle event. Fields which are not relevant to an event's `kind` are
+// zero / `NULL`. The strings are only valid for the duration of the callback.
+typedef struct {
+  uint32_t kind;
+  // The ID of the trace the event relates to, as used in yk's logs.
+  uint64_t trid;
+  uint64_t parent_trid;
+  uintptr_t gid;
+  uint32_t reason;
+  // The location's debug string (see `yk_location_set_debug_str`), if any.
+  const char *debug_str;
+  const char *msg;
+} YkEvent;
+
+typedef void (*YkEventCallback)(const YkEvent *, void *);
+
+// Call `cb` with `user_data` for every lifecycle event (tracing starting,
+// stopping, and aborting; compilation succeeding and failing; traces being
+// executed; guards failing; etc.). `cb` may be called from any thread,
+// including yk's compilation threads, and must not call back into yk. Passing
+// `NULL` for `cb` removes any previously set callback.
+void yk_mt_event_callback_set(YkMT *, YkEventCallback, void *user_data);
+
 // Returns `true` if yk is running in the "pure" interpreter i.e. (1) without
 // tracing the code and (2) not in JIT compiled code. During trace
 // optimisation, calls to this function will be fully optimised away.
@@ -80,6 +267,23 @@ YkLocation yk_location_new(void);
 // This function is only avaliable if yk was built with the "ykd" feature.
 YkLocation yk_location_set_debug_str(YkLocation *, char *);
 
+// Set the number of times this location must be executed before it is
+// considered hot, overriding the threshold set with `yk_mt_hot_threshold_set`.
+// If the location is already being traced, or has been compiled, this takes
+// effect when it next returns to counting.
+void yk_location_set_hot_threshold(YkLocation *, YkHotThreshold);
+
+// Ensure that this location is never traced in the future. If the location is
+// already being traced, or has been compiled, that trace is unaffected. This
+// cannot be undone, even by `yk_location_reset`.
+void yk_location_set_dont_trace(YkLocation *);
+
+// Discard this location's compiled trace (and its side-traces), if any, and
+// return the location to counting towards hotness. If a trace for this location
+// is being recorded or compiled, it is thrown away when compilation completes.
+// This does not undo `yk_location_set_dont_trace`.
+void yk_location_reset(YkLocation *);
+
 // Create a new NULL-equivalent `Location`. Such a `YkLocation` denotes a point
 // in a program which can never contribute to a trace.
 YkLocation yk_location_null(void);
@@ -91,7 +295,9 @@ static inline bool yk_location_is_null(YkLocation l) {
 
 // Clean-up a `Location` previously created by `yk_new_location`. The
 // `Location` must not be further used after this call or undefined behaviour
-// will occur.
+// will occur. If the `Location` has a compiled trace, that trace, and any
+// traces which couple to it, are no longer executed and are freed as soon as no
+// thread is executing JIT compiled code.
 void yk_location_drop(YkLocation);
 
 // Promote a value to a constant. This is a generic macro that will
@@ -119,6 +325,24 @@ void *__yk_promote_ptr(void *);
 /// memory after this call has completed.
 void yk_debug_str(char *);
 
+// Create a new watchpoint. While a trace is being recorded, the interpreter can
+// call `yk_watch_depend` to record that the trace depends on the watchpoint;
+// calling `yk_watch_fire` then invalidates every trace which depends on it.
+// This allows values which rarely change (e.g. global tables or method caches)
+// to be read via `yk_idempotent` functions, and thus treated as constants
+// without guards, as long as the watchpoint is fired whenever they change.
+YkWatch yk_watch_new(YkMT *);
+
+// Record that the trace currently being recorded (if any) depends on the
+// watchpoint. Calls to this function are removed from compiled traces.
+void yk_watch_depend(YkWatch);
+
+// Fire the watchpoint, invalidating every trace which depends on it (including
+// traces currently being recorded or compiled). Invalidated traces are no
+// longer executed and are freed as soon as no thread is executing JIT compiled
+// code.
+void yk_watch_fire(YkMT *, YkWatch);
+
 int yk_pthread_create(
     pthread_t *restrict thread,
     const pthread_attr_t *restrict attr,
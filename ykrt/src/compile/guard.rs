@@ -2,7 +2,7 @@
 
 use crate::{
     compile::CompiledTrace,
-    mt::{AtomicTraceCompilationErrorThreshold, HotThreshold, MT},
+    mt::{AtomicTraceCompilationErrorThreshold, HotThreshold, MT, TraceId},
 };
 use parking_lot::Mutex;
 use std::sync::{Arc, atomic::Ordering};
@@ -23,7 +23,8 @@ enum GuardState {
     Counting(HotThreshold),
     /// We are either creating a trace or waiting for that trace to compile.
     SideTracing,
-    Compiled,
+    /// A side-trace with the given [TraceId] has been compiled and patched into the parent trace.
+    Compiled(TraceId),
     /// This guard encountered errors sufficiently often when tracing or compiling that we don't
     /// want to try again.
     DontTrace,
@@ -51,7 +52,7 @@ impl Guard {
                     false
                 }
             }
-            GuardState::SideTracing | GuardState::Compiled | GuardState::DontTrace => false,
+            GuardState::SideTracing | GuardState::Compiled(_) | GuardState::DontTrace => false,
         }
     }
 
@@ -71,6 +72,15 @@ impl Guard {
         }
     }
 
+    /// Inform this guard that a trace started from it was abandoned for reasons unrelated to the
+    /// trace itself (e.g. the JIT being disabled). Unlike [Self::trace_or_compile_failed], this
+    /// does not count towards the guard's error threshold.
+    pub fn trace_abandoned(&self) {
+        let mut lk = self.kind.lock();
+        assert_eq!(*lk, GuardState::SideTracing);
+        *lk = GuardState::Counting(0);
+    }
+
     /// Stores a compiled side-trace inside this guard while patching a jump to the side-trace
     /// directly into the parent trace.
     /// * `ctr`: The compiled side-trace.
@@ -85,7 +95,7 @@ impl Guard {
         let mut lk = self.kind.lock();
         let addr = ctr.entry();
         match &*lk {
-            GuardState::SideTracing => *lk = GuardState::Compiled,
+            GuardState::SideTracing => *lk = GuardState::Compiled(ctr.ctrid()),
             _ => panic!(),
         }
         // It's important to patch the parent only after we've updated the `GuardState` to avoid a
@@ -94,6 +104,36 @@ impl Guard {
         // `set_ctr` which sets information required by deopt.
         parent.patch_guard(gid, addr);
     }
+
+    /// If a side-trace has been compiled for this guard, return its [TraceId].
+    pub fn sidetrace(&self) -> Option<TraceId> {
+        match &*self.kind.lock() {
+            GuardState::Compiled(trid) => Some(*trid),
+            _ => None,
+        }
+    }
+
+    /// Stop this guard jumping to its compiled side-trace, returning the guard to the counting
+    /// state. Returns the [TraceId] of the retired side-trace, if there was one.
+    /// * `parent`: The trace this guard is part of.
+    /// * `gid`: The guard id of this guard in `parent`.
+    ///
+    /// Note: threads which have already jumped to the side-trace may continue to execute it, so
+    /// the side-trace must not be freed until no thread can still be executing it.
+    pub fn retire_ctr(&self, parent: &dyn CompiledTrace, gid: GuardId) -> Option<TraceId> {
+        let mut lk = self.kind.lock();
+        if let GuardState::Compiled(trid) = &*lk {
+            let trid = *trid;
+            // The mirror image of [Self::set_ctr]: unpatch the parent before updating the
+            // `GuardState` so that no thread can take the jump to the side-trace and then find
+            // this guard in an unexpected state.
+            parent.unpatch_guard(gid);
+            *lk = GuardState::Counting(0);
+            Some(trid)
+        } else {
+            None
+        }
+    }
 }
 
 /// Uniquely identify a [Guard] within a trace.
@@ -98,6 +98,8 @@ pub(super) struct AotToHir<'a, Reg: RegT> {
     frames: Vec<Frame>,
     /// If logging is enabled, create a map of addresses -> names to make IR printing nicer.
     addr_name_map: Option<HashMap<usize, Option<String>>>,
+    /// How many guards have been fed to the optimiser?
+    guards_fed: usize,
     /// The JIT IR this struct builds.
     phantom: PhantomData<Reg>,
 }
@@ -111,6 +113,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         ta_iter: &'a mut Peekable<Box<dyn crate::trace::AOTTraceIterator>>,
         trid: TraceId,
         bkind: BuildKind,
+        opt_level: u8,
         promotions_iter: &'a mut dyn Iterator<Item = &'a u8>,
         debug_strs_iter: &'a mut I,
     ) -> Self
@@ -123,10 +126,10 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             unsafe { std::slice::from_raw_parts(ptr, am.global_decls_len()) }
         };
 
-        let opt = if mt.opt_level() == 0 {
+        let opt = if opt_level == 0 {
             Box::new(NoOpt::new()) as Box<dyn OptT>
         } else {
-            Box::new(FullOpt::new())
+            Box::new(FullOpt::with_config(mt, trid))
         };
 
         let debug_strs_joined = if should_log_ir(IRPhase::DebugStrs) {
@@ -151,6 +154,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             opt,
             frames: Vec::new(),
             addr_name_map: should_log_any_ir().then_some(HashMap::new()),
+            guards_fed: 0,
             phantom: PhantomData,
         }
     }
@@ -181,7 +185,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                     bbidx: *bbidx - 1,
                 };
                 let cp_bid = self.ta_to_bid(ta).unwrap();
-                let entry_statepoint = self.p_start_loop(&cp_bid)?;
+                let (entry_pc, entry_statepoint) = self.p_start_loop(&cp_bid)?;
                 assert_matches!(
                     self.ta_iter.peek(),
                     Some(&Ok(TraceAction::MappedAOTBBlock { .. }))
@@ -193,11 +197,15 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                             .downcast::<J2CompiledTrace<Reg>>()
                             .unwrap();
                         BuildModKind::Coupler {
+                            entry_pc,
                             entry_statepoint,
                             tgt_ctr,
                         }
                     }
-                    BuildKind::Loop => BuildModKind::Loop { entry_statepoint },
+                    BuildKind::Loop => BuildModKind::Loop {
+                        entry_pc,
+                        entry_statepoint,
+                    },
                     _ => unreachable!(),
                 }
             }
@@ -258,19 +266,25 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 assert!(self.promotions_iter.next().is_none());
                 assert_eq!(self.frames.len(), 1);
                 let exit_statepoint = match &bmk {
-                    BuildModKind::Loop { entry_statepoint } => entry_statepoint,
+                    BuildModKind::Loop {
+                        entry_statepoint, ..
+                    } => entry_statepoint,
                     BuildModKind::Coupler { tgt_ctr, .. } => match &tgt_ctr.trace_start {
                         J2TraceStart::ControlPoint {
                             entry_statepoint, ..
                         } => entry_statepoint,
-                        J2TraceStart::Guard { .. } => todo!(),
+                        J2TraceStart::Guard { .. } => {
+                            internal_error!("coupler trace targets a side-trace")
+                        }
                     },
                     BuildModKind::Side { tgt_ctr, .. } => {
                         match tgt_ctr.as_ref().unwrap().trace_start {
                             J2TraceStart::ControlPoint {
                                 entry_statepoint, ..
                             } => entry_statepoint,
-                            J2TraceStart::Guard { .. } => todo!(),
+                            J2TraceStart::Guard { .. } => {
+                                internal_error!("side-trace targets a side-trace")
+                            }
                         }
                     }
                 };
@@ -289,18 +303,25 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
 
         let (trace_start, trace_end, tys) = match bmk {
             BuildModKind::Coupler {
+                entry_pc,
                 entry_statepoint,
                 tgt_ctr,
             } => {
                 let (entry, tys) = self.opt.build()?;
                 match termendk {
                     TraceEndKind::Call => (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Call { entry },
                         tys,
                     ),
                     TraceEndKind::Return(exit_statepoint) => (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Return {
                             entry,
                             exit_statepoint,
@@ -308,17 +329,26 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                         tys,
                     ),
                     TraceEndKind::Term => (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Coupler { entry, tgt_ctr },
                         tys,
                     ),
                 }
             }
-            BuildModKind::Loop { entry_statepoint } => match termendk {
+            BuildModKind::Loop {
+                entry_pc,
+                entry_statepoint,
+            } => match termendk {
                 TraceEndKind::Call => {
                     let (entry, tys) = self.opt.build()?;
                     (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Call { entry },
                         tys,
                     )
@@ -326,7 +356,10 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 TraceEndKind::Return(exit_statepoint) => {
                     let (entry, tys) = self.opt.build()?;
                     (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Return {
                             entry,
                             exit_statepoint,
@@ -337,7 +370,10 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 TraceEndKind::Term => {
                     let (entry, peel, tys) = self.opt.build_with_peel()?;
                     (
-                        hir::TraceStart::ControlPoint { entry_statepoint },
+                        hir::TraceStart::ControlPoint {
+                            entry_pc,
+                            entry_statepoint,
+                        },
                         hir::TraceEnd::Loop { entry, peel },
                         tys,
                     )
@@ -389,6 +425,30 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             }
         };
 
+        // Every guard we fed to the optimiser that isn't in `entry` was removed. When a loop is
+        // peeled, `entry`'s guards are fed to the optimiser a second time to create `peel`.
+        let (guards_compiled, guards_fed) = match &trace_end {
+            hir::TraceEnd::Loop {
+                entry,
+                peel: Some(peel),
+            } => (
+                entry.guard_extras.len() + peel.guard_extras.len(),
+                self.guards_fed + entry.guard_extras.len(),
+            ),
+            hir::TraceEnd::Call { entry }
+            | hir::TraceEnd::Coupler { entry, .. }
+            | hir::TraceEnd::Loop { entry, peel: None }
+            | hir::TraceEnd::Return { entry, .. } => (entry.guard_extras.len(), self.guards_fed),
+            #[cfg(test)]
+            hir::TraceEnd::Test { .. } | hir::TraceEnd::TestPeel { .. } => unreachable!(),
+        };
+        self.mt
+            .stats
+            .guards_compiled(u64::try_from(guards_compiled).unwrap());
+        self.mt
+            .stats
+            .guards_removed(u64::try_from(guards_fed.saturating_sub(guards_compiled)).unwrap());
+
         let m = hir::Mod {
             trid: self.trid,
             trace_start,
@@ -456,6 +516,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         // If the condition variable is referenced in the guard's exit vars, we'll change it to
         // reference a const -- but we construct this as-needed.
         let mut cond_inverse_iidx = None;
+        let mut cond_deopt_vars = SmallVec::new();
 
         let mut deopt_frames = SmallVec::with_capacity(self.frames.len());
         let mut deopt_vars = Vec::with_capacity(
@@ -475,6 +536,9 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 iid.clone()
             };
             for op in pc_statepoint.lives.iter() {
+                if self.frames[i].aggs.contains_key(&op.to_inst_id()) {
+                    unsupported!("aggregate live at a guard");
+                }
                 let mut iidx = self.frames[i].get_local(&*self.opt, &op.to_inst_id());
                 if iidx == cond_iidx {
                     if cond_inverse_iidx.is_none() {
@@ -488,6 +552,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                         )?);
                     }
                     iidx = cond_inverse_iidx.unwrap();
+                    cond_deopt_vars.push(deopt_vars.len());
                 }
                 deopt_vars.push(iidx);
             }
@@ -508,9 +573,12 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             bid,
             switch,
             deopt_vars,
+            cond_deopt_vars,
             deopt_frames,
+            virt_objs: Vec::new(),
         };
 
+        self.guards_fed += 1;
         self.opt.feed_guard(hinst, gextra)?;
         Ok(())
     }
@@ -590,33 +658,32 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                     9..=16 => u64::from(u16::from_ne_bytes(iter_to_array(self.promotions_iter))),
                     17..=32 => u64::from(u32::from_ne_bytes(iter_to_array(self.promotions_iter))),
                     33..=64 => u64::from_ne_bytes(iter_to_array(self.promotions_iter)),
-                    _ => todo!("{}", x.bitw()),
+                    x => unsupported!("promotion of {x}-bit integer"),
                 };
                 assert_eq!(ty.bitw(self.am), x.bitw());
                 let tyidx = self.opt.push_ty(hir::Ty::Int(bitw))?;
                 self.const_to_iidx(tyidx, hir::ConstKind::Int(ArbBitInt::from_u64(x.bitw(), v)))
             }
-            Ty::Void => todo!(),
+            Ty::Void => internal_error!("promotion of void value"),
             Ty::Ptr => {
                 let v = match size_of::<usize>() {
                     8 => usize::from_ne_bytes(iter_to_array(self.promotions_iter)),
-                    x => todo!("{x}"),
+                    x => unsupported!("promotion of {}-bit pointer", x * 8),
                 };
                 let tyidx = self.opt.push_ty(hir::Ty::Ptr(0))?;
                 self.const_to_iidx(tyidx, hir::ConstKind::Ptr(v))
             }
-            Ty::Func(_func_ty) => todo!(),
-            Ty::Struct(_struct_ty) => todo!(),
-            Ty::Float(_float_ty) => todo!(),
-            Ty::Unimplemented(_) => todo!(),
+            Ty::Func(_) | Ty::Struct(_) | Ty::Float(_) | Ty::Unimplemented(_) => {
+                unsupported!("promotion of value of type {}", ty.display(self.am))
+            }
         }
     }
 
     fn vloc_arg_to_const(&mut self, vloc: &VarLoc<Reg>) -> Result<hir::InstIdx, CompilationError> {
         match vloc {
-            VarLoc::Stack(_) => todo!(),
-            VarLoc::StackOff(_) => todo!(),
-            VarLoc::Reg(_, _) => todo!(),
+            VarLoc::Stack(_) | VarLoc::StackOff(_) | VarLoc::Reg(_, _) => {
+                internal_error!("non-constant argument passed as a constant")
+            }
             VarLoc::Const(kind) => match kind {
                 hir::ConstKind::Double(_) => {
                     let tyidx = self.opt.push_ty(hir::Ty::Double)?;
@@ -628,7 +695,16 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                         .into(),
                     )
                 }
-                hir::ConstKind::Float(_) => todo!(),
+                hir::ConstKind::Float(_) => {
+                    let tyidx = self.opt.push_ty(hir::Ty::Float)?;
+                    self.opt.feed_arg(
+                        hir::Const {
+                            tyidx,
+                            kind: kind.clone(),
+                        }
+                        .into(),
+                    )
+                }
                 hir::ConstKind::Int(x) => {
                     let tyidx = self.opt.push_ty(hir::Ty::Int(x.bitw()))?;
                     self.opt.feed_arg(
@@ -653,8 +729,12 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         }
     }
 
-    /// Process the start of a (ControlPoint, Coupler | Loop | Return) trace.
-    fn p_start_loop(&mut self, cp_bid: &BBlockId) -> Result<&'static Statepoint, CompilationError> {
+    /// Process the start of a (ControlPoint, Coupler | Loop | Return) trace, returning the
+    /// [InstId] of the control point call and its [Statepoint].
+    fn p_start_loop(
+        &mut self,
+        cp_bid: &BBlockId,
+    ) -> Result<(InstId, &'static Statepoint), CompilationError> {
         let cp_blk = self.am.bblock(cp_bid);
         let cp_iidx = BBlockInstIdx::new(
             cp_blk
@@ -664,13 +744,16 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 .unwrap(),
         );
         let statepoint = cp_blk.insts[cp_iidx].statepoint().unwrap();
+        let pc = InstId::new(cp_bid.funcidx(), cp_bid.bbidx(), cp_iidx);
         assert!(self.frames.is_empty());
         self.frames.push(Frame {
             args: SmallVec::new(),
             locals: HashMap::new(),
-            pc: Some(InstId::new(cp_bid.funcidx(), cp_bid.bbidx(), cp_iidx)),
+            aggs: HashMap::new(),
+            pc: Some(pc.clone()),
             pc_statepoint: None,
             prev_pc: None,
+            noalias: Vec::new(),
         });
 
         for op in statepoint.lives.iter() {
@@ -682,7 +765,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 .set_local(op.to_inst_id(), iidx);
         }
 
-        Ok(statepoint)
+        Ok((pc, statepoint))
     }
 
     /// Process the beginning of a (Guard, Coupler | Return) trace.
@@ -721,9 +804,11 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 // there aren't any AOT arguments for any of the frames.
                 args: smallvec![],
                 locals,
+                aggs: HashMap::new(),
                 pc: Some(pc.clone()),
                 pc_statepoint: Some(pc_statepoint),
                 prev_pc: None,
+                noalias: Vec::new(),
             });
             deopt_vars_off += pc_statepoint.lives.len();
         }
@@ -734,6 +819,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
     fn p_ty(&mut self, ty: &Ty) -> Result<hir::TyIdx, CompilationError> {
         let tyidx = match ty {
             Ty::Void => hir::Ty::Void,
+            Ty::Integer(x) if x.bitw() > 64 => unsupported!("{}-bit integers", x.bitw()),
             Ty::Integer(x) => hir::Ty::Int(x.bitw()),
             Ty::Ptr => {
                 // FIXME: AOT IR doesn't yet tell us what the address space is, so we guess "0".
@@ -752,10 +838,11 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 hir::Ty::Func(Box::new(fty))
             }
-            Ty::Struct(_ty) => todo!(),
+            Ty::Struct(_) | Ty::Unimplemented(_) => {
+                unsupported!("type {}", ty.display(self.am))
+            }
             Ty::Float(FloatTy::Double) => hir::Ty::Double,
             Ty::Float(FloatTy::Float) => hir::Ty::Float,
-            Ty::Unimplemented(_) => todo!(),
         };
         self.opt.push_ty(tyidx)
     }
@@ -766,73 +853,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         match op {
             Operand::Const(cidx) => {
                 let c = self.am.const_(*cidx).constval(self.am);
-                let bytes = c.bytes();
-                match self.am.type_(c.tyidx()) {
-                    Ty::Integer(x) => {
-                        // FIXME: It would be better if the AOT IR had converted these integers in advance
-                        // rather than doing this dance here.
-                        let v = match x.bitw() {
-                            1 | 8 => {
-                                debug_assert_eq!(bytes.len(), 1);
-                                u64::from(bytes[0])
-                            }
-                            16 => {
-                                debug_assert_eq!(bytes.len(), 2);
-                                u64::from(u16::from_ne_bytes([bytes[0], bytes[1]]))
-                            }
-                            32 => {
-                                debug_assert_eq!(bytes.len(), 4);
-                                u64::from(u32::from_ne_bytes([
-                                    bytes[0], bytes[1], bytes[2], bytes[3],
-                                ]))
-                            }
-                            64 => {
-                                debug_assert_eq!(bytes.len(), 8);
-                                u64::from_ne_bytes([
-                                    bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5],
-                                    bytes[6], bytes[7],
-                                ])
-                            }
-                            _ => todo!("{}", x.bitw()),
-                        };
-                        let tyidx = self.opt.push_ty(hir::Ty::Int(x.bitw()))?;
-                        self.const_to_iidx(
-                            tyidx,
-                            hir::ConstKind::Int(ArbBitInt::from_u64(x.bitw(), v)),
-                        )
-                    }
-                    Ty::Float(FloatTy::Double) => {
-                        debug_assert_eq!(bytes.len(), 8);
-                        let v = f64::from_ne_bytes([
-                            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6],
-                            bytes[7],
-                        ]);
-                        let tyidx = self.opt.push_ty(hir::Ty::Double)?;
-                        self.const_to_iidx(tyidx, hir::ConstKind::Double(v))
-                    }
-                    Ty::Float(FloatTy::Float) => {
-                        // FIXME: Floats are currently stored in AOT as doubles
-                        // https://github.com/ykjit/yk/issues/1876
-                        debug_assert_eq!(bytes.len(), 8);
-                        let v = f64::from_ne_bytes([
-                            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6],
-                            bytes[7],
-                        ]);
-                        let v = v as f32;
-                        let tyidx = self.opt.push_ty(hir::Ty::Float)?;
-                        self.const_to_iidx(tyidx, hir::ConstKind::Float(v))
-                    }
-                    Ty::Ptr => {
-                        debug_assert_eq!(bytes.len(), 8);
-                        let v = u64::from_ne_bytes([
-                            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6],
-                            bytes[7],
-                        ]);
-                        let tyidx = self.opt.push_ty(hir::Ty::Ptr(0))?;
-                        self.const_to_iidx(tyidx, hir::ConstKind::Ptr(usize::try_from(v).unwrap()))
-                    }
-                    x => todo!("{x:?}"),
-                }
+                self.p_const_bytes(self.am.type_(c.tyidx()), c.bytes())
             }
             Operand::Local(iid) => Ok(self.frames.last().unwrap().get_local(&*self.opt, iid)),
             Operand::Global(gidx) => {
@@ -863,6 +884,187 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         }
     }
 
+    /// Translate the constant `bytes` of type `ty` into a HIR constant.
+    fn p_const_bytes(&mut self, ty: &Ty, bytes: &[u8]) -> Result<hir::InstIdx, CompilationError> {
+        match ty {
+            Ty::Integer(x) => {
+                // FIXME: It would be better if the AOT IR had converted these integers in advance
+                // rather than doing this dance here.
+                let v = match x.bitw() {
+                    1 | 8 => {
+                        debug_assert_eq!(bytes.len(), 1);
+                        u64::from(bytes[0])
+                    }
+                    16 => {
+                        debug_assert_eq!(bytes.len(), 2);
+                        u64::from(u16::from_ne_bytes([bytes[0], bytes[1]]))
+                    }
+                    32 => {
+                        debug_assert_eq!(bytes.len(), 4);
+                        u64::from(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    }
+                    64 => {
+                        debug_assert_eq!(bytes.len(), 8);
+                        u64::from_ne_bytes([
+                            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6],
+                            bytes[7],
+                        ])
+                    }
+                    x => unsupported!("{x}-bit integer constant"),
+                };
+                let tyidx = self.opt.push_ty(hir::Ty::Int(x.bitw()))?;
+                self.const_to_iidx(tyidx, hir::ConstKind::Int(ArbBitInt::from_u64(x.bitw(), v)))
+            }
+            Ty::Float(FloatTy::Double) => {
+                debug_assert_eq!(bytes.len(), 8);
+                let v = f64::from_ne_bytes([
+                    bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+                ]);
+                let tyidx = self.opt.push_ty(hir::Ty::Double)?;
+                self.const_to_iidx(tyidx, hir::ConstKind::Double(v))
+            }
+            Ty::Float(FloatTy::Float) => {
+                // FIXME: Floats are currently stored in AOT as doubles
+                // https://github.com/ykjit/yk/issues/1876
+                debug_assert_eq!(bytes.len(), 8);
+                let v = f64::from_ne_bytes([
+                    bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+                ]);
+                let v = v as f32;
+                let tyidx = self.opt.push_ty(hir::Ty::Float)?;
+                self.const_to_iidx(tyidx, hir::ConstKind::Float(v))
+            }
+            Ty::Ptr => {
+                debug_assert_eq!(bytes.len(), 8);
+                let v = u64::from_ne_bytes([
+                    bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+                ]);
+                let tyidx = self.opt.push_ty(hir::Ty::Ptr(0))?;
+                self.const_to_iidx(tyidx, hir::ConstKind::Ptr(usize::try_from(v).unwrap()))
+            }
+            x => unsupported!("constant of type {}", x.display(self.am)),
+        }
+    }
+
+    /// Process an [Operand] which may be an aggregate, returning the [Val] it references. Note:
+    /// this can insert instructions into [self.opt]!
+    fn p_val(&mut self, op: &Operand) -> Result<Val, CompilationError> {
+        if let Ty::Struct(_) = op.type_(self.am) {
+            Ok(Val::Agg(self.p_agg(op)?))
+        } else {
+            Ok(Val::Scalar(self.p_operand(op)?))
+        }
+    }
+
+    /// Process an aggregate [Operand] and return the HIR values of its fields. Note: this can
+    /// insert instructions into [self.opt]!
+    fn p_agg(&mut self, op: &Operand) -> Result<AggFields, CompilationError> {
+        let field_tys = self.agg_fields(op.type_(self.am))?;
+        let mut fields = AggFields::with_capacity(field_tys.len());
+        match op {
+            Operand::Const(cidx) => match self.am.const_(*cidx) {
+                Const::Poison(_) => {
+                    // Any value is a valid refinement of poison: we arbitrarily choose zero.
+                    for (fty, _) in field_tys {
+                        fields.push(self.zero_const(fty)?);
+                    }
+                }
+                Const::Val(cv) => {
+                    let bytes = cv.bytes();
+                    for (fty, off) in field_tys {
+                        let bytew = match fty {
+                            Ty::Float(FloatTy::Float) => {
+                                unsupported!("constant aggregate with a float field")
+                            }
+                            Ty::Float(FloatTy::Double) => 8,
+                            ty => usize::try_from(ty.bytew()).unwrap(),
+                        };
+                        fields.push(self.p_const_bytes(fty, &bytes[off..off + bytew])?);
+                    }
+                }
+                x => unsupported!("aggregate constant {}", x.display(self.am)),
+            },
+            Operand::Local(iid) => {
+                if let Some(x) = self.frames.last().unwrap().get_agg(&*self.opt, iid) {
+                    return Ok(x);
+                }
+                // An aggregate loaded from memory is represented by a pointer to that memory (see
+                // `p_load`), so we load each of its fields.
+                let Inst::Load { volatile, .. } = self.am.inst(iid) else {
+                    internal_error!("aggregate local is neither scalarised nor a load")
+                };
+                let ptr = self.frames.last().unwrap().get_local(&*self.opt, iid);
+                for (fty, off) in field_tys {
+                    fields.push(self.load_agg_field(ptr, off, fty, *volatile)?);
+                }
+            }
+            Operand::Global(_) | Operand::Func(_) => panic!(),
+        }
+        Ok(fields)
+    }
+
+    /// Return the type and byte offset of each field of the aggregate type `ty`. Aggregates are
+    /// only scalarised one level deep, so only aggregates whose fields are all scalars are
+    /// supported.
+    fn agg_fields(&self, ty: &Ty) -> Result<SmallVec<[(&'static Ty, usize); 2]>, CompilationError> {
+        let Ty::Struct(sty) = ty else { panic!() };
+        let mut out = SmallVec::with_capacity(sty.field_tyidxs().len());
+        for (ftyidx, bit_off) in sty.field_tyidxs().iter().zip(sty.field_bit_offs()) {
+            let fty = self.am.type_(*ftyidx);
+            if !matches!(fty, Ty::Integer(_) | Ty::Ptr | Ty::Float(_)) {
+                unsupported!("aggregate type {}", ty.display(self.am))
+            }
+            // LLVM struct fields are always byte-aligned.
+            assert_eq!(bit_off % 8, 0);
+            out.push((fty, bit_off / 8));
+        }
+        Ok(out)
+    }
+
+    /// Load the field of type `ty` at byte offset `off` from the aggregate pointed to by `ptr`.
+    fn load_agg_field(
+        &mut self,
+        mut ptr: hir::InstIdx,
+        off: usize,
+        ty: &Ty,
+        is_volatile: bool,
+    ) -> Result<hir::InstIdx, CompilationError> {
+        if off > 0 {
+            ptr = self.opt.feed(
+                hir::PtrAdd {
+                    ptr,
+                    off: i32::try_from(off).unwrap(),
+                    in_bounds: false,
+                    nusw: false,
+                    nuw: false,
+                }
+                .into(),
+            )?;
+        }
+        let tyidx = self.p_ty(ty)?;
+        self.opt.feed(
+            hir::Load {
+                tyidx,
+                ptr,
+                is_volatile,
+            }
+            .into(),
+        )
+    }
+
+    /// Return a HIR constant of type `ty` with all bits set to zero.
+    fn zero_const(&mut self, ty: &Ty) -> Result<hir::InstIdx, CompilationError> {
+        let tyidx = self.p_ty(ty)?;
+        let kind = match ty {
+            Ty::Integer(x) => hir::ConstKind::Int(ArbBitInt::from_u64(x.bitw(), 0)),
+            Ty::Ptr => hir::ConstKind::Ptr(0),
+            Ty::Float(FloatTy::Double) => hir::ConstKind::Double(0.0),
+            Ty::Float(FloatTy::Float) => hir::ConstKind::Float(0.0),
+            x => unsupported!("constant of type {}", x.display(self.am)),
+        };
+        self.const_to_iidx(tyidx, kind)
+    }
+
     /// Returns `Some(statepoint)` if an early return was encountered: parent code should stop
     /// examining the trace at this point.
     fn p_blocks(&mut self) -> Result<TraceEndKind, CompilationError> {
@@ -903,8 +1105,10 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
 
             let inst = &blk.insts[pc.iidx()];
             match inst {
-                Inst::Nop => todo!(),
-                Inst::Alloca { .. } => todo!(),
+                Inst::Nop => {
+                    unsupported!("AOT instruction '{}'", inst.display(self.am, Some(pc)))
+                }
+                Inst::Alloca { .. } => self.p_alloca(pc.clone(), inst)?,
                 Inst::BinaryOp { .. } => self.p_binop(pc.clone(), inst)?,
                 Inst::Br { .. } => (),
                 Inst::Call { .. } => match self.p_call(pc.clone(), bid, inst)? {
@@ -927,9 +1131,11 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                     CallProcessedKind::Outlined => (),
                     CallProcessedKind::Terminated => return Ok(TraceEndKind::Call),
                 },
-                Inst::InsertValue { .. } => todo!(),
+                Inst::InsertValue { .. } => self.p_insertvalue(pc.clone(), inst)?,
                 Inst::Load { .. } => self.p_load(pc.clone(), inst)?,
-                Inst::LoadArg { .. } => self.p_loadarg(pc.clone(), inst)?,
+                Inst::LoadArg { .. } | Inst::LoadNoAliasArg { .. } => {
+                    self.p_loadarg(pc.clone(), inst)?
+                }
                 Inst::Phi { .. } => unreachable!(),
                 Inst::Promote { .. } => self.p_promote(pc.clone(), bid, inst)?,
                 Inst::PtrAdd { .. } => self.p_ptradd(pc.clone(), inst)?,
@@ -964,6 +1170,44 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         }
     }
 
+    fn p_alloca(&mut self, iid: InstId, inst: &Inst) -> Result<(), CompilationError> {
+        let Inst::Alloca {
+            tyidx,
+            count,
+            align,
+        } = inst
+        else {
+            panic!()
+        };
+        // LLVM pads each element of an `alloca` to its allocation size.
+        let elem_bytew = match self.am.type_(*tyidx) {
+            Ty::Integer(x) => x.bytew().next_power_of_two(),
+            ty @ Ty::Ptr => ty.bytew(),
+            Ty::Float(FloatTy::Float) => 4,
+            Ty::Float(FloatTy::Double) => 8,
+            Ty::Struct(x) => u32::try_from(x.bit_size().div_ceil(8)).map_err(|_| {
+                CompilationError::LimitExceeded("alloca struct size doesn't fit in u32".into())
+            })?,
+            ty => unsupported!("alloca of type {}", ty.display(self.am)),
+        };
+        let bytew = u32::try_from(*count)
+            .ok()
+            .and_then(|x| x.checked_mul(elem_bytew))
+            .ok_or_else(|| {
+                CompilationError::LimitExceeded("alloca size doesn't fit in u32".into())
+            })?;
+        if bytew == 0 {
+            unsupported!("zero-sized alloca");
+        }
+        // Trace-local stack slots can be no more strictly aligned than the stack pointer itself.
+        let align = match u32::try_from(*align) {
+            Ok(x @ 1..=16) if x.is_power_of_two() => x,
+            _ => unsupported!("alloca with {align}-byte alignment"),
+        };
+        self.push_inst_and_link_local(iid, hir::Alloca { bytew, align })
+            .map(|_| ())
+    }
+
     fn p_binop(&mut self, iid: InstId, inst: &Inst) -> Result<(), CompilationError> {
         let Inst::BinaryOp { lhs, binop, rhs } = inst else {
             panic!()
@@ -1010,7 +1254,12 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 rhs,
             }
             .into(),
-            BinOp::FRem => todo!(),
+            BinOp::FRem => {
+                // x64 has no instruction for floating point remainder: LLVM itself lowers `frem`
+                // to a call to `fmod` / `fmodf`, so we do the same.
+                let tyidx = self.p_ty(inst.def_type(self.am).unwrap())?;
+                return self.p_libm_call(iid, tyidx, "fmod", "fmodf", smallvec![lhs, rhs]);
+            }
             BinOp::FSub => hir::FSub {
                 tyidx: self.p_ty(inst.def_type(self.am).unwrap())?,
                 lhs,
@@ -1137,6 +1386,11 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             return Ok(CallProcessedKind::Ignored);
         }
 
+        // Dependencies on watchpoints are only relevant while recording a trace.
+        if func.name() == "yk_watch_depend" {
+            return Ok(CallProcessedKind::Ignored);
+        }
+
         if func.name() == "yk_is_interpreting" {
             let i1_tyidx = self.opt.push_ty(hir::Ty::Int(1))?;
             let ciidx =
@@ -1145,25 +1399,24 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             return Ok(CallProcessedKind::Ignored);
         }
 
-        let mut jargs = SmallVec::with_capacity(args.len());
+        let mut vals = SmallVec::with_capacity(args.len());
         for x in args {
-            jargs.push(self.p_operand(x)?);
+            vals.push(self.p_val(x)?);
         }
 
         if func.is_idempotent() {
             let Ty::Func(fty) = self.am.type_(func.tyidx()) else {
                 panic!()
             };
-            if jargs
-                .iter()
-                .all(|x| matches!(self.opt.inst(*x), hir::Inst::Const(_)))
-            {
+            if vals.iter().all(|x| {
+                matches!(x, Val::Scalar(iidx) if matches!(self.opt.inst(*iidx), hir::Inst::Const(_)))
+            }) {
                 let const_iidx = self.promotion_data_to_const(self.am.type_(fty.ret_ty()))?;
                 self.frames.last_mut().unwrap().set_local(iid, const_iidx);
                 match self.outline_until(bid)? {
                     OutliningKind::SuccessorFound => return Ok(CallProcessedKind::Outlined),
                     OutliningKind::DidNotFindSuccessor => {
-                        panic!("Outlining in idempotent function failed")
+                        internal_error!("outlining in idempotent function failed")
                     }
                 }
             }
@@ -1184,8 +1437,9 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             // Inlinable call.
             self.frames.last_mut().unwrap().pc_statepoint = Some(statepoint.as_ref().unwrap());
             self.frames.push(Frame {
-                args: jargs,
+                args: vals,
                 locals: HashMap::new(),
+                aggs: HashMap::new(),
                 pc: Some(InstId::new(
                     callee,
                     BBlockIdx::new(0),
@@ -1193,6 +1447,7 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 )),
                 pc_statepoint: None,
                 prev_pc: None,
+                noalias: Vec::new(),
             });
             let next_ta = &self
                 .ta_iter
@@ -1210,11 +1465,21 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             //      (which could be zero, or many, and may include recursive calls) until that
             //      function returns.
 
-            let ftyidx = self.p_ty(self.am.type_(func.tyidx()))?;
-
             // Handle LLVM intrinsics.
             if func.name().starts_with("llvm.") {
-                self.p_llvm_intrinsic(iid, ftyidx, func.name(), jargs)?;
+                let mut jargs = SmallVec::with_capacity(vals.len());
+                for x in vals {
+                    match x {
+                        Val::Scalar(iidx) => jargs.push(iidx),
+                        Val::Agg(_) => unsupported!("aggregate argument to intrinsic"),
+                    }
+                }
+                if func.name().contains(".with.overflow.") {
+                    self.p_overflow_intrinsic(iid, func.name(), jargs)?;
+                } else {
+                    let ftyidx = self.p_ty(self.am.type_(func.tyidx()))?;
+                    self.p_llvm_intrinsic(iid, ftyidx, func.name(), jargs)?;
+                }
                 return Ok(CallProcessedKind::Outlined);
             }
 
@@ -1234,28 +1499,22 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             let tyidx = self.opt.push_ty(hir::Ty::Ptr(0))?;
             let tgt_iidx = self.const_to_iidx(tyidx, hir::ConstKind::Ptr(addr))?;
 
-            let effects = match func.memory() {
-                FuncMemory::None => hir::CallEffects::None,
-                FuncMemory::Read => hir::CallEffects::Read,
-                FuncMemory::Write => hir::CallEffects::Write,
-                FuncMemory::ReadWrite => hir::CallEffects::ReadWrite,
+            let effects = match (func.memory(), func.memory_loc()) {
+                _ if func.is_allocator() => hir::CallEffects::Alloc,
+                (FuncMemory::None, _) => hir::CallEffects::None,
+                (_, FuncMemoryLoc::InaccessibleMem) => hir::CallEffects::InaccessibleMem,
+                (FuncMemory::Read, FuncMemoryLoc::Any) => hir::CallEffects::Read,
+                (FuncMemory::Write, FuncMemoryLoc::Any) => hir::CallEffects::Write,
+                (FuncMemory::ReadWrite, FuncMemoryLoc::Any) => hir::CallEffects::ReadWrite,
+                (FuncMemory::Read, FuncMemoryLoc::ArgMem) => hir::CallEffects::ArgMemRead,
+                (FuncMemory::Write, FuncMemoryLoc::ArgMem) => hir::CallEffects::ArgMemWrite,
+                (FuncMemory::ReadWrite, FuncMemoryLoc::ArgMem) => hir::CallEffects::ArgMemReadWrite,
             };
 
-            let inst = hir::Call {
-                tgt: tgt_iidx,
-                func_tyidx: ftyidx,
-                args: jargs,
-                effects,
-            }
-            .into();
-            let hir::Ty::Func(box hir::FuncTy { rtn_tyidx, .. }) = self.opt.ty(ftyidx) else {
+            let Ty::Func(fty) = self.am.type_(func.tyidx()) else {
                 panic!()
             };
-            if *self.opt.ty(*rtn_tyidx) == hir::Ty::Void {
-                self.opt.feed_void(inst)?;
-            } else {
-                self.push_inst_and_link_local(iid.clone(), inst)?;
-            }
+            self.p_outlined_call(iid.clone(), tgt_iidx, fty, vals, effects)?;
             match self.outline_until(bid)? {
                 OutliningKind::SuccessorFound => Ok(CallProcessedKind::Outlined),
                 OutliningKind::DidNotFindSuccessor => {
@@ -1295,9 +1554,9 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         };
 
         let tgt_iidx = self.p_operand(callop)?;
-        let mut jargs = SmallVec::with_capacity(args.len());
+        let mut vals = SmallVec::with_capacity(args.len());
         for x in args {
-            jargs.push(self.p_operand(x)?);
+            vals.push(self.p_val(x)?);
         }
 
         // If we have a constant callee pointer, we may be able to inline the call.
@@ -1323,21 +1582,16 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             }
         }
 
-        let ftyidx = self.p_ty(self.am.type_(*ftyidx))?;
-        let inst = hir::Call {
-            tgt: tgt_iidx,
-            func_tyidx: ftyidx,
-            args: jargs,
-            effects: hir::CallEffects::ReadWrite,
-        };
-        let hir::Ty::Func(box hir::FuncTy { rtn_tyidx, .. }) = self.opt.ty(ftyidx) else {
+        let Ty::Func(fty) = self.am.type_(*ftyidx) else {
             panic!()
         };
-        if *self.opt.ty(*rtn_tyidx) == hir::Ty::Void {
-            self.opt.feed_void(inst.into())?;
-        } else {
-            self.push_inst_and_link_local(iid.clone(), inst)?;
-        }
+        self.p_outlined_call(
+            iid.clone(),
+            tgt_iidx,
+            fty,
+            vals,
+            hir::CallEffects::ReadWrite,
+        )?;
         match self.outline_until(bid)? {
             OutliningKind::SuccessorFound => Ok(CallProcessedKind::Outlined),
             OutliningKind::DidNotFindSuccessor => {
@@ -1351,6 +1605,95 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         }
     }
 
+    /// Emit an outlined call to `tgt`, of type `fty`, with arguments `vals`, linking any return
+    /// value to `iid`.
+    ///
+    /// HIR has no aggregate types, so aggregate arguments and return values are scalarised in a
+    /// way that is compatible with the SysV x64 ABI:
+    ///   * An aggregate argument whose fields each occupy exactly one eightbyte (e.g. `{i64,
+    ///     double}`) is passed in the same registers as if its fields were passed as separate
+    ///     arguments.
+    ///   * An aggregate return value of no more than one eightbyte, all of whose fields are
+    ///     integers or pointers, is returned packed into a single integer register.
+    ///
+    /// Other aggregates are not currently supported.
+    fn p_outlined_call(
+        &mut self,
+        iid: InstId,
+        tgt: hir::InstIdx,
+        fty: &FuncTy,
+        vals: SmallVec<[Val; 1]>,
+        effects: hir::CallEffects,
+    ) -> Result<(), CompilationError> {
+        let mut args = SmallVec::with_capacity(vals.len());
+        let mut args_tyidxs = SmallVec::with_capacity(fty.arg_tyidxs().len());
+        for (i, val) in vals.into_iter().enumerate() {
+            // Variadic arguments have no formal type.
+            let ty = fty.arg_tyidxs().get(i).map(|x| self.am.type_(*x));
+            match val {
+                Val::Scalar(iidx) => {
+                    if let Some(ty) = ty {
+                        args_tyidxs.push(self.p_ty(ty)?);
+                    }
+                    args.push(iidx);
+                }
+                Val::Agg(fields) => {
+                    let Some(ty) = ty else {
+                        unsupported!("aggregate passed as a variadic argument")
+                    };
+                    let field_tys = self.agg_fields(ty)?;
+                    if field_tys.len() > 2
+                        || field_tys.iter().enumerate().any(|(i, (field_ty, off))| {
+                            *off != i * 8
+                                || !(matches!(field_ty, Ty::Ptr | Ty::Float(FloatTy::Double))
+                                    || matches!(field_ty, Ty::Integer(x) if x.bitw() == 64))
+                        })
+                    {
+                        unsupported!(
+                            "outlined call with aggregate argument {}",
+                            ty.display(self.am)
+                        )
+                    }
+                    for ((field_ty, _), iidx) in field_tys.into_iter().zip(fields) {
+                        args_tyidxs.push(self.p_ty(field_ty)?);
+                        args.push(iidx);
+                    }
+                }
+            }
+        }
+
+        let rtn_ty = self.am.type_(fty.ret_ty());
+        if let Ty::Struct(_) = rtn_ty {
+            // LLVM returns each field of an aggregate in its own register (e.g. `{i32, i32}` in
+            // EAX and EDX) rather than packing them as the C ABI would, and our calls can only
+            // return a single value.
+            unsupported!(
+                "outlined call returning aggregate {}",
+                rtn_ty.display(self.am)
+            )
+        }
+        let rtn_tyidx = self.p_ty(rtn_ty)?;
+        let func_tyidx = self.opt.push_ty(hir::Ty::Func(Box::new(hir::FuncTy {
+            rtn_tyidx,
+            args_tyidxs,
+            has_varargs: fty.is_vararg(),
+        })))?;
+
+        let inst = hir::Call {
+            tgt,
+            func_tyidx,
+            args,
+            effects,
+        }
+        .into();
+        if *self.opt.ty(rtn_tyidx) == hir::Ty::Void {
+            self.opt.feed_void(inst)?;
+        } else {
+            self.push_inst_and_link_local(iid, inst)?;
+        }
+        Ok(())
+    }
+
     /// Outline until the successor block to `bid` is encountered. Returns `Err` if irregular
     /// control flow is detected.
     fn outline_until(&mut self, cur_bid: BBlockId) -> Result<OutliningKind, CompilationError> {
@@ -1364,9 +1707,9 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             Inst::CondBr { .. } => {
                 // Currently, the successor of a call is always an unconditional branch due to
                 // the block spitting pass. However, there's a FIXME in that pass which could
-                // lead to conditional branches showing up here. Leave a todo here so we know
-                // when this happens.
-                todo!()
+                // lead to conditional branches showing up here. Report an internal error so we
+                // know when this happens.
+                internal_error!("call followed by a conditional branch")
             }
             _ => panic!(),
         };
@@ -1476,6 +1819,25 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
             "assume" => Ok(()),
+            "ceil" => {
+                let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
+                let fty = self.opt.func_ty(ftyidx);
+                let hinst = hir::Ceil {
+                    tyidx: fty.rtn_tyidx,
+                    val: src,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
+            "copysign" => {
+                let [mag, sign]: [hir::InstIdx; 2] = jargs.into_vec().try_into().unwrap();
+                let fty = self.opt.func_ty(ftyidx);
+                let hinst = hir::CopySign {
+                    tyidx: fty.rtn_tyidx,
+                    mag,
+                    sign,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
             "ctpop" => {
                 let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
                 let fty = self.opt.func_ty(ftyidx);
@@ -1498,6 +1860,15 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
+            "fabs" => {
+                let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
+                let fty = self.opt.func_ty(ftyidx);
+                let hinst = hir::FAbs {
+                    tyidx: fty.rtn_tyidx,
+                    val: src,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
             "floor" => {
                 let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
                 let fty = self.opt.func_ty(ftyidx);
@@ -1507,6 +1878,23 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
+            "fma" => {
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                self.p_libm_call(iid, tyidx, "fma", "fmaf", jargs)
+            }
+            "fmuladd" => {
+                // Unlike `fma`, `fmuladd` need not be fused, so we can avoid the cost of a libm
+                // call.
+                let [lhs, rhs, addend]: [hir::InstIdx; 3] = jargs.into_vec().try_into().unwrap();
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                let mul = self.opt.feed(hir::FMul { tyidx, lhs, rhs }.into())?;
+                let hinst = hir::FAdd {
+                    tyidx,
+                    lhs: mul,
+                    rhs: addend,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
             "is" if parts[2] == "fpclass" => {
                 let [val, test]: [hir::InstIdx; 2] = jargs.into_vec().try_into().unwrap();
                 let test = if let hir::Inst::Const(hir::Const {
@@ -1528,6 +1916,10 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
             "lifetime" => Ok(()),
+            "maxnum" => {
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                self.p_libm_call(iid, tyidx, "fmax", "fmaxf", jargs)
+            }
             "memcpy" => {
                 let [dst, src, len, volatile]: [hir::InstIdx; 4] =
                     jargs.into_vec().try_into().unwrap();
@@ -1570,6 +1962,18 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 self.opt.feed_void(hinst.into()).map(|_| ())
             }
+            "minnum" => {
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                self.p_libm_call(iid, tyidx, "fmin", "fminf", jargs)
+            }
+            "pow" => {
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                self.p_libm_call(iid, tyidx, "pow", "powf", jargs)
+            }
+            "round" => {
+                let tyidx = self.opt.func_ty(ftyidx).rtn_tyidx;
+                self.p_libm_call(iid, tyidx, "round", "roundf", jargs)
+            }
             "smax" => {
                 let [lhs, rhs]: [hir::InstIdx; 2] = jargs.into_vec().try_into().unwrap();
                 let fty = self.opt.func_ty(ftyidx);
@@ -1590,6 +1994,24 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
+            "sqrt" => {
+                let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
+                let fty = self.opt.func_ty(ftyidx);
+                let hinst = hir::Sqrt {
+                    tyidx: fty.rtn_tyidx,
+                    val: src,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
+            "trunc" => {
+                let [src]: [hir::InstIdx; 1] = jargs.into_vec().try_into().unwrap();
+                let fty = self.opt.func_ty(ftyidx);
+                let hinst = hir::FTrunc {
+                    tyidx: fty.rtn_tyidx,
+                    val: src,
+                };
+                self.push_inst_and_link_local(iid, hinst).map(|_| ())
+            }
             "umax" => {
                 let [lhs, rhs]: [hir::InstIdx; 2] = jargs.into_vec().try_into().unwrap();
                 let fty = self.opt.func_ty(ftyidx);
@@ -1610,10 +2032,116 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
                 };
                 self.push_inst_and_link_local(iid, hinst).map(|_| ())
             }
-            n => todo!("{name} ('{n}')"),
+            _ => unsupported!(
+                "intrinsic '{name}' in AOT instruction '{}'",
+                self.am.inst(&iid).display(self.am, Some(iid.clone()))
+            ),
         }
     }
 
+    /// Emit a call to the libm function `double_name` (for `double`s) or `float_name` (for
+    /// `float`s), whose arguments and return value are all of type `tyidx`, linking its return
+    /// value to `iid`. This is used for floating point operations that x64 has no single
+    /// instruction for.
+    ///
+    /// LLVM assumes that floating point intrinsics (and `frem`) do not set `errno` so, even though
+    /// the libm functions may do so, we treat these calls as having no side effects.
+    fn p_libm_call(
+        &mut self,
+        iid: InstId,
+        tyidx: hir::TyIdx,
+        double_name: &str,
+        float_name: &str,
+        args: SmallVec<[hir::InstIdx; 1]>,
+    ) -> Result<(), CompilationError> {
+        let fname = match self.opt.ty(tyidx) {
+            hir::Ty::Double => double_name,
+            hir::Ty::Float => float_name,
+            x => unsupported!("libm call '{double_name}' with type {x:?}"),
+        };
+        let Some(addr) = self.j2.dlsym(fname, false) else {
+            unsupported!("libm function '{fname}' not found")
+        };
+        let addr = addr.0.addr();
+        self.addr_name_map
+            .as_mut()
+            .map(|x| x.insert(addr, Some(fname.to_owned())));
+        let ptr_tyidx = self.opt.push_ty(hir::Ty::Ptr(0))?;
+        let tgt = self.const_to_iidx(ptr_tyidx, hir::ConstKind::Ptr(addr))?;
+        let func_tyidx = self.opt.push_ty(hir::Ty::Func(Box::new(hir::FuncTy {
+            args_tyidxs: smallvec![tyidx; args.len()],
+            has_varargs: false,
+            rtn_tyidx: tyidx,
+        })))?;
+        self.push_inst_and_link_local(
+            iid,
+            hir::Call {
+                tgt,
+                func_tyidx,
+                effects: hir::CallEffects::None,
+                args,
+            },
+        )
+        .map(|_| ())
+    }
+
+    /// Process a `llvm.{s,u}{add,sub,mul}.with.overflow.*` intrinsic, scalarising its `{iN, i1}`
+    /// result into the arithmetic result and an overflow bit.
+    fn p_overflow_intrinsic(
+        &mut self,
+        iid: InstId,
+        name: &str,
+        jargs: SmallVec<[hir::InstIdx; 1]>,
+    ) -> Result<(), CompilationError> {
+        let [lhs, rhs]: [hir::InstIdx; 2] = jargs.into_vec().try_into().unwrap();
+        let tyidx = self.opt.inst(lhs).tyidx(&*self.opt);
+        let op = match name.split(".").nth(1).unwrap() {
+            "sadd" => hir::OverflowOp::SAdd,
+            "ssub" => hir::OverflowOp::SSub,
+            "smul" => hir::OverflowOp::SMul,
+            "uadd" => hir::OverflowOp::UAdd,
+            "usub" => hir::OverflowOp::USub,
+            "umul" => hir::OverflowOp::UMul,
+            _ => unsupported!(
+                "intrinsic '{name}' in AOT instruction '{}'",
+                self.am.inst(&iid).display(self.am, Some(iid.clone()))
+            ),
+        };
+        let res: hir::Inst = match op {
+            hir::OverflowOp::SAdd | hir::OverflowOp::UAdd => hir::Add {
+                tyidx,
+                lhs,
+                rhs,
+                nuw: false,
+                nsw: false,
+            }
+            .into(),
+            hir::OverflowOp::SSub | hir::OverflowOp::USub => hir::Sub {
+                tyidx,
+                lhs,
+                rhs,
+                nuw: false,
+                nsw: false,
+            }
+            .into(),
+            hir::OverflowOp::SMul | hir::OverflowOp::UMul => hir::Mul {
+                tyidx,
+                lhs,
+                rhs,
+                nuw: false,
+                nsw: false,
+            }
+            .into(),
+        };
+        let res = self.opt.feed(res)?;
+        let ovf = self.opt.feed(hir::Overflow { op, lhs, rhs }.into())?;
+        self.frames
+            .last_mut()
+            .unwrap()
+            .set_agg(iid, smallvec![res, ovf]);
+        Ok(())
+    }
+
     fn p_cast(&mut self, iid: InstId, inst: &Inst) -> Result<(), CompilationError> {
         let Inst::Cast {
             cast_kind,
@@ -1828,56 +2356,79 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         let Inst::ExtractValue { tyidx, op, indices } = inst else {
             panic!()
         };
-
-        let Ty::Struct(struct_ty) = op.type_(self.am) else {
-            panic!()
-        };
-        let Inst::Load { volatile, .. } = op.to_inst(self.am) else {
-            todo!()
+        let [idx] = indices.as_slice() else {
+            unsupported!(
+                "AOT instruction '{}' (nested indices)",
+                inst.display(self.am, Some(iid))
+            )
         };
 
-        assert_eq!(indices.len(), 1, "extractvalue with nested indices");
-        let field_bit_off = struct_ty.field_bit_offs()[indices[0]];
-        // LLVM struct fields are always byte-aligned.
-        assert_eq!(field_bit_off % 8, 0);
-        let byte_off = field_bit_off / 8;
-
-        let mut ptr = self.p_operand(op)?;
-        assert_eq!(
-            *self.opt.ty(self.opt.inst(ptr).tyidx(&*self.opt)),
-            hir::Ty::Ptr(0)
-        );
-        if byte_off > 0 {
-            ptr = self.opt.feed(
-                hir::PtrAdd {
-                    ptr,
-                    off: i32::try_from(byte_off).unwrap(),
-                    in_bounds: false,
-                    nusw: false,
-                    nuw: false,
-                }
-                .into(),
-            )?;
+        // An aggregate loaded from memory is represented by a pointer to that memory (see
+        // `p_load`): rather than loading the entire aggregate, we load only the field we need.
+        if let Operand::Local(op_iid) = op
+            && let Inst::Load { volatile, .. } = self.am.inst(op_iid)
+        {
+            let Ty::Struct(struct_ty) = op.type_(self.am) else {
+                panic!()
+            };
+            let field_bit_off = struct_ty.field_bit_offs()[*idx];
+            // LLVM struct fields are always byte-aligned.
+            assert_eq!(field_bit_off % 8, 0);
+            let ptr = self.p_operand(op)?;
+            assert_eq!(
+                *self.opt.ty(self.opt.inst(ptr).tyidx(&*self.opt)),
+                hir::Ty::Ptr(0)
+            );
+            let iidx =
+                self.load_agg_field(ptr, field_bit_off / 8, self.am.type_(*tyidx), *volatile)?;
+            self.frames.last_mut().unwrap().set_local(iid, iidx);
+        } else {
+            let fields = self.p_agg(op)?;
+            self.frames.last_mut().unwrap().set_local(iid, fields[*idx]);
         }
+        Ok(())
+    }
 
-        let res_tyidx = self.p_ty(self.am.type_(*tyidx))?;
-        self.push_inst_and_link_local(
-            iid,
-            hir::Load {
-                tyidx: res_tyidx,
-                ptr,
-                is_volatile: *volatile,
-            },
-        )
-        .map(|_| ())
+    fn p_insertvalue(&mut self, iid: InstId, inst: &Inst) -> Result<(), CompilationError> {
+        let Inst::InsertValue { agg, elem } = inst else {
+            panic!()
+        };
+        // The AOT IR doesn't record which field `elem` is inserted into, so we can only handle
+        // aggregates with exactly one field of `elem`'s type.
+        let elem_ty = elem.type_(self.am);
+        let mut idxs = self
+            .agg_fields(agg.type_(self.am))?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (fty, _))| *fty == elem_ty)
+            .map(|(i, _)| i);
+        let (Some(idx), None) = (idxs.next(), idxs.next()) else {
+            unsupported!(
+                "AOT instruction '{}' (ambiguous field)",
+                inst.display(self.am, Some(iid))
+            )
+        };
+        let mut fields = self.p_agg(agg)?;
+        fields[idx] = self.p_operand(elem)?;
+        self.frames.last_mut().unwrap().set_agg(iid, fields);
+        Ok(())
     }
 
     fn p_loadarg(&mut self, iid: InstId, inst: &Inst) -> Result<(), CompilationError> {
-        let Inst::LoadArg { arg_idx, ty_idx: _ } = inst else {
+        let (Inst::LoadArg { arg_idx, ty_idx: _ } | Inst::LoadNoAliasArg { arg_idx, ty_idx: _ }) =
+            inst
+        else {
             panic!()
         };
-        let iidx = self.frames.last().unwrap().args[*arg_idx];
-        self.frames.last_mut().unwrap().set_local(iid, iidx);
+        let val = self.frames.last().unwrap().args[*arg_idx].clone();
+        if let (Inst::LoadNoAliasArg { ty_idx, .. }, Val::Scalar(ptr)) = (inst, &val) {
+            // The `noalias` scope lasts until this frame returns: see [Self::p_return].
+            let tyidx = self.p_ty(self.am.type_(*ty_idx))?;
+            let iidx = self.push_inst_and_link_local(iid, hir::NoAlias { tyidx, ptr: *ptr })?;
+            self.frames.last_mut().unwrap().noalias.push(iidx);
+        } else {
+            self.frames.last_mut().unwrap().set_val(iid, val);
+        }
         Ok(())
     }
 
@@ -1897,8 +2448,8 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             panic!()
         };
         let v = &incoming_vals[incoming_bbs.iter().position(|x| *x == prev_bidx).unwrap()];
-        let op = self.p_operand(v)?;
-        self.frames.last_mut().unwrap().set_local(iid, op);
+        let val = self.p_val(v)?;
+        self.frames.last_mut().unwrap().set_val(iid, val);
         Ok(())
     }
 
@@ -1999,15 +2550,18 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         let Inst::Ret { val } = inst else { panic!() };
 
         let val = match val {
-            Some(x) => Some(self.p_operand(x)?),
+            Some(x) => Some(self.p_val(x)?),
             None => None,
         };
 
         let frame = self.frames.pop().unwrap();
+        for ptr in frame.noalias.iter().rev() {
+            self.opt.feed_void(hir::NoAliasEnd { ptr: *ptr }.into())?;
+        }
         if !self.frames.is_empty() {
-            if let Some(val_iidx) = val {
+            if let Some(val) = val {
                 let frame = self.frames.last_mut().unwrap();
-                frame.set_local(frame.pc.clone().unwrap(), val_iidx);
+                frame.set_val(frame.pc.clone().unwrap(), val);
             }
             Ok(None)
         } else {
@@ -2015,13 +2569,12 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             // remaining blocks and emit a return instruction that naturally returns from a
             // compiled trace into the interpreter.
             let statepoint = frame.pc_statepoint.unwrap();
-            self.opt.feed_void(
-                hir::Term(match val {
-                    Some(x) => vec![x],
-                    None => vec![],
-                })
-                .into(),
-            )?;
+            let term_vars = match val {
+                Some(Val::Scalar(x)) => vec![x],
+                Some(Val::Agg(_)) => unsupported!("aggregate returned from the outermost frame"),
+                None => vec![],
+            };
+            self.opt.feed_void(hir::Term(term_vars).into())?;
             Ok(Some(statepoint))
         }
     }
@@ -2035,7 +2588,36 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
         else {
             panic!()
         };
-        let tyidx = self.p_ty(inst.def_type(self.am).unwrap())?;
+        let ty = inst.def_type(self.am).unwrap();
+        if let Ty::Struct(_) = ty {
+            // Select each field of the aggregate individually.
+            let cond = self.p_operand(cond)?;
+            let truevs = self.p_agg(trueval)?;
+            let falsevs = self.p_agg(falseval)?;
+            let mut fields = AggFields::with_capacity(truevs.len());
+            for ((fty, _), (truev, falsev)) in self
+                .agg_fields(ty)?
+                .into_iter()
+                .zip(truevs.into_iter().zip(falsevs))
+            {
+                let tyidx = self.p_ty(fty)?;
+                fields.push(
+                    self.opt.feed(
+                        hir::Select {
+                            tyidx,
+                            cond,
+                            truev,
+                            falsev,
+                        }
+                        .into(),
+                    )?,
+                );
+            }
+            self.frames.last_mut().unwrap().set_agg(iid, fields);
+            return Ok(());
+        }
+
+        let tyidx = self.p_ty(ty)?;
         let cond = self.p_operand(cond)?;
         let truev = self.p_operand(trueval)?;
         let falsev = self.p_operand(falseval)?;
@@ -2056,6 +2638,35 @@ impl<'a, Reg: RegT + 'static> AotToHir<'a, Reg> {
             panic!()
         };
         let ptr = self.p_operand(tgt)?;
+        if let ty @ Ty::Struct(_) = val.type_(self.am) {
+            // Store each field of the aggregate individually.
+            let vals = self.p_agg(val)?;
+            for ((_, off), val) in self.agg_fields(ty)?.into_iter().zip(vals) {
+                let ptr = if off > 0 {
+                    self.opt.feed(
+                        hir::PtrAdd {
+                            ptr,
+                            off: i32::try_from(off).unwrap(),
+                            in_bounds: false,
+                            nusw: false,
+                            nuw: false,
+                        }
+                        .into(),
+                    )?
+                } else {
+                    ptr
+                };
+                self.opt.feed_void(
+                    hir::Store {
+                        ptr,
+                        val,
+                        is_volatile: *volatile,
+                    }
+                    .into(),
+                )?;
+            }
+            return Ok(());
+        }
         let val = self.p_operand(val)?;
         self.opt
             .feed_void(
@@ -2217,10 +2828,12 @@ pub(super) enum BuildKind {
 /// [hir::ModKind], while keeping the latter enum simple.
 enum BuildModKind<Reg: RegT> {
     Coupler {
+        entry_pc: InstId,
         entry_statepoint: &'static Statepoint,
         tgt_ctr: Arc<J2CompiledTrace<Reg>>,
     },
     Loop {
+        entry_pc: InstId,
         entry_statepoint: &'static Statepoint,
     },
     Side {
@@ -2233,17 +2846,33 @@ enum BuildModKind<Reg: RegT> {
     },
 }
 
+/// The HIR values of a scalarised aggregate's fields, in field order.
+type AggFields = SmallVec<[hir::InstIdx; 2]>;
+
+/// A translated AOT value. HIR has no aggregate types, so aggregates are scalarised into their
+/// (top-level) fields.
+#[derive(Clone, Debug)]
+enum Val {
+    Scalar(hir::InstIdx),
+    Agg(AggFields),
+}
+
 /// An inlined frame.
 #[derive(Debug)]
 struct Frame {
     /// This frame's arguments. This is not mutated after frame creation.
-    args: SmallVec<[hir::InstIdx; 1]>,
+    args: SmallVec<[Val; 1]>,
     locals: HashMap<InstId, hir::InstIdx>,
+    /// Scalarised aggregate locals. An AOT variable is in at most one of `locals` and `aggs`.
+    aggs: HashMap<InstId, AggFields>,
     pc: Option<InstId>,
     /// The current statepoint for this frame. This has no initial value at frame entry, and is
     /// updated at every call site.
     pc_statepoint: Option<&'static Statepoint>,
     prev_pc: Option<InstId>,
+    /// The [hir::NoAlias] instructions for this frame's `noalias` arguments: their scopes end
+    /// when this frame returns.
+    noalias: Vec<hir::InstIdx>,
 }
 
 impl Frame {
@@ -2257,6 +2886,28 @@ impl Frame {
     fn set_local(&mut self, iid: InstId, iidx: hir::InstIdx) {
         self.locals.insert(iid, iidx);
     }
+
+    /// Lookup the scalarised aggregate AOT variable `iid` relative to `opt`. Returns `None` if
+    /// `iid` is not a scalarised aggregate.
+    fn get_agg(&self, opt: &dyn OptT, iid: &InstId) -> Option<AggFields> {
+        self.aggs
+            .get(iid)
+            .map(|x| x.iter().map(|iidx| opt.equiv_iidx(*iidx)).collect())
+    }
+
+    /// Set the aggregate AOT variable `iid` mapping to the HIR fields `fields`. This is allowed to
+    /// override previous bindings (which occur due to unrolling).
+    fn set_agg(&mut self, iid: InstId, fields: AggFields) {
+        self.aggs.insert(iid, fields);
+    }
+
+    /// Set the AOT variable `iid` to `val`.
+    fn set_val(&mut self, iid: InstId, val: Val) {
+        match val {
+            Val::Scalar(iidx) => self.set_local(iid, iidx),
+            Val::Agg(fields) => self.set_agg(iid, fields),
+        }
+    }
 }
 
 /// Consume `N` bytes from `iter` and return an array of those bytes (in order).
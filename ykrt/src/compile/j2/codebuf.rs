@@ -7,11 +7,11 @@
 //! the quantity of memory it contains will not change.
 
 use crate::compile::j2::SyncSafePtr;
-use libc::{__errno_location, PROT_EXEC, PROT_READ, PROT_WRITE, mprotect, munmap};
 #[cfg(test)]
 use libc::{MAP_ANON, MAP_FAILED, MAP_PRIVATE, mmap};
+use libc::{PROT_EXEC, PROT_READ, PROT_WRITE, mprotect, munmap};
 use parking_lot::Mutex;
-use std::ffi::c_void;
+use std::{ffi::c_void, io};
 
 /// A code buffer that does backing memory allocated but no actual code stored in it.
 #[derive(Debug)]
@@ -42,7 +42,7 @@ impl CodeBufInProgress {
             )
         };
         if buf == MAP_FAILED {
-            todo!();
+            panic!("mmap failed: {}", io::Error::last_os_error());
         }
 
         Self {
@@ -69,7 +69,10 @@ impl CodeBufInProgress {
         if unused > 0 {
             let rtn = unsafe { munmap(self.buf as *mut c_void, unused) };
             if rtn != 0 {
-                todo!();
+                panic!(
+                    "munmap of unused code buffer space failed: {}",
+                    io::Error::last_os_error()
+                );
             }
             self.buf = unsafe { self.buf.byte_add(unused) };
             self.len -= unused;
@@ -77,7 +80,10 @@ impl CodeBufInProgress {
 
         // Remove write permissions.
         if unsafe { mprotect(self.buf as *mut c_void, self.len, PROT_EXEC | PROT_READ) } == -1 {
-            todo!();
+            panic!(
+                "making code buffer executable failed: {}",
+                io::Error::last_os_error()
+            );
         }
 
         ExeCodeBuf {
@@ -120,11 +126,17 @@ impl ExeCodeBuf {
     }
 
     /// Return the size of this buffer in bytes.
-    #[allow(unused)]
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Read the (possibly unaligned) `u64` at `off` bytes from the start of the used part of the
+    /// buffer.
+    pub fn read_u64(&self, off: usize) -> u64 {
+        let ptr = unsafe { self.buf.0.byte_add(self.start_off + off) };
+        unsafe { (ptr as *const u64).read_unaligned() }
+    }
+
     /// Patch part of the executable code. The address `patch_off...patch_off + len` bytes from the
     /// start of the buffer will be temporarily marked writable, at which point `f` will be called
     /// with the concrete address starting at `patch_off`. When `f` has completed, the executable
@@ -149,11 +161,28 @@ impl ExeCodeBuf {
             )
         } == -1
         {
-            todo!("{}", unsafe { *__errno_location() });
+            panic!(
+                "making code buffer writable for patching failed: {}",
+                io::Error::last_os_error()
+            );
         }
         f(patch_ptr);
         if unsafe { mprotect(page_ptr as *mut c_void, len, PROT_EXEC | PROT_READ) } == -1 {
-            todo!("{}", unsafe { *__errno_location() });
+            panic!(
+                "making patched code buffer executable failed: {}",
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+impl Drop for ExeCodeBuf {
+    fn drop(&mut self) {
+        if unsafe { munmap(self.buf.0 as *mut c_void, self.len) } != 0 {
+            panic!(
+                "munmap of code buffer failed: {}",
+                io::Error::last_os_error()
+            );
         }
     }
 }
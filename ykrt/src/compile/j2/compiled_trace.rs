@@ -19,7 +19,10 @@ use std::{
     any::Any,
     assert_matches,
     ffi::c_void,
-    sync::{Arc, Weak},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicI64, Ordering},
+    },
 };
 
 #[derive(Debug)]
@@ -30,6 +33,14 @@ pub(super) struct J2CompiledTrace<Reg: RegT> {
     codebuf: ExeCodeBuf,
     pub guards: TypedVec<CompiledGuardIdx, J2CompiledGuard<Reg>>,
     pub trace_start: J2TraceStart<Reg>,
+    /// If this is a coupler trace, the [TraceId] of the trace it jumps to.
+    coupler: Option<TraceId>,
+    /// If this is a loop trace, where its back-edge is, and where that back-edge should jump to
+    /// when the trace is invalidated.
+    loop_exit: Option<LoopExit>,
+    /// If this is a baseline trace, how many more executions (including loop iterations) it needs
+    /// before it is recompiled (see [CompiledTrace::count_execution]).
+    tier_up: Option<Box<AtomicI64>>,
     /// The name used for this trace as linker symbol.
     symbol_name: String,
 }
@@ -40,9 +51,22 @@ impl<Reg: RegT> J2CompiledTrace<Reg> {
         m: &Mod<Reg>,
         hl: Weak<Mutex<HotLocation>>,
         codebuf: ExeCodeBuf,
-        guards: TypedVec<CompiledGuardIdx, J2CompiledGuard<Reg>>,
+        mut guards: TypedVec<CompiledGuardIdx, J2CompiledGuard<Reg>>,
         trace_start: J2TraceStart<Reg>,
+        loop_exit: Option<LoopExit>,
+        tier_up: Option<Box<AtomicI64>>,
     ) -> Self {
+        // Record where each patchable jump initially points to, so that it can be unpatched.
+        #[cfg(target_arch = "x86_64")]
+        for g in guards.iter_mut() {
+            g.unpatch_tgts = g
+                .patch_offs
+                .iter()
+                // Skip the `mov r64` prefix and opcode to read the `imm64`.
+                .map(|x| codebuf.read_u64(usize::try_from(*x).unwrap() + 2))
+                .collect();
+        }
+
         // Extract source trace ID for guard traces.
         let src_ctr = match &m.trace_start {
             TraceStart::Guard { src_ctr, .. } => Some(src_ctr.trid),
@@ -65,6 +89,9 @@ impl<Reg: RegT> J2CompiledTrace<Reg> {
             codebuf,
             guards,
             trace_start,
+            coupler: tgt_ctr,
+            loop_exit,
+            tier_up,
             symbol_name,
         }
     }
@@ -107,6 +134,38 @@ impl<Reg: RegT> J2CompiledTrace<Reg> {
         }
     }
 
+    /// Overwrite the `imm64` of the `mov r64, imm64` at `patch_off` with `tgt`.
+    #[cfg(target_arch = "x86_64")]
+    fn patch_imm64(&self, patch_off: u32, tgt: u64) {
+        let patch_off = usize::try_from(patch_off).unwrap();
+        self.codebuf.patch(patch_off, 10, |patch_addr| {
+            // We can only patch `mov r64, imm64`.
+            assert_matches!(unsafe { patch_addr.read() }, 0x48 | 0x49);
+            let patch_addr = unsafe { patch_addr.byte_add(2) };
+            unsafe {
+                (patch_addr as *mut u64).write(tgt);
+            }
+        });
+    }
+
+    /// Overwrite the `rel32` of the `jmp rel32` at `patch_off` so that it jumps to `tgt_off`.
+    #[cfg(target_arch = "x86_64")]
+    fn patch_rel32(&self, patch_off: u32, tgt_off: u32) {
+        // The `rel32` is relative to the end of the 5 byte `jmp`.
+        let rel32 = i32::try_from(i64::from(tgt_off) - i64::from(patch_off) - 5).unwrap();
+        let patch_off = usize::try_from(patch_off).unwrap();
+        self.codebuf.patch(patch_off, 5, |patch_addr| {
+            // We can only patch `jmp rel32`.
+            assert_eq!(unsafe { patch_addr.read() }, 0xE9);
+            let patch_addr = unsafe { patch_addr.byte_add(1) };
+            // The backend aligns the `rel32` so that this write is atomic.
+            assert!(patch_addr.addr().is_multiple_of(4));
+            unsafe {
+                (patch_addr as *mut i32).write(rel32);
+            }
+        });
+    }
+
     pub(super) fn bid(&self, gidx: CompiledGuardIdx) -> aot_ir::BBlockId {
         self.guards[gidx].bid()
     }
@@ -168,6 +227,11 @@ impl<Reg: RegT + 'static> CompiledTrace for J2CompiledTrace<Reg> {
         self
     }
 
+    fn invalidate(self: Arc<Self>) {
+        let mt = Arc::clone(&self.mt);
+        mt.invalidate_trace(self);
+    }
+
     fn guard(&self, gid: GuardId) -> &Guard {
         let gidx = CompiledGuardIdx::from_raw_index(usize::from(gid));
         self.guards[gidx].guard()
@@ -178,19 +242,46 @@ impl<Reg: RegT + 'static> CompiledTrace for J2CompiledTrace<Reg> {
     #[cfg(target_arch = "x86_64")]
     fn patch_guard(&self, gid: GuardId, tgt: *const std::ffi::c_void) {
         let gidx = CompiledGuardIdx::from_raw_index(usize::from(gid));
+        let tgt = u64::try_from(tgt.addr()).unwrap();
         for patch_off in &self.guards[gidx].patch_offs {
-            let patch_off = usize::try_from(*patch_off).unwrap();
-            self.codebuf.patch(patch_off, 10, |patch_addr| {
-                // We can only patch `mov r64, imm64`.
-                assert_matches!(unsafe { patch_addr.read() }, 0x48 | 0x49);
-                let patch_addr = unsafe { patch_addr.byte_add(2) };
-                unsafe {
-                    (patch_addr as *mut u64).write(u64::try_from(tgt.addr()).unwrap());
-                }
-            });
+            self.patch_imm64(*patch_off, tgt);
         }
     }
 
+    #[cfg(target_arch = "x86_64")]
+    fn unpatch_guard(&self, gid: GuardId) {
+        let gidx = CompiledGuardIdx::from_raw_index(usize::from(gid));
+        let g = &self.guards[gidx];
+        for (patch_off, tgt) in g.patch_offs.iter().zip(g.unpatch_tgts.iter()) {
+            self.patch_imm64(*patch_off, *tgt);
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn exit_loop(&self) {
+        if let Some(LoopExit {
+            backedge_off,
+            exit_off,
+        }) = self.loop_exit
+        {
+            self.patch_rel32(backedge_off, exit_off);
+        }
+    }
+
+    fn count_execution(&self) -> bool {
+        self.tier_up
+            .as_ref()
+            .is_some_and(|x| x.fetch_sub(1, Ordering::Relaxed) <= 1)
+    }
+
+    fn num_guards(&self) -> usize {
+        self.guards.len_usize()
+    }
+
+    fn coupler(&self) -> Option<TraceId> {
+        self.coupler
+    }
+
     fn entry(&self) -> *const c_void {
         self.codebuf.entry_ptr() as *const c_void
     }
@@ -208,6 +299,15 @@ impl<Reg: RegT + 'static> CompiledTrace for J2CompiledTrace<Reg> {
     }
 }
 
+/// The offsets in a loop trace's code needed by [CompiledTrace::exit_loop].
+#[derive(Debug)]
+pub(super) struct LoopExit {
+    /// The offset of the loop's back-edge.
+    pub backedge_off: u32,
+    /// The offset of the guard body which deopts to the control point at the start of the loop.
+    pub exit_off: u32,
+}
+
 /// Where did this J2 compiled trace start?
 #[derive(Debug)]
 pub(super) enum J2TraceStart<Reg: RegT> {
@@ -260,6 +360,9 @@ pub(super) struct J2CompiledGuard<Reg: RegT> {
     /// All of the instruction offsets in the associated machine code which will need to be patched
     /// when a sidetrace is compiled.
     patch_offs: SmallVec<[u32; 2]>,
+    /// The original targets of each entry in [Self::patch_offs], which are restored when a
+    /// sidetrace is retired.
+    unpatch_tgts: SmallVec<[u64; 2]>,
     /// How much additional space will this guard have consumed relative to the main part of the
     /// trace it was part of?
     pub extra_stack_len: u32,
@@ -288,6 +391,7 @@ impl<Reg: RegT> J2CompiledGuard<Reg> {
             deopt_vars,
             guard: Guard::new(),
             patch_offs,
+            unpatch_tgts: SmallVec::new(),
             extra_stack_len,
             switch,
         }
@@ -320,6 +424,15 @@ pub(super) struct DeoptVar<Reg: RegT> {
     pub bitw: u32,
     pub fromvlocs: VarLocs<Reg>,
     pub tovlocs: VarLocs<Reg>,
+    /// If this variable is a pointer to an [super::hir::Alloca] in the trace's frame, the size in
+    /// bytes of that allocation: deopt must copy the allocation's contents into each
+    /// [super::regalloc::VarLoc::StackOff] in `tovlocs`.
+    pub alloca_bytew: Option<u32>,
+    /// Is this variable a pointer into an [super::hir::Alloca] in the trace's frame (either the
+    /// allocation itself, or a pointer derived from it)? If so, deopt must relocate it to point
+    /// into the equivalent materialised allocation (see `alloca_bytew`) before writing it to any
+    /// location in `tovlocs`.
+    pub alloca_ptr: bool,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, IndexType, Ord, PartialEq, PartialOrd)]
@@ -319,8 +319,11 @@ impl<Reg: RegT> Mod<Reg> {
                 entry,
                 peel,
             } => {
-                entry.assert_well_formed(self, args_vlocs, args_vlocs);
-                peel.assert_well_formed(self, args_vlocs, args_vlocs);
+                // Loop invariant code motion can give the peel extra arguments.
+                let mut peel_vlocs = args_vlocs.clone();
+                peel_vlocs.resize(peel.term_vars().len(), VarLocs::new());
+                entry.assert_well_formed(self, args_vlocs, &peel_vlocs);
+                peel.assert_well_formed(self, &peel_vlocs, &peel_vlocs);
             }
         }
     }
@@ -433,6 +436,8 @@ impl<Reg: RegT> ModLikeT for Mod<Reg> {
 pub(super) enum TraceStart<Reg: RegT> {
     /// This trace started from a control point.
     ControlPoint {
+        /// The control point call this trace started from.
+        entry_pc: aot_ir::InstId,
         entry_statepoint: &'static Statepoint,
     },
     /// This trace started from a guard failing.
@@ -684,6 +689,14 @@ impl BlockLikeT for Block {
     }
 }
 
+impl EquivIIdxT for Block {
+    /// A complete [Block] has no outstanding equivalences: every instruction is equivalent only to
+    /// itself.
+    fn equiv_iidx(&self, iidx: InstIdx) -> InstIdx {
+        iidx
+    }
+}
+
 /// A HIR type.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub(super) enum Ty {
@@ -836,6 +849,7 @@ pub(super) trait InstT: std::fmt::Debug {
 pub(super) enum Inst {
     Abs,
     Add,
+    Alloca,
     And,
     Arg,
     AShr,
@@ -843,11 +857,14 @@ pub(super) enum Inst {
     #[cfg(test)]
     BlackBox,
     Call,
+    Ceil,
     Const,
+    CopySign,
     CtPop,
     CtTz,
     DebugStr,
     DynPtrAdd,
+    FAbs,
     FAdd,
     FCmp,
     FDiv,
@@ -857,6 +874,7 @@ pub(super) enum Inst {
     FPClass,
     FPExt,
     FSub,
+    FTrunc,
     FPToSI,
     Freeze,
     Guard,
@@ -867,7 +885,10 @@ pub(super) enum Inst {
     MemCpy,
     MemSet,
     Mul,
+    NoAlias,
+    NoAliasEnd,
     Or,
+    Overflow,
     PtrAdd,
     PtrToInt,
     SDiv,
@@ -877,6 +898,7 @@ pub(super) enum Inst {
     SIToFP,
     SMax,
     SMin,
+    Sqrt,
     SRem,
     Store,
     Sub,
@@ -1051,6 +1073,59 @@ impl InstT for Add {
     }
 }
 
+/// A stack allocation of `bytew` bytes, aligned to `align` bytes, with semantics similar to
+/// LLVM's static `alloca`. The memory lives in the trace's frame and is valid until the trace is
+/// exited: the backend allocates a fixed slot for each `alloca`, so the same address is returned
+/// each time a given `alloca` is executed.
+#[derive(Clone, Debug)]
+pub(super) struct Alloca {
+    pub bytew: u32,
+    pub align: u32,
+}
+
+impl InstT for Alloca {
+    fn assert_well_formed(&self, _m: &dyn ModLikeT, _b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert!(self.bytew > 0, "%{iidx:?}: zero-sized alloca");
+        assert!(
+            self.align.is_power_of_two(),
+            "%{iidx:?}: alignment is not a power of two"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, _opt: &mut T) {}
+
+    /// Each `alloca` returns distinct memory, so no two are equivalent.
+    fn cse_eq(&self, _opt: &dyn EquivIIdxT, _other: &Inst) -> bool {
+        false
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::none(b)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut _iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("alloca {}, {}", self.bytew, self.align)
+    }
+
+    fn tyidx(&self, m: &dyn ModLikeT) -> TyIdx {
+        m.tyidx_ptr0()
+    }
+}
+
 /// `&` with normal LLVM semantics.
 #[derive(Clone, Debug)]
 pub(super) struct And {
@@ -1410,19 +1485,27 @@ impl InstT for Call {
 
     fn read_effects(&self) -> Effects {
         match self.effects {
-            CallEffects::None => Effects::none(),
-            CallEffects::Read | CallEffects::ReadWrite => Effects::none().add_heap().add_volatile(),
-            CallEffects::Write => Effects::none(),
+            CallEffects::None | CallEffects::Alloc | CallEffects::InaccessibleMem => {
+                Effects::none()
+            }
+            CallEffects::Read
+            | CallEffects::ReadWrite
+            | CallEffects::ArgMemRead
+            | CallEffects::ArgMemReadWrite => Effects::none().add_heap().add_volatile(),
+            CallEffects::Write | CallEffects::ArgMemWrite => Effects::none(),
         }
     }
 
     fn write_effects(&self) -> Effects {
         match self.effects {
-            CallEffects::None => Effects::none(),
-            CallEffects::Read => Effects::none(),
-            CallEffects::ReadWrite | CallEffects::Write => {
-                Effects::none().add_heap().add_volatile()
-            }
+            CallEffects::None | CallEffects::Alloc => Effects::none(),
+            CallEffects::Read | CallEffects::ArgMemRead => Effects::none(),
+            CallEffects::ReadWrite
+            | CallEffects::Write
+            | CallEffects::ArgMemReadWrite
+            | CallEffects::ArgMemWrite => Effects::none().add_heap().add_volatile(),
+            // The call's effects can't be observed by the trace, but it must still be executed.
+            CallEffects::InaccessibleMem => Effects::none().add_internal(),
         }
     }
 
@@ -1473,6 +1556,87 @@ pub(super) enum CallEffects {
     Read,
     Write,
     ReadWrite,
+    /// Like [CallEffects::Read], but only memory reachable from the call's pointer arguments is
+    /// read.
+    ArgMemRead,
+    /// Like [CallEffects::Write], but only memory reachable from the call's pointer arguments is
+    /// written.
+    ArgMemWrite,
+    /// Like [CallEffects::ReadWrite], but only memory reachable from the call's pointer arguments
+    /// is read or written.
+    ArgMemReadWrite,
+    /// The call only reads or writes memory that the trace cannot access (e.g. an allocator's
+    /// internal state or a counter private to a library).
+    InaccessibleMem,
+    /// The call is to an allocator: it returns a pointer to fresh memory that nothing else in the
+    /// trace can alias, and it has no other effects visible to the trace. An allocator call whose
+    /// result is unused can thus be removed.
+    Alloc,
+}
+
+/// Ceil with the same semantics as `llvm.ceil.`.
+#[derive(Clone, Debug)]
+pub(super) struct Ceil {
+    pub tyidx: TyIdx,
+    pub val: InstIdx,
+}
+
+impl InstT for Ceil {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert_eq!(
+            self.tyidx,
+            b.inst(self.val).tyidx(m),
+            "%{iidx:?}: inconsistent types for return type and val"
+        );
+
+        assert_matches!(
+            m.ty(self.tyidx),
+            Ty::Float | Ty::Double,
+            "%{iidx:?}: float / double required"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.val = opt.equiv_iidx(self.val);
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::Ceil(Ceil { tyidx, val }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.val) == *val
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.val)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.val = iidx_map(self.val);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("ceil %{}", self.val.to_raw_index())
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1577,6 +1741,82 @@ pub(super) enum ConstKind {
     Ptr(usize),
 }
 
+/// Copy sign with the same semantics as `llvm.copysign.`: returns a value with the magnitude of
+/// `mag` and the sign of `sign`.
+#[derive(Clone, Debug)]
+pub(super) struct CopySign {
+    pub tyidx: TyIdx,
+    /// What LLVM calls `mag`.
+    pub mag: InstIdx,
+    /// What LLVM calls `sgn`.
+    pub sign: InstIdx,
+}
+
+impl InstT for CopySign {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert!(
+            b.inst(self.mag).tyidx(m) == b.inst(self.sign).tyidx(m)
+                && b.inst(self.sign).tyidx(m) == self.tyidx,
+            "%{iidx:?}: inconsistent return / mag / sign types"
+        );
+
+        assert_matches!(
+            m.ty(self.tyidx),
+            Ty::Float | Ty::Double,
+            "%{iidx:?}: float / double required"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.mag = opt.equiv_iidx(self.mag);
+        self.sign = opt.equiv_iidx(self.sign);
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::CopySign(CopySign { tyidx, mag, sign }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.mag) == *mag
+            && opt.equiv_iidx(self.sign) == *sign
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::two(b, self.mag, self.sign)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.mag = iidx_map(self.mag);
+        self.sign = iidx_map(self.sign);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!(
+            "copysign %{}, %{}",
+            self.mag.to_raw_index(),
+            self.sign.to_raw_index()
+        )
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
 /// Count with the number of set bits, with the same semantics as `llvm.ctpop.`.
 #[derive(Clone, Debug)]
 pub(super) struct CtPop {
@@ -1811,6 +2051,71 @@ impl InstT for DynPtrAdd {
     }
 }
 
+/// Floating point absolute value with the same semantics as `llvm.fabs.`.
+#[derive(Clone, Debug)]
+pub(super) struct FAbs {
+    pub tyidx: TyIdx,
+    pub val: InstIdx,
+}
+
+impl InstT for FAbs {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert_eq!(
+            self.tyidx,
+            b.inst(self.val).tyidx(m),
+            "%{iidx:?}: inconsistent types for return type and val"
+        );
+
+        assert_matches!(
+            m.ty(self.tyidx),
+            Ty::Float | Ty::Double,
+            "%{iidx:?}: float / double required"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.val = opt.equiv_iidx(self.val);
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::FAbs(FAbs { tyidx, val }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.val) == *val
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.val)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.val = iidx_map(self.val);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("fabs %{}", self.val.to_raw_index())
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
 /// Floating point `+` with normal LLVM semantics.
 #[derive(Clone, Debug)]
 pub(super) struct FAdd {
@@ -2473,6 +2778,71 @@ impl InstT for FPExt {
     }
 }
 
+/// Floating point round towards zero with the same semantics as `llvm.trunc.`.
+#[derive(Clone, Debug)]
+pub(super) struct FTrunc {
+    pub tyidx: TyIdx,
+    pub val: InstIdx,
+}
+
+impl InstT for FTrunc {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert_eq!(
+            self.tyidx,
+            b.inst(self.val).tyidx(m),
+            "%{iidx:?}: inconsistent types for return type and val"
+        );
+
+        assert_matches!(
+            m.ty(self.tyidx),
+            Ty::Float | Ty::Double,
+            "%{iidx:?}: float / double required"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.val = opt.equiv_iidx(self.val);
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::FTrunc(FTrunc { tyidx, val }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.val) == *val
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.val)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.val = iidx_map(self.val);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("ftrunc %{}", self.val.to_raw_index())
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
 /// Cast a floating point number to a signed integer with the same semantics as LLVM's `fptosi`.
 #[derive(Clone, Debug)]
 pub(super) struct FPToSI {
@@ -2626,6 +2996,11 @@ impl InstT for Guard {
             *x = opt.equiv_iidx(*x);
         }
         opt.gextra_mut(self.geidx).deopt_vars = deopt_vars;
+        let mut virt_objs = std::mem::take(&mut opt.gextra_mut(self.geidx).virt_objs);
+        for x in virt_objs.iter_mut() {
+            x.rewrite_iidxs(|y| opt.equiv_iidx(y));
+        }
+        opt.gextra_mut(self.geidx).virt_objs = virt_objs;
     }
 
     fn cse_eq(&self, _opt: &dyn EquivIIdxT, _other: &Inst) -> bool {
@@ -2649,17 +3024,31 @@ impl InstT for Guard {
         F: FnMut(InstIdx) -> InstIdx,
     {
         self.cond = iidx_map(self.cond);
-        for x in b.gextra_mut(self.geidx).deopt_vars.iter_mut() {
+        let gextra = b.gextra_mut(self.geidx);
+        for x in gextra.deopt_vars.iter_mut() {
             *x = iidx_map(*x);
         }
+        for x in gextra.virt_objs.iter_mut() {
+            x.rewrite_iidxs(&mut iidx_map);
+        }
     }
 
     fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, b: &B) -> String {
+        let gextra = b.gextra(self.geidx);
+        let mut virt_objs = String::new();
+        for VirtObj { alloc, fields } in &gextra.virt_objs {
+            let fields = fields
+                .iter()
+                .map(|(ptr, val)| format!("%{}: %{}", ptr.to_raw_index(), val.to_raw_index()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            virt_objs.push_str(&format!(" virtual %{} [{fields}]", alloc.to_raw_index()));
+        }
         format!(
-            "guard {}, %{}, [{}]",
+            "guard {}, %{}, [{}]{virt_objs}",
             if self.expect { "true" } else { "false" },
             self.cond.to_raw_index(),
-            b.gextra(self.geidx)
+            gextra
                 .deopt_vars
                 .iter()
                 .map(|iidx| format!("%{}", iidx.to_raw_index()))
@@ -2698,6 +3087,12 @@ pub(super) struct GuardExtra {
     ///  deopt_frames[0] variables
     /// ```
     pub deopt_vars: Vec<InstIdx>,
+    /// The indexes into [Self::deopt_vars] of variables which were this guard's condition.
+    /// [super::aot_to_hir] replaces these with a constant, since a guard only fails if its
+    /// condition is the inverse of `expect`. If the guard's condition is later strengthened (see
+    /// [super::opt::range]), the original condition may hold when the guard fails, so these
+    /// variables must be replaced with the original condition.
+    pub cond_deopt_vars: SmallVec<[usize; 1]>,
     /// The frames needed for deopt and side-tracing with the most recent frame at the tail-end of
     /// this list. This is a 1:1 mapping with the call frames at the point of the respective guard
     /// *except* that the most recent call frame is replaced with the deopt information for the
@@ -2705,6 +3100,49 @@ pub(super) struct GuardExtra {
     /// interpreter, the depth of frames decreases exponentially: in ~50-90% (and 90% is more
     /// common than 50%) of cases there is 1 frame, about 10x fewer have 2 frames, and so on.
     pub deopt_frames: SmallVec<[Frame; 2]>,
+    /// The virtual objects (see [super::opt::escape]) that must be materialised if this guard
+    /// fails, before deopt or a side-trace can make use of [Self::deopt_vars].
+    pub virt_objs: Vec<VirtObj>,
+}
+
+/// An allocation whose fields the optimiser has not stored to memory: if the guard that records
+/// it fails, the allocation is performed (if it has not been already) and the stores made.
+#[derive(Clone, Debug)]
+pub(super) struct VirtObj {
+    /// The allocation.
+    pub alloc: InstIdx,
+    /// The object's fields as `(ptr, val)` pairs. Materialising the object stores each `val` to
+    /// its `ptr` in order.
+    pub fields: Vec<(InstIdx, InstIdx)>,
+}
+
+impl VirtObj {
+    pub(super) fn rewrite_iidxs<F>(&mut self, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.alloc = iidx_map(self.alloc);
+        for (ptr, val) in self.fields.iter_mut() {
+            *ptr = iidx_map(*ptr);
+            *val = iidx_map(*val);
+        }
+    }
+
+    /// The number of [InstIdx]s this object references.
+    fn iidxs_len(&self) -> usize {
+        1 + self.fields.len() * 2
+    }
+
+    /// Return the `i`th [InstIdx] this object references.
+    fn iidx(&self, i: usize) -> InstIdx {
+        if i == 0 {
+            self.alloc
+        } else if i % 2 == 1 {
+            self.fields[(i - 1) / 2].0
+        } else {
+            self.fields[(i - 1) / 2].1
+        }
+    }
 }
 
 /// If a guard relates to an AOT `switch`, this struct records the extra information we need to
@@ -2838,6 +3276,22 @@ impl IPred {
         }
     }
 
+    /// Return the predicate which is true exactly when `self` is false.
+    pub(super) fn inverse(&self) -> IPred {
+        match self {
+            IPred::Eq => IPred::Ne,
+            IPred::Ne => IPred::Eq,
+            IPred::Ugt => IPred::Ule,
+            IPred::Uge => IPred::Ult,
+            IPred::Ult => IPred::Uge,
+            IPred::Ule => IPred::Ugt,
+            IPred::Sgt => IPred::Sle,
+            IPred::Sge => IPred::Slt,
+            IPred::Slt => IPred::Sge,
+            IPred::Sle => IPred::Sgt,
+        }
+    }
+
     fn as_str(&self) -> &str {
         match self {
             IPred::Eq => "eq",
@@ -3186,26 +3640,215 @@ impl InstT for MemSet {
     }
 
     fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
-        IterIidxsIterator::three(b, self.dst, self.val, self.len)
+        IterIidxsIterator::three(b, self.dst, self.val, self.len)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.dst = iidx_map(self.dst);
+        self.val = iidx_map(self.val);
+        self.len = iidx_map(self.len);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!(
+            "memset %{}, %{}, %{}, {}",
+            self.dst.to_raw_index(),
+            self.val.to_raw_index(),
+            self.len.to_raw_index(),
+            if self.is_volatile { "true" } else { "false" }
+        )
+    }
+
+    fn tyidx(&self, m: &dyn ModLikeT) -> TyIdx {
+        m.tyidx_void()
+    }
+}
+
+/// `*` with normal LLVM semantics.
+#[derive(Clone, Debug)]
+pub(super) struct Mul {
+    pub tyidx: TyIdx,
+    /// What LLVM calls `op1`.
+    pub lhs: InstIdx,
+    /// What LLVM calls `op2`.
+    pub rhs: InstIdx,
+    pub nuw: bool,
+    pub nsw: bool,
+}
+
+impl InstT for Mul {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert!(
+            b.inst(self.lhs).tyidx(m) == b.inst(self.rhs).tyidx(m)
+                && b.inst(self.rhs).tyidx(m) == self.tyidx,
+            "%{iidx:?}: inconsistent return / lhs / rhs types"
+        );
+    }
+
+    /// Canonicalise to favour references to constants on the RHS.
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.lhs = opt.equiv_iidx(self.lhs);
+        self.rhs = opt.equiv_iidx(self.rhs);
+        if matches!(opt.inst(self.lhs), Inst::Const(_))
+            && !matches!(opt.inst(self.rhs), Inst::Const(_))
+        {
+            std::mem::swap(&mut self.lhs, &mut self.rhs);
+        }
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::Mul(Mul {
+            tyidx,
+            lhs,
+            rhs,
+            nuw,
+            nsw,
+        }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.lhs) == *lhs
+            && opt.equiv_iidx(self.rhs) == *rhs
+            && self.nuw == *nuw
+            && self.nsw == *nsw
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::two(b, self.lhs, self.rhs)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.lhs = iidx_map(self.lhs);
+        self.rhs = iidx_map(self.rhs);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!(
+            "mul %{}, %{}",
+            self.lhs.to_raw_index(),
+            self.rhs.to_raw_index()
+        )
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
+/// A copy of the pointer `ptr` which, until the matching [NoAliasEnd], has the semantics of an
+/// argument with LLVM's `noalias` attribute: if memory accessed through a pointer based on this
+/// instruction is modified before the [NoAliasEnd], it is not accessed through any other pointer
+/// in that time. This is emitted for `noalias` arguments of inlined functions, where the scope is
+/// the execution of the function.
+///
+/// This has internal effects so that it is neither moved nor removed relative to other
+/// instructions.
+#[derive(Clone, Debug)]
+pub(super) struct NoAlias {
+    pub tyidx: TyIdx,
+    pub ptr: InstIdx,
+}
+
+impl InstT for NoAlias {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert_matches!(m.ty(self.tyidx), Ty::Ptr(_), "%{iidx:?}: not a ptr type");
+        assert_eq!(
+            m.ty(self.tyidx),
+            m.ty(b.inst(self.ptr).tyidx(m)),
+            "%{iidx:?}: inconsistent return / operand types"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.ptr = opt.equiv_iidx(self.ptr);
+    }
+
+    fn cse_eq(&self, _opt: &dyn EquivIIdxT, _other: &Inst) -> bool {
+        false
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none().add_internal()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none().add_internal()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.ptr)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.ptr = iidx_map(self.ptr);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("noalias %{}", self.ptr.to_raw_index())
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
+/// The end of the scope of the [NoAlias] `ptr`. If an optimisation has replaced `ptr` with a
+/// value that is not a [NoAlias], this has no effect.
+#[derive(Clone, Debug)]
+pub(super) struct NoAliasEnd {
+    pub ptr: InstIdx,
+}
+
+impl InstT for NoAliasEnd {
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.ptr = opt.equiv_iidx(self.ptr);
+    }
+
+    fn cse_eq(&self, _opt: &dyn EquivIIdxT, _other: &Inst) -> bool {
+        false
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none().add_internal()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none().add_internal()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.ptr)
     }
 
     fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
     where
         F: FnMut(InstIdx) -> InstIdx,
     {
-        self.dst = iidx_map(self.dst);
-        self.val = iidx_map(self.val);
-        self.len = iidx_map(self.len);
+        self.ptr = iidx_map(self.ptr);
     }
 
     fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
-        format!(
-            "memset %{}, %{}, %{}, {}",
-            self.dst.to_raw_index(),
-            self.val.to_raw_index(),
-            self.len.to_raw_index(),
-            if self.is_volatile { "true" } else { "false" }
-        )
+        format!("noalias_end %{}", self.ptr.to_raw_index())
     }
 
     fn tyidx(&self, m: &dyn ModLikeT) -> TyIdx {
@@ -3213,19 +3856,18 @@ impl InstT for MemSet {
     }
 }
 
-/// `*` with normal LLVM semantics.
+/// `|` with normal LLVM semantics.
 #[derive(Clone, Debug)]
-pub(super) struct Mul {
+pub(super) struct Or {
     pub tyidx: TyIdx,
     /// What LLVM calls `op1`.
     pub lhs: InstIdx,
     /// What LLVM calls `op2`.
     pub rhs: InstIdx,
-    pub nuw: bool,
-    pub nsw: bool,
+    pub disjoint: bool,
 }
 
-impl InstT for Mul {
+impl InstT for Or {
     fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
         assert!(
             b.inst(self.lhs).tyidx(m) == b.inst(self.rhs).tyidx(m)
@@ -3246,18 +3888,16 @@ impl InstT for Mul {
     }
 
     fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
-        if let Inst::Mul(Mul {
+        if let Inst::Or(Or {
             tyidx,
             lhs,
             rhs,
-            nuw,
-            nsw,
+            disjoint,
         }) = other
             && self.tyidx == *tyidx
             && opt.equiv_iidx(self.lhs) == *lhs
             && opt.equiv_iidx(self.rhs) == *rhs
-            && self.nuw == *nuw
-            && self.nsw == *nsw
+            && self.disjoint == *disjoint
         {
             true
         } else {
@@ -3287,7 +3927,7 @@ impl InstT for Mul {
 
     fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
         format!(
-            "mul %{}, %{}",
+            "or %{}, %{}",
             self.lhs.to_raw_index(),
             self.rhs.to_raw_index()
         )
@@ -3298,31 +3938,36 @@ impl InstT for Mul {
     }
 }
 
-/// `|` with normal LLVM semantics.
+/// Integer overflow check, returning `1` iff `lhs op rhs` overflows. This has the same semantics
+/// as the overflow bit of LLVM's `llvm.{s,u}{add,sub,mul}.with.overflow` intrinsics: the
+/// arithmetic result itself is computed separately by an [Add], [Sub], or [Mul].
 #[derive(Clone, Debug)]
-pub(super) struct Or {
-    pub tyidx: TyIdx,
-    /// What LLVM calls `op1`.
+pub(super) struct Overflow {
+    pub op: OverflowOp,
     pub lhs: InstIdx,
-    /// What LLVM calls `op2`.
     pub rhs: InstIdx,
-    pub disjoint: bool,
 }
 
-impl InstT for Or {
+impl InstT for Overflow {
     fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
-        assert!(
-            b.inst(self.lhs).tyidx(m) == b.inst(self.rhs).tyidx(m)
-                && b.inst(self.rhs).tyidx(m) == self.tyidx,
-            "%{iidx:?}: inconsistent return / lhs / rhs types"
+        assert_eq!(
+            b.inst(self.lhs).tyidx(m),
+            b.inst(self.rhs).tyidx(m),
+            "%{iidx:?}: inconsistent lhs / rhs types"
+        );
+        assert_matches!(
+            m.ty(b.inst(self.lhs).tyidx(m)),
+            Ty::Int(_),
+            "%{iidx:?}: lhs is not an integer type"
         );
     }
 
-    /// Canonicalise to favour references to constants on the RHS.
+    /// Canonicalise to favour references to constants on the RHS of commutative operations.
     fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
         self.lhs = opt.equiv_iidx(self.lhs);
         self.rhs = opt.equiv_iidx(self.rhs);
-        if matches!(opt.inst(self.lhs), Inst::Const(_))
+        if self.op.is_commutative()
+            && matches!(opt.inst(self.lhs), Inst::Const(_))
             && !matches!(opt.inst(self.rhs), Inst::Const(_))
         {
             std::mem::swap(&mut self.lhs, &mut self.rhs);
@@ -3330,16 +3975,10 @@ impl InstT for Or {
     }
 
     fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
-        if let Inst::Or(Or {
-            tyidx,
-            lhs,
-            rhs,
-            disjoint,
-        }) = other
-            && self.tyidx == *tyidx
+        if let Inst::Overflow(Overflow { op, lhs, rhs }) = other
+            && self.op == *op
             && opt.equiv_iidx(self.lhs) == *lhs
             && opt.equiv_iidx(self.rhs) == *rhs
-            && self.disjoint == *disjoint
         {
             true
         } else {
@@ -3369,14 +4008,100 @@ impl InstT for Or {
 
     fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
         format!(
-            "or %{}, %{}",
+            "overflow {} %{}, %{}",
+            self.op.as_str(),
             self.lhs.to_raw_index(),
             self.rhs.to_raw_index()
         )
     }
 
-    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
-        self.tyidx
+    fn tyidx(&self, m: &dyn ModLikeT) -> TyIdx {
+        m.tyidx_int1()
+    }
+}
+
+/// The arithmetic operation whose overflow an [Overflow] instruction checks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) enum OverflowOp {
+    SAdd,
+    SSub,
+    SMul,
+    UAdd,
+    USub,
+    UMul,
+}
+
+impl OverflowOp {
+    /// Does this operation check for signed (rather than unsigned) overflow?
+    pub(super) fn is_signed(&self) -> bool {
+        match self {
+            OverflowOp::SAdd | OverflowOp::SSub | OverflowOp::SMul => true,
+            OverflowOp::UAdd | OverflowOp::USub | OverflowOp::UMul => false,
+        }
+    }
+
+    fn is_commutative(&self) -> bool {
+        match self {
+            OverflowOp::SAdd | OverflowOp::SMul | OverflowOp::UAdd | OverflowOp::UMul => true,
+            OverflowOp::SSub | OverflowOp::USub => false,
+        }
+    }
+
+    /// Given inclusive bounds `lhs` and `rhs` on the operands of a `bitw`-bit operation, each
+    /// interpreted as signed or unsigned integers as appropriate for this operation, return
+    /// `Some(true)` if the operation overflows for all such operands, `Some(false)` if it
+    /// overflows for none of them, or `None` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// If `bitw` is not in the range `1..=64`.
+    pub(super) fn overflows(
+        &self,
+        bitw: u32,
+        lhs: (i128, i128),
+        rhs: (i128, i128),
+    ) -> Option<bool> {
+        assert!(bitw > 0 && bitw <= 64);
+        let (lo, hi) = match self {
+            OverflowOp::SAdd | OverflowOp::UAdd => (lhs.0 + rhs.0, lhs.1 + rhs.1),
+            OverflowOp::SSub | OverflowOp::USub => (lhs.0 - rhs.1, lhs.1 - rhs.0),
+            OverflowOp::SMul | OverflowOp::UMul => {
+                // Unsigned 64-bit products may not fit in an `i128`, in which case we give up.
+                let corners = [
+                    lhs.0.checked_mul(rhs.0)?,
+                    lhs.0.checked_mul(rhs.1)?,
+                    lhs.1.checked_mul(rhs.0)?,
+                    lhs.1.checked_mul(rhs.1)?,
+                ];
+                (
+                    *corners.iter().min().unwrap(),
+                    *corners.iter().max().unwrap(),
+                )
+            }
+        };
+        let (min, max) = if self.is_signed() {
+            (-(1i128 << (bitw - 1)), (1i128 << (bitw - 1)) - 1)
+        } else {
+            (0, (1i128 << bitw) - 1)
+        };
+        if lo >= min && hi <= max {
+            Some(false)
+        } else if hi < min || lo > max {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            OverflowOp::SAdd => "sadd",
+            OverflowOp::SSub => "ssub",
+            OverflowOp::SMul => "smul",
+            OverflowOp::UAdd => "uadd",
+            OverflowOp::USub => "usub",
+            OverflowOp::UMul => "umul",
+        }
     }
 }
 
@@ -4036,6 +4761,71 @@ impl InstT for SMin {
     }
 }
 
+/// Square root with the same semantics as `llvm.sqrt.`.
+#[derive(Clone, Debug)]
+pub(super) struct Sqrt {
+    pub tyidx: TyIdx,
+    pub val: InstIdx,
+}
+
+impl InstT for Sqrt {
+    fn assert_well_formed(&self, m: &dyn ModLikeT, b: &dyn BlockLikeT, iidx: InstIdx) {
+        assert_eq!(
+            self.tyidx,
+            b.inst(self.val).tyidx(m),
+            "%{iidx:?}: inconsistent types for return type and val"
+        );
+
+        assert_matches!(
+            m.ty(self.tyidx),
+            Ty::Float | Ty::Double,
+            "%{iidx:?}: float / double required"
+        );
+    }
+
+    fn canonicalise<T: BlockLikeT + EquivIIdxT + ModLikeT>(&mut self, opt: &mut T) {
+        self.val = opt.equiv_iidx(self.val);
+    }
+
+    fn cse_eq(&self, opt: &dyn EquivIIdxT, other: &Inst) -> bool {
+        if let Inst::Sqrt(Sqrt { tyidx, val }) = other
+            && self.tyidx == *tyidx
+            && opt.equiv_iidx(self.val) == *val
+        {
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn write_effects(&self) -> Effects {
+        Effects::none()
+    }
+
+    fn iter_iidxs<'a>(&'a self, b: &'a dyn BlockLikeT) -> IterIidxsIterator<'a> {
+        IterIidxsIterator::one(b, self.val)
+    }
+
+    fn rewrite_iidxs<F>(&mut self, _b: &mut dyn BlockLikeT, mut iidx_map: F)
+    where
+        F: FnMut(InstIdx) -> InstIdx,
+    {
+        self.val = iidx_map(self.val);
+    }
+
+    fn to_string<M: ModLikeT, B: BlockLikeT>(&self, _m: &M, _b: &B) -> String {
+        format!("sqrt %{}", self.val.to_raw_index())
+    }
+
+    fn tyidx(&self, _m: &dyn ModLikeT) -> TyIdx {
+        self.tyidx
+    }
+}
+
 /// Return the remainder from signed division with the same semantics as LLVM's `srem`.
 #[derive(Clone, Debug)]
 pub(super) struct SRem {
@@ -5031,11 +5821,20 @@ impl<'a> Iterator for IterIidxsIterator<'a> {
                         bid: _,
                         switch: _,
                         deopt_vars,
+                        cond_deopt_vars: _,
                         deopt_frames: _,
+                        virt_objs,
                     } = self.b.gextra(*geidx);
                     if self.i - 1 <= deopt_vars.len() {
                         return Some(deopt_vars[self.i - 2]);
                     }
+                    let mut j = self.i - 2 - deopt_vars.len();
+                    for x in virt_objs {
+                        if j < x.iidxs_len() {
+                            return Some(x.iidx(j));
+                        }
+                        j -= x.iidxs_len();
+                    }
                 }
             }
             IterIidxsIteratorKind::Term(Term(vars)) => {
@@ -5387,6 +6186,26 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%0: alignment is not a power of two")]
+    fn alloca_align() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: ptr = alloca 16, 3
+        ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "%0: zero-sized alloca")]
+    fn alloca_zero_sized() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: ptr = alloca 0, 8
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%2: inconsistent return / lhs / rhs types")]
     fn and_type_consistency1() {
@@ -5517,6 +6336,41 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%1: float / double required")]
+    fn ceil_type_consistency() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = ceil %0
+        ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "%2: inconsistent return / mag / sign types")]
+    fn copysign_type_consistency1() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: float = arg [reg]
+          %1: double = arg [reg]
+          %2: float = copysign %0, %1
+        ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "%2: float / double required")]
+    fn copysign_type_consistency2() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i32 = copysign %0, %1
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%1: inconsistent bit widths")]
     fn ctpop_type_consistency() {
@@ -5551,6 +6405,17 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%1: inconsistent types for return type and val")]
+    fn fabs_type_consistency() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: float = arg [reg]
+          %1: double = fabs %0
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%2: inconsistent return / lhs / rhs types")]
     fn fadd_type_consistency1() {
@@ -5680,6 +6545,17 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%1: float / double required")]
+    fn ftrunc_type_consistency() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = ftrunc %0
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%1: guard references a non-i1 for its condition")]
     fn guard_cond_must_be_i1() {
@@ -5873,6 +6749,30 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%2: inconsistent lhs / rhs types")]
+    fn overflow_type_consistency() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: i8 = arg [reg]
+          %1: i16 = arg [reg]
+          %2: i1 = overflow sadd %0, %1
+        ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "%2: lhs is not an integer type")]
+    fn overflow_must_take_int() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i1 = overflow umul %0, %1
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%1: pointer is not a ptr type")]
     fn ptradd_not_ptr() {
@@ -6081,6 +6981,28 @@ mod test {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "%1: inconsistent types for return type and val")]
+    fn sqrt_type_consistency1() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: float = arg [reg]
+          %1: double = sqrt %0
+        ",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "%1: float / double required")]
+    fn sqrt_type_consistency2() {
+        str_to_mod::<DummyReg>(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = sqrt %0
+        ",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "%2: inconsistent return / lhs / rhs types")]
     fn srem_type_consistency1() {
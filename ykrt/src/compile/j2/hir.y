@@ -50,6 +50,13 @@ FuncAttr -> Result<AstFuncAttr, Box<dyn Error>>:
   | "MEMORY" "(" "READ" ")" { Ok(AstFuncAttr::MemoryRead) }
   | "MEMORY" "(" "WRITE" ")" { Ok(AstFuncAttr::MemoryWrite) }
   | "MEMORY" "(" "READWRITE" ")" { Ok(AstFuncAttr::MemoryReadWrite) }
+  | "MEMORY" "(" "ARGMEM" ":" "READ" ")" { Ok(AstFuncAttr::ArgMemRead) }
+  | "MEMORY" "(" "ARGMEM" ":" "WRITE" ")" { Ok(AstFuncAttr::ArgMemWrite) }
+  | "MEMORY" "(" "ARGMEM" ":" "READWRITE" ")" { Ok(AstFuncAttr::ArgMemReadWrite) }
+  | "MEMORY" "(" "INACCESSIBLEMEM" ":" "READ" ")" { Ok(AstFuncAttr::InaccessibleMem) }
+  | "MEMORY" "(" "INACCESSIBLEMEM" ":" "WRITE" ")" { Ok(AstFuncAttr::InaccessibleMem) }
+  | "MEMORY" "(" "INACCESSIBLEMEM" ":" "READWRITE" ")" { Ok(AstFuncAttr::InaccessibleMem) }
+  | "ALLOCATOR" { Ok(AstFuncAttr::Allocator) }
   ;
 
 Insts -> Result<Vec<AstInst>, Box<dyn Error>>:
@@ -62,8 +69,8 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "CALL" "ID" "LOCAL" "(" Locals ")" {
       Ok(AstInst::Call { local: None, ty: None, extern_: $2?.span(), tgt: $3?.span(), args: $5? })
     }
-  | "GUARD" Bool "," "LOCAL" "," "[" Locals "]" GuardStackMaps {
-       Ok(AstInst::Guard { expect: $2?, cond: $4?.span(), exit_vars: $7?, smaps: $9? })
+  | "GUARD" Bool "," "LOCAL" "," "[" Locals "]" GuardStackMaps GuardVirtObjs {
+       Ok(AstInst::Guard { expect: $2?, cond: $4?.span(), exit_vars: $7?, smaps: $9?, virt_objs: $10? })
     }
   | "LOCAL" ":" Ty "=" "CALL" "ID" "LOCAL" "(" Locals ")" {
       Ok(AstInst::Call { local: Some($1?.span()), ty: Some($3?), extern_: $6?.span(), tgt: $7?.span(), args: $9? })
@@ -74,6 +81,9 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "=" "ADD" "LOCAL" "," "LOCAL" {
        Ok(AstInst::Add { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
+  | "LOCAL" ":" Ty "=" "ALLOCA" "INT" "," "INT" {
+      Ok(AstInst::Alloca { local: $1?.span(), ty: $3?, bytew: $6?.span(), align: $8?.span() })
+    }
   | "LOCAL" ":" Ty "=" "AND" "LOCAL" "," "LOCAL" {
       Ok(AstInst::And { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
@@ -86,9 +96,15 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "=" "BITCAST" "LOCAL" {
       Ok(AstInst::BitCast{ local: $1?.span(), ty: $3?, val: $6?.span() })
     }
+  | "LOCAL" ":" Ty "=" "CEIL" "LOCAL" {
+      Ok(AstInst::Ceil { local: $1?.span(), ty: $3?, val: $6?.span() })
+    }
   | "LOCAL" ":" Ty "=" Const {
        Ok(AstInst::Const { local: $1?.span(), ty: $3?, kind: $5? })
     }
+  | "LOCAL" ":" Ty "=" "COPYSIGN" "LOCAL" "," "LOCAL" {
+      Ok(AstInst::CopySign { local: $1?.span(), ty: $3?, mag: $6?.span(), sign: $8?.span() })
+    }
   | "LOCAL" ":" Ty "=" "CTPOP" "LOCAL" {
       Ok(AstInst::CtPop { local: $1?.span(), ty: $3?, val: $6?.span() })
     }
@@ -98,6 +114,9 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "="  "DYNPTRADD" "LOCAL" "," "LOCAL" "," "INT" {
        Ok(AstInst::DynPtrAdd { local: $1?.span(), ty: $3?, ptr: $6?.span(), num_elems: $8?.span(), elem_size: $10?.span() })
     }
+  | "LOCAL" ":" Ty "=" "FABS" "LOCAL" {
+      Ok(AstInst::FAbs { local: $1?.span(), ty: $3?, val: $6?.span() })
+    }
   | "LOCAL" ":" Ty "=" "FADD" "LOCAL" "," "LOCAL" {
        Ok(AstInst::FAdd { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
@@ -119,6 +138,9 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "=" "FSUB" "LOCAL" "," "LOCAL" {
        Ok(AstInst::FSub { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
+  | "LOCAL" ":" Ty "=" "FTRUNC" "LOCAL" {
+      Ok(AstInst::FTrunc { local: $1?.span(), ty: $3?, val: $6?.span() })
+    }
   | "LOCAL" ":" Ty "=" "FPCLASS" "LOCAL" "," "INT" {
       Ok(AstInst::FPClass { local: $1?.span(), ty: $3?, val: $6?.span(), test: $8?.span() })
     }
@@ -149,9 +171,18 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "=" "MUL" "LOCAL" "," "LOCAL" {
        Ok(AstInst::Mul { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
+  | "LOCAL" ":" Ty "=" "NOALIAS" "LOCAL" {
+      Ok(AstInst::NoAlias { local: $1?.span(), ty: $3?, ptr: $6?.span() })
+    }
+  | "NOALIAS_END" "LOCAL" {
+      Ok(AstInst::NoAliasEnd { ptr: $2?.span() })
+    }
   | "LOCAL" ":" Ty "=" "OR" "LOCAL" "," "LOCAL" {
        Ok(AstInst::Or { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
+  | "LOCAL" ":" Ty "=" "OVERFLOW" OverflowOp "LOCAL" "," "LOCAL" {
+      Ok(AstInst::Overflow { local: $1?.span(), ty: $3?, op: $6?, lhs: $7?.span(), rhs: $9?.span() })
+    }
   | "LOCAL" ":" Ty "=" "PTRADD" "LOCAL" "," "INT" {
       Ok(AstInst::PtrAdd { local: $1?.span(), ty: $3?, ptr: $6?.span(), off: $8?.span() })
     }
@@ -185,6 +216,9 @@ Inst -> Result<AstInst, Box<dyn Error>>:
   | "LOCAL" ":" Ty "=" "SMIN" "LOCAL" "," "LOCAL" {
        Ok(AstInst::SMin { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
+  | "LOCAL" ":" Ty "=" "SQRT" "LOCAL" {
+      Ok(AstInst::Sqrt { local: $1?.span(), ty: $3?, val: $6?.span() })
+    }
   | "LOCAL" ":" Ty "=" "SREM" "LOCAL" "," "LOCAL" {
        Ok(AstInst::SRem { local: $1?.span(), ty: $3?, lhs: $6?.span(), rhs: $8?.span() })
     }
@@ -274,6 +308,17 @@ GuardStackMapVLocs -> Result<Vec<Vec<AstVLoc>>, Box<dyn Error>>:
   | "[" VLocList "]" { Ok(vec![$2?]) }
   ;
 
+GuardVirtObjs -> Result<Vec<(Span, Vec<(Span, Span)>)>, Box<dyn Error>>:
+    GuardVirtObjs "VIRTUAL" "LOCAL" "[" GuardVirtFields "]" { flattenr($1, Ok(($3?.span(), $5?))) }
+  | GuardVirtObjs "VIRTUAL" "LOCAL" "[" "]" { flattenr($1, Ok(($3?.span(), Vec::new()))) }
+  | { Ok(Vec::new()) }
+  ;
+
+GuardVirtFields -> Result<Vec<(Span, Span)>, Box<dyn Error>>:
+    GuardVirtFields "," "LOCAL" ":" "LOCAL" { flattenr($1, Ok(($3?.span(), $5?.span()))) }
+  | "LOCAL" ":" "LOCAL" { Ok(vec![($1?.span(), $3?.span())]) }
+  ;
+
 IntMinPoison -> Result<bool, Box<dyn Error>>:
     "," "INT_MIN_POISON" { Ok(true) }
   | { Ok(false) }
@@ -302,6 +347,15 @@ IPred -> Result<IPred, Box<dyn Error>>:
   | "SLE" { Ok(IPred::Sle) }
   ;
 
+OverflowOp -> Result<OverflowOp, Box<dyn Error>>:
+    "SADD" { Ok(OverflowOp::SAdd) }
+  | "SSUB" { Ok(OverflowOp::SSub) }
+  | "SMUL" { Ok(OverflowOp::SMul) }
+  | "UADD" { Ok(OverflowOp::UAdd) }
+  | "USUB" { Ok(OverflowOp::USub) }
+  | "UMUL" { Ok(OverflowOp::UMul) }
+  ;
+
 Ty -> Result<AstTy, Box<dyn Error>>:
     "INT_TY" { Ok(AstTy::Int($1?.span())) }
   | "FLOAT_TY" { Ok(AstTy::Float) }
@@ -334,7 +388,11 @@ Unmatched -> ():
 
 %%
 
-use crate::compile::j2::{hir::IPred, hir_parser::*, regalloc::RegFill};
+use crate::compile::j2::{
+    hir::{IPred, OverflowOp},
+    hir_parser::*,
+    regalloc::RegFill,
+};
 use std::error::Error;
 
 fn flattenr<T>(lhs: Result<Vec<T>, Box<dyn Error>>, rhs: Result<T, Box<dyn Error>>)
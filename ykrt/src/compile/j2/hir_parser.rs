@@ -15,7 +15,9 @@
 //!    [GuardExtra::deopt_vars]. The former are reordered and deduplicated as appropriate.
 //! 5. Guards can optionally specify an additional list which is the stack of frames, and the
 //!    [VarLocs] for their corresponding [GuardExtra::deopt_vars].
-//! 6. Function types and declarations are merged into one concept: that means that e.g. function
+//! 6. Guards can optionally be followed by `virtual %alloc [%ptr: %val, ...]` for each of their
+//!    [GuardExtra::virt_objs].
+//! 7. Function types and declarations are merged into one concept: that means that e.g. function
 //!    attributes are associated with a type (unlike LLVM IR where attributes are associated with
 //!    definitions and not with types).
 
@@ -183,6 +185,25 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         .into(),
                     );
                 }
+                AstInst::Alloca {
+                    local,
+                    ty,
+                    bytew,
+                    align,
+                } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    if tyidx != self.ty_map[&Ty::Ptr(0)] {
+                        self.err_span(local, "Return type is not a pointer");
+                    }
+                    let [bytew, align] = [bytew, align].map(|span| {
+                        self.lexer
+                            .span_str(span)
+                            .parse::<u32>()
+                            .unwrap_or_else(|e| self.err_span(span, &e.to_string()))
+                    });
+                    self.insts.push(Alloca { bytew, align }.into());
+                }
                 AstInst::And {
                     local,
                     ty,
@@ -254,6 +275,11 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                             AstFuncAttr::MemoryRead => effects = CallEffects::Read,
                             AstFuncAttr::MemoryWrite => effects = CallEffects::Write,
                             AstFuncAttr::MemoryReadWrite => effects = CallEffects::ReadWrite,
+                            AstFuncAttr::ArgMemRead => effects = CallEffects::ArgMemRead,
+                            AstFuncAttr::ArgMemWrite => effects = CallEffects::ArgMemWrite,
+                            AstFuncAttr::ArgMemReadWrite => effects = CallEffects::ArgMemReadWrite,
+                            AstFuncAttr::InaccessibleMem => effects = CallEffects::InaccessibleMem,
+                            AstFuncAttr::Allocator => effects = CallEffects::Alloc,
                         }
                     }
                     let tgt = self.p_local(tgt);
@@ -271,6 +297,12 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         .into(),
                     );
                 }
+                AstInst::Ceil { local, ty, val } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let val = self.p_local(val);
+                    self.insts.push(Ceil { tyidx, val }.into());
+                }
                 AstInst::Const { local, ty, kind } => {
                     self.p_def_local(local);
                     let tyidx = self.p_ty(ty);
@@ -355,6 +387,18 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         }
                     }
                 }
+                AstInst::CopySign {
+                    local,
+                    ty,
+                    mag,
+                    sign,
+                } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let mag = self.p_local(mag);
+                    let sign = self.p_local(sign);
+                    self.insts.push(CopySign { tyidx, mag, sign }.into());
+                }
                 AstInst::CtPop { local, ty, val } => {
                     self.p_def_local(local);
                     let tyidx = self.p_ty(ty);
@@ -391,6 +435,12 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         .into(),
                     );
                 }
+                AstInst::FAbs { local, ty, val } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let val = self.p_local(val);
+                    self.insts.push(FAbs { tyidx, val }.into());
+                }
                 AstInst::FAdd {
                     local,
                     ty,
@@ -465,6 +515,12 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                     let rhs = self.p_local(rhs);
                     self.insts.push(FSub { tyidx, lhs, rhs }.into());
                 }
+                AstInst::FTrunc { local, ty, val } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let val = self.p_local(val);
+                    self.insts.push(FTrunc { tyidx, val }.into());
+                }
                 AstInst::FPClass {
                     local,
                     ty,
@@ -522,6 +578,7 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                     cond,
                     exit_vars,
                     smaps: gsmaps,
+                    virt_objs,
                 } => {
                     let bid = BBlockId::new(FuncIdx::from(0), BBlockIdx::from(0));
                     let deopt_vars = exit_vars
@@ -553,11 +610,23 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         );
                     }
                     let cond = self.p_local(cond);
+                    let virt_objs = virt_objs
+                        .into_iter()
+                        .map(|(alloc, fields)| VirtObj {
+                            alloc: self.p_local(alloc),
+                            fields: fields
+                                .into_iter()
+                                .map(|(ptr, val)| (self.p_local(ptr), self.p_local(val)))
+                                .collect::<Vec<_>>(),
+                        })
+                        .collect::<Vec<_>>();
                     let geidx = guards.push(GuardExtra {
                         bid,
                         switch: None,
                         deopt_vars,
+                        cond_deopt_vars: SmallVec::new(),
                         deopt_frames,
+                        virt_objs,
                     });
                     self.insts.push(Inst::Guard(Guard {
                         geidx,
@@ -690,6 +759,16 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         .into(),
                     );
                 }
+                AstInst::NoAlias { local, ty, ptr } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let ptr = self.p_local(ptr);
+                    self.insts.push(NoAlias { tyidx, ptr }.into());
+                }
+                AstInst::NoAliasEnd { ptr } => {
+                    let ptr = self.p_local(ptr);
+                    self.insts.push(NoAliasEnd { ptr }.into());
+                }
                 AstInst::Or {
                     local,
                     ty,
@@ -710,6 +789,20 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                         .into(),
                     );
                 }
+                AstInst::Overflow {
+                    local,
+                    ty,
+                    op,
+                    lhs,
+                    rhs,
+                } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    assert_eq!(&self.tys[tyidx], &Ty::Int(1));
+                    let lhs = self.p_local(lhs);
+                    let rhs = self.p_local(rhs);
+                    self.insts.push(Overflow { op, lhs, rhs }.into());
+                }
                 AstInst::PtrAdd {
                     local,
                     ty,
@@ -843,6 +936,12 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                     let rhs = self.p_local(rhs);
                     self.insts.push(SMin { tyidx, lhs, rhs }.into());
                 }
+                AstInst::Sqrt { local, ty, val } => {
+                    self.p_def_local(local);
+                    let tyidx = self.p_ty(ty);
+                    let val = self.p_local(val);
+                    self.insts.push(Sqrt { tyidx, val }.into());
+                }
                 AstInst::SRem {
                     local,
                     ty,
@@ -1139,7 +1238,7 @@ impl<'lexer, 'input: 'lexer, Reg: RegT> HirParser<'lexer, 'input, Reg> {
                     *autoregused = true;
                     VarLoc::Reg(
                         testregiter.next_reg(&self.tys[tyidx]).unwrap_or_else(|| {
-                            self.err_span(local, "Exhausted automatic test registers")
+                            self.err_span(local, "No automatic test register available")
                         }),
                         RegFill::Undefined,
                     )
@@ -1266,6 +1365,11 @@ enum AstFuncAttr {
     MemoryRead,
     MemoryWrite,
     MemoryReadWrite,
+    ArgMemRead,
+    ArgMemWrite,
+    ArgMemReadWrite,
+    InaccessibleMem,
+    Allocator,
 }
 
 enum AstInst {
@@ -1282,6 +1386,12 @@ enum AstInst {
         lhs: Span,
         rhs: Span,
     },
+    Alloca {
+        local: Span,
+        ty: AstTy,
+        bytew: Span,
+        align: Span,
+    },
     And {
         local: Span,
         ty: AstTy,
@@ -1311,11 +1421,22 @@ enum AstInst {
         tgt: Span,
         args: Vec<Span>,
     },
+    Ceil {
+        local: Span,
+        ty: AstTy,
+        val: Span,
+    },
     Const {
         local: Span,
         ty: AstTy,
         kind: AstConst,
     },
+    CopySign {
+        local: Span,
+        ty: AstTy,
+        mag: Span,
+        sign: Span,
+    },
     CtPop {
         local: Span,
         ty: AstTy,
@@ -1333,6 +1454,11 @@ enum AstInst {
         num_elems: Span,
         elem_size: Span,
     },
+    FAbs {
+        local: Span,
+        ty: AstTy,
+        val: Span,
+    },
     FAdd {
         local: Span,
         ty: AstTy,
@@ -1374,6 +1500,11 @@ enum AstInst {
         lhs: Span,
         rhs: Span,
     },
+    FTrunc {
+        local: Span,
+        ty: AstTy,
+        val: Span,
+    },
     FPClass {
         local: Span,
         ty: AstTy,
@@ -1405,6 +1536,7 @@ enum AstInst {
         cond: Span,
         exit_vars: Vec<Span>,
         smaps: Vec<Vec<Vec<AstVLoc>>>,
+        virt_objs: Vec<(Span, Vec<(Span, Span)>)>,
     },
     ICmp {
         local: Span,
@@ -1448,12 +1580,27 @@ enum AstInst {
         lhs: Span,
         rhs: Span,
     },
+    NoAlias {
+        local: Span,
+        ty: AstTy,
+        ptr: Span,
+    },
+    NoAliasEnd {
+        ptr: Span,
+    },
     Or {
         local: Span,
         ty: AstTy,
         lhs: Span,
         rhs: Span,
     },
+    Overflow {
+        local: Span,
+        ty: AstTy,
+        op: OverflowOp,
+        lhs: Span,
+        rhs: Span,
+    },
     PtrAdd {
         local: Span,
         ty: AstTy,
@@ -1506,6 +1653,11 @@ enum AstInst {
         lhs: Span,
         rhs: Span,
     },
+    Sqrt {
+        local: Span,
+        ty: AstTy,
+        val: Span,
+    },
     SRem {
         local: Span,
         ty: AstTy,
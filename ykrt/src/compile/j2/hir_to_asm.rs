@@ -97,7 +97,7 @@ use crate::{
             codebuf::ExeCodeBuf,
             compiled_trace::{
                 CompiledGuardIdx, DeoptFrame, DeoptVar, J2CompiledGuard, J2CompiledTrace,
-                J2TraceStart,
+                J2TraceStart, LoopExit,
             },
             effects::Effects,
             hir::*,
@@ -113,7 +113,11 @@ use crate::{
 use index_type::{IndexType, vec::TypedVec};
 use parking_lot::Mutex;
 use smallvec::{SmallVec, smallvec};
-use std::{ffi::c_void, sync::Arc};
+use std::{
+    borrow::Cow,
+    ffi::c_void,
+    sync::{Arc, atomic::AtomicI64},
+};
 use test_stubs::test_stubs;
 use vob::Vob;
 
@@ -141,11 +145,20 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
         }
     }
 
-    pub(super) fn build(mut self, mt: Arc<MT>) -> Result<Arc<dyn CompiledTrace>, CompilationError> {
+    /// Build the compiled trace. If `tier_up` is `Some`, it is the counter used by
+    /// [CompiledTrace::count_execution].
+    pub(super) fn build(
+        mut self,
+        mt: Arc<MT>,
+        tier_up: Option<Box<AtomicI64>>,
+    ) -> Result<Arc<dyn CompiledTrace>, CompilationError> {
         // `labels_off` are the offsets required by `gbodies`: note that some guard bodies have
         // multiple labels, so this is an M:N (where N>=M) relationship.
-        let (buf, gbodies, labels_off, log, trace_start) = match &self.m.trace_start {
-            TraceStart::ControlPoint { entry_statepoint } => {
+        let (buf, gbodies, labels_off, log, trace_start, loop_exit) = match &self.m.trace_start {
+            TraceStart::ControlPoint {
+                entry_pc,
+                entry_statepoint,
+            } => {
                 let aot_smaps = AOT_STACKMAPS.as_ref().unwrap();
                 // FIXME: Relying on stackmap 0 being the control point is a horrible hack.
                 let base_stack_off = u32::try_from({
@@ -168,7 +181,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     .live_vals
                     .iter()
                     .map(|smap| AB::smp_to_vloc(smap, RegFill::Undefined))
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>, _>>()?;
 
                 for vlocs in args_vlocs.iter_mut() {
                     vlocs.retain(|x| {
@@ -185,6 +198,9 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     });
                 }
 
+                // For loop traces, the labels of the loop's back-edge and of the guard body that the
+                // back-edge can be patched to jump to (see [Self::push_loop_exit]).
+                let mut loop_labels = None;
                 let (post_stack_label, entry_stack_off) = match &self.m.trace_end {
                     TraceEnd::Call { entry } => {
                         let ra = RegAlloc::<AB>::new(self.m, entry, &args_vlocs, base_stack_off);
@@ -213,13 +229,25 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     }
                     TraceEnd::Loop { entry, peel } => match peel {
                         Some(peel) => {
-                            assert_eq!(args_vlocs.len(), peel.term_vars().len());
+                            // Loop invariant code motion can give the peel extra arguments.
+                            assert!(args_vlocs.len() <= peel.term_vars().len());
 
-                            let peel_vlocs = self.peel_vlocs(&args_vlocs, peel);
+                            let (peel_vlocs, peel_args_stack_off) =
+                                self.peel_vlocs(&args_vlocs, peel, base_stack_off);
                             let mut ra =
-                                RegAlloc::<AB>::new(self.m, peel, &peel_vlocs, base_stack_off);
-                            let peel_label = self.be.controlpoint_loop_end()?;
+                                RegAlloc::<AB>::new(self.m, peel, &peel_vlocs, peel_args_stack_off);
+                            let (peel_label, backedge_label, exit_label) =
+                                self.be.controlpoint_loop_end(tier_up.as_deref())?;
                             ra.set_term_vlocs(&mut self.be, peel, true, &peel_vlocs, &peel_vlocs)?;
+                            self.push_loop_exit(
+                                peel,
+                                &peel_vlocs,
+                                ra.stack_off(),
+                                entry_pc,
+                                entry_statepoint,
+                                exit_label.clone(),
+                            );
+                            loop_labels = Some((backedge_label, exit_label));
                             let peel_stack_off = self.p_block(peel, Some(peel), ra, &peel_vlocs)?;
                             let iter0_label = self.be.controlpoint_peel_start(peel_label);
 
@@ -241,8 +269,18 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         None => {
                             let mut ra =
                                 RegAlloc::<AB>::new(self.m, entry, &args_vlocs, base_stack_off);
-                            let iter0_label = self.be.controlpoint_loop_end()?;
+                            let (iter0_label, backedge_label, exit_label) =
+                                self.be.controlpoint_loop_end(tier_up.as_deref())?;
                             ra.set_term_vlocs(&mut self.be, entry, true, &args_vlocs, &args_vlocs)?;
+                            self.push_loop_exit(
+                                entry,
+                                &args_vlocs,
+                                ra.stack_off(),
+                                entry_pc,
+                                entry_statepoint,
+                                exit_label.clone(),
+                            );
+                            loop_labels = Some((backedge_label, exit_label));
                             let entry_stack_off =
                                 self.p_block(entry, Some(entry), ra, &args_vlocs)?;
                             self.be.controlpoint_loop_start(
@@ -288,17 +326,26 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                 let all_labels = gbodies
                     .iter()
                     .flat_map(|GuardBody { patch_labels, .. }| patch_labels.iter().cloned())
+                    .chain(loop_labels.iter().flat_map(|(x, y)| [x.clone(), y.clone()]))
                     .chain([post_stack_label])
                     .collect::<Vec<_>>();
                 let (buf, log, mut labels_off) = self.be.build_exe(&all_labels)?;
                 let sidetrace_off = labels_off.pop().unwrap();
+                let loop_exit = loop_labels.map(|_| {
+                    let exit_off = labels_off.pop().unwrap();
+                    let backedge_off = labels_off.pop().unwrap();
+                    LoopExit {
+                        backedge_off: u32::try_from(backedge_off).unwrap(),
+                        exit_off: u32::try_from(exit_off).unwrap(),
+                    }
+                });
                 let trace_start = J2TraceStart::ControlPoint {
                     args_vlocs,
                     entry_statepoint,
                     stack_off: entry_stack_off,
                     sidetrace_off,
                 };
-                (buf, gbodies, labels_off, log, trace_start)
+                (buf, gbodies, labels_off, log, trace_start, loop_exit)
             }
             TraceStart::Guard {
                 src_ctr,
@@ -357,7 +404,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     .flat_map(|GuardBody { patch_labels, .. }| patch_labels.iter().cloned())
                     .collect::<Vec<_>>();
                 let (buf, log, labels_off) = self.be.build_exe(&all_labels)?;
-                (buf, gbodies, labels_off, log, modkind)
+                (buf, gbodies, labels_off, log, modkind, None)
             }
             #[cfg(test)]
             TraceStart::Test => unreachable!(),
@@ -414,6 +461,8 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             buf,
             guards,
             trace_start,
+            loop_exit,
+            tier_up,
         )))
     }
 
@@ -451,9 +500,9 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             panic!()
         };
         // Assemble the body
-        let peel_vlocs = self.peel_vlocs(args_vlocs, peel);
-        let mut ra = RegAlloc::<AB>::new(self.m, peel, &peel_vlocs, 0);
-        let peel_label = self.be.controlpoint_loop_end()?;
+        let (peel_vlocs, peel_args_stack_off) = self.peel_vlocs(args_vlocs, peel, 0);
+        let mut ra = RegAlloc::<AB>::new(self.m, peel, &peel_vlocs, peel_args_stack_off);
+        let (peel_label, _, _) = self.be.controlpoint_loop_end(None)?;
         ra.set_term_vlocs(&mut self.be, peel, true, &peel_vlocs, &peel_vlocs)?;
         let peel_stack_off = self.p_block(peel, Some(peel), ra, &peel_vlocs)?;
         let iter0_label = self.be.controlpoint_peel_start(peel_label);
@@ -469,8 +518,16 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
         Ok(self.be.build_test(&[]))
     }
 
-    /// Return decent (we can probably never get perfect!) [VarLocs] for the peeled block.
-    fn peel_vlocs(&self, args_vlocs: &[VarLocs<AB::Reg>], peel: &Block) -> Vec<VarLocs<AB::Reg>> {
+    /// Return decent (we can probably never get perfect!) [VarLocs] for the peeled block. The peel
+    /// may have more arguments than `args_vlocs`: any of those that can't be given a register are
+    /// allocated stack slots starting at `stack_off`. Returns the [VarLocs] and the stack offset
+    /// after any such slots.
+    fn peel_vlocs(
+        &self,
+        args_vlocs: &[VarLocs<AB::Reg>],
+        peel: &Block,
+        mut stack_off: u32,
+    ) -> (Vec<VarLocs<AB::Reg>>, u32) {
         // The challenge we have is that we can't know exactly what would the best set of [VarLocs]
         // will be until we've generated code, at which point it's far too late. Fortunately we can
         // do some things that are definite wins and some things that are likely to be wins.
@@ -502,6 +559,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                 }
             })
             .collect::<Vec<_>>();
+        peel_vlocs.resize(peel.term_vars().len(), VarLocs::new());
 
         let mut prb = AB::peel_regs_builder();
         // We now have to make sure we don't allocate the same register twice. There may still be
@@ -564,7 +622,79 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             }
         }
 
-        peel_vlocs
+        for (iidx, vlocs) in peel_vlocs.iter_mut().enumerate().skip(args_vlocs.len()) {
+            if vlocs.is_empty()
+                && !matches!(peel.inst(InstIdx::from_raw_index(iidx)), Inst::Const(_))
+            {
+                let bitw = peel.inst_bitw(self.m, InstIdx::from_raw_index(iidx));
+                stack_off = self.be.align_spill(stack_off, bitw);
+                vlocs.push(VarLoc::Stack(stack_off));
+            }
+        }
+
+        (peel_vlocs, stack_off)
+    }
+
+    /// Record a [GuardExit] through which a thread can leave a loop at the end of `b` (i.e. just
+    /// before it jumps back to the start of the loop), deoptimising to the control point
+    /// `entry_pc` the trace started from. `term_vlocs` are the [VarLocs] of `b`'s term variables
+    /// and `stack_off` is the register allocator's stack offset at `b`'s end. The guard body will
+    /// be attached to `label`.
+    ///
+    /// Nothing ordinarily jumps to this guard body: the loop's back-edge is only patched to do so
+    /// when the trace is invalidated (see [J2CompiledTrace::exit_loop]).
+    fn push_loop_exit(
+        &mut self,
+        b: &'a Block,
+        term_vlocs: &[VarLocs<AB::Reg>],
+        stack_off: u32,
+        entry_pc: &aot_ir::InstId,
+        entry_statepoint: &'static Statepoint,
+        label: AB::Label,
+    ) {
+        // Loop invariant code motion can give the loop extra arguments, but only the variables
+        // live at the control point are needed to deopt to it.
+        let deopt_vars = b.term_vars()[..entry_statepoint.lives.len()].to_vec();
+        let mut exit_vars = Vec::new();
+        let mut copy_in = Vec::new();
+        for (iidx, vlocs) in deopt_vars.iter().zip(term_vlocs) {
+            if let Inst::Const(_) = b.inst(*iidx) {
+                copy_in.push(*iidx);
+            } else {
+                exit_vars.push((*iidx, vlocs.clone()));
+            }
+        }
+        // [GuardExit] requires its variables to be sorted and free of duplicates. A variable which
+        // is passed more than once is in each of the corresponding locations at the end of `b`, so
+        // we can use any of them.
+        exit_vars.sort_by_key(|(iidx, _)| *iidx);
+        exit_vars.dedup_by_key(|(iidx, _)| *iidx);
+        copy_in.sort();
+        copy_in.dedup();
+        let (exit_vars, exit_vlocs) = exit_vars.into_iter().unzip();
+
+        let gextra = GuardExtra {
+            bid: aot_ir::BBlockId::new(entry_pc.funcidx(), entry_pc.bbidx()),
+            switch: None,
+            deopt_vars,
+            cond_deopt_vars: SmallVec::new(),
+            deopt_frames: smallvec![Frame {
+                pc: entry_pc.clone(),
+                pc_statepoint: entry_statepoint,
+                #[cfg(test)]
+                smapidx: entry_statepoint.smapidx,
+            }],
+            virt_objs: Vec::new(),
+        };
+        self.gexits.push(GuardExit {
+            gextra: Cow::Owned(gextra),
+            label,
+            block: b,
+            exit_vars,
+            exit_vlocs,
+            copy_in,
+            stack_off,
+        });
     }
 
     /// Assemble guards.
@@ -591,7 +721,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
         let mut gbodies: TypedVec<CompiledGuardIdx, GuardBody<AB>> =
             TypedVec::with_capacity(gexits.len());
         for gexit in gexits.into_iter() {
-            let gextra = gexit.block.gextra(gexit.geidx);
+            let gextra = &gexit.gextra;
 
             // The temporary `Block` that is the guard body. This will end up as:
             //
@@ -602,6 +732,8 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             // %n + 1: <copy_in[0]>
             // ...
             // %n + m: <copy_in[m]> ; where m == gexit.copy_in.len()
+            // store ... ; one for each field of each of `gextra.virt_objs`
+            // ...
             // ```
             ginsts.clear();
             let mut gblock = Block {
@@ -634,6 +766,19 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                 inst.rewrite_iidxs(&mut gblock, map);
                 gblock.insts.push(inst);
             }
+            // Materialise virtual objects by performing the stores the optimiser deferred.
+            for VirtObj { alloc: _, fields } in gextra.virt_objs.iter() {
+                for (ptr, val) in fields {
+                    gblock.insts.push(
+                        Store {
+                            ptr: map(*ptr),
+                            val: map(*val),
+                            is_volatile: false,
+                        }
+                        .into(),
+                    );
+                }
+            }
 
             // Finally, push the `term` instruction.
             gterms.clear();
@@ -649,6 +794,10 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             let mut deopt_vars = Vec::with_capacity(gextra.deopt_vars.len());
             assert_eq!(gextra.deopt_vars.len(), gblock.term_vars().len());
             let mut deopt_term_iter = gextra.deopt_vars.iter().zip(gblock.term_vars().iter());
+            // The trace-local `alloca`s which deopt materialises, and those which pointers that
+            // deopt has to relocate point into.
+            let mut materialised = Vec::new();
+            let mut relocated = Vec::new();
             for (frame_idx, frame) in gextra.deopt_frames.iter().enumerate() {
                 let frame_deopt_vars_off = deopt_vars.len();
                 #[cfg(not(test))]
@@ -698,13 +847,46 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         }
                     };
                     #[cfg(not(test))]
-                    let tovlocs = AB::smp_to_vloc(smap_loc, RegFill::Zeroed);
+                    let tovlocs = AB::smp_to_vloc(smap_loc, RegFill::Zeroed)?;
                     #[cfg(test)]
                     let tovlocs = smap_loc.clone();
+                    // An `alloca` in a frame which we reconstruct has to be materialised from the
+                    // equivalent trace-local `alloca`; and pointers into a trace-local `alloca`,
+                    // whether to the `alloca` itself or derived from it, that live anywhere else
+                    // have to be relocated to point into the materialised `alloca`.
+                    let base = alloca_base(gexit.block, *deopt_iidx);
+                    let has_stackoff = tovlocs.iter().any(|x| matches!(x, VarLoc::StackOff(_)));
+                    let alloca_bytew = match gexit.block.inst(*deopt_iidx) {
+                        Inst::Alloca(Alloca { bytew, .. }) if has_stackoff => {
+                            materialised.push(*deopt_iidx);
+                            Some(*bytew)
+                        }
+                        _ => {
+                            if base.is_none() && frame_idx > 0 && has_stackoff {
+                                unsupported!("alloca not created by this trace live at deopt");
+                            }
+                            None
+                        }
+                    };
+                    let alloca_ptr = match base {
+                        Some(base) if tovlocs.iter().any(|x| !matches!(x, VarLoc::StackOff(_))) => {
+                            relocated.push(base);
+                            true
+                        }
+                        _ => false,
+                    };
+                    // Deopt reconstructs each value with at most one 64-bit write, so wider
+                    // values must be rejected now rather than when the guard fails.
+                    let bitw = gblock.inst_bitw(self.m, *term_iidx);
+                    if bitw > 64 {
+                        unsupported!("{bitw}-bit value live at deopt");
+                    }
                     deopt_vars.push(DeoptVar {
-                        bitw: gblock.inst_bitw(self.m, *term_iidx),
+                        bitw,
                         fromvlocs,
                         tovlocs,
+                        alloca_bytew,
+                        alloca_ptr,
                     });
                 }
 
@@ -731,9 +913,12 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                 // Optimise no-ops. We have to be careful here: same-offset-stack-based writes are
                 // no-ops in the controlpoint frame, but not in subsequent frames (since we create
                 // the latter from scratch); and `Const` / `Reg` `StackOff` / `Const`s are no-ops
-                // everywhere.
+                // everywhere, except for `alloca`s, whose contents always have to be copied, and
+                // pointers into `alloca`s, which always have to be relocated.
                 for deopt_var in frame_deopt_vars {
-                    if deopt_var.fromvlocs == deopt_var.tovlocs
+                    if deopt_var.alloca_bytew.is_none()
+                        && !deopt_var.alloca_ptr
+                        && deopt_var.fromvlocs == deopt_var.tovlocs
                         && (frame_idx == 0
                             || deopt_var.fromvlocs.iter().all(|vloc| {
                                 matches!(
@@ -755,6 +940,9 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
             }
             assert!(deopt_term_iter.next().is_none());
             assert!(!gblock.insts.is_empty());
+            if relocated.iter().any(|x| !materialised.contains(x)) {
+                unsupported!("pointer into an alloca live at deopt without the alloca itself");
+            }
 
             let mut merged = false;
             let mut gidx = gbodies.len();
@@ -790,7 +978,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     // (hacky) amount we add to `extra_stack_len` a little below, or we could
                     // introduce a patching mechanism where we go back and patch the base guard
                     // body, and any other merged guard bodies, to share the same stack size.
-                    todo!();
+                    unsupported!("merged guard body requires too much stack");
                 }
                 gbodies[gidx].patch_labels.push(patch_label.clone());
                 self.be.guard_completed(
@@ -896,6 +1084,27 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
         let mut gexit_vars = Vob::from_elem(false, b.insts_len());
         let mut gcopy = Vob::from_elem(false, b.insts_len());
         let mut gqueue: Vec<InstIdx> = Vec::new();
+        // Which instructions does the block need, ignoring guards' deopt variables? An allocation
+        // that isn't needed is only referenced by guards (e.g. because it is a virtual object: see
+        // [GuardExtra::virt_objs]) and can be copied into their guard bodies.
+        let mut needed = Vob::from_elem(false, b.insts_len());
+        for (iidx, inst) in b.insts_iter(..).rev() {
+            if needed[iidx.to_raw_index()]
+                || inst.write_effects().interferes(Effects::all())
+                || inst
+                    .read_effects()
+                    .interferes(Effects::none().add_volatile())
+            {
+                needed.set(iidx.to_raw_index(), true);
+                if let Inst::Guard(Guard { cond, .. }) = inst {
+                    needed.set(cond.to_raw_index(), true);
+                } else {
+                    for op_iidx in inst.iter_iidxs(b) {
+                        needed.set(op_iidx.to_raw_index(), true);
+                    }
+                }
+            }
+        }
 
         self.be.about_to_process_block(b, args_vlocs);
 
@@ -921,6 +1130,9 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         self.be.i_add(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::Alloca(_) => {
+                    ra.alloc_alloca(&mut self.be, iidx)?;
+                }
                 Inst::And(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_and(&mut ra, b, iidx, x)?;
@@ -944,10 +1156,34 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                 Inst::BlackBox(BlackBox { val }) => {
                     ra.blackbox(iidx, *val);
                 }
-                Inst::Call(x) => self.be.i_call(&mut ra, b, iidx, x)?,
+                Inst::Call(x) => {
+                    // After deopt, an `alloca` lives in the reconstructed frame rather than the
+                    // trace's frame, so its address must not escape somewhere that deopt can't
+                    // relocate it.
+                    if matches!(x.effects, CallEffects::Write | CallEffects::ReadWrite)
+                        && x.args.iter().any(|x| alloca_base(b, *x).is_some())
+                    {
+                        unsupported!("alloca address passed to a call which may capture it");
+                    }
+                    // Allocations whose result is unused (e.g. because the optimiser removed all
+                    // of their uses) can be skipped: all other calls may have side effects.
+                    if ra.is_used(iidx) || !matches!(x.effects, CallEffects::Alloc) {
+                        self.be.i_call(&mut ra, b, iidx, x)?;
+                    }
+                }
+                Inst::Ceil(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_ceil(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::Const(_) => {
                     ra.alloc_const(&mut self.be, iidx)?;
                 }
+                Inst::CopySign(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_copysign(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::CtPop(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_ctpop(&mut ra, b, iidx, x)?;
@@ -964,6 +1200,11 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         self.be.i_dynptradd(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::FAbs(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_fabs(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::FAdd(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_fadd(&mut ra, b, iidx, x)?;
@@ -1009,6 +1250,11 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         self.be.i_fsub(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::FTrunc(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_ftrunc(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::FPToSI(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_fptosi(&mut ra, b, iidx, x)?;
@@ -1043,6 +1289,10 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                     gcopy.set_all(false);
                     assert!(gqueue.is_empty());
                     gqueue.extend(&gextra.deopt_vars);
+                    for VirtObj { alloc, fields } in &gextra.virt_objs {
+                        gqueue.push(*alloc);
+                        gqueue.extend(fields.iter().flat_map(|(ptr, val)| [*ptr, *val]));
+                    }
 
                     while let Some(giidx) = gqueue.pop() {
                         if gcopy.get(giidx.to_raw_index()).unwrap() {
@@ -1067,13 +1317,24 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                                 continue;
                             }
                         } else if inst.read_write_effects().interferes(Effects::all())
+                            || matches!(inst, Inst::Alloca(_))
+                            || (matches!(
+                                inst,
+                                Inst::Call(Call {
+                                    effects: CallEffects::Alloc,
+                                    ..
+                                })
+                            ) && needed[giidx.to_raw_index()])
                             || (ra.is_used(giidx)
                                 && !matches!(inst, Inst::Const(_))
                                 && ra.iter_reg_for(giidx).nth(0).is_some())
                         {
                             // We don't copy instructions that are used by non-guard instructions
                             // unless: they're a `Const`; aren't in a register; don't have
-                            // side-effects.
+                            // side-effects. `alloca`s, and allocations that the block needs, are
+                            // never copied, as that would create a second, distinct, object. An
+                            // allocation referenced only by guards is copied, so that it is only
+                            // performed if a guard fails.
                             gexit_vars.set(giidx.to_raw_index(), true);
                             continue;
                         }
@@ -1094,9 +1355,10 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         .collect::<Vec<_>>();
                     let label = self.be.i_guard(&mut ra, b, iidx, x, &exit_vars)?;
                     let exit_vlocs = ra.vlocs_from_iidxs(&exit_vars);
+                    let block = b_self.unwrap();
                     self.gexits.push(GuardExit {
-                        geidx: *geidx,
-                        block: b_self.unwrap(),
+                        gextra: Cow::Borrowed(block.gextra(*geidx)),
+                        block,
                         label,
                         exit_vars,
                         exit_vlocs,
@@ -1138,11 +1400,22 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         self.be.i_mul(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::NoAlias(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_noalias(&mut ra, iidx, x)?;
+                    }
+                }
+                Inst::NoAliasEnd(_) => {}
                 Inst::Or(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_or(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::Overflow(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_overflow(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::PtrAdd(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_ptradd(&mut ra, b, iidx, x)?;
@@ -1188,12 +1461,22 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
                         self.be.i_smin(&mut ra, b, iidx, x)?;
                     }
                 }
+                Inst::Sqrt(x) => {
+                    if ra.is_used(iidx) {
+                        self.be.i_sqrt(&mut ra, b, iidx, x)?;
+                    }
+                }
                 Inst::SRem(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_srem(&mut ra, b, iidx, x)?;
                     }
                 }
-                Inst::Store(x) => self.be.i_store(&mut ra, b, iidx, x)?,
+                Inst::Store(x) => {
+                    if alloca_base(b, x.val).is_some() {
+                        unsupported!("alloca address stored to memory");
+                    }
+                    self.be.i_store(&mut ra, b, iidx, x)?
+                }
                 Inst::Sub(x) => {
                     if ra.is_used(iidx) {
                         self.be.i_sub(&mut ra, b, iidx, x)?;
@@ -1255,7 +1538,7 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
         // We deal with [Arg] instructions specially: they're best thought of as
         // pseudo-instructions in the sense that they define variables but don't directly
         // generate code themselves.
-        ra.set_args_vlocs_at_start(&mut self.be, args_vlocs);
+        ra.set_args_vlocs_at_start(&mut self.be, args_vlocs)?;
         if self.log {
             for (iidx, _inst) in insts_iter {
                 let pp_vlocs = args_vlocs[iidx.to_raw_index()]
@@ -1271,6 +1554,20 @@ impl<'a, AB: HirToAsmBackend> HirToAsm<'a, AB> {
     }
 }
 
+/// If `iidx` is a pointer into an [Alloca] in `b`, either the [Alloca] itself or a pointer derived
+/// from it by `ptradd`s, return the [InstIdx] of the [Alloca].
+fn alloca_base(b: &Block, mut iidx: InstIdx) -> Option<InstIdx> {
+    loop {
+        match b.inst(iidx) {
+            Inst::Alloca(_) => return Some(iidx),
+            Inst::PtrAdd(PtrAdd { ptr, .. }) | Inst::DynPtrAdd(DynPtrAdd { ptr, .. }) => {
+                iidx = *ptr
+            }
+            _ => return None,
+        }
+    }
+}
+
 /// Are the "from" deopt vars in `cnd` compatible with those in `with`? Note: this relationship is
 /// not necessarily symmetric.
 fn deopt_vars_compatible<Reg: RegT>(cnd: &[DeoptVar<Reg>], with: &[DeoptVar<Reg>]) -> bool {
@@ -1279,6 +1576,8 @@ fn deopt_vars_compatible<Reg: RegT>(cnd: &[DeoptVar<Reg>], with: &[DeoptVar<Reg>
     }
     cnd.iter().zip(with.iter()).all(|(x, y)| {
         x.bitw == y.bitw
+            && x.alloca_bytew == y.alloca_bytew
+            && x.alloca_ptr == y.alloca_ptr
             && x.fromvlocs.len() == y.fromvlocs.len()
             && x.fromvlocs
                 .iter()
@@ -1333,7 +1632,7 @@ pub(super) trait HirToAsmBackend {
     fn smp_to_vloc(
         smp_locs: &SmallVec<[yksmp::Location; 1]>,
         reg_fill: RegFill,
-    ) -> VarLocs<Self::Reg>;
+    ) -> Result<VarLocs<Self::Reg>, CompilationError>;
 
     /// Given a set of [VarLocs], return a register that is not used by them. That register must be
     /// suitable for things like moving stack values, and pushing constants.
@@ -1390,7 +1689,13 @@ pub(super) trait HirToAsmBackend {
     fn move_stackoff(&mut self, reg: Self::Reg, stack_off: u32) -> Result<(), CompilationError>;
 
     /// Adjust `dst_bitw` bits of `reg` from `src_fill` to `dst_fill`.
-    fn arrange_fill(&mut self, reg: Self::Reg, src_fill: RegFill, dst_bitw: u32, dst_fill: RegFill);
+    fn arrange_fill(
+        &mut self,
+        reg: Self::Reg,
+        src_fill: RegFill,
+        dst_bitw: u32,
+        dst_fill: RegFill,
+    ) -> Result<(), CompilationError>;
 
     /// Copy `src_reg` to `dst_reg`, adjusting the copied value's fill if necessary.
     fn copy_reg_with_fill(
@@ -1413,6 +1718,12 @@ pub(super) trait HirToAsmBackend {
     ///   of the spilt value.
     fn align_spill(&self, stack_off: u32, bitw: u32) -> u32;
 
+    /// If the stack is currently at offset `stack_off`, return an offset suitable for an
+    /// [Alloca] of `bytew` bytes aligned to `align` bytes: the lowest address of the allocation
+    /// will be exactly at the returned offset. `align` is guaranteed to be a power of two no
+    /// greater than 16.
+    fn align_alloca(stack_off: u32, bytew: u32, align: u32) -> u32;
+
     /// Spill `bitw` bits in `reg`, whose fill is `in_fill` to `stack_off` (which may or may not be
     /// aligned for `bitw`). Note: this function may leave CPU flags in an undefined state.
     fn spill(
@@ -1440,7 +1751,7 @@ pub(super) trait HirToAsmBackend {
         src_stack_off: u32,
         dst_stack_off: u32,
         tmp_reg: Self::Reg,
-    );
+    ) -> Result<(), CompilationError>;
 
     // Functions for the start and end of various kinds of traces. Note that some functions are
     // used by more than one (x, y) trace kind.
@@ -1453,8 +1764,19 @@ pub(super) trait HirToAsmBackend {
         stack_off: u32,
     ) -> Result<Self::Label, CompilationError>;
 
-    /// Produce code for the backwards jump at the end of a (ControlPoint, Loop) trace.
-    fn controlpoint_loop_end(&mut self) -> Result<Self::Label, CompilationError>;
+    /// Produce code for the backwards jump at the end of a (ControlPoint, Loop) trace. Returns a
+    /// triple `(loop_label, backedge_label, exit_label)` where: `loop_label` is the target of the
+    /// backwards jump; `backedge_label` is attached to the backwards jump itself, which must be
+    /// patchable so that it jumps to `exit_label` instead (see [J2CompiledTrace::exit_loop]); and
+    /// `exit_label` is a fresh label for the guard body which leaves the loop.
+    ///
+    /// If `tier_up` is `Some`, the counter must be decremented before each backwards jump and, if
+    /// it reaches zero, the loop must be left by jumping to `exit_label` (see
+    /// [CompiledTrace::count_execution]).
+    fn controlpoint_loop_end(
+        &mut self,
+        tier_up: Option<&AtomicI64>,
+    ) -> Result<(Self::Label, Self::Label, Self::Label), CompilationError>;
 
     fn controlpoint_peel_start(&mut self, peel_label: Self::Label) -> Self::Label;
 
@@ -1556,6 +1878,22 @@ pub(super) trait HirToAsmBackend {
         inst: &Call,
     ) -> Result<(), CompilationError>;
 
+    fn i_ceil(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &Ceil,
+    ) -> Result<(), CompilationError>;
+
+    fn i_copysign(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &CopySign,
+    ) -> Result<(), CompilationError>;
+
     fn i_ctpop(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1580,6 +1918,14 @@ pub(super) trait HirToAsmBackend {
         inst: &DynPtrAdd,
     ) -> Result<(), CompilationError>;
 
+    fn i_fabs(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &FAbs,
+    ) -> Result<(), CompilationError>;
+
     fn i_fadd(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1636,6 +1982,14 @@ pub(super) trait HirToAsmBackend {
         inst: &FSub,
     ) -> Result<(), CompilationError>;
 
+    fn i_ftrunc(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &FTrunc,
+    ) -> Result<(), CompilationError>;
+
     fn i_fpclass(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1736,6 +2090,13 @@ pub(super) trait HirToAsmBackend {
         inst: &Mul,
     ) -> Result<(), CompilationError>;
 
+    fn i_noalias(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        iidx: InstIdx,
+        inst: &NoAlias,
+    ) -> Result<(), CompilationError>;
+
     fn i_or(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1744,6 +2105,14 @@ pub(super) trait HirToAsmBackend {
         inst: &Or,
     ) -> Result<(), CompilationError>;
 
+    fn i_overflow(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &Overflow,
+    ) -> Result<(), CompilationError>;
+
     fn i_ptradd(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1816,6 +2185,14 @@ pub(super) trait HirToAsmBackend {
         inst: &SMin,
     ) -> Result<(), CompilationError>;
 
+    fn i_sqrt(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        inst: &Sqrt,
+    ) -> Result<(), CompilationError>;
+
     fn i_srem(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -1917,7 +2294,9 @@ pub(super) trait HirToAsmBackend {
 /// body of a trace. They will be converted by [HirToAsm::asm_guards] to [GuardBody]s.
 #[derive(Debug)]
 struct GuardExit<'a, AB: HirToAsmBackend + ?Sized> {
-    geidx: GuardExtraIdx,
+    /// The deopt information for this exit: for guards, this is the [GuardExtra] of the guard
+    /// instruction; for loop exits, it is created by [HirToAsm::push_loop_exit].
+    gextra: Cow<'a, GuardExtra>,
     label: AB::Label,
     /// The block that contained the associated guard.
     block: &'a Block,
@@ -1978,6 +2357,8 @@ mod test {
                 VarLoc::Reg(TestReg::R0, RegFill::Undefined)
             ],
             tovlocs: VarLocs::new(),
+            alloca_bytew: None,
+            alloca_ptr: false,
         }];
         let y = vec![DeoptVar {
             bitw: 64,
@@ -1986,11 +2367,15 @@ mod test {
                 VarLoc::Reg(TestReg::R0, RegFill::Undefined)
             ],
             tovlocs: VarLocs::new(),
+            alloca_bytew: None,
+            alloca_ptr: false,
         }];
         let z = vec![DeoptVar {
             bitw: 64,
             fromvlocs: varlocs![VarLoc::Stack(8), VarLoc::Reg(TestReg::R0, RegFill::Zeroed)],
             tovlocs: VarLocs::new(),
+            alloca_bytew: None,
+            alloca_ptr: false,
         }];
 
         assert!(deopt_vars_compatible(&x, &x));
@@ -2004,6 +2389,8 @@ mod test {
             bitw: 64,
             fromvlocs: varlocs![VarLoc::Stack(8), VarLoc::Reg(TestReg::R0, RegFill::Signed)],
             tovlocs: VarLocs::new(),
+            alloca_bytew: None,
+            alloca_ptr: false,
         }];
         assert!(!deopt_vars_compatible(&z, &sgn));
         assert!(!deopt_vars_compatible(&sgn, &z));
@@ -2081,13 +2468,8 @@ mod test {
             match ty {
                 Ty::Double | Ty::Float => todo!(),
                 Ty::Func(_func_ty) => todo!(),
-                Ty::Int(bitw) => {
-                    if *bitw <= 64 {
-                        self.gp_regs.next()
-                    } else {
-                        todo!()
-                    }
-                }
+                Ty::Int(bitw) if *bitw <= 64 => self.gp_regs.next(),
+                Ty::Int(_) => None,
                 Ty::Ptr(addrspace) => {
                     assert_eq!(*addrspace, 0);
                     self.gp_regs.next()
@@ -2197,10 +2579,11 @@ mod test {
             src_fill: RegFill,
             dst_bitw: u32,
             dst_fill: RegFill,
-        ) {
+        ) -> Result<(), CompilationError> {
             self.log.push(format!(
                 "arrange_fill: reg={reg:?}, from={src_fill:?}, dst_bitw={dst_bitw}, to={dst_fill:?}"
             ));
+            Ok(())
         }
 
         fn copy_reg_with_fill(
@@ -2219,6 +2602,10 @@ mod test {
             stack_off + (bitw / 8).next_multiple_of(8)
         }
 
+        fn align_alloca(stack_off: u32, bytew: u32, align: u32) -> u32 {
+            (stack_off + bytew).next_multiple_of(align)
+        }
+
         fn spill(
             &mut self,
             reg: Self::Reg,
@@ -2232,9 +2619,16 @@ mod test {
             Ok(())
         }
 
-        fn controlpoint_loop_end(&mut self) -> Result<Self::Label, CompilationError> {
+        fn controlpoint_loop_end(
+            &mut self,
+            _tier_up: Option<&AtomicI64>,
+        ) -> Result<(Self::Label, Self::Label, Self::Label), CompilationError> {
             self.log.push("controlpoint_loop_end".to_owned());
-            Ok(TestLabelIdx::from_raw_index(1))
+            Ok((
+                TestLabelIdx::from_raw_index(1),
+                TestLabelIdx::from_raw_index(3),
+                TestLabelIdx::from_raw_index(4),
+            ))
         }
 
         fn controlpoint_peel_start(&mut self, peel_label: Self::Label) -> Self::Label {
@@ -2490,6 +2884,10 @@ mod test {
             tracecompilation_errors: 0,
             #[cfg(feature = "ykd")]
             debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
         }));
 
         let be = TestHirToAsm::new(&m);
@@ -2521,6 +2919,10 @@ mod test {
             tracecompilation_errors: 0,
             #[cfg(feature = "ykd")]
             debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
         }));
 
         let be = TestHirToAsm::new(&m);
@@ -2831,6 +3233,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn gbody_wide_deopt_var() {
+        // Deopt can't reconstruct values wider than 64 bits, so compilation must fail.
+        let m = str_to_mod::<TestReg>(
+            r#"
+          %0: i1 = arg [reg]
+          %1: i128 = arg [stack(16)]
+          guard true, %0, [%1], [[[stack(16)]]]
+          term [%0, %1]
+        "#,
+        );
+        let hl = Arc::new(Mutex::new(HotLocation {
+            kind: HotLocationKind::Tracing(TraceId::testing()),
+            tracecompilation_errors: 0,
+            #[cfg(feature = "ykd")]
+            debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
+        }));
+        let be = TestHirToAsm::new(&m);
+        match HirToAsm::new(&m, hl, be, true).build_test() {
+            Err(CompilationError::General(e)) => assert_eq!(e, "128-bit value live at deopt"),
+            x => panic!("{x:?}"),
+        }
+    }
+
+    #[test]
+    fn gbody_virt_objs() {
+        // The fields of virtual objects are stored in the guard body.
+        build_and_test(
+            r#"
+          %0: i1 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = arg [reg]
+          %3: ptr = ptradd %2, 8
+          guard true, %0, [%2] virtual %2 [%3: %1]
+          term [%0, %1, %2]
+        "#,
+            |s| s.starts_with("; "),
+            &[r#"
+          ...
+          ; guard true, %0, [%2] virtual %2 [%3: %1]
+          ...
+          ; term [%1]
+          ; store %0, %2
+          ; %2: ptr = ptradd %1, 8
+          ...
+            "#],
+        );
+    }
+
     #[test]
     fn peel() {
         // Basic peeling with nothing to optimise
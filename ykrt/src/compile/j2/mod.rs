@@ -10,6 +10,23 @@
 //! Pass 1 is a a "forward" (i.e. normal) pass. Pass 2 is a "reverse" pass: roughly speaking, it
 //! iterates from the last to the first instruction in a trace.
 
+/// Abort compilation of the current trace because it contains something j2 does not (yet)
+/// support. Takes the same arguments as [format!]: the resulting string should say what is
+/// unsupported, since it is reported to the user as the reason the trace was aborted.
+macro_rules! unsupported {
+    ($($arg:tt)*) => {
+        return Err($crate::compile::CompilationError::General(format!($($arg)*)))
+    };
+}
+
+/// Abort compilation of the current trace because j2 has reached a state that should be
+/// impossible. Takes the same arguments as [format!].
+macro_rules! internal_error {
+    ($($arg:tt)*) => {
+        return Err($crate::compile::CompilationError::InternalError(format!($($arg)*)))
+    };
+}
+
 mod aot_to_hir;
 mod codebuf;
 mod compiled_trace;
@@ -23,13 +40,15 @@ mod regalloc;
 #[cfg(target_arch = "x86_64")]
 mod x64;
 
+pub(crate) use opt::OptConfig;
+
 use crate::{
     compile::{
-        CompilationError, CompiledTrace, Compiler, Trace, TraceEnd, TraceStart,
+        CompilationError, CompiledTrace, Compiler, Tier, Trace, TraceEnd, TraceStart,
         j2::codebuf::CodeBufInProgress, jitc_yk::AOT_MOD,
     },
     log::{IRPhase, should_log_ir},
-    mt::MT,
+    mt::{MT, TraceId},
 };
 use libc::{MAP_ANON, MAP_FAILED, MAP_PRIVATE, PROT_READ, PROT_WRITE, mmap, munmap};
 use parking_lot::Mutex;
@@ -39,7 +58,7 @@ use std::{
     error::Error,
     ffi::{CStr, CString, c_void},
     mem::MaybeUninit,
-    sync::Arc,
+    sync::{Arc, atomic::AtomicI64},
 };
 
 thread_local! {
@@ -228,6 +247,15 @@ impl Compiler for J2 {
         mt: Arc<MT>,
         trace: &mut Trace,
     ) -> Result<Arc<dyn CompiledTrace>, CompilationError> {
+        // The trace we couple to may have been freed while this trace was waiting to be compiled.
+        let coupler_ctr = |trid: TraceId| {
+            mt.try_compiled_trace(trid).ok_or_else(|| {
+                CompilationError::General(format!(
+                    "Coupler target trace {} has been freed",
+                    trid.as_u64()
+                ))
+            })
+        };
         let (hl, bkind) = match (&trace.trace_start, &trace.trace_end) {
             (TraceStart::ControlPoint { hl }, TraceEnd::Loop) => {
                 (Arc::clone(hl), aot_to_hir::BuildKind::Loop)
@@ -235,7 +263,7 @@ impl Compiler for J2 {
             (TraceStart::ControlPoint { hl }, TraceEnd::Coupler(coupler_tid)) => (
                 Arc::clone(hl),
                 aot_to_hir::BuildKind::Coupler {
-                    tgt_ctr: mt.compiled_trace(*coupler_tid),
+                    tgt_ctr: coupler_ctr(*coupler_tid)?,
                 },
             ),
             (TraceStart::ControlPoint { hl }, TraceEnd::Return) => {
@@ -247,7 +275,7 @@ impl Compiler for J2 {
                 aot_to_hir::BuildKind::Side {
                     src_ctr: Arc::clone(parent_ctr),
                     src_gid: *gid,
-                    tgt_ctr: Some(mt.compiled_trace(*coupler_tid)),
+                    tgt_ctr: Some(coupler_ctr(*coupler_tid)?),
                 },
             ),
             (TraceStart::Guard { parent_ctr, gid }, TraceEnd::Return) => (
@@ -263,6 +291,12 @@ impl Compiler for J2 {
         #[cfg(target_arch = "x86_64")]
         type AotToHir<'a> = aot_to_hir::AotToHir<'a, x64::Reg>;
 
+        // Baseline traces are compiled as quickly as possible: they are later recompiled with
+        // optimisation if they are executed often enough.
+        let opt_level = match trace.tier {
+            Tier::Baseline => 0,
+            Tier::Normal | Tier::Recompile(_) => mt.opt_level(),
+        };
         let mut promotions_iter = trace.promotions.iter();
         let mut debug_strs_iter = trace.debug_strs.iter().map(|x| x.as_str());
         let hm = AotToHir::new(
@@ -273,6 +307,7 @@ impl Compiler for J2 {
             &mut trace.ta_iter,
             trace.ctrid,
             bkind,
+            opt_level,
             &mut promotions_iter,
             &mut debug_strs_iter,
         )
@@ -286,7 +321,15 @@ impl Compiler for J2 {
         #[cfg(target_arch = "x86_64")]
         let be = x64::x64hir_to_asm::X64HirToAsm::new(&hm, buf, log);
 
-        let ct = hir_to_asm::HirToAsm::new(&hm, hl, be, log).build(mt.clone())?;
+        // Baseline traces count their executions (including loop iterations) down from the
+        // recompile threshold in force now (see [CompiledTrace::count_execution]).
+        let tier_up = match trace.tier {
+            Tier::Baseline => Some(Box::new(AtomicI64::new(i64::from(
+                mt.recompile_threshold(),
+            )))),
+            Tier::Normal | Tier::Recompile(_) => None,
+        };
+        let ct = hir_to_asm::HirToAsm::new(&hm, hl, be, log).build(mt.clone(), tier_up)?;
 
         // Register JITted code (if required).
         mt.trace_profiler().register_ctr(&ct).map_err(|e| {
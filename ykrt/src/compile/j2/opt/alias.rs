@@ -0,0 +1,235 @@
+//! Alias analysis.
+//!
+//! This module answers the question "might two memory accesses overlap?" for optimisation passes.
+//! Pointers are described as an [Address] (a base pointer plus a constant offset, or a constant
+//! address) and the extent of an access as an [Extent]. We can prove that two accesses do not
+//! alias when:
+//!
+//!   * they are at non-overlapping offsets from the same base pointer;
+//!   * they are at non-overlapping constant addresses;
+//!   * one is relative to memory returned by an allocator in the trace ([CallEffects::Alloc]) or
+//!     a trace-local stack allocation ([Alloca]) and the other is relative to: a constant address
+//!     (which cannot point to memory allocated after the trace was compiled); or a pointer defined
+//!     earlier in the trace than the allocation (which cannot point to memory that did not exist
+//!     when it was defined). The latter means that distinct allocations never alias.
+//!
+//! In all other cases we must assume that two accesses may alias: in particular we currently
+//! assume that any two pointers derived from different non-allocation bases (e.g. two `arg`s) may
+//! alias.
+//!
+//! The exception is LLVM's `noalias` argument attribute, which only holds for the duration of a
+//! function call (see [NoAlias]): passes which track which [NoAlias] scopes are open can use
+//! [noalias_disjoint] to show that a write within a scope does not alias an earlier access within
+//! that scope.
+
+use crate::compile::j2::{
+    effects::Effects,
+    hir::*,
+    opt::{BlockLikeT, EquivIIdxT, ModLikeT},
+};
+
+/// The extent of a memory access relative to an [Address].
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Extent {
+    /// An access of the given number of bytes starting at the address.
+    Bytes(u32),
+    /// An access that may be at any offset from the address. This is the case for a callee
+    /// passed a pointer, since it can access any part of the object that the pointer points to.
+    Object,
+}
+
+/// Might an access of extent `a_ext` at `a` overlap with an access of extent `b_ext` at `b`?
+pub(super) fn may_alias<T: BlockLikeT + EquivIIdxT>(
+    opt: &T,
+    a: &Address,
+    a_ext: Extent,
+    b: &Address,
+    b_ext: Extent,
+) -> bool {
+    match (a, b) {
+        (Address::PtrOff(a_base, a_off), Address::PtrOff(b_base, b_off)) => {
+            let a_base = opt.equiv_iidx(*a_base);
+            let b_base = opt.equiv_iidx(*b_base);
+            if a_base == b_base {
+                overlaps(i128::from(*a_off), a_ext, i128::from(*b_off), b_ext)
+            } else {
+                !(predates_alloc(opt, a_base, b_base) || predates_alloc(opt, b_base, a_base))
+            }
+        }
+        (Address::Const(a_addr), Address::Const(b_addr)) => overlaps(
+            i128::try_from(*a_addr).unwrap(),
+            a_ext,
+            i128::try_from(*b_addr).unwrap(),
+            b_ext,
+        ),
+        (Address::PtrOff(base, _), Address::Const(_))
+        | (Address::Const(_), Address::PtrOff(base, _)) => !is_alloc(opt, opt.equiv_iidx(*base)),
+    }
+}
+
+/// `scopes` are the [NoAlias] instructions whose scopes are open. If the instruction at `a_iidx`
+/// writes to `a`, and the instruction at `b_iidx` accesses `b`, do any of those scopes tell us
+/// that the two accesses do not alias? This is the case if both instructions are within the scope
+/// of a [NoAlias], one access is relative to that [NoAlias], and the other is relative to a
+/// constant address or a pointer defined before the scope started (which therefore cannot be
+/// based on the [NoAlias]).
+pub(super) fn noalias_disjoint<T: EquivIIdxT>(
+    opt: &T,
+    scopes: &[InstIdx],
+    a: &Address,
+    a_iidx: InstIdx,
+    b: &Address,
+    b_iidx: InstIdx,
+) -> bool {
+    scopes.iter().any(|scope| {
+        a_iidx > *scope
+            && b_iidx > *scope
+            && ((is_based_on(opt, a, *scope) && predates(opt, b, *scope))
+                || (is_based_on(opt, b, *scope) && predates(opt, a, *scope)))
+    })
+}
+
+/// Might the instruction `inst` write to memory overlapping an access of extent `ext` at `addr`?
+pub(super) fn may_write<T: BlockLikeT + EquivIIdxT>(
+    m: &dyn ModLikeT,
+    opt: &T,
+    inst: &Inst,
+    addr: &Address,
+    ext: Extent,
+) -> bool {
+    match inst {
+        Inst::Store(Store { val, ptr, .. }) => {
+            let bytew = opt.inst_bitw(m, opt.equiv_iidx(*val)).div_ceil(8);
+            may_alias(
+                opt,
+                &Address::from(opt, *ptr),
+                Extent::Bytes(bytew),
+                addr,
+                ext,
+            )
+        }
+        Inst::Call(Call {
+            effects: CallEffects::ArgMemWrite | CallEffects::ArgMemReadWrite,
+            args,
+            ..
+        }) => args
+            .iter()
+            .filter(|x| matches!(m.ty(opt.inst(**x).tyidx(m)), Ty::Ptr(_)))
+            .any(|x| may_alias(opt, &Address::from(opt, *x), Extent::Object, addr, ext)),
+        _ => inst
+            .write_effects()
+            .interferes(Effects::none().add_heap().add_volatile()),
+    }
+}
+
+/// Does an access of `a_bytew` bytes at `a` certainly include every byte of an access of
+/// `b_bytew` bytes at `b`? Note: `a` and `b` must have been created from the same [Block] (or
+/// from an optimiser at the same point) for their bases to be compared.
+pub(super) fn covers(a: &Address, a_bytew: u32, b: &Address, b_bytew: u32) -> bool {
+    let (a_off, b_off) = match (a, b) {
+        (Address::PtrOff(a_base, a_off), Address::PtrOff(b_base, b_off)) if a_base == b_base => {
+            (i128::from(*a_off), i128::from(*b_off))
+        }
+        (Address::Const(a_addr), Address::Const(b_addr)) => (
+            i128::try_from(*a_addr).unwrap(),
+            i128::try_from(*b_addr).unwrap(),
+        ),
+        _ => return false,
+    };
+    a_off <= b_off && b_off + i128::from(b_bytew) <= a_off + i128::from(a_bytew)
+}
+
+/// Do accesses of extent `a_ext` at `a` and extent `b_ext` at `b` overlap?
+fn overlaps(a: i128, a_ext: Extent, b: i128, b_ext: Extent) -> bool {
+    match (a_ext, b_ext) {
+        (Extent::Bytes(a_bytew), Extent::Bytes(b_bytew)) => {
+            a < b + i128::from(b_bytew) && b < a + i128::from(a_bytew)
+        }
+        _ => true,
+    }
+}
+
+/// Is `addr` relative to the pointer `iidx`?
+fn is_based_on<T: EquivIIdxT>(opt: &T, addr: &Address, iidx: InstIdx) -> bool {
+    matches!(addr, Address::PtrOff(base, _) if opt.equiv_iidx(*base) == iidx)
+}
+
+/// Is `addr` a constant address or relative to a pointer defined before `iidx`?
+fn predates<T: EquivIIdxT>(opt: &T, addr: &Address, iidx: InstIdx) -> bool {
+    match addr {
+        Address::PtrOff(base, _) => opt.equiv_iidx(*base) < iidx,
+        Address::Const(_) => true,
+    }
+}
+
+/// Is `iidx` an allocation?
+fn is_alloc<T: BlockLikeT>(opt: &T, iidx: InstIdx) -> bool {
+    matches!(
+        opt.inst(iidx),
+        Inst::Alloca(_)
+            | Inst::Call(Call {
+                effects: CallEffects::Alloc,
+                ..
+            })
+    )
+}
+
+/// Is `alloc` an allocation and `other` a pointer defined before it? If so, `other` cannot point
+/// into the memory that `alloc` returns.
+fn predates_alloc<T: BlockLikeT>(opt: &T, alloc: InstIdx, other: InstIdx) -> bool {
+    other < alloc && is_alloc(opt, alloc)
+}
+
+/// An abstract "address" representing a location in RAM.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub(super) enum Address {
+    /// A constant offset from a base pointer. The base pointer is never a [PtrAdd].
+    PtrOff(InstIdx, i32),
+    /// A constant address (e.g. a global variable or a promoted pointer).
+    Const(usize),
+}
+
+impl Address {
+    /// Create a new `address` for the instruction at `ptr`. Note: `ptr` does not need to have
+    /// been `equiv_iidx`ed.
+    pub(super) fn from<T: BlockLikeT + EquivIIdxT>(opt: &T, mut ptr: InstIdx) -> Self {
+        // We now chase the instruction at `ptr` backwards if it's a chain of `PtrAdd`s. So
+        // if we have:
+        //
+        // ```
+        // %0: ptr = arg [reg]
+        // %1: ptradd %0, 8
+        // %2: ptradd %1, 4
+        // ```
+        //
+        // and we call `Address::from(%2)` then `Address::PtrOff(%0, 12)` is returned.
+
+        let mut cum_off: i32 = 0; // Cumulative offset over the chain of `PtrAdd`s.
+        ptr = opt.equiv_iidx(ptr);
+        while let Inst::PtrAdd(PtrAdd {
+            ptr: child_ptr,
+            off,
+            nusw,
+            nuw,
+            in_bounds,
+        }) = opt.inst(ptr)
+        {
+            // We don't support nusw or nuw yet
+            assert!(!nusw && !nuw && !in_bounds);
+            cum_off = cum_off.checked_add(*off).unwrap();
+            ptr = opt.equiv_iidx(*child_ptr);
+        }
+        if let Inst::Const(c) = opt.inst(ptr) {
+            let Const {
+                tyidx: _,
+                kind: ConstKind::Ptr(addr),
+            } = c
+            else {
+                panic!()
+            };
+            Address::Const(*addr + usize::try_from(cum_off).unwrap())
+        } else {
+            Address::PtrOff(ptr, cum_off)
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Dead store elimination.
+//!
+//! A `store` is dead if the memory it writes is overwritten before anything can observe it. We
+//! find such stores with a backwards pass over a [Block], maintaining the set of memory ranges
+//! that are certain to be overwritten before being observed. Memory can be observed by:
+//!
+//!   * a `load` that may alias the range;
+//!   * a guard: if it fails, deoptimisation (and the interpreter thereafter) can read any part of
+//!     the heap;
+//!   * any other instruction with effects (e.g. calls, `volatile` stores, and `memcpy`s);
+//!   * the end of the block.
+//!
+//! In other words, a store before a guard is only dead if it is overwritten before that guard.
+
+use crate::compile::j2::{
+    effects::Effects,
+    hir::*,
+    opt::{
+        BlockLikeT, ModLikeT,
+        alias::{Address, Extent, covers, may_alias},
+    },
+};
+use index_type::{IndexType, typed_vec, vec::TypedVec};
+use vob::Vob;
+
+/// Remove dead stores from `b`. `may_remove` is called for each dead store: if it returns `false`,
+/// the store is kept.
+pub(super) fn dse(
+    m: &dyn ModLikeT,
+    b: Block,
+    may_remove: &mut dyn FnMut(&Block, InstIdx) -> bool,
+) -> Block {
+    let dead = dead_stores(m, &b, may_remove);
+    if dead.iter_set_bits(..).next().is_none() {
+        return b;
+    }
+
+    // Removing instructions changes the [InstIdx]s of those after them, so we rebuild the block.
+    let Block {
+        insts: old_insts,
+        guard_extras,
+    } = b;
+    let mut b = Block {
+        insts: TypedVec::with_capacity(old_insts.len_usize()),
+        guard_extras,
+    };
+    let mut map = typed_vec![InstIdx::MAX; old_insts.len_usize()];
+    for (i, mut inst) in old_insts.into_iter().enumerate() {
+        if dead[i] {
+            continue;
+        }
+        inst.rewrite_iidxs(&mut b, |x| map[x]);
+        map[InstIdx::from_raw_index(i)] = b.insts.push(inst);
+    }
+    b
+}
+
+/// Return the set of stores in `b` which are dead and which `may_remove` allows to be removed.
+fn dead_stores(
+    m: &dyn ModLikeT,
+    b: &Block,
+    may_remove: &mut dyn FnMut(&Block, InstIdx) -> bool,
+) -> Vob {
+    let mut dead = Vob::from_elem(false, b.insts_len());
+    // Memory ranges, as `(address, byte width)` pairs, which will definitely be overwritten before
+    // they can be observed.
+    let mut overwritten: Vec<(Address, u32)> = Vec::new();
+    for (iidx, inst) in b.insts_iter(..).rev() {
+        match inst {
+            Inst::Store(Store {
+                val,
+                ptr,
+                is_volatile: false,
+            }) => {
+                let addr = Address::from(b, *ptr);
+                let bytew = b.inst_bitw(m, *val).div_ceil(8);
+                if overwritten
+                    .iter()
+                    .any(|(x_addr, x_bytew)| covers(x_addr, *x_bytew, &addr, bytew))
+                    && may_remove(b, iidx)
+                {
+                    dead.set(iidx.to_raw_index(), true);
+                } else {
+                    overwritten.push((addr, bytew));
+                }
+            }
+            Inst::Load(Load {
+                tyidx,
+                ptr,
+                is_volatile: false,
+            }) => {
+                let addr = Address::from(b, *ptr);
+                let ext = Extent::Bytes(m.ty(*tyidx).bitw().div_ceil(8));
+                overwritten.retain(|(x_addr, x_bytew)| {
+                    !may_alias(b, x_addr, Extent::Bytes(*x_bytew), &addr, ext)
+                });
+            }
+            // `noalias` scopes only inform alias analysis: they can't observe memory.
+            Inst::NoAlias(_) | Inst::NoAliasEnd(_) => (),
+            _ => {
+                if matches!(inst, Inst::Guard(_) | Inst::Term(_))
+                    || inst.read_write_effects().interferes(Effects::all())
+                {
+                    overwritten.clear();
+                }
+            }
+        }
+    }
+    dead
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compile::j2::opt::fullopt::test::full_opt_test;
+
+    #[test]
+    fn dead_stores() {
+        // A store overwritten before it can be observed is removed.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          store %1, %0
+          store %2, %0
+          term [%0, %1, %2]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          store %2, %0
+          term [%0, %1, %2]
+          ...
+        ",
+        );
+
+        // ...as is one whose memory is covered by a later, wider, store.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i32 = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          %3: ptr = ptradd %0, 4
+          store %1, %3
+          store %2, %0
+          term [%0, %1, %2]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i32 = arg
+          %2: i64 = arg
+          store %2, %0
+          term [%0, %1, %2]
+          ...
+        ",
+        );
+
+        // Stores to different addresses are both needed.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          %3: ptr = ptradd %0, 4
+          store %1, %3
+          store %2, %0
+          term [%0, %1, %2]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          %3: ptr = ptradd %0, 4
+          store %1, %3
+          store %2, %0
+          term [%0, %1, %2]
+          ...
+        ",
+        );
+    }
+
+    #[test]
+    fn observed_stores() {
+        // A load which may alias the store observes it.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: ptr = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          %3: i64 = arg [reg("GPR3", undefined)]
+          store %2, %0
+          %5: i64 = load %1
+          store %3, %0
+          blackbox %5
+          term [%0, %1, %2, %3]
+        "#,
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i64 = arg
+          %3: i64 = arg
+          store %2, %0
+          %5: i64 = load %1
+          store %3, %0
+          blackbox %5
+          term [%0, %1, %2, %3]
+          ...
+        ",
+        );
+
+        // A guard's deopt may observe the store.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          store %1, %0
+          %4: i64 = 10
+          %5: i1 = icmp ult %2, %4
+          guard true, %5, []
+          store %2, %0
+          term [%0, %1, %2]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          store %1, %0
+          %4: i64 = 10
+          %5: i1 = icmp ult %2, %4
+          guard true, %5, []
+          store %2, %0
+          term [%0, %1, %2]
+          ...
+        ",
+        );
+
+        // As may a volatile store.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: ptr = arg [reg("GPR1", undefined)]
+          %2: i64 = arg [reg("GPR2", undefined)]
+          %3: i64 = arg [reg("GPR3", undefined)]
+          store %2, %0
+          store volatile %3, %1
+          store %3, %0
+          term [%0, %1, %2, %3]
+        "#,
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i64 = arg
+          %3: i64 = arg
+          store %2, %0
+          store volatile %3, %1
+          store %3, %0
+          term [%0, %1, %2, %3]
+          ...
+        ",
+        );
+    }
+}
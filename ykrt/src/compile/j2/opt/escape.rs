@@ -0,0 +1,852 @@
+//! Escape analysis and allocation removal.
+//!
+//! Interpreters often allocate short-lived objects (e.g. boxed floats or temporary tuples) via
+//! calls to allocator functions. Consider this input trace:
+//!
+//! ```text
+//! %0: double = arg [reg]
+//! %1: ptr = 0x1234 ; @malloc
+//! %2: i64 = 16
+//! %3: ptr = call %1(%2)
+//! %4: ptr = ptradd %3, 8
+//! store %0, %4
+//! %6: double = load %4
+//! %7: double = fadd %6, %6
+//! ```
+//!
+//! The allocation at `%3` is never seen by anything other than the `store` and `load`: we say
+//! that it does not "escape". We therefore treat `%3` as a "virtual" object, whose fields are SSA
+//! values rather than memory: the `store` is not emitted and the `load` is replaced with `%0`.
+//! Since nothing then uses `%3`, the allocation itself is removed by dead code elimination (see
+//! [CallEffects::Alloc]).
+//!
+//! If a virtual object does escape (e.g. its pointer is passed to a call or stored in non-virtual
+//! memory) then, immediately before the escaping instruction, we "materialise" it by emitting the
+//! stores we deferred. Since the object cannot have been observed by anything before that point,
+//! deferring the stores is safe. Virtual objects can be stored in other virtual objects:
+//! materialising the outer object materialises the inner object.
+//!
+//! A guard whose deopt variables reference a virtual object does not cause it to escape: the
+//! object is only needed if the guard fails. Instead, we record the object, and its fields as they
+//! are at the guard, in [GuardExtra::virt_objs]. If the guard fails, the guard's exit then
+//! performs the allocation (if the trace hasn't already) and the deferred stores before deopt or
+//! a side-trace uses the object.
+//!
+//! This pass is currently deliberately simple:
+//!   * Only fields at a constant offset from the allocation's pointer are virtualised: anything
+//!     else (e.g. a `dynptradd` or a load from a field that has not been stored to) materialises
+//!     the object.
+//!   * Virtual objects do not survive a loop's back edge: an object referenced by a `term`
+//!     escapes.
+
+use crate::compile::j2::{
+    hir::*,
+    opt::{
+        BlockLikeT, EquivIIdxT,
+        fullopt::{CommitInstOpt, OptOutcome, PassOpt, PassT},
+    },
+};
+use index_type::vec::TypedVec;
+use smallvec::SmallVec;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+};
+
+/// Escape analysis and allocation removal.
+pub(super) struct Escape {
+    /// The allocations which have not (yet) escaped.
+    objs: HashMap<InstIdx, VirtualObj>,
+    /// For each pointer derived from an allocation (including the allocation itself), a tuple
+    /// `(allocation, offset)`. Entries may reference allocations that are no longer in
+    /// [Self::objs] (i.e. which have escaped).
+    ptrs: HashMap<InstIdx, (InstIdx, i32)>,
+    /// Allocations which must be materialised before the next instruction is processed.
+    pending: Vec<InstIdx>,
+}
+
+impl Escape {
+    pub(super) fn new() -> Self {
+        Self {
+            objs: HashMap::new(),
+            ptrs: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// If `ptr` is derived from an allocation that has not escaped, return `Some((allocation,
+    /// offset))`.
+    fn virt(&self, opt: &PassOpt, ptr: InstIdx) -> Option<(InstIdx, i32)> {
+        self.ptrs
+            .get(&opt.equiv_iidx(ptr))
+            .filter(|(base, _)| self.objs.contains_key(base))
+            .copied()
+    }
+
+    /// If `iidx` is derived from an allocation that has not escaped, materialise that allocation.
+    fn escape(&mut self, opt: &mut PassOpt, iidx: InstIdx) {
+        if let Some((base, _)) = self.virt(opt, iidx) {
+            self.materialise(opt, base);
+        }
+    }
+
+    /// Record, in the [GuardExtra] `geidx`, the virtual objects reachable from the guard's deopt
+    /// variables, so that they can be materialised if the guard fails. The [GuardExtra] may
+    /// already record objects (e.g. when a guard is copied into a loop's peel): any fields we know
+    /// about are stored after those already recorded.
+    fn record_virt_objs(&self, opt: &mut PassOpt, geidx: GuardExtraIdx) {
+        let gextra = opt.gextra(geidx);
+        let mut queue = gextra
+            .deopt_vars
+            .iter()
+            .chain(
+                gextra
+                    .virt_objs
+                    .iter()
+                    .flat_map(|x| x.fields.iter().map(|(_, val)| val)),
+            )
+            .copied()
+            .collect::<Vec<_>>();
+        let mut virt_objs = mem::take(&mut opt.gextra_mut(geidx).virt_objs);
+        let mut seen = HashSet::new();
+        while let Some(iidx) = queue.pop() {
+            let Some((base, _)) = self.virt(opt, iidx) else {
+                continue;
+            };
+            if !seen.insert(base) {
+                continue;
+            }
+            let fields = self.objs[&base]
+                .fields
+                .values()
+                .map(|Field { ptr, val, .. }| (opt.equiv_iidx(*ptr), opt.equiv_iidx(*val)))
+                .collect::<Vec<_>>();
+            queue.extend(fields.iter().map(|(_, val)| *val));
+            match virt_objs.iter_mut().find(|x| x.alloc == base) {
+                Some(x) => x.fields.extend(fields),
+                None => virt_objs.push(VirtObj {
+                    alloc: base,
+                    fields,
+                }),
+            }
+        }
+        opt.gextra_mut(geidx).virt_objs = virt_objs;
+    }
+
+    /// Materialise the virtual object `base` by emitting, as preinstructions, the stores that we
+    /// have so far deferred.
+    fn materialise(&mut self, opt: &mut PassOpt, base: InstIdx) {
+        // Removing `base` before materialising its fields means that an object which (directly
+        // or indirectly) contains a pointer to itself is only materialised once.
+        let Some(obj) = self.objs.remove(&base) else {
+            return;
+        };
+        for Field { ptr, val, bytew: _ } in obj.fields.into_values() {
+            let val = opt.equiv_iidx(val);
+            self.escape(opt, val);
+            let ptr = opt.equiv_iidx(ptr);
+            opt.push_pre_inst(
+                Store {
+                    ptr,
+                    val,
+                    is_volatile: false,
+                }
+                .into(),
+            );
+        }
+    }
+}
+
+impl PassT for Escape {
+    fn feed(&mut self, opt: &mut PassOpt, inst: Inst) -> OptOutcome {
+        for base in mem::take(&mut self.pending) {
+            self.materialise(opt, base);
+        }
+
+        match inst {
+            Inst::Load(Load {
+                tyidx,
+                ptr,
+                is_volatile: false,
+            }) => {
+                if let Some((base, off)) = self.virt(opt, ptr) {
+                    if let Some(Field { val, .. }) = self.objs[&base].fields.get(&off) {
+                        let val = opt.equiv_iidx(*val);
+                        if opt.inst(val).tyidx(opt) == tyidx {
+                            return OptOutcome::Equiv(val);
+                        }
+                    }
+                    // Either this field has not been stored to, or it's being loaded as a
+                    // different type to the one stored.
+                    self.materialise(opt, base);
+                }
+                OptOutcome::Rewritten(inst)
+            }
+            Inst::Store(Store {
+                ptr,
+                val,
+                is_volatile: false,
+            }) => {
+                if let Some((base, off)) = self.virt(opt, ptr) {
+                    let val = opt.equiv_iidx(val);
+                    let bytew = opt.inst_bitw(opt, val).div_ceil(8);
+                    let obj = self.objs.get_mut(&base).unwrap();
+                    if obj.can_set(off, bytew) {
+                        obj.fields.insert(off, Field { ptr, val, bytew });
+                        return OptOutcome::NotNeeded;
+                    }
+                    self.materialise(opt, base);
+                }
+                // If `val` is a pointer to a virtual object, storing it in non-virtual memory
+                // means that object escapes.
+                self.escape(opt, val);
+                OptOutcome::Rewritten(inst)
+            }
+            // Neither deriving a new pointer from, nor comparing, pointers to virtual objects
+            // causes them to escape.
+            Inst::ICmp(_) | Inst::NoAliasEnd(_) | Inst::PtrAdd(_) => OptOutcome::Rewritten(inst),
+            // We know more about a virtual object than `noalias` can tell us, so a `noalias` copy
+            // of a pointer to one is replaced by the pointer itself.
+            Inst::NoAlias(NoAlias { ptr, .. }) if self.virt(opt, ptr).is_some() => {
+                OptOutcome::Equiv(opt.equiv_iidx(ptr))
+            }
+            Inst::Guard(Guard { geidx, .. }) => {
+                self.record_virt_objs(opt, geidx);
+                OptOutcome::Rewritten(inst)
+            }
+            _ => {
+                let ops = inst.iter_iidxs(opt).collect::<SmallVec<[_; 4]>>();
+                for op in ops {
+                    self.escape(opt, op);
+                }
+                OptOutcome::Rewritten(inst)
+            }
+        }
+    }
+
+    fn preinst_committed(&mut self, opt: &CommitInstOpt, iidx: InstIdx) {
+        self.inst_committed(opt, iidx);
+    }
+
+    fn inst_committed(&mut self, opt: &CommitInstOpt, iidx: InstIdx) {
+        match opt.inst(iidx) {
+            Inst::Call(Call {
+                effects: CallEffects::Alloc,
+                ..
+            }) => {
+                self.objs.insert(iidx, VirtualObj::new());
+                self.ptrs.insert(iidx, (iidx, 0));
+            }
+            Inst::PtrAdd(PtrAdd { ptr, off, .. }) => {
+                if let Some((base, base_off)) = self.ptrs.get(&opt.equiv_iidx(*ptr)).copied()
+                    && self.objs.contains_key(&base)
+                    && let Some(off) = base_off.checked_add(*off)
+                {
+                    self.ptrs.insert(iidx, (base, off));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn equiv_committed(&mut self, equiv1: InstIdx, equiv2: InstIdx) {
+        // If a pointer to a virtual object is equivalent to another value, our knowledge of which
+        // pointers can reference the object is no longer complete. We can't emit instructions
+        // here, so we materialise the object before the next instruction.
+        for iidx in [equiv1, equiv2] {
+            if let Some((base, _)) = self.ptrs.get(&iidx)
+                && self.objs.contains_key(base)
+            {
+                self.pending.push(*base);
+            }
+        }
+    }
+
+    fn prepare_for_peel(
+        &mut self,
+        _opt: &mut PassOpt,
+        _entry: &Block,
+        _map: &TypedVec<InstIdx, InstIdx>,
+    ) {
+        // Any virtual object that is still live at the end of the entry block must have escaped
+        // (at the latest via `term`), so nothing carries over to the peel.
+        self.objs.clear();
+        self.ptrs.clear();
+        self.pending.clear();
+    }
+
+    fn fuel_exhausted(&mut self, opt: &mut PassOpt) {
+        // We won't see any further instructions, so we can't tell when objects escape: we must
+        // materialise everything now. Sorting the objects makes the order of the stores
+        // deterministic.
+        self.pending.clear();
+        let mut bases = self.objs.keys().copied().collect::<Vec<_>>();
+        bases.sort();
+        for base in bases {
+            self.materialise(opt, base);
+        }
+    }
+}
+
+/// An allocation that has not (yet) escaped.
+struct VirtualObj {
+    /// The fields stored to so far, ordered by offset from the start of the allocation.
+    fields: BTreeMap<i32, Field>,
+}
+
+impl VirtualObj {
+    fn new() -> Self {
+        Self {
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Can a field of `bytew` bytes be stored at `off` without partially overwriting an existing
+    /// field?
+    fn can_set(&self, off: i32, bytew: u32) -> bool {
+        let start = i64::from(off);
+        let end = start + i64::from(bytew);
+        self.fields.iter().all(|(f_off, f)| {
+            let f_start = i64::from(*f_off);
+            let f_end = f_start + i64::from(f.bytew);
+            (f_start == start && f_end == end) || end <= f_start || f_end <= start
+        })
+    }
+}
+
+/// A field in a [VirtualObj].
+struct Field {
+    /// The pointer used to store to this field: when the object is materialised, this is the
+    /// pointer that will be stored to.
+    ptr: InstIdx,
+    /// The value stored.
+    val: InstIdx,
+    /// The width of `val` in bytes.
+    bytew: u32,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile::j2::opt::fullopt::test::{full_opt_test, user_defined_opt_test};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn test_escape(mod_s: &str, ptn: &str) {
+        let escape = Rc::new(RefCell::new(Escape::new()));
+        user_defined_opt_test(
+            mod_s,
+            |opt, mut inst| {
+                inst.canonicalise(opt);
+                escape.borrow_mut().feed(opt, inst)
+            },
+            |opt, iidx| escape.borrow_mut().inst_committed(opt, iidx),
+            |equiv1, equiv2| escape.borrow_mut().equiv_committed(equiv1, equiv2),
+            ptn,
+        );
+    }
+
+    #[test]
+    fn virtual_fields() {
+        // Stores to a non-escaping allocation are removed and loads forwarded.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i64 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          %6: ptr = ptradd %4, 8
+          store %1, %6
+          %8: i8 = load %4
+          %9: i64 = load %6
+          blackbox %8
+          blackbox %9
+        ",
+            "
+          %0: i8 = arg
+          %1: i64 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = ptradd %4, 8
+          blackbox %0
+          blackbox %1
+        ",
+        );
+
+        // Later stores to the same field overwrite earlier stores.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          store %1, %4
+          %7: i8 = load %4
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          blackbox %1
+        ",
+        );
+
+        // A `noalias` copy of a pointer to a virtual object is the pointer itself.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          %4: ptr = noalias %3
+          store %0, %4
+          %6: i8 = load %4
+          noalias_end %4
+          blackbox %6
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          noalias_end %3
+          blackbox %0
+        ",
+        );
+
+        // Non-allocator calls aren't virtualised.
+        test_escape(
+            "
+          extern f(i64) -> ptr
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call f %1(%2)
+          store %0, %3
+          %5: i8 = load %3
+          blackbox %5
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          store %0, %3
+          %5: i8 = load %3
+          blackbox %5
+        ",
+        );
+    }
+
+    #[test]
+    fn materialise() {
+        // Passing the allocation to a call materialises it.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+          extern f(ptr)
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          store %0, %3
+          %5: ptr = 0x5678
+          call f %5(%3)
+          %7: i8 = load %3
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = 0x5678
+          store %0, %3
+          call %4(%3)
+          %7: i8 = load %3
+          blackbox %7
+        ",
+        );
+
+        // Guards which don't reference the allocation don't record it.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i1 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          guard true, %1, [%0]
+          %7: i8 = load %4
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: i1 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          guard true, %1, [%0]
+          blackbox %0
+        ",
+        );
+
+        // Storing the allocation into non-virtual memory materialises it.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          store %4, %1
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          store %0, %4
+          store %4, %1
+        ",
+        );
+
+        // Partially overlapping stores materialise the allocation.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i16 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          %6: ptr = ptradd %4, 1
+          store %1, %6
+        ",
+            "
+          %0: i16 = arg
+          %1: i8 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = ptradd %4, 1
+          store %0, %4
+          store %1, %5
+        ",
+        );
+
+        // Loading a field that hasn't been stored to materialises the allocation.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          store %0, %3
+          %5: ptr = ptradd %3, 8
+          %6: i8 = load %5
+          blackbox %6
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = ptradd %3, 8
+          store %0, %3
+          %6: i8 = load %4
+          blackbox %6
+        ",
+        );
+    }
+
+    #[test]
+    fn guards() {
+        // Guards which reference the allocation record it, rather than materialising it.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i1 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          guard true, %1, [%4]
+          %7: i8 = load %4
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: i1 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          guard true, %1, [%4] virtual %4 [%4: %0]
+          blackbox %0
+        ",
+        );
+
+        // Guards referencing a pointer derived from the allocation record it.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i1 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          %5: ptr = ptradd %4, 8
+          store %0, %5
+          guard true, %1, [%5]
+        ",
+            "
+          %0: i8 = arg
+          %1: i1 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = ptradd %4, 8
+          guard true, %1, [%5] virtual %4 [%5: %0]
+        ",
+        );
+
+        // Each guard records the fields as they are at that guard.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: i1 = arg [reg]
+          %3: ptr = 0x1234
+          %4: i64 = 16
+          %5: ptr = call malloc %3(%4)
+          store %0, %5
+          guard true, %2, [%5]
+          store %1, %5
+          guard false, %2, [%5]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = arg
+          %2: i1 = arg
+          %3: ptr = 0x1234
+          %4: i64 = 16
+          %5: ptr = call %3(%4)
+          guard true, %2, [%5] virtual %5 [%5: %0]
+          guard false, %2, [%5] virtual %5 [%5: %1]
+        ",
+        );
+
+        // Virtual objects stored in a recorded object are also recorded.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i1 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          %5: ptr = call malloc %2(%3)
+          store %0, %5
+          store %5, %4
+          guard true, %1, [%4]
+        ",
+            "
+          %0: i8 = arg
+          %1: i1 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = call %2(%3)
+          guard true, %1, [%4] virtual %4 [%4: %5] virtual %5 [%5: %0]
+        ",
+        );
+
+        // An object which has escaped before a guard isn't recorded.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+          extern f(ptr)
+
+          %0: i8 = arg [reg]
+          %1: i1 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          store %0, %4
+          %6: ptr = 0x5678
+          call f %6(%4)
+          guard true, %1, [%4]
+        ",
+            "
+          %0: i8 = arg
+          %1: i1 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = 0x5678
+          store %0, %4
+          call %5(%4)
+          guard true, %1, [%4]
+        ",
+        );
+    }
+
+    #[test]
+    fn nested() {
+        // An allocation stored in a virtual allocation stays virtual...
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          %4: ptr = call malloc %1(%2)
+          store %0, %4
+          store %4, %3
+          %7: ptr = load %3
+          %8: i8 = load %7
+          blackbox %8
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = call %1(%2)
+          blackbox %0
+        ",
+        );
+
+        // ...until the outer allocation escapes, at which point both are materialised.
+        test_escape(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          %4: ptr = call malloc %1(%2)
+          store %0, %4
+          store %4, %3
+          blackbox %3
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = call %1(%2)
+          store %0, %4
+          store %4, %3
+          blackbox %3
+        ",
+        );
+    }
+
+    #[test]
+    fn allocation_removal() {
+        // With the full optimiser, an allocation which doesn't escape is removed from the peel.
+        full_opt_test(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          store %0, %3
+          %5: i8 = load %3
+          %6: i8 = 1
+          %7: i8 = add %5, %6
+          term [%7]
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: i8 = 1
+          %5: i8 = add %0, %4
+          term [%5]
+          ; peel
+          %0: i8 = arg
+          %1: i8 = 1
+          %2: i8 = add %0, %1
+          term [%2]
+        ",
+        );
+
+        // An allocation only referenced by a guard is recorded by the guard in both the entry and
+        // the peel.
+        full_opt_test(
+            "
+          extern malloc(i64) -> ptr allocator
+          extern f() -> i1
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          store %0, %3
+          %5: ptr = 0x5678
+          %6: i1 = call f %5()
+          guard true, %6, [%3]
+          term [%0]
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = 0x5678
+          %5: i1 = call %4()
+          guard true, %5, [%3] virtual %3 [%3: %0]
+          term [%0]
+          ; peel
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = 0x5678
+          %5: i1 = call %4()
+          guard true, %5, [%3] virtual %3 [%3: %0]
+          term [%0]
+        ",
+        );
+    }
+}
@@ -121,24 +121,37 @@
 //! pass, bearing in mind that these instruction equivalences will only be committed when the pass
 //! completes.
 
-use crate::compile::{
-    CompilationError,
-    j2::{
-        effects::Effects,
-        hir::*,
-        opt::{
-            EquivIIdxT, OptT, cse::CSE, known_bits::KnownBits, load_store::LoadStore,
-            strength_fold::StrengthFold,
+use crate::{
+    compile::{
+        CompilationError,
+        j2::{
+            effects::Effects,
+            hir::*,
+            opt::{
+                EquivIIdxT, OptConfig, OptFuel, OptPass, OptT,
+                cse::CSE,
+                dse::dse,
+                escape::Escape,
+                known_bits::KnownBits,
+                licm::licm,
+                load_store::LoadStore,
+                range::{Range, StrengthenedCond, strengthen_guards},
+                strength_fold::StrengthFold,
+            },
         },
     },
+    log::Verbosity,
+    mt::{MT, TraceId},
 };
 use index_type::{IndexType, typed_vec, vec::TypedVec};
 use smallvec::SmallVec;
 use std::{
     assert_matches,
     collections::HashMap,
+    fmt::Write,
     hash::{Hash, Hasher},
     mem,
+    sync::Arc,
 };
 use vob::Vob;
 
@@ -147,11 +160,16 @@ use vob::Vob;
 
 pub(in crate::compile::j2) struct FullOpt {
     /// The ordered set of optimisation passes that all instructions will be fed through.
-    passes: [Box<dyn PassT>; 4],
+    passes: Vec<(OptPass, Box<dyn PassT>)>,
+    /// The passes run over whole [Block]s once they have been built.
+    block_passes: Vec<OptPass>,
+    /// The optimisation fuel available to this trace.
+    fuel: Fuel,
     inner: OptInternal,
 }
 
 impl FullOpt {
+    /// Create an optimiser that runs all passes with unlimited fuel.
     pub(in crate::compile::j2) fn new() -> Self {
         let mut tys = TypedVec::new();
         let mut ty_map = HashMap::new();
@@ -162,16 +180,18 @@ impl FullOpt {
         let tyidx_int1 = tys.push(Ty::Int(1));
         ty_map.insert(Ty::Int(1), tyidx_int1);
         Self {
-            passes: [
-                Box::new(KnownBits::new()),
-                Box::new(StrengthFold::new()),
-                Box::new(LoadStore::new()),
-                Box::new(CSE::new()),
-            ],
+            passes: Self::all_passes(),
+            block_passes: vec![OptPass::LICM, OptPass::DSE],
+            fuel: Fuel {
+                limit: None,
+                exhausted: false,
+                mt: None,
+            },
             inner: OptInternal {
                 insts: TypedVec::new(),
                 consts_map: HashMap::new(),
                 guard_extras: TypedVec::new(),
+                strengthened: HashMap::new(),
                 tys,
                 tyidx_int1,
                 tyidx_ptr0,
@@ -181,6 +201,34 @@ impl FullOpt {
         }
     }
 
+    /// Create an optimiser for the trace `trid` that runs the passes, with the fuel, specified by
+    /// `mt`'s optimiser configuration.
+    pub(in crate::compile::j2) fn with_config(mt: &Arc<MT>, trid: TraceId) -> Self {
+        let mut fopt = Self::new();
+        fopt.configure(mt.opt_config());
+        fopt.fuel.mt = Some((Arc::clone(mt), trid));
+        fopt
+    }
+
+    /// Enable only the passes, and use the fuel, specified by `config`.
+    fn configure(&mut self, config: &OptConfig) {
+        self.passes.retain(|(x, _)| config.enabled(*x));
+        self.block_passes.retain(|x| config.enabled(*x));
+        self.fuel.limit = config.fuel();
+    }
+
+    /// Return all the forward optimisation passes in the order they are run.
+    fn all_passes() -> Vec<(OptPass, Box<dyn PassT>)> {
+        vec![
+            (OptPass::KnownBits, Box::new(KnownBits::new())),
+            (OptPass::Range, Box::new(Range::new())),
+            (OptPass::StrengthFold, Box::new(StrengthFold::new())),
+            (OptPass::Escape, Box::new(Escape::new())),
+            (OptPass::LoadStore, Box::new(LoadStore::new())),
+            (OptPass::CSE, Box::new(CSE::new())),
+        ]
+    }
+
     #[cfg(test)]
     pub(in crate::compile::j2) fn new_testing(tys: TypedVec<TyIdx, Ty>) -> Self {
         let ty_map = HashMap::from_iter(
@@ -192,16 +240,18 @@ impl FullOpt {
         let tyidx_void = *ty_map.get(&Ty::Void).unwrap_or_else(|| panic!());
         let tyidx_int1 = *ty_map.get(&Ty::Int(1)).unwrap_or_else(|| panic!());
         Self {
-            passes: [
-                Box::new(KnownBits::new()),
-                Box::new(StrengthFold::new()),
-                Box::new(LoadStore::new()),
-                Box::new(CSE::new()),
-            ],
+            passes: Self::all_passes(),
+            block_passes: vec![OptPass::LICM, OptPass::DSE],
+            fuel: Fuel {
+                limit: None,
+                exhausted: false,
+                mt: None,
+            },
             inner: OptInternal {
                 insts: TypedVec::new(),
                 consts_map: HashMap::new(),
                 guard_extras: TypedVec::new(),
+                strengthened: HashMap::new(),
                 tys,
                 tyidx_int1,
                 tyidx_ptr0,
@@ -218,12 +268,51 @@ impl FullOpt {
         mut inst: Inst,
     ) -> Result<Option<InstIdx>, CompilationError> {
         for i in 0..self.passes.len() {
+            if self.fuel.exhausted {
+                break;
+            }
+            // If fuel is limited, we need to know what `inst` looked like before this pass so that
+            // we can tell whether the pass rewrote it.
+            let (last_fuel, before) = if self.fuel.limit.is_some() {
+                let Some(last_fuel) = self.fuel.take() else {
+                    self.exhaust_fuel(&mut popt_inner);
+                    break;
+                };
+                let before_s = last_fuel.then(|| inst.to_string(&*self, &*self));
+                (last_fuel, Some((format!("{inst:?}"), before_s)))
+            } else {
+                (false, None)
+            };
+            let equivs_len = popt_inner.new_equivs.len();
+
             let mut opt = PassOpt {
                 optinternal: &mut self.inner,
                 inner: &mut popt_inner,
             };
 
-            let fed = self.passes[i].feed(&mut opt, inst);
+            let fed = self.passes[i].1.feed(&mut opt, inst);
+
+            if let Some((before, before_s)) = before {
+                let rewritten = !popt_inner.pre_insts.is_empty()
+                    || popt_inner.new_equivs.len() != equivs_len
+                    || match &fed {
+                        OptOutcome::Rewritten(x) => format!("{x:?}") != before,
+                        OptOutcome::NotNeeded | OptOutcome::Equiv(_) => true,
+                    };
+                if !rewritten {
+                    self.fuel.give_back();
+                } else if last_fuel {
+                    let after_s = match &fed {
+                        OptOutcome::NotNeeded => "nothing".to_owned(),
+                        OptOutcome::Rewritten(x) => format!("`{}`", x.to_string(&*self, &*self)),
+                        OptOutcome::Equiv(iidx) => format!("%{iidx:?}"),
+                    };
+                    self.fuel.log_exhausted(
+                        self.passes[i].0,
+                        &format!("rewrote `{}` to {after_s}", before_s.unwrap()),
+                    );
+                }
+            }
 
             for inst in popt_inner.pre_insts.drain(..) {
                 self.commit_preinst(inst);
@@ -245,7 +334,7 @@ impl FullOpt {
                 (_, _) => (equiv2, equiv1),
             };
             self.inner.insts.get_mut(equiv1).unwrap().equiv = equiv2;
-            for pass in &mut self.passes {
+            for (_, pass) in &mut self.passes {
                 pass.equiv_committed(equiv1, equiv2);
             }
         }
@@ -266,6 +355,46 @@ impl FullOpt {
         Ok(Some(self.commit_inst(inst)))
     }
 
+    /// The fuel has run out: give passes a chance to emit instructions they have deferred and
+    /// stop feeding instructions to passes.
+    fn exhaust_fuel(&mut self, popt_inner: &mut PassOptInner) {
+        assert!(!self.fuel.exhausted);
+        self.fuel.exhausted = true;
+        for i in 0..self.passes.len() {
+            let mut opt = PassOpt {
+                optinternal: &mut self.inner,
+                inner: &mut *popt_inner,
+            };
+            self.passes[i].1.fuel_exhausted(&mut opt);
+            for inst in popt_inner.pre_insts.drain(..) {
+                self.commit_preinst(inst);
+            }
+        }
+    }
+
+    /// If [OptPass::LICM] is enabled, hoist loop invariant instructions out of `peel` into
+    /// `entry`.
+    fn run_licm(&mut self, entry: Block, peel: Block) -> (Block, Block) {
+        if !self.block_passes.contains(&OptPass::LICM) {
+            return (entry, peel);
+        }
+        let fuel = &mut self.fuel;
+        licm(&self.inner, entry, peel, &mut |b, iidx| {
+            fuel.take_for_block(&self.inner, b, OptPass::LICM, "hoisted", iidx)
+        })
+    }
+
+    /// If [OptPass::DSE] is enabled, remove dead stores from `b`.
+    fn run_dse(&mut self, b: Block) -> Block {
+        if !self.block_passes.contains(&OptPass::DSE) {
+            return b;
+        }
+        let fuel = &mut self.fuel;
+        dse(&self.inner, b, &mut |b, iidx| {
+            fuel.take_for_block(&self.inner, b, OptPass::DSE, "removed", iidx)
+        })
+    }
+
     fn commit_inst(&mut self, inst: Inst) -> InstIdx {
         self.commit_inst_internal(|pass, opt, iidx| pass.inst_committed(opt, iidx), inst)
     }
@@ -291,13 +420,86 @@ impl FullOpt {
         });
 
         let opt = CommitInstOpt { inner: &self.inner };
-        for pass in &mut self.passes {
+        for (_, pass) in &mut self.passes {
             f(&mut **pass, &opt, iidx);
         }
         iidx
     }
 }
 
+/// The optimisation fuel available to a trace.
+struct Fuel {
+    /// `None` means that unlimited fuel is available.
+    limit: Option<OptFuel>,
+    /// Has the fuel run out? If so, instructions are no longer fed to passes.
+    exhausted: bool,
+    /// The meta-tracer and trace ID this optimiser is running for, if any. [OptFuel::Total] fuel
+    /// is taken from the meta-tracer, and if the fuel runs out, it is logged against both.
+    mt: Option<(Arc<MT>, TraceId)>,
+}
+
+impl Fuel {
+    /// Try to take one unit of fuel, returning `Some(true)` if that was the last unit,
+    /// `Some(false)` if more fuel remains, or `None` if there was no fuel to take.
+    fn take(&mut self) -> Option<bool> {
+        match &mut self.limit {
+            None => Some(false),
+            Some(OptFuel::PerTrace(n)) => {
+                let prev = *n;
+                *n = prev.checked_sub(1)?;
+                Some(prev == 1)
+            }
+            Some(OptFuel::Total(_)) => {
+                let (mt, _) = self.mt.as_ref().unwrap();
+                mt.take_opt_fuel().map(|x| x == 1)
+            }
+        }
+    }
+
+    /// Return a unit of fuel taken by [Self::take] that turned out not to be needed.
+    fn give_back(&mut self) {
+        match &mut self.limit {
+            None => (),
+            Some(OptFuel::PerTrace(n)) => *n += 1,
+            Some(OptFuel::Total(_)) => self.mt.as_ref().unwrap().0.return_opt_fuel(),
+        }
+    }
+
+    /// Try to take one unit of fuel so that the [Block] pass `pass` can rewrite the instruction
+    /// `iidx` in `b`, returning `true` if the rewrite may go ahead. `verb` describes the rewrite.
+    fn take_for_block<M: ModLikeT>(
+        &mut self,
+        m: &M,
+        b: &Block,
+        pass: OptPass,
+        verb: &str,
+        iidx: InstIdx,
+    ) -> bool {
+        match self.take() {
+            Some(last_fuel) => {
+                if last_fuel {
+                    let inst_s = b.inst(iidx).to_string(m, b);
+                    self.log_exhausted(pass, &format!("{verb} `{inst_s}`"));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Log that the last unit of fuel was consumed by `pass` performing the rewrite `what`.
+    fn log_exhausted(&self, pass: OptPass, what: &str) {
+        if let Some((mt, trid)) = &self.mt {
+            mt.log.log(Verbosity::Warning, |f| {
+                write!(
+                    f,
+                    "Optimisation fuel exhausted in trace {trid}: {pass} {what}"
+                )
+            });
+        }
+    }
+}
+
 impl ModLikeT for FullOpt {
     fn addr_to_name(&self, _addr: usize) -> Option<&str> {
         panic!("Not available in optimiser");
@@ -339,19 +541,17 @@ impl BlockLikeT for FullOpt {
 }
 
 impl OptT for FullOpt {
-    fn build(self: Box<Self>) -> Result<(Block, TypedVec<TyIdx, Ty>), CompilationError> {
-        Ok((
-            Block {
-                insts: self
-                    .inner
-                    .insts
-                    .into_iter()
-                    .map(|x| x.inst)
-                    .collect::<TypedVec<_, _>>(),
-                guard_extras: self.inner.guard_extras,
-            },
-            self.inner.tys,
-        ))
+    fn build(mut self: Box<Self>) -> Result<(Block, TypedVec<TyIdx, Ty>), CompilationError> {
+        let b = Block {
+            insts: mem::take(&mut self.inner.insts)
+                .into_iter()
+                .map(|x| x.inst)
+                .collect::<TypedVec<_, _>>(),
+            guard_extras: mem::take(&mut self.inner.guard_extras),
+        };
+        let b = strengthen_guards(b, mem::take(&mut self.inner.strengthened));
+        let b = self.run_dse(b);
+        Ok((b, self.inner.tys))
     }
 
     fn build_with_peel(
@@ -366,6 +566,9 @@ impl OptT for FullOpt {
                 .collect::<TypedVec<_, _>>(),
             guard_extras: mem::take(&mut self.inner.guard_extras),
         };
+        // Guards in `entry` can only be strengthened once the peel has been built, as the peel is
+        // built from the [InstIdx]s of the unstrengthened `entry`.
+        let strengthened = mem::take(&mut self.inner.strengthened);
         self.inner.consts_map.clear();
         assert!(self.inner.guard_extras.is_empty());
 
@@ -383,6 +586,11 @@ impl OptT for FullOpt {
                 for op_iidx in inst.iter_iidxs(&entry) {
                     is_used.set(op_iidx.to_raw_index(), true);
                 }
+                if let Some(cond) = strengthened.get(&iidx) {
+                    for op_iidx in cond.bases() {
+                        is_used.set(op_iidx.to_raw_index(), true);
+                    }
+                }
             }
         }
 
@@ -420,7 +628,7 @@ impl OptT for FullOpt {
             optinternal: &mut self.inner,
             inner: &mut popt_inner,
         };
-        for pass in &mut self.passes {
+        for (_, pass) in &mut self.passes {
             pass.prepare_for_peel(&mut popt, &entry, &map);
         }
         assert!(popt_inner.gextra.is_none());
@@ -436,32 +644,58 @@ impl OptT for FullOpt {
             }
             let mut inst = entry.inst(iidx).clone();
             if let Inst::Guard(mut x) = inst {
+                // A guard that is to be strengthened in `entry` must also be strengthened here.
+                let (expect, cond) = match strengthened.get(&iidx) {
+                    Some(cond) => (true, cond.emit(|y| map[y], |y| self.feed(y))?),
+                    None => (x.expect, map[x.cond]),
+                };
                 if let Inst::Const(Const {
                     kind: ConstKind::Int(y),
                     ..
-                }) = self.inst(self.equiv_iidx(map[x.cond]))
+                }) = self.inst(self.equiv_iidx(cond))
                 {
                     let v = y.to_zero_ext_u8().unwrap();
-                    if (x.expect && v == 0) || (!x.expect && v == 1) {
+                    if (expect && v == 0) || (!expect && v == 1) {
                         // We've found a contradiction. The peel is semi-pointless: we could
                         // generate it up to this instruction, but the risk of creating extra
                         // sidetraces doesn't seem worth it.
+                        let entry = strengthen_guards(entry, strengthened);
+                        let entry = self.run_dse(entry);
                         return Ok((entry, None, self.inner.tys));
                     }
                 }
 
                 let old_gextra = &entry.guard_extras[x.geidx];
+                let mut deopt_vars = old_gextra
+                    .deopt_vars
+                    .iter()
+                    .map(|x| map[*x])
+                    .collect::<Vec<_>>();
+                let mut cond_deopt_vars = old_gextra.cond_deopt_vars.clone();
+                if strengthened.contains_key(&iidx) {
+                    // See [strengthen_guards].
+                    for k in mem::take(&mut cond_deopt_vars) {
+                        deopt_vars[k] = map[x.cond];
+                    }
+                }
                 let gextra = GuardExtra {
                     bid: old_gextra.bid,
                     switch: old_gextra.switch.clone(),
-                    deopt_vars: old_gextra
-                        .deopt_vars
+                    deopt_vars,
+                    cond_deopt_vars,
+                    deopt_frames: old_gextra.deopt_frames.clone(),
+                    virt_objs: old_gextra
+                        .virt_objs
                         .iter()
-                        .map(|x| map[*x])
+                        .map(|x| {
+                            let mut x = x.clone();
+                            x.rewrite_iidxs(|y| map[y]);
+                            x
+                        })
                         .collect::<Vec<_>>(),
-                    deopt_frames: old_gextra.deopt_frames.clone(),
                 };
-                x.cond = map[x.cond];
+                x.expect = expect;
+                x.cond = cond;
                 x.geidx = GuardExtraIdx::MAX;
                 self.feed_guard(x, gextra)?;
             } else if inst.tyidx(&*self) == self.inner.tyidx_void {
@@ -480,7 +714,11 @@ impl OptT for FullOpt {
                 .collect::<TypedVec<_, _>>(),
             guard_extras: mem::take(&mut self.inner.guard_extras),
         };
-
+        let entry = strengthen_guards(entry, strengthened);
+        let peel = strengthen_guards(peel, mem::take(&mut self.inner.strengthened));
+        let (entry, peel) = self.run_licm(entry, peel);
+        let entry = self.run_dse(entry);
+        let peel = self.run_dse(peel);
         Ok((entry, Some(peel), self.inner.tys))
     }
 
@@ -531,6 +769,9 @@ struct OptInternal {
     /// numbers.
     consts_map: HashMap<HashableConst, InstIdx>,
     guard_extras: TypedVec<GuardExtraIdx, GuardExtra>,
+    /// Guards which are to be strengthened when the [Block] containing them is built (see
+    /// [PassOpt::strengthen_guard]).
+    strengthened: HashMap<InstIdx, StrengthenedCond>,
     tys: TypedVec<TyIdx, Ty>,
     /// The [TyIdx] for [Ty::Int(1)].
     tyidx_int1: TyIdx,
@@ -577,6 +818,28 @@ impl OptInternal {
     }
 }
 
+impl ModLikeT for OptInternal {
+    fn addr_to_name(&self, _addr: usize) -> Option<&str> {
+        panic!("Not available in optimiser");
+    }
+
+    fn ty(&self, tyidx: TyIdx) -> &Ty {
+        OptInternal::ty(self, tyidx)
+    }
+
+    fn tyidx_int1(&self) -> TyIdx {
+        self.tyidx_int1
+    }
+
+    fn tyidx_ptr0(&self) -> TyIdx {
+        self.tyidx_ptr0
+    }
+
+    fn tyidx_void(&self) -> TyIdx {
+        self.tyidx_void
+    }
+}
+
 /// A wrapper around [Const]s that allows them to be hashed for deduplication purposes. This struct
 /// is conservative as per [super::super::hir]'s documentation: it treats floating point numbers as
 /// bit patterns. That means, for example, that it will not identify `+0.0` and `-0.0` as
@@ -663,6 +926,10 @@ pub(super) trait PassT {
         entry: &Block,
         map: &TypedVec<InstIdx, InstIdx>,
     );
+
+    /// The optimisation fuel has run out: no more instructions will be fed to this pass. If the
+    /// pass has deferred emitting any instructions, it must emit them now as preinstructions.
+    fn fuel_exhausted(&mut self, _opt: &mut PassOpt) {}
 }
 
 /// The object passed to [PassT::feed] so that they can interact with the optimiser.
@@ -723,6 +990,14 @@ impl PassOpt<'_> {
     pub(super) fn push_equiv(&mut self, equiv1: InstIdx, equiv2: InstIdx) {
         self.inner.new_equivs.push((equiv1, equiv2));
     }
+
+    /// When the [Block] being optimised is built, the committed guard `iidx` will be rewritten to
+    /// check `cond`, which must imply the guard's current condition. Since `cond` is computed
+    /// immediately before the guard, it can only reference instructions before `iidx`.
+    pub(super) fn strengthen_guard(&mut self, iidx: InstIdx, cond: StrengthenedCond) {
+        assert_matches!(self.optinternal.insts[iidx].inst, Inst::Guard(_));
+        self.optinternal.strengthened.insert(iidx, cond);
+    }
 }
 
 impl BlockLikeT for PassOpt<'_> {
@@ -870,6 +1145,11 @@ pub(in crate::compile::j2) mod test {
     }
 
     pub(in crate::compile::j2) fn str_to_peel_mod<Reg: RegT>(mod_s: &str) -> Mod<Reg> {
+        str_to_peel_mod_with_opt(mod_s, FullOpt::new())
+    }
+
+    /// As [str_to_peel_mod], but using the optimiser `fopt`.
+    fn str_to_peel_mod_with_opt<Reg: RegT>(mod_s: &str, fopt: FullOpt) -> Mod<Reg> {
         let m = str_to_mod::<Reg>(mod_s);
         let TraceEnd::Test {
             args_vlocs,
@@ -881,7 +1161,7 @@ pub(in crate::compile::j2) mod test {
         else {
             panic!()
         };
-        let mut fopt = Box::new(FullOpt::new());
+        let mut fopt = Box::new(fopt);
         fopt.inner.guard_extras = guard_extras.clone();
         fopt.inner.tys = m.tys.clone();
 
@@ -955,7 +1235,19 @@ pub(in crate::compile::j2) mod test {
     }
 
     pub(in crate::compile::j2::opt) fn full_opt_test(mod_s: &str, ptn: &str) {
-        let m = str_to_peel_mod::<TestReg>(mod_s);
+        match_mod(&str_to_peel_mod::<TestReg>(mod_s), ptn);
+    }
+
+    /// As [full_opt_test], but with the passes and fuel specified by the `YKD_OPT_PASSES`-style
+    /// string `passes` and the `YKD_OPT_FUEL`-style string `fuel`.
+    fn config_opt_test(passes: Option<&str>, fuel: Option<&str>, mod_s: &str, ptn: &str) {
+        let mut fopt = FullOpt::new();
+        fopt.configure(&OptConfig::from_strs(passes, fuel).unwrap());
+        match_mod(&str_to_peel_mod_with_opt::<TestReg>(mod_s, fopt), ptn);
+    }
+
+    /// Check that `m`, when printed, matches the fm pattern `ptn`.
+    pub(in crate::compile::j2::opt) fn match_mod(m: &Mod<TestReg>, ptn: &str) {
         let s = m.to_string();
         let fmb = FMBuilder::new(ptn)
             .unwrap()
@@ -985,6 +1277,9 @@ pub(in crate::compile::j2) mod test {
     {
         let m = str_to_mod::<TestReg>(mod_s);
         let mut fopt = Box::new(FullOpt::new());
+        // Post-optimisation passes (e.g. dead store elimination) would obscure the effect of the
+        // user-defined passes.
+        fopt.block_passes.retain(|x| *x != OptPass::DSE);
         let TraceEnd::Test {
             args_vlocs,
             block: Block {
@@ -1226,4 +1521,136 @@ pub(in crate::compile::j2) mod test {
         ",
         );
     }
+
+    #[test]
+    fn config() {
+        let mod_s = "
+          %0: i8 = arg [reg]
+          %1: i8 = 0
+          %2: i8 = add %0, %1
+          %3: i8 = 1
+          %4: i8 = mul %2, %3
+          blackbox %4
+          term [%0]
+        ";
+
+        // With no passes, or no fuel, nothing is rewritten.
+        for (passes, fuel) in [(Some(""), None), (None, Some("trace:0"))] {
+            config_opt_test(
+                passes,
+                fuel,
+                mod_s,
+                "
+              %0: i8 = arg
+              %1: i8 = 0
+              %2: i8 = add %0, %1
+              %3: i8 = 1
+              %4: i8 = mul %2, %3
+              blackbox %4
+              term [%0]
+              ; peel
+              ...
+            ",
+            );
+        }
+
+        // With fuel for one rewrite, only the `add` is optimised.
+        config_opt_test(
+            None,
+            Some("trace:1"),
+            mod_s,
+            "
+          %0: i8 = arg
+          ...
+          %3: i8 = mul %0, %2
+          blackbox %3
+          term [%0]
+          ; peel
+          ...
+        ",
+        );
+
+        // With fuel for two rewrites, both the `add` and `mul` are optimised.
+        config_opt_test(
+            None,
+            Some("trace:2"),
+            mod_s,
+            "
+          %0: i8 = arg
+          ...
+          blackbox %0
+          term [%0]
+          ; peel
+          ...
+        ",
+        );
+    }
+
+    #[test]
+    fn config_block_passes() {
+        // Loop-invariant code motion consumes fuel for each hoisted instruction.
+        let mod_s = r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          term [%0, %3]
+        "#;
+        config_opt_test(
+            Some("LICM"),
+            Some("trace:0"),
+            mod_s,
+            "
+          ...
+          term [%0, %3]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          term [%0, %3]
+        ",
+        );
+        config_opt_test(
+            Some("LICM"),
+            Some("trace:1"),
+            mod_s,
+            "
+          ...
+          term [%0, %3, %2]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          %3: i64 = add %1, %2
+          term [%0, %3, %2]
+        ",
+        );
+
+        // Dead store elimination consumes fuel for each removed store: with fuel for one removal,
+        // only the dead store in the entry block is removed.
+        config_opt_test(
+            Some("DSE"),
+            Some("trace:1"),
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          store %1, %0
+          store %1, %0
+          term [%0, %1]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          store %1, %0
+          term [%0, %1]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          store %1, %0
+          store %1, %0
+          term [%0, %1]
+        ",
+        );
+    }
 }
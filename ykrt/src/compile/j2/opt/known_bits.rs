@@ -37,6 +37,7 @@ impl PassT for KnownBits {
             Inst::ICmp(x) => self.opt_icmp(opt, x),
             Inst::LShr(x) => self.opt_lshr(opt, x),
             Inst::Or(x) => self.opt_or(opt, x),
+            Inst::Overflow(x) => self.opt_overflow(opt, x),
             Inst::SExt(x) => self.opt_sext(opt, x),
             Inst::Shl(x) => self.opt_shl(opt, x),
             Inst::ZExt(x) => self.opt_zext(opt, x),
@@ -333,6 +334,24 @@ impl KnownBits {
         OptOutcome::Rewritten(inst.into())
     }
 
+    fn opt_overflow(&mut self, opt: &mut PassOpt, mut inst: Overflow) -> OptOutcome {
+        inst.canonicalise(opt);
+        let Overflow { op, lhs, rhs } = inst;
+        if let Some(lhs_b) = self.as_knownbits(opt, lhs)
+            && let Some(rhs_b) = self.as_knownbits(opt, rhs)
+            && let Some(lhs_bounds) = lhs_b.bounds(op.is_signed())
+            && let Some(rhs_bounds) = rhs_b.bounds(op.is_signed())
+            && let Some(x) = op.overflows(lhs_b.bitw(), lhs_bounds, rhs_bounds)
+        {
+            let tyidx = opt.push_ty(Ty::Int(1)).unwrap();
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(ArbBitInt::from_u64(1, u64::from(x))),
+            }));
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
     fn opt_sext(&mut self, opt: &mut PassOpt, inst: SExt) -> OptOutcome {
         let SExt { tyidx, val } = inst;
         if let Some(val_b) = self.as_knownbits(opt, val) {
@@ -456,6 +475,34 @@ impl KnownBitValue {
         self.ones.bitw()
     }
 
+    /// Returns the inclusive bounds of the values these bits allow when interpreted as signed (if
+    /// `signed` is true) or unsigned integers, or `None` if the value is wider than 64 bits.
+    fn bounds(&self, signed: bool) -> Option<(i128, i128)> {
+        let bitw = self.bitw();
+        if bitw > 64 {
+            return None;
+        }
+        // Unknown bits are zero in the smallest value and one in the largest, except that for
+        // signed values an unknown sign bit is the other way around.
+        let mut lo = self.known_ones();
+        let mut hi = self.known_ones().bitor(&self.unknowns);
+        if !signed {
+            return Some((
+                i128::from(lo.to_zero_ext_u64().unwrap()),
+                i128::from(hi.to_zero_ext_u64().unwrap()),
+            ));
+        }
+        let sign = ArbBitInt::from_u64(bitw, 1).checked_shl(bitw - 1).unwrap();
+        if self.unknowns.bitand(&sign) == sign {
+            lo = lo.bitor(&sign);
+            hi = hi.bitand(&sign.bitneg());
+        }
+        Some((
+            i128::from(lo.to_sign_ext_i64().unwrap()),
+            i128::from(hi.to_sign_ext_i64().unwrap()),
+        ))
+    }
+
     fn bitand(&self, other: &KnownBitValue) -> KnownBitValue {
         let set_ones = self.ones.bitand(&other.ones);
         let set_zeroes = self.zeroes().bitor(&other.zeroes());
@@ -1024,6 +1071,90 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_overflow() {
+        // Values whose top bits are known to be zero can't overflow when added.
+        test_known_bits(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 63
+          %2: i8 = and %0, %1
+          %3: i1 = overflow sadd %2, %2
+          guard false, %3, []
+          %5: i1 = overflow uadd %2, %1
+          guard false, %5, []
+          %7: i1 = overflow usub %1, %2
+          guard false, %7, []
+          blackbox %2
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 63
+          %2: i8 = and %0, %1
+          %3: i1 = 0
+          %4: i1 = 0
+          %5: i1 = 0
+          blackbox %2
+        ",
+        );
+
+        // ... nor can small values be multiplied.
+        test_known_bits(
+            "
+          %0: i16 = arg [reg]
+          %1: i16 = arg [reg]
+          %2: i16 = 255
+          %3: i16 = and %0, %2
+          %4: i16 = and %1, %2
+          %5: i1 = overflow umul %3, %4
+          guard false, %5, []
+          %7: i1 = overflow smul %3, %4
+          guard false, %7, []
+          blackbox %3
+        ",
+            "
+          %0: i16 = arg
+          %1: i16 = arg
+          %2: i16 = 255
+          %3: i16 = and %0, %2
+          %4: i16 = and %1, %2
+          %5: i1 = 0
+          %6: i1 = overflow smul %3, %4
+          guard false, %6, []
+          blackbox %3
+        ",
+        );
+
+        // Signed bounds take the sign bit into account.
+        test_known_bits(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 128
+          %2: i8 = or %0, %1
+          %3: i8 = 1
+          %4: i1 = overflow sadd %2, %3
+          guard false, %4, []
+          %6: i1 = overflow ssub %2, %3
+          guard false, %6, []
+          %8: i1 = overflow sadd %0, %3
+          guard false, %8, []
+          blackbox %2
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 128
+          %2: i8 = or %0, %1
+          %3: i8 = 1
+          %4: i1 = 0
+          %5: i1 = overflow ssub %2, %3
+          guard false, %5, []
+          %7: i1 = overflow sadd %0, %3
+          guard false, %7, []
+          blackbox %2
+        ",
+        );
+    }
+
     #[test]
     fn opt_sext() {
         test_known_bits(
@@ -0,0 +1,577 @@
+//! Loop-invariant code motion.
+//!
+//! [super::fullopt::FullOpt::build_with_peel] produces an entry [Block], representing the first
+//! iteration of a loop, and a peel [Block], representing all subsequent iterations. A peel
+//! argument is loop invariant if the peel's `term` passes it back unchanged. This module hoists
+//! instructions in the peel whose operands are all loop invariant to the end of the entry block,
+//! passing their results (if still needed) into the peel as extra arguments. We hoist:
+//!
+//!   * instructions without effects;
+//!   * non-`volatile` loads, provided that no instruction in the peel may write to the memory
+//!     they read (as determined by [super::alias]);
+//!   * guards, provided their deopt variables are also loop invariant.
+//!
+//! A hoisted instruction executes once, when the entry jumps to the peel, rather than at its
+//! original position in every iteration. We must therefore not hoist anything which might fault
+//! (loads, divisions, and calls) past a guard that might have prevented it from executing.
+//! Similarly, a guard is only hoisted if every guard and every instruction with write effects
+//! before it in the peel has also been hoisted: deoptimising from the hoisted guard must then
+//! produce the same state as deoptimising from the original guard in the first iteration of the
+//! peel. Since the guard's condition is loop invariant, it cannot fail in later iterations.
+//!
+//! Where the entry already contains an equivalent of a hoisted instruction (e.g. the entry loaded
+//! the same address and nothing has since overwritten it), that instruction is reused.
+
+use crate::compile::j2::{
+    effects::Effects,
+    hir::*,
+    opt::{
+        BlockLikeT, ModLikeT,
+        alias::{Address, Extent, may_write},
+    },
+};
+use index_type::{IndexType, typed_vec, vec::TypedVec};
+use vob::Vob;
+
+/// Hoist loop invariant instructions out of `peel` to the end of `entry`, returning the new
+/// `(entry, peel)` pair. `may_hoist` is called for each instruction in `peel` that could be
+/// hoisted: if it returns `false`, the instruction is left where it is.
+pub(super) fn licm(
+    m: &dyn ModLikeT,
+    mut entry: Block,
+    peel: Block,
+    may_hoist: &mut dyn FnMut(&Block, InstIdx) -> bool,
+) -> (Block, Block) {
+    let used = used(&peel);
+    let hoist = hoistable(m, &peel, &used, may_hoist);
+    if hoist.iter_set_bits(..).next().is_none() {
+        return (entry, peel);
+    }
+    let nargs = peel.term_vars().len();
+
+    // Which of the instructions we are hoisting, and which constants, are still referenced by the
+    // peel? Hoisted instructions will need to be passed in as arguments.
+    let mut needed = Vob::from_elem(false, peel.insts_len());
+    for (iidx, inst) in peel.insts_iter(..) {
+        if used[iidx.to_raw_index()] && !hoist[iidx.to_raw_index()] {
+            for op_iidx in inst.iter_iidxs(&peel) {
+                needed.set(op_iidx.to_raw_index(), true);
+            }
+        }
+    }
+
+    // Copy the hoisted instructions to the end of `entry`.
+    let Some(Inst::Term(Term(mut entry_term_vars))) = entry.insts.pop() else {
+        panic!()
+    };
+    // Maps peel instructions to their equivalents in `entry`.
+    let mut emap = typed_vec![InstIdx::MAX; peel.insts_len()];
+    for (i, iidx) in entry_term_vars.iter().enumerate() {
+        emap[InstIdx::from_raw_index(i)] = *iidx;
+    }
+    for (iidx, inst) in peel.insts_iter(InstIdx::from_raw_index(nargs)..) {
+        if !hoist[iidx.to_raw_index()] {
+            continue;
+        }
+        // Constants aren't themselves hoisted, but any that the instruction references need to
+        // be available in `entry`.
+        for op_iidx in inst.iter_iidxs(&peel) {
+            if emap[op_iidx] == InstIdx::MAX {
+                let Inst::Const(x) = peel.inst(op_iidx) else {
+                    panic!()
+                };
+                emap[op_iidx] = push_or_reuse(m, &mut entry, x.clone().into());
+            }
+        }
+        if let Inst::Guard(Guard {
+            expect,
+            cond,
+            geidx,
+        }) = inst
+        {
+            let cond = emap[*cond];
+            // If `entry` has already guarded on the same condition, we needn't do so again.
+            let exists = entry.insts_iter(..).any(|(_, x)| {
+                matches!(x, Inst::Guard(Guard { expect: y_expect, cond: y_cond, .. })
+                         if y_expect == expect && *y_cond == cond)
+            });
+            if !exists {
+                let mut gextra = peel.gextra(*geidx).clone();
+                for x in gextra.deopt_vars.iter_mut() {
+                    *x = emap[*x];
+                }
+                for x in gextra.virt_objs.iter_mut() {
+                    x.rewrite_iidxs(|y| emap[y]);
+                }
+                let geidx = entry.guard_extras.push(gextra);
+                entry.insts.push(
+                    Guard {
+                        expect: *expect,
+                        cond,
+                        geidx,
+                    }
+                    .into(),
+                );
+            }
+        } else {
+            let mut inst = inst.clone();
+            inst.rewrite_iidxs(&mut entry, |x| emap[x]);
+            emap[iidx] = push_or_reuse(m, &mut entry, inst);
+        }
+    }
+
+    // Build the new peel, with hoisted instructions replaced by extra arguments.
+    let mut insts = TypedVec::with_capacity(peel.insts_len());
+    let mut guard_extras = TypedVec::new();
+    // Maps old peel instructions to new peel instructions.
+    let mut pmap = typed_vec![InstIdx::MAX; peel.insts_len()];
+    for (iidx, inst) in peel.insts_iter(..InstIdx::from_raw_index(nargs)) {
+        pmap[iidx] = insts.push(inst.clone());
+    }
+    let mut extra_args = Vec::new();
+    for (iidx, inst) in peel.insts_iter(InstIdx::from_raw_index(nargs)..) {
+        if hoist[iidx.to_raw_index()] && needed[iidx.to_raw_index()] {
+            entry_term_vars.push(emap[iidx]);
+            pmap[iidx] = insts.push(
+                Arg {
+                    tyidx: inst.tyidx(m),
+                }
+                .into(),
+            );
+            extra_args.push(pmap[iidx]);
+        }
+    }
+    entry.insts.push(Term(entry_term_vars).into());
+    for (iidx, inst) in peel.insts_iter(InstIdx::from_raw_index(nargs)..) {
+        if hoist[iidx.to_raw_index()]
+            || !used[iidx.to_raw_index()]
+            || (matches!(inst, Inst::Const(_)) && !needed[iidx.to_raw_index()])
+        {
+            continue;
+        }
+        match inst {
+            Inst::Guard(Guard {
+                expect,
+                cond,
+                geidx,
+            }) => {
+                let mut gextra = peel.gextra(*geidx).clone();
+                for x in gextra.deopt_vars.iter_mut() {
+                    *x = pmap[*x];
+                }
+                for x in gextra.virt_objs.iter_mut() {
+                    x.rewrite_iidxs(|y| pmap[y]);
+                }
+                let geidx = guard_extras.push(gextra);
+                pmap[iidx] = insts.push(
+                    Guard {
+                        expect: *expect,
+                        cond: pmap[*cond],
+                        geidx,
+                    }
+                    .into(),
+                );
+            }
+            Inst::Term(Term(term_vars)) => {
+                let mut term_vars = term_vars.iter().map(|x| pmap[*x]).collect::<Vec<_>>();
+                term_vars.extend(extra_args.iter());
+                insts.push(Term(term_vars).into());
+            }
+            _ => {
+                let mut inst = inst.clone();
+                inst.rewrite_iidxs(&mut entry, |x| pmap[x]);
+                pmap[iidx] = insts.push(inst);
+            }
+        }
+    }
+
+    (
+        entry,
+        Block {
+            insts,
+            guard_extras,
+        },
+    )
+}
+
+/// Return the set of instructions in `b` whose results are used, or which must be executed for
+/// their effects.
+fn used(b: &Block) -> Vob {
+    let mut used = Vob::from_elem(false, b.insts.len_usize());
+    for (iidx, inst) in b.insts_iter(..).rev() {
+        if used[iidx.to_raw_index()]
+            || matches!(inst, Inst::Term(_))
+            || inst
+                .read_effects()
+                .interferes(Effects::none().add_volatile())
+            || inst.write_effects().interferes(Effects::all())
+        {
+            used.set(iidx.to_raw_index(), true);
+            for op_iidx in inst.iter_iidxs(b) {
+                used.set(op_iidx.to_raw_index(), true);
+            }
+        }
+    }
+    used
+}
+
+/// Return the set of instructions in `peel` which can be hoisted. Unused instructions, and those
+/// for which `may_hoist` returns `false`, are never hoisted.
+fn hoistable(
+    m: &dyn ModLikeT,
+    peel: &Block,
+    used: &Vob,
+    may_hoist: &mut dyn FnMut(&Block, InstIdx) -> bool,
+) -> Vob {
+    let nargs = peel.term_vars().len();
+    // Instructions whose value is the same in every iteration of the peel. Note that an argument
+    // which the peel's `term` sets to a constant is not invariant: in the first iteration of the
+    // peel it has whatever value the entry passed in.
+    let mut invariant = Vob::from_elem(false, peel.insts_len());
+    for (i, iidx) in peel.term_vars().iter().enumerate() {
+        if iidx.to_raw_index() == i {
+            invariant.set(i, true);
+        }
+    }
+    // The instructions in the peel which might write to memory.
+    let writes = peel
+        .insts_iter(..)
+        .filter(|(_, inst)| {
+            inst.write_effects()
+                .interferes(Effects::none().add_heap().add_volatile())
+        })
+        .map(|(_, inst)| inst)
+        .collect::<Vec<_>>();
+
+    let mut hoist = Vob::from_elem(false, peel.insts_len());
+    // Have we encountered a guard which is not being hoisted?
+    let mut guard_barrier = false;
+    // Have we encountered an instruction with write effects which is not being hoisted?
+    let mut write_barrier = false;
+    for (iidx, inst) in peel.insts_iter(InstIdx::from_raw_index(nargs)..) {
+        let ops_invariant = inst.iter_iidxs(peel).all(|x| invariant[x.to_raw_index()]);
+        let can_hoist = match inst {
+            Inst::Const(_) => {
+                invariant.set(iidx.to_raw_index(), true);
+                continue;
+            }
+            _ if !used[iidx.to_raw_index()] => continue,
+            Inst::Guard(Guard { geidx, .. }) => {
+                ops_invariant
+                    && !guard_barrier
+                    && !write_barrier
+                    // Only the first guard in a trace can record a `switch` and hoisting might
+                    // change which guard that is.
+                    && peel.gextra(*geidx).switch.is_none()
+            }
+            Inst::Load(Load {
+                tyidx,
+                ptr,
+                is_volatile: false,
+            }) => {
+                ops_invariant && !guard_barrier && {
+                    let addr = Address::from(peel, *ptr);
+                    let ext = Extent::Bytes(m.ty(*tyidx).bitw().div_ceil(8));
+                    !writes.iter().any(|x| may_write(m, peel, x, &addr, ext))
+                }
+            }
+            Inst::SDiv(_) | Inst::SRem(_) | Inst::UDiv(_) | Inst::URem(_) => {
+                ops_invariant && !guard_barrier
+            }
+            // Even calls without effects must be executed once per iteration: for example,
+            // allocators must return distinct memory each time they are called. `alloca`s are
+            // not hoisted so that each block's stack slots stay local to that block.
+            Inst::Alloca(_) | Inst::Call(_) | Inst::Term(_) => false,
+            _ => ops_invariant && !inst.read_write_effects().interferes(Effects::all()),
+        };
+        if can_hoist && may_hoist(peel, iidx) {
+            hoist.set(iidx.to_raw_index(), true);
+            invariant.set(iidx.to_raw_index(), true);
+        } else {
+            if let Inst::Guard(_) = inst {
+                guard_barrier = true;
+            }
+            // `noalias` scopes only inform alias analysis: they don't write to anything.
+            if matches!(inst, Inst::Call(_))
+                || (inst
+                    .write_effects()
+                    .interferes(Effects::all().minus_guard())
+                    && !matches!(inst, Inst::NoAlias(_) | Inst::NoAliasEnd(_)))
+            {
+                write_barrier = true;
+            }
+        }
+    }
+    hoist
+}
+
+/// Push `inst` to the end of `entry` unless an equivalent instruction already exists, returning
+/// the [InstIdx] of whichever instruction holds the value.
+fn push_or_reuse(m: &dyn ModLikeT, entry: &mut Block, inst: Inst) -> InstIdx {
+    if let Inst::Load(Load {
+        tyidx,
+        ptr,
+        is_volatile: false,
+    }) = inst
+    {
+        // A previous load of (or store to) the same address can be reused provided nothing since
+        // might have overwritten it.
+        let addr = Address::from(&*entry, ptr);
+        let ext = Extent::Bytes(m.ty(tyidx).bitw().div_ceil(8));
+        for (iidx, x) in entry.insts_iter(..).rev() {
+            match x {
+                Inst::Load(Load {
+                    tyidx: x_tyidx,
+                    ptr: x_ptr,
+                    is_volatile: false,
+                }) if *x_tyidx == tyidx && Address::from(&*entry, *x_ptr) == addr => {
+                    return iidx;
+                }
+                Inst::Store(Store {
+                    val, ptr: x_ptr, ..
+                }) if entry.inst(*val).tyidx(m) == tyidx
+                    && Address::from(&*entry, *x_ptr) == addr =>
+                {
+                    return *val;
+                }
+                _ if may_write(m, &*entry, x, &addr, ext) => break,
+                _ => (),
+            }
+        }
+    } else if let Some((iidx, _)) = entry
+        .insts_iter(..)
+        .rev()
+        .find(|(_, x)| inst.cse_eq(&*entry, x))
+    {
+        return iidx;
+    }
+    entry.insts.push(inst)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compile::j2::opt::fullopt::test::full_opt_test;
+
+    #[test]
+    fn hoist_loads() {
+        // An invariant load is hoisted: since the entry has already performed the same load, its
+        // value is passed into the peel.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          term [%0, %3]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          term [%0, %3, %2]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          %3: i64 = add %1, %2
+          term [%0, %3, %2]
+        ",
+        );
+
+        // A load which may be overwritten in the peel is not hoisted.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          store %3, %0
+          term [%0, %1]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          store %3, %0
+          term [%0, %1]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = add %1, %2
+          store %3, %0
+          term [%0, %1]
+        ",
+        );
+
+        // A load which a guard that isn't hoisted might have prevented from executing is not
+        // hoisted.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = 10
+          %3: i1 = icmp ult %1, %2
+          guard true, %3, [%0, %1]
+          %5: i64 = load %0
+          %6: i64 = add %1, %5
+          term [%0, %6]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = 10
+          %3: i1 = icmp ult %1, %2
+          guard true, %3, [%0, %1]
+          %5: i64 = load %0
+          %6: i64 = add %1, %5
+          term [%0, %6]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = 10
+          %3: i1 = icmp ult %1, %2
+          guard true, %3, [%0, %1]
+          %5: i64 = load %0
+          %6: i64 = add %1, %5
+          term [%0, %6]
+        ",
+        );
+    }
+
+    #[test]
+    fn hoist_guards() {
+        // An invariant guard which the entry has already performed is removed from the peel.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = load %0
+          %3: i64 = 10
+          %4: i1 = icmp ult %2, %3
+          guard true, %4, [%0, %2]
+          %6: i64 = add %1, %2
+          term [%0, %6]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = 10
+          %4: i1 = icmp ult %2, %3
+          guard true, %4, [%0, %2]
+          %6: i64 = add %1, %2
+          term [%0, %6, %2]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          %3: i64 = add %1, %2
+          term [%0, %3, %2]
+        ",
+        );
+
+        // A guard which follows a store that isn't hoisted stays in the peel, but its condition
+        // is hoisted.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: ptr = ptradd %0, 8
+          store %1, %2
+          %4: i64 = load %0
+          %5: i64 = 10
+          %6: i1 = icmp ult %4, %5
+          guard true, %6, [%0, %4]
+          %8: i64 = add %1, %4
+          term [%0, %8]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: ptr = ptradd %0, 8
+          store %1, %2
+          %4: i64 = load %0
+          %5: i64 = 10
+          %6: i1 = icmp ult %4, %5
+          guard true, %6, [%0, %4]
+          %8: i64 = add %1, %4
+          term [%0, %8, %2, %4, %6]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: ptr = arg
+          %3: i64 = arg
+          %4: i1 = arg
+          store %1, %2
+          guard true, %4, [%0, %3]
+          %7: i64 = add %1, %3
+          term [%0, %7, %2, %3, %4]
+        ",
+        );
+
+        // A guard whose deopt variables aren't invariant isn't hoisted.
+        full_opt_test(
+            r#"
+          %0: ptr = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = load %0
+          %3: i64 = 10
+          %4: i1 = icmp ult %2, %3
+          guard true, %4, [%0, %1]
+          %6: i64 = add %1, %2
+          term [%0, %6]
+        "#,
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = load %0
+          %3: i64 = 10
+          %4: i1 = icmp ult %2, %3
+          guard true, %4, [%0, %1]
+          %6: i64 = add %1, %2
+          term [%0, %6, %2, %4]
+          ; peel
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i64 = arg
+          %3: i1 = arg
+          guard true, %3, [%0, %1]
+          %5: i64 = add %1, %2
+          term [%0, %5, %2, %3]
+        ",
+        );
+
+        // A guard on an argument which the `term` rotates, and which only becomes constant in the
+        // second iteration of the peel, isn't hoisted.
+        full_opt_test(
+            r#"
+          %0: i64 = arg [reg("GPR0", undefined)]
+          %1: i64 = arg [reg("GPR1", undefined)]
+          %2: i64 = 5
+          %3: i64 = 10
+          %4: i1 = icmp ult %0, %3
+          guard true, %4, [%0, %1]
+          term [%1, %2]
+        "#,
+            "
+          %0: i64 = arg
+          %1: i64 = arg
+          %2: i64 = 5
+          %3: i64 = 10
+          %4: i1 = icmp ult %0, %3
+          guard true, %4, [%0, %1]
+          term [%1, %2]
+          ; peel
+          %0: i64 = arg
+          %1: i64 = 5
+          %2: i64 = 10
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, [%0, %1]
+          term [%1, %1]
+        ",
+        );
+    }
+}
@@ -13,33 +13,45 @@
 //! both the store and the second load are redundant (assuming they are non-`volatile`): we know
 //! that the byte stored at %0's pointer has the value in %1 and it cannot be changed.
 //!
-//! This pass is currently very naive in two main ways:
-//!   * Its approach to alias analysis is to only trust pointers derived from the same base: so
-//!     `ptradd %0, 4` and `ptradd %0, 8` are distinct pointers but it can never prove that two
-//!     pointers `%0` and `%1` that derive from different bases do not alias.
-//!   * It has no notion of "effect" other than "things like calls are barriers". As soon as it
-//!     encounters a barrier, all of its knowledge of heap values is destroyed.
+//! When a store (or a call which can only write through its pointer arguments) is encountered, we
+//! use [super::alias] to determine which of our existing knowledge it may overwrite.
+//!
+//! This pass is currently naive in that it has little notion of "effect" other than "things like
+//! calls are barriers". As soon as it encounters a barrier, all of its knowledge of heap values is
+//! destroyed. The exceptions are calls which only read memory, which only access memory the trace
+//! cannot see, or which only access memory through their pointer arguments.
+//!
+//! Within the scope of a [NoAlias] (i.e. a `noalias` argument of an inlined function), knowledge
+//! gained in that scope is kept across writes which [super::alias::noalias_disjoint] tells us
+//! cannot overwrite it.
 
 use crate::compile::j2::{
     effects::Effects,
     hir::*,
     opt::{
         BlockLikeT, EquivIIdxT, ModLikeT,
+        alias::{Address, Extent, may_alias, noalias_disjoint},
         fullopt::{CommitInstOpt, OptOutcome, PassOpt, PassT},
     },
 };
-use index_type::vec::TypedVec;
+use index_type::{IndexType, vec::TypedVec};
 use std::{collections::HashMap, mem};
 
 /// Load/Store elimination
 pub(super) struct LoadStore {
-    /// A map telling us what value we know is currently stored at a given [Address].
-    hv: HashMap<Address, InstIdx>,
+    /// A map telling us what value we know is currently stored at a given [Address], and the
+    /// instruction which told us.
+    hv: HashMap<Address, (InstIdx, InstIdx)>,
+    /// The [NoAlias] instructions whose scopes are open.
+    noalias: Vec<InstIdx>,
 }
 
 impl LoadStore {
     pub(super) fn new() -> Self {
-        Self { hv: HashMap::new() }
+        Self {
+            hv: HashMap::new(),
+            noalias: Vec::new(),
+        }
     }
 }
 
@@ -52,7 +64,7 @@ impl PassT for LoadStore {
                 is_volatile: false,
             }) => {
                 let addr = Address::from(opt, ptr);
-                if let Some(hv_iidx) = self.hv.get(&addr) {
+                if let Some((hv_iidx, _)) = self.hv.get(&addr) {
                     let inst_ty = opt.ty(tyidx);
                     let hv_iidx = opt.equiv_iidx(*hv_iidx);
                     let hv_tyidx = opt.inst(hv_iidx).tyidx(opt);
@@ -107,7 +119,7 @@ impl PassT for LoadStore {
             }) => {
                 let val = opt.equiv_iidx(val);
                 let addr = Address::from(opt, ptr);
-                if let Some(iidx) = self.hv.get(&addr) {
+                if let Some((iidx, _)) = self.hv.get(&addr) {
                     let iidx = opt.equiv_iidx(*iidx);
                     if val == iidx {
                         // We currently only allow the same number of bits to lead to load
@@ -135,7 +147,7 @@ impl PassT for LoadStore {
                 ptr,
                 is_volatile: _,
             }) => {
-                self.hv.insert(Address::from(opt, *ptr), iidx);
+                self.hv.insert(Address::from(opt, *ptr), (iidx, iidx));
             }
             Inst::Store(Store {
                 ptr,
@@ -144,45 +156,50 @@ impl PassT for LoadStore {
             }) => {
                 let addr = Address::from(opt, *ptr);
                 let val = opt.equiv_iidx(*val);
-                // We are ultra conservative here: we only say "these don't overlap" if two stores
-                // ultimately reference the same SSA variable with pointer adds. In other words, if
-                // we're writing 8 bytes and we're storing to `%3 + 8` and `%3 + 24` we can be
-                // entirely sure the stores don't overlap: in any other situation, we assume
-                // overlap is possible.
-                match addr {
-                    Address::PtrOff(ptr, off) => {
-                        let ptr = opt.equiv_iidx(ptr);
-                        let off = isize::try_from(off).unwrap();
-                        let bytew = isize::try_from(opt.inst_bitw(opt, val).div_ceil(8)).unwrap();
-                        self.hv.retain(|hv_addr, hv_val| match hv_addr {
-                            Address::PtrOff(hv_ptr, hv_off) => {
-                                let hv_ptr = opt.equiv_iidx(*hv_ptr);
-                                let hv_off = isize::try_from(*hv_off).unwrap();
-                                let hv_val = opt.equiv_iidx(*hv_val);
-                                let hv_bytew =
-                                    isize::try_from(opt.inst_bitw(opt, hv_val).div_ceil(8))
-                                        .unwrap();
-                                ptr == hv_ptr && (off + bytew <= hv_off || hv_off + hv_bytew <= off)
-                            }
-                            Address::Const(_) => false,
-                        });
-                    }
-                    Address::Const(addr) => {
-                        self.hv.retain(|hv_addr, hv_val| match hv_addr {
-                            Address::PtrOff(_, _) => false,
-                            Address::Const(hv_addr) => {
-                                let bytew =
-                                    usize::try_from(opt.inst_bitw(opt, val).div_ceil(8)).unwrap();
-                                let hv_val = opt.equiv_iidx(*hv_val);
-                                let hv_bytew =
-                                    usize::try_from(opt.inst_bitw(opt, hv_val).div_ceil(8))
-                                        .unwrap();
-                                addr + bytew <= *hv_addr || hv_addr + hv_bytew <= addr
-                            }
-                        });
-                    }
+                let bytew = opt.inst_bitw(opt, val).div_ceil(8);
+                self.hv.retain(|hv_addr, (hv_val, hv_iidx)| {
+                    let hv_bytew = opt.inst_bitw(opt, opt.equiv_iidx(*hv_val)).div_ceil(8);
+                    !may_alias(
+                        opt,
+                        &addr,
+                        Extent::Bytes(bytew),
+                        hv_addr,
+                        Extent::Bytes(hv_bytew),
+                    ) || noalias_disjoint(opt, &self.noalias, &addr, iidx, hv_addr, *hv_iidx)
+                });
+                self.hv.insert(addr, (val, iidx));
+            }
+            Inst::Call(Call {
+                effects: CallEffects::ArgMemWrite | CallEffects::ArgMemReadWrite,
+                args,
+                ..
+            }) => {
+                // The call can only write to memory reachable from its pointer arguments, so we
+                // only need to forget about addresses which those arguments may alias.
+                let arg_addrs = args
+                    .iter()
+                    .filter(|x| matches!(opt.ty(opt.inst(**x).tyidx(opt)), Ty::Ptr(_)))
+                    .map(|x| Address::from(opt, *x))
+                    .collect::<Vec<_>>();
+                self.hv.retain(|hv_addr, (_, hv_iidx)| {
+                    !arg_addrs.iter().any(|arg_addr| {
+                        may_alias(opt, arg_addr, Extent::Object, hv_addr, Extent::Object)
+                            && !noalias_disjoint(
+                                opt,
+                                &self.noalias,
+                                arg_addr,
+                                iidx,
+                                hv_addr,
+                                *hv_iidx,
+                            )
+                    })
+                });
+            }
+            Inst::NoAlias(_) => self.noalias.push(iidx),
+            Inst::NoAliasEnd(NoAliasEnd { ptr }) => {
+                if let Some(i) = self.noalias.iter().rposition(|x| x == ptr) {
+                    self.noalias.remove(i);
                 }
-                self.hv.insert(addr, val);
             }
             _ => {
                 if inst
@@ -198,7 +215,7 @@ impl PassT for LoadStore {
     fn equiv_committed(&mut self, equiv1: InstIdx, equiv2: InstIdx) {
         // FIXME: This is of a hack until `prepare_for_peel` can properly determine equivalences.
         // At that point, there's no need for this method to do anything.
-        for hv_val in self.hv.values_mut() {
+        for (hv_val, _) in self.hv.values_mut() {
             if *hv_val == equiv1 {
                 *hv_val = equiv2;
             }
@@ -209,82 +226,45 @@ impl PassT for LoadStore {
         &mut self,
         opt: &mut PassOpt,
         entry: &Block,
-        map: &TypedVec<InstIdx, InstIdx>,
+        _map: &TypedVec<InstIdx, InstIdx>,
     ) {
+        // `map` also maps each entry argument to the corresponding peel argument, but what we know
+        // about the heap holds at the end of the entry block: only the entry's term vars still
+        // have the same value at the start of the peel.
+        let term_map = entry
+            .term_vars()
+            .iter()
+            .enumerate()
+            .map(|(i, iidx)| (*iidx, InstIdx::from_raw_index(i)))
+            .collect::<HashMap<_, _>>();
         let mut new_hv = HashMap::new();
+        // What we know about the heap at the start of the peel predates any [NoAlias] in it.
+        let start_iidx = InstIdx::from_raw_index(0);
+        self.noalias.clear();
 
-        for (hv_addr, hv_val) in mem::take(&mut self.hv) {
+        for (hv_addr, (hv_val, _)) in mem::take(&mut self.hv) {
             let new_addr = match hv_addr {
                 Address::PtrOff(iidx, off) => {
-                    if map[iidx] == InstIdx::MAX {
+                    let Some(peel_iidx) = term_map.get(&iidx) else {
                         continue;
-                    }
-                    Address::PtrOff(map[iidx], off)
+                    };
+                    Address::PtrOff(*peel_iidx, off)
                 }
                 Address::Const(_) => hv_addr,
             };
-            if map[hv_val] != InstIdx::MAX {
-                new_hv.insert(new_addr, opt.equiv_iidx(map[hv_val]));
+            if let Some(peel_iidx) = term_map.get(&hv_val) {
+                new_hv.insert(new_addr, (opt.equiv_iidx(*peel_iidx), start_iidx));
             } else if let Inst::Const(x) = entry.inst(hv_val) {
-                new_hv.insert(new_addr, opt.push_pre_inst(Inst::Const(x.to_owned())));
+                new_hv.insert(
+                    new_addr,
+                    (opt.push_pre_inst(Inst::Const(x.to_owned())), start_iidx),
+                );
             }
         }
         self.hv = new_hv;
     }
 }
 
-/// An abstract "address" representing a location in RAM.
-#[derive(Debug, Eq, Hash, PartialEq)]
-enum Address {
-    PtrOff(InstIdx, i32),
-    Const(usize),
-}
-
-impl Address {
-    /// Create a new `address` for the instruction at `ptr`. Note: `ptr` does not need to have
-    /// been `equiv_iidx`ed.
-    fn from<T: BlockLikeT + EquivIIdxT>(opt: &T, mut ptr: InstIdx) -> Self {
-        // We now chase the instruction at `ptr` backwards if it's a chain of `PtrAdd`s. So
-        // if we have:
-        //
-        // ```
-        // %0: ptr = arg [reg]
-        // %1: ptradd %0, 8
-        // %2: ptradd %1, 4
-        // ```
-        //
-        // and we call `Address::from(%2)` then `Address::PtrOff(%0, 12)` is returned.
-
-        let mut cum_off: i32 = 0; // Cumulative offset over the chain of `PtrAdd`s.
-        ptr = opt.equiv_iidx(ptr);
-        while let Inst::PtrAdd(PtrAdd {
-            ptr: child_ptr,
-            off,
-            nusw,
-            nuw,
-            in_bounds,
-        }) = opt.inst(ptr)
-        {
-            // We don't support nusw or nuw yet
-            assert!(!nusw && !nuw && !in_bounds);
-            cum_off = cum_off.checked_add(*off).unwrap();
-            ptr = opt.equiv_iidx(*child_ptr);
-        }
-        if let Inst::Const(c) = opt.inst(ptr) {
-            let Const {
-                tyidx: _,
-                kind: ConstKind::Ptr(addr),
-            } = c
-            else {
-                panic!()
-            };
-            Address::Const(*addr + usize::try_from(cum_off).unwrap())
-        } else {
-            Address::PtrOff(ptr, cum_off)
-        }
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -618,6 +598,329 @@ mod test {
         }
     }
 
+    #[test]
+    fn aliasing() {
+        // Distinct allocations don't alias.
+        test_ls(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          %5: ptr = call malloc %2(%3)
+          store %0, %4
+          store %1, %5
+          %8: i8 = load %4
+          blackbox %8
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: ptr = call %2(%3)
+          store %0, %4
+          store %1, %5
+          blackbox %0
+        ",
+        );
+
+        // An allocation doesn't alias pointers defined before it.
+        test_ls(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: ptr = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          %5: i8 = load %0
+          store %1, %4
+          %7: i8 = load %0
+          blackbox %7
+        ",
+            "
+          %0: ptr = arg
+          %1: i8 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: i8 = load %0
+          store %1, %4
+          blackbox %5
+        ",
+        );
+
+        // ...but may alias pointers defined after it.
+        test_ls(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: ptr = arg [reg]
+          %1: i8 = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = 0x1234
+          %4: i64 = 16
+          %5: ptr = call malloc %3(%4)
+          store %1, %5
+          %7: ptr = load %0
+          store %2, %7
+          %9: i8 = load %5
+          blackbox %9
+        ",
+            "
+          %0: ptr = arg
+          %1: i8 = arg
+          %2: i8 = arg
+          %3: ptr = 0x1234
+          %4: i64 = 16
+          %5: ptr = call %3(%4)
+          store %1, %5
+          %7: ptr = load %0
+          store %2, %7
+          %9: i8 = load %5
+          blackbox %9
+        ",
+        );
+
+        // An allocation doesn't alias constant addresses.
+        test_ls(
+            "
+          extern malloc(i64) -> ptr allocator
+
+          %0: i8 = arg [reg]
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call malloc %1(%2)
+          %4: ptr = 0x5678
+          %5: i8 = load %4
+          store %0, %3
+          %7: i8 = load %4
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: ptr = 0x1234
+          %2: i64 = 16
+          %3: ptr = call %1(%2)
+          %4: ptr = 0x5678
+          %5: i8 = load %4
+          store %0, %3
+          blackbox %5
+        ",
+        );
+
+        // Stack allocations are treated like any other allocation: they don't alias each other,
+        // pointers defined before them, or constant addresses.
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: i8 = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = alloca 8, 8
+          %4: ptr = alloca 8, 8
+          %5: ptr = 0x5678
+          store %1, %3
+          store %2, %4
+          store %2, %0
+          store %2, %5
+          %10: i8 = load %3
+          blackbox %10
+        ",
+            "
+          %0: ptr = arg
+          %1: i8 = arg
+          %2: i8 = arg
+          %3: ptr = alloca 8, 8
+          %4: ptr = alloca 8, 8
+          %5: ptr = 0x5678
+          store %1, %3
+          store %2, %4
+          store %2, %0
+          store %2, %5
+          blackbox %1
+        ",
+        );
+
+        // A call which only writes through its pointer arguments only clobbers addresses those
+        // arguments may alias.
+        test_ls(
+            "
+          extern malloc(i64) -> ptr allocator
+          extern f(ptr) memory(argmem: write)
+
+          %0: ptr = arg [reg]
+          %1: i8 = arg [reg]
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call malloc %2(%3)
+          %5: i8 = load %0
+          store %1, %4
+          %7: ptr = 0x5678
+          call f %7(%4)
+          %9: i8 = load %0
+          %10: i8 = load %4
+          blackbox %9
+          blackbox %10
+        ",
+            "
+          %0: ptr = arg
+          %1: i8 = arg
+          %2: ptr = 0x1234
+          %3: i64 = 16
+          %4: ptr = call %2(%3)
+          %5: i8 = load %0
+          store %1, %4
+          %7: ptr = 0x5678
+          call %7(%4)
+          %9: i8 = load %4
+          blackbox %5
+          blackbox %9
+        ",
+        );
+    }
+
+    #[test]
+    fn noalias() {
+        // Within a `noalias` scope, writes through pointers defined before the scope don't alias
+        // accesses relative to the `noalias` pointer...
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          store %2, %1
+          %6: i8 = load %3
+          noalias_end %3
+          blackbox %6
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = arg
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          store %2, %1
+          noalias_end %3
+          blackbox %4
+        ",
+        );
+
+        // ...and vice versa.
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = noalias %0
+          %4: i8 = load %1
+          store %2, %3
+          %6: i8 = load %1
+          noalias_end %3
+          blackbox %6
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = arg
+          %3: ptr = noalias %0
+          %4: i8 = load %1
+          store %2, %3
+          noalias_end %3
+          blackbox %4
+        ",
+        );
+
+        // What we knew before the scope started isn't kept.
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = arg [reg]
+          %3: i8 = load %1
+          %4: ptr = noalias %0
+          store %2, %4
+          %6: i8 = load %1
+          noalias_end %4
+          blackbox %3
+          blackbox %6
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = arg
+          %3: i8 = load %1
+          %4: ptr = noalias %0
+          store %2, %4
+          %6: i8 = load %1
+          noalias_end %4
+          blackbox %3
+          blackbox %6
+        ",
+        );
+
+        // Writes after the scope has ended may alias.
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          noalias_end %3
+          store %2, %1
+          %7: i8 = load %3
+          blackbox %7
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = arg
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          noalias_end %3
+          store %2, %1
+          %7: i8 = load %3
+          blackbox %7
+        ",
+        );
+
+        // Pointers defined within the scope may be based on the `noalias` pointer.
+        test_ls(
+            "
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = arg [reg]
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          %5: ptr = load %1
+          store %2, %5
+          %7: i8 = load %3
+          noalias_end %3
+          blackbox %7
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = arg
+          %3: ptr = noalias %0
+          %4: i8 = load %3
+          %5: ptr = load %1
+          store %2, %5
+          %7: i8 = load %3
+          noalias_end %3
+          blackbox %7
+        ",
+        );
+    }
+
     #[test]
     fn barriers() {
         // Test that the things that should be barriers really do act as barriers.
@@ -720,6 +1023,87 @@ mod test {
         ",
         );
 
+        // Calls which only access memory via their pointer arguments
+        test_ls(
+            "
+          extern f(ptr) memory(argmem: write)
+
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call f %3(%1)
+          store %2, %0
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call %3(%1)
+          store %2, %0
+        ",
+        );
+
+        test_ls(
+            "
+          extern f(i64) memory(argmem: readwrite)
+
+          %0: ptr = arg [reg]
+          %1: i64 = arg [reg]
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call f %3(%1)
+          store %2, %0
+        ",
+            "
+          %0: ptr = arg
+          %1: i64 = arg
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call %3(%1)
+        ",
+        );
+
+        test_ls(
+            "
+          extern f(ptr) memory(argmem: read)
+
+          %0: ptr = arg [reg]
+          %1: ptr = arg [reg]
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call f %3(%1)
+          store %2, %0
+        ",
+            "
+          %0: ptr = arg
+          %1: ptr = arg
+          %2: i8 = load %0
+          %3: ptr = 0x1234
+          call %3(%1)
+        ",
+        );
+
+        // Calls which only access memory the trace cannot see
+        test_ls(
+            "
+          extern f(ptr) memory(inaccessiblemem: readwrite)
+
+          %0: ptr = arg [reg]
+          %1: i8 = load %0
+          %2: ptr = 0x1234
+          call f %2(%0)
+          store %1, %0
+        ",
+            "
+          %0: ptr = arg
+          %1: i8 = load %0
+          %2: ptr = 0x1234
+          call %2(%0)
+        ",
+        );
+
         // memcpy
         test_ls(
             "
@@ -809,8 +1193,8 @@ mod test {
         ",
         );
 
-        // FIXME: This test shouldn't pass! We _should_ be capable of passing the equivalence of %2
-        // and %1 into the peel, but we need to extend the term_vars to do so.
+        // The peel's load of %0 and the guard on it are loop invariant, so they are hoisted into
+        // the entry, leaving the peel with nothing to check.
         full_opt_test(
             r#"
           %0: ptr = arg [reg("GPR0", undefined)]
@@ -830,13 +1214,12 @@ mod test {
           guard true, %3, []
           blackbox %2
           %6: i8 = 2
+          %7: i1 = icmp eq %2, %6
+          guard true, %7, []
           term [%0, %6]
           ; peel
           %0: ptr = arg
           %1: i8 = 2
-          %2: i8 = load %0
-          %3: i1 = icmp eq %2, %1
-          guard true, %3, []
           blackbox %1
           term [%0, %1]
         ",
@@ -7,12 +7,22 @@
 
 use crate::compile::{CompilationError, j2::hir::*};
 use index_type::vec::TypedVec;
+use std::{
+    env,
+    fmt::{self, Display},
+};
+use strum::{EnumCount, VariantArray};
 
+mod alias;
 mod cse;
+mod dse;
+mod escape;
 pub(super) mod fullopt;
 mod known_bits;
+mod licm;
 mod load_store;
 pub(super) mod noopt;
+mod range;
 mod strength_fold;
 
 /// An outward-facing optimiser, used by [super::aot_to_hir]. By definition this operates on one
@@ -93,3 +103,191 @@ pub(super) trait EquivIIdxT {
     /// If `iidx` is greater than the number of instructions the optimiser currently holds.
     fn equiv_iidx(&self, iidx: InstIdx) -> InstIdx;
 }
+
+/// The optimisation passes that [fullopt::FullOpt] can run, in the order they are run. All are
+/// enabled by default: `YKD_OPT_PASSES` can be used to select a subset.
+#[derive(Clone, Copy, Debug, EnumCount, PartialEq, VariantArray)]
+pub(super) enum OptPass {
+    KnownBits,
+    Range,
+    StrengthFold,
+    Escape,
+    LoadStore,
+    CSE,
+    /// Loop-invariant code motion, run after the peel has been created.
+    LICM,
+    /// Dead store elimination, run after all other passes.
+    DSE,
+}
+
+impl OptPass {
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "KnownBits" => Ok(Self::KnownBits),
+            "Range" => Ok(Self::Range),
+            "StrengthFold" => Ok(Self::StrengthFold),
+            "Escape" => Ok(Self::Escape),
+            "LoadStore" => Ok(Self::LoadStore),
+            "CSE" => Ok(Self::CSE),
+            "LICM" => Ok(Self::LICM),
+            "DSE" => Ok(Self::DSE),
+            _ => {
+                let valid = Self::VARIANTS
+                    .iter()
+                    .map(|x| x.name())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Err(format!(
+                    "Invalid YKD_OPT_PASSES value '{s}': must be one of {valid}"
+                ))
+            }
+        }
+    }
+
+    /// The name of this pass as used in `YKD_OPT_PASSES`.
+    fn name(&self) -> &'static str {
+        match self {
+            Self::KnownBits => "KnownBits",
+            Self::Range => "Range",
+            Self::StrengthFold => "StrengthFold",
+            Self::Escape => "Escape",
+            Self::LoadStore => "LoadStore",
+            Self::CSE => "CSE",
+            Self::LICM => "LICM",
+            Self::DSE => "DSE",
+        }
+    }
+}
+
+impl Display for OptPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How much optimisation fuel is available: each time a pass rewrites an instruction, one unit of
+/// fuel is consumed. When no fuel remains, the optimiser stops rewriting instructions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum OptFuel {
+    /// `n` units of fuel shared between all traces compiled by a meta-tracer.
+    Total(u64),
+    /// `n` units of fuel for each trace.
+    PerTrace(u64),
+}
+
+/// User configuration of the optimiser, derived from the `YKD_OPT_PASSES` and `YKD_OPT_FUEL`
+/// environment variables when the meta-tracer is created.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OptConfig {
+    /// Which passes are enabled? Indexed by `OptPass as usize`.
+    passes: [bool; OptPass::COUNT],
+    /// `None` means that unlimited fuel is available.
+    fuel: Option<OptFuel>,
+}
+
+impl OptConfig {
+    /// Create a configuration from the `YKD_OPT_PASSES` and `YKD_OPT_FUEL` environment variables.
+    pub(crate) fn from_env() -> Result<Self, String> {
+        Self::from_strs(
+            env::var("YKD_OPT_PASSES").ok().as_deref(),
+            env::var("YKD_OPT_FUEL").ok().as_deref(),
+        )
+    }
+
+    /// Create a configuration from the (optional) values of the `YKD_OPT_PASSES` and
+    /// `YKD_OPT_FUEL` environment variables.
+    ///
+    /// `YKD_OPT_PASSES` is a comma separated list of [OptPass]es, which are then the only passes
+    /// run: an empty string disables all passes. `YKD_OPT_FUEL` is either `<n>`, for `n` rewrites
+    /// across all traces, or `trace:<n>`, for `n` rewrites in each trace.
+    fn from_strs(passes: Option<&str>, fuel: Option<&str>) -> Result<Self, String> {
+        let passes = match passes {
+            Some(s) => {
+                let mut passes = [false; OptPass::COUNT];
+                for x in s.split(',').filter(|x| !x.is_empty()) {
+                    passes[OptPass::from_str(x)? as usize] = true;
+                }
+                passes
+            }
+            None => [true; OptPass::COUNT],
+        };
+        let fuel = fuel
+            .map(|s| {
+                let (n, per_trace) = match s.strip_prefix("trace:") {
+                    Some(n) => (n, true),
+                    None => (s, false),
+                };
+                let n = n
+                    .parse::<u64>()
+                    .map_err(|e| format!("Invalid YKD_OPT_FUEL value '{s}': {e}"))?;
+                Ok::<_, String>(if per_trace {
+                    OptFuel::PerTrace(n)
+                } else {
+                    OptFuel::Total(n)
+                })
+            })
+            .transpose()?;
+        Ok(Self { passes, fuel })
+    }
+
+    /// Is `pass` enabled?
+    pub(super) fn enabled(&self, pass: OptPass) -> bool {
+        self.passes[pass as usize]
+    }
+
+    /// What fuel limit, if any, was requested?
+    pub(super) fn fuel(&self) -> Option<OptFuel> {
+        self.fuel
+    }
+
+    /// If fuel shared between all traces was requested, how much?
+    pub(crate) fn total_fuel(&self) -> Option<u64> {
+        match self.fuel {
+            Some(OptFuel::Total(n)) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+impl Default for OptConfig {
+    /// Run all passes with unlimited fuel.
+    fn default() -> Self {
+        Self {
+            passes: [true; OptPass::COUNT],
+            fuel: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opt_config() {
+        let c = OptConfig::from_strs(None, None).unwrap();
+        assert!(OptPass::VARIANTS.iter().all(|x| c.enabled(*x)));
+        assert_eq!(c.fuel(), None);
+        assert_eq!(c, OptConfig::default());
+
+        let c = OptConfig::from_strs(Some("KnownBits,CSE"), Some("10")).unwrap();
+        assert!(c.enabled(OptPass::KnownBits) && c.enabled(OptPass::CSE));
+        assert!(!c.enabled(OptPass::StrengthFold) && !c.enabled(OptPass::DSE));
+        assert_eq!(c.fuel(), Some(OptFuel::Total(10)));
+        assert_eq!(c.total_fuel(), Some(10));
+
+        let c = OptConfig::from_strs(Some(""), Some("trace:0")).unwrap();
+        assert!(OptPass::VARIANTS.iter().all(|x| !c.enabled(*x)));
+        assert_eq!(c.fuel(), Some(OptFuel::PerTrace(0)));
+        assert_eq!(c.total_fuel(), None);
+
+        // Every pass can be named.
+        for x in OptPass::VARIANTS {
+            assert_eq!(OptPass::from_str(x.name()), Ok(*x));
+        }
+
+        assert!(OptConfig::from_strs(Some("KnownBits,Foo"), None).is_err());
+        assert!(OptConfig::from_strs(None, Some("-1")).is_err());
+        assert!(OptConfig::from_strs(None, Some("trace:")).is_err());
+    }
+}
@@ -0,0 +1,1686 @@
+//! Integer range analysis.
+//!
+//! Data-flow analysis which tracks, for each integer value, the inclusive range of values it can
+//! take under both signed and unsigned interpretations. Ranges are narrowed by guards on `icmp`s
+//! and propagated through arithmetic, casts, and `select`s. When an `icmp` can be decided from
+//! ranges alone it is folded to a constant, which [super::strength_fold] then uses to remove any
+//! guard that references it. Ranges of the entry iteration's terminal variables are carried over
+//! into the peel, so bounds checks proven in the first iteration need not be repeated in the loop.
+//!
+//! Only integers of up to 64 bits are analysed: larger integers are treated as unknown.
+//!
+//! Alongside per-value ranges, guards also record relational facts of the form `x - y < d`, where
+//! `x` and `y` are SSA values (after stripping constant, non-overflowing, `add`s and `sub`s) and the
+//! subtraction is exact. This allows us to see that e.g. a guard `i + 1 < n` implies a later guard
+//! `i < n`, even when nothing is known about the ranges of `i` and `n`.
+//!
+//! Facts also allow guards to be merged. If a guard `i < n` is followed by a stronger guard
+//! `i + 1 < n`, the earlier guard is strengthened to check `i + 1 < n` (provided that `i + 1`
+//! can't overflow given what we knew before the earlier guard) and the later guard is removed.
+//! Since the strengthened guard can now fail when its original condition holds, any of its deopt
+//! variables which [super::super::aot_to_hir] had replaced with the inverse of the original
+//! condition (see [GuardExtra::cond_deopt_vars]) are replaced with the original condition itself:
+//! deopt then resumes at the branch the guard was created from, which takes whichever path the
+//! original condition dictates. Because a strengthened guard's condition is computed before the
+//! guard's position in the trace, strengthening is performed when a [Block] is built (see
+//! [strengthen_guards]).
+
+use crate::compile::{
+    j2::{
+        hir::*,
+        opt::{
+            BlockLikeT, EquivIIdxT,
+            fullopt::{CommitInstOpt, OptOutcome, PassOpt, PassT},
+        },
+    },
+    jitc_yk::arbbitint::ArbBitInt,
+};
+use index_type::{IndexType, typed_vec, vec::TypedVec};
+use std::{collections::HashMap, convert::Infallible, mem};
+
+/// Integer range analysis.
+pub(super) struct Range {
+    /// Maps an SSA value to its corresponding range. The value is `None` when we know nothing
+    /// more about a value than its type tells us.
+    ranges: TypedVec<InstIdx, Option<IntRange>>,
+    /// The range of the current instruction being processed. This is only committed at the end of
+    /// the instruction's analysis.
+    pending_commit: Option<IntRange>,
+    /// Relational facts established by guards. A key `(signed, x, y)` mapping to a [Fact] with
+    /// bound `d` records that `x - y < d` where `x` and `y` are interpreted as `signed` (or
+    /// unsigned) integers and the subtraction is performed without overflow. If several facts have
+    /// the same key, only the strongest (i.e. smallest `d`) is kept.
+    facts: HashMap<(bool, InstIdx, InstIdx), Fact>,
+    /// If the guard currently being processed established a fact that a later guard may
+    /// strengthen it with, that fact's key. The guard's [InstIdx] is only known once it has been
+    /// committed.
+    pending_guard: Option<(bool, InstIdx, InstIdx)>,
+}
+
+/// A relational fact `x - y < bound` (see [Range::facts]).
+struct Fact {
+    bound: i128,
+    /// The guard which established this fact, if it can be strengthened by a later guard.
+    guard: Option<InstIdx>,
+    /// The ranges of `x` and `y` before the guard which established this fact.
+    rngs: (IntRange, IntRange),
+}
+
+impl PassT for Range {
+    fn feed(&mut self, opt: &mut PassOpt, inst: Inst) -> OptOutcome {
+        self.pending_commit = None;
+        self.pending_guard = None;
+        match inst {
+            Inst::Add(x) => self.opt_add(opt, x),
+            Inst::And(x) => self.opt_and(opt, x),
+            Inst::Const(x) => self.opt_const(x),
+            Inst::Guard(x) => self.opt_guard(opt, x),
+            Inst::ICmp(x) => self.opt_icmp(opt, x),
+            Inst::LShr(x) => self.opt_lshr(opt, x),
+            Inst::Mul(x) => self.opt_mul(opt, x),
+            Inst::Overflow(x) => self.opt_overflow(opt, x),
+            Inst::SExt(x) => self.opt_sext(opt, x),
+            Inst::Select(x) => self.opt_select(opt, x),
+            Inst::Sub(x) => self.opt_sub(opt, x),
+            Inst::Trunc(x) => self.opt_trunc(opt, x),
+            Inst::UDiv(x) => self.opt_udiv(opt, x),
+            Inst::URem(x) => self.opt_urem(opt, x),
+            Inst::ZExt(x) => self.opt_zext(opt, x),
+            _ => OptOutcome::Rewritten(inst),
+        }
+    }
+
+    fn preinst_committed(&mut self, opt: &CommitInstOpt, iidx: InstIdx) {
+        if let Inst::Const(Const {
+            kind: ConstKind::Int(x),
+            ..
+        }) = opt.inst(iidx)
+        {
+            self.ranges.push(Some(IntRange::from_const(x)));
+        } else {
+            self.ranges.push(None);
+        }
+    }
+
+    fn inst_committed(&mut self, opt: &CommitInstOpt, iidx: InstIdx) {
+        if let Inst::Const(Const {
+            kind: ConstKind::Int(x),
+            ..
+        }) = opt.inst(iidx)
+        {
+            self.ranges.push(Some(IntRange::from_const(x)));
+        } else {
+            self.ranges.push(self.pending_commit.take());
+        }
+        if let Inst::Guard(_) = opt.inst(iidx)
+            && let Some(k) = self.pending_guard.take()
+        {
+            self.facts.get_mut(&k).unwrap().guard = Some(iidx);
+        }
+    }
+
+    fn equiv_committed(&mut self, equiv1: InstIdx, equiv2: InstIdx) {
+        // Both instructions have the same value, so whatever we know about one also holds for the
+        // other.
+        let rng = match (self.ranges[equiv1], self.ranges[equiv2]) {
+            (Some(x), Some(y)) => x.intersect(&y).or(Some(y)),
+            (x, None) => x,
+            (None, y) => y,
+        };
+        self.ranges[equiv1] = rng;
+        self.ranges[equiv2] = rng;
+    }
+
+    fn prepare_for_peel(
+        &mut self,
+        opt: &mut PassOpt,
+        entry: &Block,
+        map: &TypedVec<InstIdx, InstIdx>,
+    ) {
+        assert!(self.pending_commit.is_none());
+        let mut new = TypedVec::with_capacity(entry.insts_len());
+        for iidx in entry.term_vars().iter().cloned() {
+            if let Some(ConstKind::Int(x)) = opt.as_constkind(map[iidx]) {
+                new.push(Some(IntRange::from_const(&x)));
+            } else {
+                new.push(self.ranges[iidx]);
+            }
+        }
+        self.ranges = new;
+        // Facts refer to entry instructions, which are not mapped one-to-one into the peel.
+        self.facts.clear();
+    }
+}
+
+impl Range {
+    /// Create an empty range analysis object.
+    pub(super) fn new() -> Self {
+        Range {
+            ranges: TypedVec::new(),
+            pending_commit: None,
+            facts: HashMap::new(),
+            pending_guard: None,
+        }
+    }
+
+    /// Returns what we know about the range of `iidx`, or `None` if `iidx` is not an integer that
+    /// this analysis can reason about.
+    fn as_range(&self, opt: &PassOpt, iidx: InstIdx) -> Option<IntRange> {
+        let iidx = opt.equiv_iidx(iidx);
+        match opt.ty(opt.inst(iidx).tyidx(opt)) {
+            Ty::Int(bitw) if *bitw <= 64 => {
+                Some(self.ranges[iidx].unwrap_or_else(|| IntRange::full(*bitw)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Express `iidx` as `base + off`, where `base` is not a constant, by stripping `add`s and
+    /// `sub`s of constants which cannot overflow when interpreted as `signed` (or unsigned)
+    /// integers. Returns `None` if `iidx` is not an integer we can reason about or is a constant.
+    fn decompose(&self, opt: &PassOpt, iidx: InstIdx, signed: bool) -> Option<(InstIdx, i128)> {
+        let iidx = opt.equiv_iidx(iidx);
+        let rng = self.as_range(opt, iidx)?;
+        if rng.as_const().is_some() || opt.as_constkind(iidx).is_some() {
+            return None;
+        }
+        let (x, c, neg) = match opt.inst(iidx) {
+            Inst::Add(Add { lhs, rhs, .. }) => (*lhs, *rhs, false),
+            Inst::Sub(Sub { lhs, rhs, .. }) => (*lhs, *rhs, true),
+            _ => return Some((iidx, 0)),
+        };
+        let Some(ConstKind::Int(c)) = opt.as_constkind(opt.equiv_iidx(c)) else {
+            return Some((iidx, 0));
+        };
+        let mut c = if signed {
+            i128::from(c.to_sign_ext_i64().unwrap())
+        } else {
+            i128::from(c.to_zero_ext_u64().unwrap())
+        };
+        if neg {
+            c = -c;
+        }
+        let Some(x_r) = self.as_range(opt, x) else {
+            return Some((iidx, 0));
+        };
+        let (min, max, lo, hi) = if signed {
+            (x_r.smin, x_r.smax, smin_of(x_r.bitw), smax_of(x_r.bitw))
+        } else {
+            (x_r.umin, x_r.umax, 0, umax_of(x_r.bitw))
+        };
+        if min + c < lo || max + c > hi {
+            return Some((iidx, 0));
+        }
+        match self.decompose(opt, x, signed) {
+            Some((base, off)) => Some((base, off + c)),
+            None => Some((iidx, 0)),
+        }
+    }
+
+    /// If `lhs pred rhs` can be expressed as a relational fact, return the fact's key and bound
+    /// (see [Self::facts]).
+    fn fact(
+        &self,
+        opt: &PassOpt,
+        pred: IPred,
+        lhs: InstIdx,
+        rhs: InstIdx,
+    ) -> Option<((bool, InstIdx, InstIdx), i128)> {
+        let signed = pred.is_signed();
+        fact_of(
+            pred,
+            self.decompose(opt, lhs, signed)?,
+            self.decompose(opt, rhs, signed)?,
+        )
+    }
+
+    /// Do the relational facts established so far imply that `lhs pred rhs` is true
+    /// (`Some(true)`) or false (`Some(false)`)?
+    fn implied(&self, opt: &PassOpt, pred: IPred, lhs: InstIdx, rhs: InstIdx) -> Option<bool> {
+        let holds = |pred| {
+            self.fact(opt, pred, lhs, rhs)
+                .is_some_and(|(k, d)| self.facts.get(&k).is_some_and(|x| x.bound <= d))
+        };
+        if holds(pred) {
+            Some(true)
+        } else if holds(pred.inverse()) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Record `rng` as the range of the instruction currently being processed. If `rng` contains
+    /// only a single value, the instruction is replaced with that constant.
+    fn set_pending(&mut self, tyidx: TyIdx, rng: IntRange, inst: Inst) -> OptOutcome {
+        if let Some(x) = rng.as_const() {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(x),
+            }));
+        }
+        self.pending_commit = Some(rng);
+        OptOutcome::Rewritten(inst)
+    }
+
+    fn opt_add(&mut self, opt: &mut PassOpt, inst: Add) -> OptOutcome {
+        let Add {
+            tyidx, lhs, rhs, ..
+        } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.add(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_and(&mut self, opt: &mut PassOpt, inst: And) -> OptOutcome {
+        let And { tyidx, lhs, rhs } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.and(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_const(&mut self, inst: Const) -> OptOutcome {
+        if let ConstKind::Int(x) = &inst.kind {
+            self.pending_commit = Some(IntRange::from_const(x));
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_guard(
+        &mut self,
+        opt: &mut PassOpt,
+        inst @ Guard { expect, cond, .. }: Guard,
+    ) -> OptOutcome {
+        let mut cond_inst = opt.inst(opt.equiv_iidx(cond)).to_owned();
+        if let Inst::ICmp(_) = cond_inst {
+            cond_inst.canonicalise(opt);
+            let Inst::ICmp(ICmp { pred, lhs, rhs, .. }) = cond_inst else {
+                panic!()
+            };
+            if let Some(lhs_r) = self.as_range(opt, lhs)
+                && let Some(rhs_r) = self.as_range(opt, rhs)
+            {
+                match lhs_r
+                    .icmp(pred, &rhs_r)
+                    .or_else(|| self.implied(opt, pred, lhs, rhs))
+                {
+                    // The condition is implied by what we already know.
+                    Some(x) if x == expect => return OptOutcome::NotNeeded,
+                    // Let contradictions pass through.
+                    Some(_) => return OptOutcome::Rewritten(inst.into()),
+                    None => (),
+                }
+                let pred = if expect { pred } else { pred.inverse() };
+                // Record the relational fact before narrowing: the fact is only valid if the
+                // `add`s and `sub`s it strips don't overflow given the ranges we had before this
+                // guard, and narrowing can only shrink those ranges.
+                let signed = pred.is_signed();
+                let mut strengthened = false;
+                if let Some(lhs_d) = self.decompose(opt, lhs, signed)
+                    && let Some(rhs_d) = self.decompose(opt, rhs, signed)
+                    && let Some((k, d)) = fact_of(pred, lhs_d, rhs_d)
+                {
+                    let switch = opt.gextra(inst.geidx).switch.is_some();
+                    match self.facts.get_mut(&k) {
+                        Some(fact) if fact.guard.is_some() => {
+                            // Since this guard isn't implied by `fact`, `d` is the stronger bound.
+                            fact.bound = d;
+                            if !switch
+                                && let Some(cond) =
+                                    strengthened_cond(opt, fact, k, pred, lhs_d, rhs_d)
+                            {
+                                opt.strengthen_guard(fact.guard.unwrap(), cond);
+                                strengthened = true;
+                            }
+                        }
+                        _ => {
+                            let rng = |iidx| self.as_range(opt, iidx).unwrap();
+                            let fact = Fact {
+                                bound: self.facts.get(&k).map_or(d, |x| x.bound.min(d)),
+                                guard: None,
+                                rngs: (rng(k.1), rng(k.2)),
+                            };
+                            self.facts.insert(k, fact);
+                            if !switch {
+                                self.pending_guard = Some(k);
+                            }
+                        }
+                    }
+                }
+                if let Some((lhs_r, rhs_r)) = lhs_r.narrow(pred, &rhs_r) {
+                    self.ranges[lhs] = Some(lhs_r);
+                    self.ranges[rhs] = Some(rhs_r);
+                }
+                if strengthened {
+                    return OptOutcome::NotNeeded;
+                }
+            }
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_icmp(&mut self, opt: &mut PassOpt, mut inst: ICmp) -> OptOutcome {
+        inst.canonicalise(opt);
+        let ICmp { pred, lhs, rhs, .. } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+            && let Some(x) = lhs_r
+                .icmp(pred, &rhs_r)
+                .or_else(|| self.implied(opt, pred, lhs, rhs))
+        {
+            let tyidx = opt.push_ty(Ty::Int(1)).unwrap();
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(ArbBitInt::from_u64(1, u64::from(x))),
+            }));
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_lshr(&mut self, opt: &mut PassOpt, inst: LShr) -> OptOutcome {
+        let LShr {
+            tyidx, lhs, rhs, ..
+        } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(ConstKind::Int(rhs_c)) = opt.as_constkind(rhs)
+            && let Some(rhs_int) = rhs_c.to_zero_ext_u32()
+            && rhs_int < lhs_r.bitw
+        {
+            return self.set_pending(tyidx, lhs_r.lshr(rhs_int), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_mul(&mut self, opt: &mut PassOpt, inst: Mul) -> OptOutcome {
+        let Mul {
+            tyidx, lhs, rhs, ..
+        } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.mul(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_overflow(&mut self, opt: &mut PassOpt, mut inst: Overflow) -> OptOutcome {
+        inst.canonicalise(opt);
+        let Overflow { op, lhs, rhs } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+            && let Some(x) = lhs_r.overflows(op, &rhs_r)
+        {
+            let tyidx = opt.push_ty(Ty::Int(1)).unwrap();
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(ArbBitInt::from_u64(1, u64::from(x))),
+            }));
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_select(&mut self, opt: &mut PassOpt, inst: Select) -> OptOutcome {
+        let Select {
+            tyidx,
+            cond: _,
+            truev,
+            falsev,
+        } = inst;
+        if let Some(truev_r) = self.as_range(opt, truev)
+            && let Some(falsev_r) = self.as_range(opt, falsev)
+        {
+            return self.set_pending(tyidx, truev_r.union(&falsev_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_sext(&mut self, opt: &mut PassOpt, inst: SExt) -> OptOutcome {
+        let SExt { tyidx, val } = inst;
+        if let Some(val_r) = self.as_range(opt, val)
+            && let Ty::Int(dst_bitw @ ..=64) = *opt.ty(tyidx)
+        {
+            return self.set_pending(tyidx, val_r.sext(dst_bitw), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_sub(&mut self, opt: &mut PassOpt, inst: Sub) -> OptOutcome {
+        let Sub {
+            tyidx, lhs, rhs, ..
+        } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.sub(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_trunc(&mut self, opt: &mut PassOpt, inst: Trunc) -> OptOutcome {
+        let Trunc { tyidx, val, .. } = inst;
+        if let Some(val_r) = self.as_range(opt, val) {
+            let dst_bitw = opt.ty(tyidx).bitw();
+            return self.set_pending(tyidx, val_r.trunc(dst_bitw), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_udiv(&mut self, opt: &mut PassOpt, inst: UDiv) -> OptOutcome {
+        let UDiv {
+            tyidx, lhs, rhs, ..
+        } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.udiv(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_urem(&mut self, opt: &mut PassOpt, inst: URem) -> OptOutcome {
+        let URem { tyidx, lhs, rhs } = inst;
+        if let Some(lhs_r) = self.as_range(opt, lhs)
+            && let Some(rhs_r) = self.as_range(opt, rhs)
+        {
+            return self.set_pending(tyidx, lhs_r.urem(&rhs_r), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+
+    fn opt_zext(&mut self, opt: &mut PassOpt, inst: ZExt) -> OptOutcome {
+        let ZExt { tyidx, val } = inst;
+        if let Some(val_r) = self.as_range(opt, val)
+            && let Ty::Int(dst_bitw @ ..=64) = *opt.ty(tyidx)
+        {
+            return self.set_pending(tyidx, val_r.zext(dst_bitw), inst.into());
+        }
+        OptOutcome::Rewritten(inst.into())
+    }
+}
+
+/// If `x + a pred y + b`, where `(x, a)` and `(y, b)` are `lhs` and `rhs` decomposed by
+/// [Range::decompose], can be expressed as a relational fact, return the fact's key and bound (see
+/// [Range::facts]).
+fn fact_of(
+    pred: IPred,
+    (x, a): (InstIdx, i128),
+    (y, b): (InstIdx, i128),
+) -> Option<((bool, InstIdx, InstIdx), i128)> {
+    let signed = pred.is_signed();
+    if x == y {
+        return None;
+    }
+    // `x + a < y + b` <=> `x - y < b - a` and so on.
+    match pred {
+        IPred::Eq | IPred::Ne => None,
+        IPred::Ult | IPred::Slt => Some(((signed, x, y), b - a)),
+        IPred::Ule | IPred::Sle => Some(((signed, x, y), b - a + 1)),
+        IPred::Ugt | IPred::Sgt => Some(((signed, y, x), a - b)),
+        IPred::Uge | IPred::Sge => Some(((signed, y, x), a - b + 1)),
+    }
+}
+
+/// If the guard which established `fact`, whose key is `k`, can be strengthened to check `lhs pred
+/// rhs`, where `lhs` and `rhs` have been decomposed by [Range::decompose], return the strengthened
+/// condition.
+fn strengthened_cond(
+    opt: &PassOpt,
+    fact: &Fact,
+    k: (bool, InstIdx, InstIdx),
+    pred: IPred,
+    lhs: (InstIdx, i128),
+    rhs: (InstIdx, i128),
+) -> Option<StrengthenedCond> {
+    let guard = fact.guard?;
+    let signed = pred.is_signed();
+    // The strengthened condition is computed immediately before the guard, so it can only
+    // reference values defined before the guard, and its `add`s and `sub`s must not overflow given
+    // the ranges we had before the guard.
+    let exact = |(base, off): (InstIdx, i128)| {
+        let rng = if base == k.1 {
+            fact.rngs.0
+        } else {
+            fact.rngs.1
+        };
+        let (min, max, lo, hi) = if signed {
+            (rng.smin, rng.smax, smin_of(rng.bitw), smax_of(rng.bitw))
+        } else {
+            (rng.umin, rng.umax, 0, umax_of(rng.bitw))
+        };
+        base < guard && min + off >= lo && max + off <= hi
+    };
+    if !exact(lhs) || !exact(rhs) {
+        return None;
+    }
+    Some(StrengthenedCond {
+        tyidx: opt.inst(lhs.0).tyidx(opt),
+        bitw: fact.rngs.0.bitw,
+        pred,
+        lhs,
+        rhs,
+    })
+}
+
+/// The condition `x + a pred y + b`, where `lhs` is `(x, a)` and `rhs` is `(y, b)`, that an earlier
+/// guard is to be strengthened to check (see [strengthen_guards]).
+#[derive(Debug)]
+pub(super) struct StrengthenedCond {
+    /// The type of `x` and `y`.
+    tyidx: TyIdx,
+    bitw: u32,
+    pred: IPred,
+    lhs: (InstIdx, i128),
+    rhs: (InstIdx, i128),
+}
+
+impl StrengthenedCond {
+    /// The instructions this condition references.
+    pub(super) fn bases(&self) -> [InstIdx; 2] {
+        [self.lhs.0, self.rhs.0]
+    }
+
+    /// Emit the instructions which compute this condition, returning the [InstIdx] of the final
+    /// `icmp`. `map` maps [Self::bases] to their current [InstIdx]s and `push` emits an instruction,
+    /// returning its [InstIdx].
+    pub(super) fn emit<E>(
+        &self,
+        map: impl Fn(InstIdx) -> InstIdx,
+        mut push: impl FnMut(Inst) -> Result<InstIdx, E>,
+    ) -> Result<InstIdx, E> {
+        let mut operand = |(base, off): (InstIdx, i128)| -> Result<InstIdx, E> {
+            let base = map(base);
+            if off == 0 {
+                return Ok(base);
+            }
+            // `base + off` can't overflow, so adding the (possibly negative) offset modulo
+            // 2^`bitw` gives the exact result.
+            let c = push(
+                Const {
+                    tyidx: self.tyidx,
+                    kind: ConstKind::Int(ArbBitInt::from_u64(self.bitw, off as u64)),
+                }
+                .into(),
+            )?;
+            push(
+                Add {
+                    tyidx: self.tyidx,
+                    lhs: base,
+                    rhs: c,
+                    nuw: false,
+                    nsw: false,
+                }
+                .into(),
+            )
+        };
+        let lhs = operand(self.lhs)?;
+        let rhs = operand(self.rhs)?;
+        push(
+            ICmp {
+                pred: self.pred,
+                lhs,
+                rhs,
+                samesign: false,
+            }
+            .into(),
+        )
+    }
+}
+
+/// Rewrite each guard in `b` which has an entry in `strengthened` to check that entry's condition,
+/// which is computed immediately before the guard. Any of the guard's deopt variables which were
+/// its original condition (see [GuardExtra::cond_deopt_vars]) are made to reference that
+/// condition, since the guard may now fail when its original condition holds.
+pub(super) fn strengthen_guards(
+    b: Block,
+    strengthened: HashMap<InstIdx, StrengthenedCond>,
+) -> Block {
+    if strengthened.is_empty() {
+        return b;
+    }
+
+    // Inserting instructions changes the [InstIdx]s of those after them, so we rebuild the block.
+    let Block {
+        insts: old_insts,
+        guard_extras,
+    } = b;
+    let mut b = Block {
+        insts: TypedVec::with_capacity(old_insts.len_usize()),
+        guard_extras,
+    };
+    let mut map = typed_vec![InstIdx::MAX; old_insts.len_usize()];
+    for (i, mut inst) in old_insts.into_iter().enumerate() {
+        let iidx = InstIdx::from_raw_index(i);
+        inst.rewrite_iidxs(&mut b, |x| map[x]);
+        if let Some(cond) = strengthened.get(&iidx) {
+            let Inst::Guard(x) = &mut inst else { panic!() };
+            let Ok(new_cond) = cond.emit(|y| map[y], |y| Ok::<_, Infallible>(b.insts.push(y)));
+            let gextra = b.gextra_mut(x.geidx);
+            for k in mem::take(&mut gextra.cond_deopt_vars) {
+                gextra.deopt_vars[k] = x.cond;
+            }
+            x.expect = true;
+            x.cond = new_cond;
+        }
+        map[iidx] = b.insts.push(inst);
+    }
+    b
+}
+
+/// The minimum value of a signed `bitw`-bit integer.
+fn smin_of(bitw: u32) -> i128 {
+    -(1 << (bitw - 1))
+}
+
+/// The maximum value of a signed `bitw`-bit integer.
+fn smax_of(bitw: u32) -> i128 {
+    (1 << (bitw - 1)) - 1
+}
+
+/// The maximum value of an unsigned `bitw`-bit integer.
+fn umax_of(bitw: u32) -> i128 {
+    (1 << bitw) - 1
+}
+
+/// The possible values of an integer of up to 64 bits, as inclusive ranges under both signed and
+/// unsigned interpretations. Both ranges are always non-empty and, since they describe the same
+/// set of bit patterns, each is used to tighten the other (see [IntRange::normalise]).
+///
+/// Bounds are stored as `i128`s so that the arithmetic on them in this module cannot overflow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct IntRange {
+    bitw: u32,
+    smin: i128,
+    smax: i128,
+    umin: i128,
+    umax: i128,
+}
+
+impl IntRange {
+    /// A range containing every `bitw`-bit integer.
+    fn full(bitw: u32) -> Self {
+        debug_assert!(bitw > 0 && bitw <= 64);
+        IntRange {
+            bitw,
+            smin: smin_of(bitw),
+            smax: smax_of(bitw),
+            umin: 0,
+            umax: umax_of(bitw),
+        }
+    }
+
+    /// A range containing only the constant `x`.
+    fn from_const(x: &ArbBitInt) -> Self {
+        let s = i128::from(x.to_sign_ext_i64().unwrap());
+        let u = i128::from(x.to_zero_ext_u64().unwrap());
+        IntRange {
+            bitw: x.bitw(),
+            smin: s,
+            smax: s,
+            umin: u,
+            umax: u,
+        }
+    }
+
+    /// A range whose signed interpretation is `lo..=hi`. If that can't be represented in `bitw`
+    /// bits (i.e. the operation that produced it may have overflowed), the full range is returned.
+    fn from_signed(bitw: u32, lo: i128, hi: i128) -> Self {
+        let full = Self::full(bitw);
+        if lo > hi || lo < full.smin || hi > full.smax {
+            return full;
+        }
+        full.with_smin(lo)
+            .and_then(|x| x.with_smax(hi))
+            .unwrap_or(full)
+    }
+
+    /// A range whose unsigned interpretation is `lo..=hi`. If that can't be represented in
+    /// `bitw` bits (i.e. the operation that produced it may have overflowed), the full range is
+    /// returned.
+    fn from_unsigned(bitw: u32, lo: i128, hi: i128) -> Self {
+        let full = Self::full(bitw);
+        if lo > hi || lo < 0 || hi > full.umax {
+            return full;
+        }
+        full.with_umin(lo)
+            .and_then(|x| x.with_umax(hi))
+            .unwrap_or(full)
+    }
+
+    /// Tighten the signed and unsigned ranges with respect to each other, returning `None` if the
+    /// resulting range is empty.
+    fn normalise(mut self) -> Option<Self> {
+        let half = 1i128 << (self.bitw - 1);
+        let modulus = 1i128 << self.bitw;
+        // If the signed range doesn't cross zero, it maps directly onto unsigned values.
+        if self.smin >= 0 {
+            self.umin = self.umin.max(self.smin);
+            self.umax = self.umax.min(self.smax);
+        } else if self.smax < 0 {
+            self.umin = self.umin.max(self.smin + modulus);
+            self.umax = self.umax.min(self.smax + modulus);
+        }
+        // If the unsigned range doesn't cross the sign bit, it maps directly onto signed values.
+        if self.umax < half {
+            self.smin = self.smin.max(self.umin);
+            self.smax = self.smax.min(self.umax);
+        } else if self.umin >= half {
+            self.smin = self.smin.max(self.umin - modulus);
+            self.smax = self.smax.min(self.umax - modulus);
+        }
+        if self.smin > self.smax || self.umin > self.umax {
+            None
+        } else {
+            Some(self)
+        }
+    }
+
+    fn with_smin(self, smin: i128) -> Option<Self> {
+        IntRange {
+            smin: self.smin.max(smin),
+            ..self
+        }
+        .normalise()
+    }
+
+    fn with_smax(self, smax: i128) -> Option<Self> {
+        IntRange {
+            smax: self.smax.min(smax),
+            ..self
+        }
+        .normalise()
+    }
+
+    fn with_umin(self, umin: i128) -> Option<Self> {
+        IntRange {
+            umin: self.umin.max(umin),
+            ..self
+        }
+        .normalise()
+    }
+
+    fn with_umax(self, umax: i128) -> Option<Self> {
+        IntRange {
+            umax: self.umax.min(umax),
+            ..self
+        }
+        .normalise()
+    }
+
+    /// If this range contains a single value, return it.
+    fn as_const(&self) -> Option<ArbBitInt> {
+        if self.umin == self.umax {
+            Some(ArbBitInt::from_u64(
+                self.bitw,
+                u64::try_from(self.umin).unwrap(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The values contained in both `self` and `other`, or `None` if there are no such values.
+    ///
+    /// # Panics
+    ///
+    /// If the bitwidths are different.
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        assert_eq!(self.bitw, other.bitw);
+        IntRange {
+            bitw: self.bitw,
+            smin: self.smin.max(other.smin),
+            smax: self.smax.min(other.smax),
+            umin: self.umin.max(other.umin),
+            umax: self.umax.min(other.umax),
+        }
+        .normalise()
+    }
+
+    /// A range containing (at least) the values in both `self` and `other`.
+    ///
+    /// # Panics
+    ///
+    /// If the bitwidths are different.
+    fn union(&self, other: &Self) -> Self {
+        assert_eq!(self.bitw, other.bitw);
+        IntRange {
+            bitw: self.bitw,
+            smin: self.smin.min(other.smin),
+            smax: self.smax.max(other.smax),
+            umin: self.umin.min(other.umin),
+            umax: self.umax.max(other.umax),
+        }
+    }
+
+    /// Combine separately derived signed and unsigned ranges for the same value.
+    fn meet(signed: Self, unsigned: Self) -> Self {
+        signed
+            .intersect(&unsigned)
+            .unwrap_or_else(|| Self::full(signed.bitw))
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self::meet(
+            Self::from_signed(self.bitw, self.smin + other.smin, self.smax + other.smax),
+            Self::from_unsigned(self.bitw, self.umin + other.umin, self.umax + other.umax),
+        )
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Self::meet(
+            Self::from_signed(self.bitw, self.smin - other.smax, self.smax - other.smin),
+            Self::from_unsigned(self.bitw, self.umin - other.umax, self.umax - other.umin),
+        )
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        // Signed bounds are at most 2^63 in magnitude, so their products fit in an `i128`;
+        // unsigned bounds may not.
+        let corners = [
+            self.smin * other.smin,
+            self.smin * other.smax,
+            self.smax * other.smin,
+            self.smax * other.smax,
+        ];
+        let signed = Self::from_signed(
+            self.bitw,
+            *corners.iter().min().unwrap(),
+            *corners.iter().max().unwrap(),
+        );
+        let unsigned = match self.umax.checked_mul(other.umax) {
+            Some(umax) => Self::from_unsigned(self.bitw, self.umin * other.umin, umax),
+            None => Self::full(self.bitw),
+        };
+        Self::meet(signed, unsigned)
+    }
+
+    fn and(&self, other: &Self) -> Self {
+        Self::from_unsigned(self.bitw, 0, self.umax.min(other.umax))
+    }
+
+    fn lshr(&self, bits: u32) -> Self {
+        assert!(bits < self.bitw);
+        Self::from_unsigned(self.bitw, self.umin >> bits, self.umax >> bits)
+    }
+
+    fn udiv(&self, other: &Self) -> Self {
+        // Division by zero is undefined behaviour, so we can assume the divisor is at least 1.
+        if other.umax == 0 {
+            return Self::full(self.bitw);
+        }
+        Self::from_unsigned(
+            self.bitw,
+            self.umin / other.umax,
+            self.umax / other.umin.max(1),
+        )
+    }
+
+    fn urem(&self, other: &Self) -> Self {
+        if other.umax == 0 {
+            return Self::full(self.bitw);
+        }
+        Self::from_unsigned(self.bitw, 0, self.umax.min(other.umax - 1))
+    }
+
+    fn sext(&self, bitw: u32) -> Self {
+        Self::from_signed(bitw, self.smin, self.smax)
+    }
+
+    fn zext(&self, bitw: u32) -> Self {
+        Self::from_unsigned(bitw, self.umin, self.umax)
+    }
+
+    fn trunc(&self, bitw: u32) -> Self {
+        // If all values fit in `bitw` bits under either interpretation, truncation preserves them
+        // under that interpretation.
+        Self::meet(
+            Self::from_signed(bitw, self.smin, self.smax),
+            Self::from_unsigned(bitw, self.umin, self.umax),
+        )
+    }
+
+    /// If `self op other` overflows for all values in the two ranges return `Some(true)`; if it
+    /// overflows for none return `Some(false)`; otherwise return `None`.
+    fn overflows(&self, op: OverflowOp, other: &Self) -> Option<bool> {
+        if op.is_signed() {
+            op.overflows(self.bitw, (self.smin, self.smax), (other.smin, other.smax))
+        } else {
+            op.overflows(self.bitw, (self.umin, self.umax), (other.umin, other.umax))
+        }
+    }
+
+    /// If `self pred other` is true for all values in the two ranges return `Some(true)`; if it is
+    /// false for all values return `Some(false)`; otherwise return `None`.
+    fn icmp(&self, pred: IPred, other: &Self) -> Option<bool> {
+        match pred {
+            IPred::Eq => {
+                if self.as_const().is_some() && other.as_const().is_some() {
+                    Some(self.umin == other.umin)
+                } else if self.intersect(other).is_none() {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            IPred::Ne => self.icmp(IPred::Eq, other).map(|x| !x),
+            IPred::Ult => {
+                if self.umax < other.umin {
+                    Some(true)
+                } else if self.umin >= other.umax {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            IPred::Ule => {
+                if self.umax <= other.umin {
+                    Some(true)
+                } else if self.umin > other.umax {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            IPred::Slt => {
+                if self.smax < other.smin {
+                    Some(true)
+                } else if self.smin >= other.smax {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            IPred::Sle => {
+                if self.smax <= other.smin {
+                    Some(true)
+                } else if self.smin > other.smax {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            IPred::Ugt => other.icmp(IPred::Ult, self),
+            IPred::Uge => other.icmp(IPred::Ule, self),
+            IPred::Sgt => other.icmp(IPred::Slt, self),
+            IPred::Sge => other.icmp(IPred::Sle, self),
+        }
+    }
+
+    /// Assuming that `self pred other` holds, return the narrowed ranges of `self` and `other`,
+    /// or `None` if the assumption contradicts what we already know.
+    fn narrow(&self, pred: IPred, other: &Self) -> Option<(Self, Self)> {
+        match pred {
+            IPred::Eq => {
+                let x = self.intersect(other)?;
+                Some((x, x))
+            }
+            IPred::Ne => Some((self.exclude(other)?, other.exclude(self)?)),
+            IPred::Ult => Some((
+                self.with_umax(other.umax - 1)?,
+                other.with_umin(self.umin + 1)?,
+            )),
+            IPred::Ule => Some((self.with_umax(other.umax)?, other.with_umin(self.umin)?)),
+            IPred::Slt => Some((
+                self.with_smax(other.smax - 1)?,
+                other.with_smin(self.smin + 1)?,
+            )),
+            IPred::Sle => Some((self.with_smax(other.smax)?, other.with_smin(self.smin)?)),
+            IPred::Ugt | IPred::Uge | IPred::Sgt | IPred::Sge => {
+                let pred = match pred {
+                    IPred::Ugt => IPred::Ult,
+                    IPred::Uge => IPred::Ule,
+                    IPred::Sgt => IPred::Slt,
+                    IPred::Sge => IPred::Sle,
+                    _ => unreachable!(),
+                };
+                let (other, x) = other.narrow(pred, self)?;
+                Some((x, other))
+            }
+        }
+    }
+
+    /// If `other` is a constant at either end of this range, return this range without it.
+    fn exclude(&self, other: &Self) -> Option<Self> {
+        if other.as_const().is_none() {
+            return Some(*self);
+        }
+        let mut x = *self;
+        if x.umin == other.umin {
+            x = x.with_umin(other.umin + 1)?;
+        } else if x.umax == other.umin {
+            x = x.with_umax(other.umin - 1)?;
+        }
+        if x.smin == other.smin {
+            x = x.with_smin(other.smin + 1)?;
+        } else if x.smax == other.smin {
+            x = x.with_smax(other.smin - 1)?;
+        }
+        Some(x)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile::j2::{
+        hir_parser::str_to_mod,
+        opt::{
+            fullopt::test::{full_opt_test, match_mod, user_defined_opt_test},
+            strength_fold::StrengthFold,
+        },
+        regalloc::test::TestReg,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    fn test_range(mod_s: &str, ptn: &str) {
+        let range = Rc::new(RefCell::new(Range::new()));
+        let strength_fold = Rc::new(RefCell::new(StrengthFold::new()));
+        user_defined_opt_test(
+            mod_s,
+            |opt, inst| match range.borrow_mut().feed(opt, inst) {
+                OptOutcome::Rewritten(new_inst) => strength_fold.borrow_mut().feed(opt, new_inst),
+                x => x,
+            },
+            |opt, iidx| range.borrow_mut().inst_committed(opt, iidx),
+            |equiv1, equiv2| range.borrow_mut().equiv_committed(equiv1, equiv2),
+            ptn,
+        );
+    }
+
+    #[test]
+    fn opt_icmp() {
+        // Comparisons implied by an earlier guard are folded, and the guards that reference them
+        // removed.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 20
+          %5: i1 = icmp ult %0, %4
+          guard true, %5, []
+          %7: i1 = icmp ugt %0, %4
+          guard false, %7, []
+          blackbox %0
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 20
+          %5: i1 = 1
+          %6: i1 = 0
+          blackbox %0
+        ",
+        );
+
+        // Contradictions pass through.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 20
+          %5: i1 = icmp ugt %0, %4
+          guard true, %5, []
+          blackbox %0
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 20
+          %5: i1 = 0
+          guard true, %5, []
+          blackbox %0
+        ",
+        );
+
+        // Signed and unsigned ranges inform each other.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 0
+          %2: i1 = icmp sge %0, %1
+          guard true, %2, []
+          %4: i8 = 128
+          %5: i1 = icmp ult %0, %4
+          guard true, %5, []
+          blackbox %0
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 0
+          %2: i1 = icmp sge %0, %1
+          guard true, %2, []
+          %4: i8 = 128
+          %5: i1 = 1
+          blackbox %0
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_guard() {
+        // A guard whose condition was computed before a narrowing guard is still removed.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 10
+          %2: i8 = 20
+          %3: i1 = icmp ult %0, %1
+          %4: i1 = icmp ult %0, %2
+          guard true, %3, []
+          guard true, %4, []
+          blackbox %0
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 10
+          %2: i8 = 20
+          %3: i1 = icmp ult %0, %1
+          %4: i1 = icmp ult %0, %2
+          guard true, %3, []
+          blackbox %0
+        ",
+        );
+
+        // `ne` only narrows at the edges of a range.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 2
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 0
+          %5: i1 = icmp ne %0, %4
+          guard true, %5, []
+          %7: i8 = 1
+          %8: i1 = icmp eq %0, %7
+          guard true, %8, []
+          blackbox %0
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 2
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 0
+          %5: i1 = icmp ne %0, %4
+          guard true, %5, []
+          %7: i8 = 1
+          %8: i1 = 1
+          blackbox %0
+        ",
+        );
+    }
+
+    #[test]
+    fn relational() {
+        // `i + 1 < n` implies `i < n` provided `i + 1` can't overflow.
+        test_range(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i32 = 100
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, []
+          %5: i32 = 1
+          %6: i32 = add %0, %5
+          %7: i1 = icmp ult %6, %1
+          guard true, %7, []
+          %9: i1 = icmp ult %0, %1
+          guard true, %9, []
+          blackbox %6
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i32 = 100
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, []
+          %5: i32 = 1
+          %6: i32 = add %0, %5
+          %7: i1 = icmp ult %6, %1
+          guard true, %7, []
+          %9: i1 = 1
+          blackbox %6
+        ",
+        );
+
+        // Facts are recorded from negated guards and used for swapped and inverse predicates.
+        test_range(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i1 = icmp sge %0, %1
+          guard false, %2, []
+          %4: i1 = icmp sgt %1, %0
+          guard true, %4, []
+          %6: i1 = icmp sle %1, %0
+          blackbox %6
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i1 = icmp sge %0, %1
+          guard false, %2, []
+          %4: i1 = 1
+          %5: i1 = 0
+          blackbox %5
+        ",
+        );
+
+        // If `i + 1` might overflow, `i + 1 < n` does not imply `i < n`.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = arg [reg]
+          %2: i8 = 1
+          %3: i8 = add %0, %2
+          %4: i1 = icmp slt %3, %1
+          guard true, %4, []
+          %6: i1 = icmp slt %0, %1
+          guard true, %6, []
+          blackbox %3
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = arg
+          %2: i8 = 1
+          %3: i8 = add %0, %2
+          %4: i1 = icmp slt %3, %1
+          guard true, %4, []
+          %6: i1 = icmp slt %0, %1
+          guard true, %6, []
+          blackbox %3
+        ",
+        );
+    }
+
+    #[test]
+    fn strengthen() {
+        // A weaker guard does not imply a stronger one, but the weaker guard can be strengthened
+        // and the stronger guard removed.
+        test_range(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i32 = 100
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, []
+          %5: i1 = icmp ult %0, %1
+          guard true, %5, [%0, %5]
+          %7: i32 = 1
+          %8: i32 = add %0, %7
+          %9: i1 = icmp ult %8, %1
+          guard true, %9, [%0, %9]
+          blackbox %8
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i32 = 100
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, []
+          %5: i1 = icmp ult %0, %1
+          %6: i32 = 1
+          %7: i32 = add %0, %6
+          %8: i1 = icmp ult %7, %1
+          guard true, %8, [%0, %5]
+          %10: i32 = 1
+          %11: i32 = add %0, %10
+          %12: i1 = icmp ult %11, %1
+          blackbox %11
+        ",
+        );
+
+        // Negated guards, and negative offsets, are strengthened.
+        test_range(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i32 = 10
+          %3: i1 = icmp sgt %0, %2
+          guard true, %3, []
+          %5: i1 = icmp sge %1, %0
+          guard false, %5, []
+          %7: i32 = 2
+          %8: i32 = sub %0, %7
+          %9: i1 = icmp sgt %8, %1
+          guard true, %9, []
+          blackbox %8
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i32 = 10
+          %3: i1 = icmp sgt %0, %2
+          guard true, %3, []
+          %5: i1 = icmp sge %1, %0
+          %6: i32 = 4294967294
+          %7: i32 = add %0, %6
+          %8: i1 = icmp sgt %7, %1
+          guard true, %8, []
+          %10: i32 = 2
+          %11: i32 = sub %0, %10
+          %12: i1 = icmp sgt %11, %1
+          blackbox %11
+        ",
+        );
+
+        // If `i + 1` might overflow before the earlier guard, the earlier guard can't be
+        // strengthened, even though the earlier guard means that `i + 1` can't overflow by the
+        // time of the later guard.
+        test_range(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i32 = 1
+          %5: i32 = add %0, %4
+          %6: i1 = icmp ult %5, %1
+          guard true, %6, []
+          blackbox %5
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i32 = 1
+          %5: i32 = add %0, %4
+          %6: i1 = icmp ult %5, %1
+          guard true, %6, []
+          blackbox %5
+        ",
+        );
+
+        // Guards in both the entry iteration and the peel are strengthened.
+        full_opt_test(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i32 = 100
+          %3: i1 = icmp ult %0, %2
+          guard true, %3, []
+          %5: i1 = icmp ult %0, %1
+          guard true, %5, []
+          %7: i32 = 1
+          %8: i32 = add %0, %7
+          %9: i1 = icmp ult %8, %1
+          guard true, %9, []
+          term [%8, %1]
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          ...
+          %{{a}}: i32 = add %0, %{{_}}
+          %{{b}}: i1 = icmp ult %{{a}}, %1
+          guard true, %{{b}}, []
+          ...
+          term [%{{_}}, %1]
+          ; peel
+          %0: i32 = arg
+          %1: i32 = arg
+          ...
+          %{{c}}: i32 = add %0, %{{_}}
+          %{{d}}: i1 = icmp ult %{{c}}, %1
+          guard true, %{{d}}, []
+          term [%{{c}}, %1]
+        ",
+        );
+    }
+
+    #[test]
+    fn strengthen_deopt_vars() {
+        // A strengthened guard may fail when its original condition holds, so deopt variables
+        // which were replaced with the inverse of the original condition now reference the
+        // original condition.
+        let mut m = str_to_mod::<TestReg>(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = arg [reg]
+          %2: i1 = icmp ult %0, %1
+          %3: i1 = 0
+          guard true, %2, [%0, %3]
+          blackbox %0
+        ",
+        );
+        let TraceEnd::Test { block, .. } = &mut m.trace_end else {
+            panic!()
+        };
+        let mut b = mem::replace(
+            block,
+            Block {
+                insts: TypedVec::new(),
+                guard_extras: TypedVec::new(),
+            },
+        );
+        b.guard_extras[GuardExtraIdx::from_raw_index(0)]
+            .cond_deopt_vars
+            .push(1);
+        let cond = StrengthenedCond {
+            tyidx: b.inst(InstIdx::from_raw_index(0)).tyidx(&m),
+            bitw: 32,
+            pred: IPred::Ult,
+            lhs: (InstIdx::from_raw_index(0), 1),
+            rhs: (InstIdx::from_raw_index(1), 0),
+        };
+        let b = strengthen_guards(b, HashMap::from([(InstIdx::from_raw_index(4), cond)]));
+        let TraceEnd::Test { block, .. } = &mut m.trace_end else {
+            panic!()
+        };
+        *block = b;
+        match_mod(
+            &m,
+            "
+          %0: i32 = arg
+          %1: i32 = arg
+          %2: i1 = icmp ult %0, %1
+          %3: i1 = 0
+          %4: i32 = 1
+          %5: i32 = add %0, %4
+          %6: i1 = icmp ult %5, %1
+          guard true, %6, [%0, %2]
+          blackbox %0
+        ",
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = add %0, %1
+          %5: i8 = 3
+          %6: i8 = mul %4, %5
+          %7: i8 = sub %1, %6
+          %8: i16 = sext %7
+          %9: i16 = 0
+          %10: i1 = icmp slt %8, %9
+          guard true, %10, []
+          %12: i16 = zext %7
+          %13: i16 = 200
+          %14: i1 = icmp ugt %12, %13
+          guard true, %14, []
+          blackbox %8
+          blackbox %12
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 10
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = add %0, %1
+          %5: i8 = 3
+          %6: i8 = mul %4, %5
+          %7: i8 = sub %1, %6
+          %8: i16 = sext %7
+          %9: i16 = 0
+          %10: i1 = 1
+          %11: i16 = zext %7
+          %12: i16 = 200
+          %13: i1 = 1
+          blackbox %8
+          blackbox %11
+        ",
+        );
+
+        // Possible overflow means we know nothing.
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 100
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = add %0, %0
+          %5: i8 = 0
+          %6: i1 = icmp sge %4, %5
+          guard true, %6, []
+          blackbox %4
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 100
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = add %0, %0
+          %5: i8 = 0
+          %6: i1 = icmp sge %4, %5
+          guard true, %6, []
+          blackbox %4
+        ",
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        test_range(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 100
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 27
+          %5: i1 = overflow sadd %0, %4
+          guard false, %5, []
+          %7: i8 = add %0, %4
+          %8: i1 = overflow umul %0, %0
+          guard false, %8, []
+          %10: i8 = 29
+          %11: i1 = overflow sadd %0, %10
+          guard false, %11, []
+          %13: i1 = overflow usub %0, %1
+          guard true, %13, []
+          blackbox %7
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 100
+          %2: i1 = icmp ult %0, %1
+          guard true, %2, []
+          %4: i8 = 27
+          %5: i1 = 0
+          %6: i8 = add %0, %4
+          %7: i1 = overflow umul %0, %0
+          guard false, %7, []
+          %9: i8 = 29
+          %10: i1 = overflow sadd %0, %9
+          guard false, %10, []
+          %12: i1 = 1
+          blackbox %6
+        ",
+        );
+    }
+
+    #[test]
+    fn select_trunc() {
+        test_range(
+            "
+          %0: i16 = arg [reg]
+          %1: i16 = arg [reg]
+          %2: i1 = arg [reg]
+          %3: i16 = 100
+          %4: i16 = urem %0, %3
+          %5: i16 = 127
+          %6: i16 = and %1, %5
+          %7: i16 = select %2, %4, %6
+          %8: i8 = trunc %7
+          %9: i8 = 0
+          %10: i1 = icmp sge %8, %9
+          guard true, %10, []
+          blackbox %8
+        ",
+            "
+          %0: i16 = arg
+          %1: i16 = arg
+          %2: i1 = arg
+          %3: i16 = 100
+          %4: i16 = urem %0, %3
+          %5: i16 = 127
+          %6: i16 = and %1, %5
+          %7: i16 = select %2, %4, %6
+          %8: i8 = trunc %7
+          %9: i8 = 0
+          %10: i1 = 1
+          blackbox %8
+        ",
+        );
+    }
+
+    #[test]
+    fn peeling() {
+        // The entry iteration proves that `%8` is in the range 1..=100, so in the peel the lower
+        // bounds check is redundant, but the upper bounds check is not.
+        full_opt_test(
+            "
+          %0: i32 = arg [reg]
+          %1: i32 = 0
+          %2: i1 = icmp sge %0, %1
+          guard true, %2, []
+          %4: i32 = 100
+          %5: i1 = icmp slt %0, %4
+          guard true, %5, []
+          %7: i32 = 1
+          %8: i32 = add %0, %7
+          term [%8]
+        ",
+            "
+          %0: i32 = arg
+          %1: i32 = 0
+          %2: i1 = icmp sge %0, %1
+          guard true, %2, []
+          %4: i32 = 100
+          %5: i1 = icmp slt %0, %4
+          guard true, %5, []
+          %7: i32 = 1
+          %8: i32 = add %0, %7
+          term [%8]
+          ; peel
+          %0: i32 = arg
+          %3: i32 = 100
+          %4: i1 = icmp slt %0, %3
+          guard true, %4, []
+          %6: i32 = 1
+          %7: i32 = add %0, %6
+          term [%7]
+        ",
+        );
+    }
+}
@@ -14,6 +14,7 @@ use crate::compile::{
 };
 use index_type::vec::TypedVec;
 use num_traits::FromPrimitive;
+use std::num::FpCategory;
 
 pub(super) struct StrengthFold;
 
@@ -31,13 +32,24 @@ impl PassT for StrengthFold {
             Inst::Add(x) => opt_add(opt, x),
             Inst::And(x) => opt_and(opt, x),
             Inst::BitCast(x) => opt_bitcast(opt, x),
+            Inst::Ceil(x) => opt_ceil(opt, x),
+            Inst::CopySign(x) => opt_copysign(opt, x),
             Inst::CtPop(x) => opt_ctpop(opt, x),
             Inst::CtTz(x) => opt_cttz(opt, x),
             Inst::DynPtrAdd(x) => opt_dynptradd(opt, x),
+            Inst::FAbs(x) => opt_fabs(opt, x),
             Inst::FAdd(x) => opt_fadd(opt, x),
+            Inst::FCmp(x) => opt_fcmp(opt, x),
             Inst::FDiv(x) => opt_fdiv(opt, x),
+            Inst::Floor(x) => opt_floor(opt, x),
             Inst::FMul(x) => opt_fmul(opt, x),
+            Inst::FNeg(x) => opt_fneg(opt, x),
+            Inst::FPClass(x) => opt_fpclass(opt, x),
+            Inst::FPExt(x) => opt_fpext(opt, x),
+            Inst::FPToSI(x) => opt_fptosi(opt, x),
+            Inst::Freeze(x) => opt_freeze(opt, x),
             Inst::FSub(x) => opt_fsub(opt, x),
+            Inst::FTrunc(x) => opt_ftrunc(opt, x),
             Inst::Guard(x) => opt_guard(opt, x),
             Inst::ICmp(x) => opt_icmp(opt, x),
             Inst::IntToPtr(x) => opt_inttoptr(opt, x),
@@ -45,15 +57,25 @@ impl PassT for StrengthFold {
             Inst::MemCpy(x) => opt_memcpy(opt, x),
             Inst::Mul(x) => opt_mul(opt, x),
             Inst::Or(x) => opt_or(opt, x),
+            Inst::Overflow(x) => opt_overflow(opt, x),
             Inst::PtrAdd(x) => opt_ptradd(opt, x),
             Inst::PtrToInt(x) => opt_ptrtoint(opt, x),
+            Inst::SDiv(x) => opt_sdiv(opt, x),
             Inst::Select(x) => opt_select(opt, x),
             Inst::SExt(x) => opt_sext(opt, x),
             Inst::Shl(x) => opt_shl(opt, x),
             Inst::SIToFP(x) => opt_sitofp(opt, x),
+            Inst::SMax(x) => opt_smax(opt, x),
+            Inst::SMin(x) => opt_smin(opt, x),
+            Inst::Sqrt(x) => opt_sqrt(opt, x),
+            Inst::SRem(x) => opt_srem(opt, x),
             Inst::Sub(x) => opt_sub(opt, x),
             Inst::Trunc(x) => opt_trunc(opt, x),
             Inst::UDiv(x) => opt_udiv(opt, x),
+            Inst::UIToFP(x) => opt_uitofp(opt, x),
+            Inst::UMax(x) => opt_umax(opt, x),
+            Inst::UMin(x) => opt_umin(opt, x),
+            Inst::URem(x) => opt_urem(opt, x),
             Inst::Xor(x) => opt_xor(opt, x),
             Inst::ZExt(x) => opt_zext(opt, x),
             _ => {
@@ -274,6 +296,60 @@ fn opt_bitcast(opt: &mut PassOpt, mut inst: BitCast) -> OptOutcome {
     }
 }
 
+fn opt_ceil(opt: &mut PassOpt, mut inst: Ceil) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Ceil { tyidx, val } = inst;
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Float(c.ceil()),
+        })),
+        Some(ConstKind::Double(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Double(c.ceil()),
+        })),
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
+fn opt_copysign(opt: &mut PassOpt, mut inst: CopySign) -> OptOutcome {
+    inst.canonicalise(opt);
+    let CopySign { tyidx, mag, sign } = inst;
+    // Note: `copysign` only manipulates the sign bit, so it is exact for all values including
+    // zeros and NaNs.
+    match (opt.as_constkind(mag), opt.as_constkind(sign)) {
+        (Some(ConstKind::Float(mag_c)), Some(ConstKind::Float(sign_c))) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Float(mag_c.copysign(sign_c)),
+            }));
+        }
+        (Some(ConstKind::Double(mag_c)), Some(ConstKind::Double(sign_c))) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Double(mag_c.copysign(sign_c)),
+            }));
+        }
+        (_, Some(ConstKind::Float(sign_c))) if sign_c.is_sign_positive() => {
+            // Reduce `copysign x, +c` to `fabs x`.
+            return OptOutcome::Rewritten(FAbs { tyidx, val: mag }.into());
+        }
+        (_, Some(ConstKind::Double(sign_c))) if sign_c.is_sign_positive() => {
+            // Reduce `copysign x, +c` to `fabs x`.
+            return OptOutcome::Rewritten(FAbs { tyidx, val: mag }.into());
+        }
+        _ => (),
+    }
+
+    if mag == sign {
+        // Reduce `copysign x, x` to `x`.
+        return OptOutcome::Equiv(mag);
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
 fn opt_ctpop(opt: &mut PassOpt, mut inst: CtPop) -> OptOutcome {
     inst.canonicalise(opt);
     let CtPop { tyidx, val } = inst;
@@ -344,6 +420,47 @@ fn opt_dynptradd(opt: &mut PassOpt, mut inst: DynPtrAdd) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_fabs(opt: &mut PassOpt, mut inst: FAbs) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FAbs { tyidx, val } = inst;
+    // Note: `fabs` only clears the sign bit, so it is exact for all values including zeros and
+    // NaNs.
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Float(c.abs()),
+            }));
+        }
+        Some(ConstKind::Double(c)) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Double(c.abs()),
+            }));
+        }
+        Some(_) => unreachable!(),
+        None => (),
+    }
+
+    match opt.inst(val) {
+        Inst::FAbs(_) => {
+            // Reduce `fabs (fabs x)` to `fabs x`.
+            OptOutcome::Equiv(val)
+        }
+        Inst::FNeg(FNeg { val: val_val, .. }) => {
+            // Reduce `fabs (fneg x)` to `fabs x`.
+            OptOutcome::Rewritten(
+                FAbs {
+                    tyidx,
+                    val: opt.equiv_iidx(*val_val),
+                }
+                .into(),
+            )
+        }
+        _ => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
 fn opt_fadd(opt: &mut PassOpt, mut inst: FAdd) -> OptOutcome {
     inst.canonicalise(opt);
     let FAdd { tyidx, lhs, rhs } = inst;
@@ -370,6 +487,58 @@ fn opt_fadd(opt: &mut PassOpt, mut inst: FAdd) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_fcmp(opt: &mut PassOpt, mut inst: FCmp) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FCmp { pred, lhs, rhs } = inst;
+    let v = match pred {
+        FPred::False => Some(false),
+        FPred::True => Some(true),
+        _ => match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+            // Converting a `float` to a `double` is exact, so we can compare `float`s as `double`s.
+            (Some(ConstKind::Float(lhs_c)), Some(ConstKind::Float(rhs_c))) => {
+                Some(fcmp_fold(pred, f64::from(lhs_c), f64::from(rhs_c)))
+            }
+            (Some(ConstKind::Double(lhs_c)), Some(ConstKind::Double(rhs_c))) => {
+                Some(fcmp_fold(pred, lhs_c, rhs_c))
+            }
+            _ => None,
+        },
+    };
+    if let Some(v) = v {
+        let tyidx = opt.push_ty(Ty::Int(1)).unwrap();
+        return OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Int(ArbBitInt::from_u64(1, v as u64)),
+        }));
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
+/// Constant fold `fcmp pred lhs, rhs`. Ordered predicates are false if either operand is NaN;
+/// unordered predicates are true.
+fn fcmp_fold(pred: FPred, lhs: f64, rhs: f64) -> bool {
+    let uno = lhs.is_nan() || rhs.is_nan();
+    match pred {
+        FPred::False => false,
+        FPred::Oeq => lhs == rhs,
+        FPred::Ogt => lhs > rhs,
+        FPred::Oge => lhs >= rhs,
+        FPred::Olt => lhs < rhs,
+        FPred::Ole => lhs <= rhs,
+        FPred::One => !uno && lhs != rhs,
+        FPred::Ord => !uno,
+        FPred::Ueq => uno || lhs == rhs,
+        FPred::Ugt => uno || lhs > rhs,
+        FPred::Uge => uno || lhs >= rhs,
+        FPred::Ult => uno || lhs < rhs,
+        FPred::Ule => uno || lhs <= rhs,
+        FPred::Une => lhs != rhs,
+        FPred::Uno => uno,
+        FPred::True => true,
+    }
+}
+
 fn opt_fdiv(opt: &mut PassOpt, mut inst: FDiv) -> OptOutcome {
     inst.canonicalise(opt);
     let FDiv { tyidx, lhs, rhs } = inst;
@@ -395,6 +564,23 @@ fn opt_fdiv(opt: &mut PassOpt, mut inst: FDiv) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_floor(opt: &mut PassOpt, mut inst: Floor) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Floor { tyidx, val } = inst;
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Float(c.floor()),
+        })),
+        Some(ConstKind::Double(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Double(c.floor()),
+        })),
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
 fn opt_fmul(opt: &mut PassOpt, mut inst: FMul) -> OptOutcome {
     inst.canonicalise(opt);
     let FMul { tyidx, lhs, rhs } = inst;
@@ -421,6 +607,124 @@ fn opt_fmul(opt: &mut PassOpt, mut inst: FMul) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_fneg(opt: &mut PassOpt, mut inst: FNeg) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FNeg { tyidx, val } = inst;
+    // Note: `fneg` only flips the sign bit, so (unlike `0.0 - x`) it is exact for all values
+    // including zeros and NaNs.
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Float(-c),
+            }));
+        }
+        Some(ConstKind::Double(c)) => {
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Double(-c),
+            }));
+        }
+        Some(_) => unreachable!(),
+        None => (),
+    }
+
+    if let Inst::FNeg(FNeg { val: val_val, .. }) = opt.inst(val) {
+        // Reduce `fneg (fneg x)` to `x`.
+        return OptOutcome::Equiv(opt.equiv_iidx(*val_val));
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
+fn opt_fpclass(opt: &mut PassOpt, mut inst: FPClass) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FPClass { tyidx, val, test } = inst;
+    let (cat, is_neg, is_snan) = match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => (
+            c.classify(),
+            c.is_sign_negative(),
+            c.is_nan() && c.to_bits() & (1 << 22) == 0,
+        ),
+        Some(ConstKind::Double(c)) => (
+            c.classify(),
+            c.is_sign_negative(),
+            c.is_nan() && c.to_bits() & (1 << 51) == 0,
+        ),
+        Some(_) => unreachable!(),
+        None => return OptOutcome::Rewritten(inst.into()),
+    };
+    // The bits of `test` are, from least to most significant: signalling NaN; quiet NaN; negative
+    // infinity; negative normal; negative subnormal; negative zero; positive zero; positive
+    // subnormal; positive normal; positive infinity.
+    let bit = match cat {
+        FpCategory::Nan if is_snan => 0,
+        FpCategory::Nan => 1,
+        x => {
+            let bit = match x {
+                FpCategory::Infinite => 2,
+                FpCategory::Normal => 3,
+                FpCategory::Subnormal => 4,
+                FpCategory::Zero => 5,
+                FpCategory::Nan => unreachable!(),
+            };
+            if is_neg { bit } else { 11 - bit }
+        }
+    };
+    OptOutcome::Rewritten(Inst::Const(Const {
+        tyidx,
+        kind: ConstKind::Int(ArbBitInt::from_u64(1, u64::from((test >> bit) & 1))),
+    }))
+}
+
+fn opt_fpext(opt: &mut PassOpt, mut inst: FPExt) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FPExt { tyidx, val } = inst;
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Double(f64::from(c)),
+        })),
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
+fn opt_fptosi(opt: &mut PassOpt, mut inst: FPToSI) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FPToSI { tyidx, val } = inst;
+    let c = match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => f64::from(c),
+        Some(ConstKind::Double(c)) => c,
+        Some(_) => unreachable!(),
+        None => return OptOutcome::Rewritten(inst.into()),
+    };
+    // `fptosi` rounds towards zero. If the result doesn't fit in the destination type (including
+    // if `c` is NaN or infinite) the result is poison: we leave such instructions alone.
+    let bitw = opt.ty(tyidx).bitw();
+    let c = c.trunc();
+    let limit = 2f64.powi(i32::try_from(bitw).unwrap() - 1);
+    if c >= -limit && c < limit {
+        return OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Int(ArbBitInt::from_u64(bitw, (c as i64).cast_unsigned())),
+        }));
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
+fn opt_freeze(opt: &mut PassOpt, mut inst: Freeze) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Freeze { val, .. } = inst;
+    // Neither constants nor the results of `freeze` can be poison.
+    if let Inst::Const(_) | Inst::Freeze(_) = opt.inst(val) {
+        return OptOutcome::Equiv(val);
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
 fn opt_fsub(opt: &mut PassOpt, mut inst: FSub) -> OptOutcome {
     inst.canonicalise(opt);
     let FSub { tyidx, lhs, rhs } = inst;
@@ -447,6 +751,23 @@ fn opt_fsub(opt: &mut PassOpt, mut inst: FSub) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_ftrunc(opt: &mut PassOpt, mut inst: FTrunc) -> OptOutcome {
+    inst.canonicalise(opt);
+    let FTrunc { tyidx, val } = inst;
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Float(c.trunc()),
+        })),
+        Some(ConstKind::Double(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Double(c.trunc()),
+        })),
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
 fn opt_guard(opt: &mut PassOpt, mut inst @ Guard { expect, cond, .. }: Guard) -> OptOutcome {
     // Since guards tend to have lots of operands, we avoid `canonicalising` unless we really need
     // to. This needs to be done carefully, because after we've called `set_equiv` below,
@@ -773,7 +1094,7 @@ fn opt_or(opt: &mut PassOpt, mut inst: Or) -> OptOutcome {
             if rhs_c == ArbBitInt::all_bits_set(rhs_c.bitw()) {
                 // Reduce `x | y` to `y` if `y` is a constant that has all the necessary bits set
                 // for this integer type.
-                return OptOutcome::Equiv(lhs);
+                return OptOutcome::Equiv(rhs);
             }
         }
         _ => (),
@@ -782,6 +1103,45 @@ fn opt_or(opt: &mut PassOpt, mut inst: Or) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_overflow(opt: &mut PassOpt, mut inst: Overflow) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Overflow { op, lhs, rhs } = inst;
+    let v = match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+        (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) if lhs_c.bitw() <= 64 => {
+            // Constant fold `overflow op c1, c2`.
+            let (lhs_v, rhs_v) = if op.is_signed() {
+                (
+                    i128::from(lhs_c.to_sign_ext_i64().unwrap()),
+                    i128::from(rhs_c.to_sign_ext_i64().unwrap()),
+                )
+            } else {
+                (
+                    i128::from(lhs_c.to_zero_ext_u64().unwrap()),
+                    i128::from(rhs_c.to_zero_ext_u64().unwrap()),
+                )
+            };
+            op.overflows(lhs_c.bitw(), (lhs_v, lhs_v), (rhs_v, rhs_v))
+        }
+        (_, Some(ConstKind::Int(rhs_c))) => match (op, rhs_c.to_zero_ext_u8()) {
+            // `x + 0`, `x - 0`, `x * 0`, and `x * 1` never overflow.
+            (_, Some(0)) | (OverflowOp::SMul | OverflowOp::UMul, Some(1)) => Some(false),
+            _ => None,
+        },
+        // `x - x` never overflows.
+        _ if lhs == rhs && matches!(op, OverflowOp::SSub | OverflowOp::USub) => Some(false),
+        _ => None,
+    };
+    if let Some(v) = v {
+        let tyidx = opt.push_ty(Ty::Int(1)).unwrap();
+        return OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Int(ArbBitInt::from_u64(1, v as u64)),
+        }));
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
 fn opt_ptrtoint(opt: &mut PassOpt, mut inst: PtrToInt) -> OptOutcome {
     inst.canonicalise(opt);
     let PtrToInt { tyidx, val } = inst;
@@ -801,6 +1161,36 @@ fn opt_ptrtoint(opt: &mut PassOpt, mut inst: PtrToInt) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
+fn opt_sdiv(opt: &mut PassOpt, mut inst: SDiv) -> OptOutcome {
+    inst.canonicalise(opt);
+    let SDiv {
+        tyidx,
+        lhs,
+        rhs,
+        exact,
+    } = inst;
+    assert!(!exact);
+    match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+        (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) => {
+            // Constant fold `c1 / c2`. Division by zero, and `MIN / -1`, are undefined behaviour:
+            // we leave such instructions alone.
+            if let Some(c) = lhs_c.checked_sdiv(&rhs_c) {
+                return OptOutcome::Rewritten(Inst::Const(Const {
+                    tyidx,
+                    kind: ConstKind::Int(c),
+                }));
+            }
+        }
+        (_, Some(ConstKind::Int(rhs_c))) if rhs_c.to_zero_ext_u8() == Some(1) => {
+            // Reduce `x / 1` to `x`.
+            return OptOutcome::Equiv(lhs);
+        }
+        _ => (),
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
 fn opt_select(opt: &mut PassOpt, mut inst: Select) -> OptOutcome {
     inst.canonicalise(opt);
     let Select {
@@ -889,7 +1279,8 @@ fn opt_sitofp(opt: &mut PassOpt, mut inst: SIToFP) -> OptOutcome {
             })),
             Ty::Float => OptOutcome::Rewritten(Inst::Const(Const {
                 tyidx,
-                kind: ConstKind::Float(f32::from_i32(src_val.to_sign_ext_i32().unwrap()).unwrap()),
+                // `as` rounds to the nearest representable value, as `sitofp` does.
+                kind: ConstKind::Float(src_val.to_sign_ext_i64().unwrap() as f32),
             })),
             _ => unreachable!(),
         },
@@ -898,6 +1289,122 @@ fn opt_sitofp(opt: &mut PassOpt, mut inst: SIToFP) -> OptOutcome {
     }
 }
 
+fn opt_smax(opt: &mut PassOpt, mut inst: SMax) -> OptOutcome {
+    inst.canonicalise(opt);
+    let SMax { tyidx, lhs, rhs } = inst;
+    let bitw = opt.ty(tyidx).bitw();
+    opt_min_max(
+        opt,
+        inst.into(),
+        lhs,
+        rhs,
+        ArbBitInt::smax,
+        ArbBitInt::from_u64(bitw, 1 << (bitw - 1)),
+        ArbBitInt::all_bits_set(bitw).checked_lshr(1).unwrap(),
+    )
+}
+
+fn opt_smin(opt: &mut PassOpt, mut inst: SMin) -> OptOutcome {
+    inst.canonicalise(opt);
+    let SMin { tyidx, lhs, rhs } = inst;
+    let bitw = opt.ty(tyidx).bitw();
+    opt_min_max(
+        opt,
+        inst.into(),
+        lhs,
+        rhs,
+        ArbBitInt::smin,
+        ArbBitInt::all_bits_set(bitw).checked_lshr(1).unwrap(),
+        ArbBitInt::from_u64(bitw, 1 << (bitw - 1)),
+    )
+}
+
+/// Optimise one of `smax`/`smin`/`umax`/`umin`, where `fold` constant folds the operation,
+/// `identity` is the value `c` for which `op(x, c) == x`, and `absorbing` is the value `c` for
+/// which `op(x, c) == c`.
+fn opt_min_max(
+    opt: &mut PassOpt,
+    inst: Inst,
+    lhs: InstIdx,
+    rhs: InstIdx,
+    fold: fn(&ArbBitInt, &ArbBitInt) -> ArbBitInt,
+    identity: ArbBitInt,
+    absorbing: ArbBitInt,
+) -> OptOutcome {
+    let tyidx = inst.tyidx(opt);
+    if lhs == rhs {
+        // Reduce `op(x, x)` to `x`.
+        return OptOutcome::Equiv(lhs);
+    }
+
+    match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+        (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) => {
+            // Constant fold `op(c1, c2)`.
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(fold(&lhs_c, &rhs_c)),
+            }));
+        }
+        (_, Some(ConstKind::Int(rhs_c))) if rhs_c == identity => {
+            // Reduce `op(x, identity)` to `x`.
+            return OptOutcome::Equiv(lhs);
+        }
+        (_, Some(ConstKind::Int(rhs_c))) if rhs_c == absorbing => {
+            // Reduce `op(x, absorbing)` to `absorbing`.
+            return OptOutcome::Equiv(rhs);
+        }
+        _ => (),
+    }
+
+    OptOutcome::Rewritten(inst)
+}
+
+fn opt_sqrt(opt: &mut PassOpt, mut inst: Sqrt) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Sqrt { tyidx, val } = inst;
+    // Note: IEEE 754 requires `sqrt` to be correctly rounded, as is Rust's `sqrt`, so constant
+    // folding gives the same result as the hardware instruction.
+    match opt.as_constkind(val) {
+        Some(ConstKind::Float(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Float(c.sqrt()),
+        })),
+        Some(ConstKind::Double(c)) => OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Double(c.sqrt()),
+        })),
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
+fn opt_srem(opt: &mut PassOpt, mut inst: SRem) -> OptOutcome {
+    inst.canonicalise(opt);
+    let SRem { tyidx, lhs, rhs } = inst;
+    match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+        (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) => {
+            // Constant fold `c1 % c2`. Division by zero, and `MIN % -1`, are undefined behaviour:
+            // we leave such instructions alone.
+            if let Some(c) = lhs_c.checked_srem(&rhs_c) {
+                return OptOutcome::Rewritten(Inst::Const(Const {
+                    tyidx,
+                    kind: ConstKind::Int(c),
+                }));
+            }
+        }
+        (_, Some(ConstKind::Int(rhs_c))) if rhs_c.to_zero_ext_u8() == Some(1) => {
+            // Reduce `x % 1` to `0`.
+            return OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Int(ArbBitInt::from_u64(rhs_c.bitw(), 0)),
+            }));
+        }
+        _ => (),
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
 fn opt_sub(opt: &mut PassOpt, mut inst: Sub) -> OptOutcome {
     inst.canonicalise(opt);
     let Sub {
@@ -1001,17 +1508,114 @@ fn opt_udiv(opt: &mut PassOpt, mut inst: UDiv) -> OptOutcome {
     OptOutcome::Rewritten(inst.into())
 }
 
-fn opt_xor(opt: &mut PassOpt, mut inst: Xor) -> OptOutcome {
+fn opt_uitofp(opt: &mut PassOpt, mut inst: UIToFP) -> OptOutcome {
     inst.canonicalise(opt);
-    let Xor { tyidx, lhs, rhs } = inst;
-    let bitw = opt.ty(tyidx).bitw();
-    if lhs == rhs {
-        // Reduce x ^ x to 0.
-        return OptOutcome::Rewritten(Inst::Const(Const {
-            tyidx,
-            kind: ConstKind::Int(ArbBitInt::from_u64(bitw, 0)),
-        }));
-    }
+    let UIToFP { tyidx, val, nneg } = inst;
+    assert!(!nneg);
+    match opt.as_constkind(val) {
+        // Rust's `as` rounds to the nearest representable value, as LLVM does.
+        Some(ConstKind::Int(src_val)) => match opt.ty(tyidx) {
+            Ty::Double => OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Double(src_val.to_zero_ext_u64().unwrap() as f64),
+            })),
+            Ty::Float => OptOutcome::Rewritten(Inst::Const(Const {
+                tyidx,
+                kind: ConstKind::Float(src_val.to_zero_ext_u64().unwrap() as f32),
+            })),
+            _ => unreachable!(),
+        },
+        Some(_) => unreachable!(),
+        None => OptOutcome::Rewritten(inst.into()),
+    }
+}
+
+fn opt_umax(opt: &mut PassOpt, mut inst: UMax) -> OptOutcome {
+    inst.canonicalise(opt);
+    let UMax { tyidx, lhs, rhs } = inst;
+    let bitw = opt.ty(tyidx).bitw();
+    opt_min_max(
+        opt,
+        inst.into(),
+        lhs,
+        rhs,
+        ArbBitInt::umax,
+        ArbBitInt::from_u64(bitw, 0),
+        ArbBitInt::all_bits_set(bitw),
+    )
+}
+
+fn opt_umin(opt: &mut PassOpt, mut inst: UMin) -> OptOutcome {
+    inst.canonicalise(opt);
+    let UMin { tyidx, lhs, rhs } = inst;
+    let bitw = opt.ty(tyidx).bitw();
+    opt_min_max(
+        opt,
+        inst.into(),
+        lhs,
+        rhs,
+        ArbBitInt::umin,
+        ArbBitInt::all_bits_set(bitw),
+        ArbBitInt::from_u64(bitw, 0),
+    )
+}
+
+fn opt_urem(opt: &mut PassOpt, mut inst: URem) -> OptOutcome {
+    inst.canonicalise(opt);
+    let URem { tyidx, lhs, rhs } = inst;
+    match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
+        (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) => {
+            // Constant fold `c1 % c2`. Division by zero is undefined behaviour: we leave such
+            // instructions alone.
+            if let Some(c) = lhs_c.checked_urem(&rhs_c) {
+                return OptOutcome::Rewritten(Inst::Const(Const {
+                    tyidx,
+                    kind: ConstKind::Int(c),
+                }));
+            }
+        }
+        (_, Some(ConstKind::Int(rhs_c))) => match rhs_c.to_zero_ext_u64() {
+            Some(1) => {
+                // Reduce `x % 1` to `0`.
+                return OptOutcome::Rewritten(Inst::Const(Const {
+                    tyidx,
+                    kind: ConstKind::Int(ArbBitInt::from_u64(rhs_c.bitw(), 0)),
+                }));
+            }
+            Some(x) if x.is_power_of_two() => {
+                // Replace `x % y` with `x & (y - 1)`.
+                let c_iidx = opt.push_pre_inst(Inst::Const(Const {
+                    tyidx,
+                    kind: ConstKind::Int(ArbBitInt::from_u64(rhs_c.bitw(), x - 1)),
+                }));
+                return OptOutcome::Rewritten(
+                    And {
+                        tyidx,
+                        lhs,
+                        rhs: c_iidx,
+                    }
+                    .into(),
+                );
+            }
+            _ => (),
+        },
+        _ => (),
+    }
+
+    OptOutcome::Rewritten(inst.into())
+}
+
+fn opt_xor(opt: &mut PassOpt, mut inst: Xor) -> OptOutcome {
+    inst.canonicalise(opt);
+    let Xor { tyidx, lhs, rhs } = inst;
+    let bitw = opt.ty(tyidx).bitw();
+    if lhs == rhs {
+        // Reduce x ^ x to 0.
+        return OptOutcome::Rewritten(Inst::Const(Const {
+            tyidx,
+            kind: ConstKind::Int(ArbBitInt::from_u64(bitw, 0)),
+        }));
+    }
 
     match (opt.as_constkind(lhs), opt.as_constkind(rhs)) {
         (Some(ConstKind::Int(lhs_c)), Some(ConstKind::Int(rhs_c))) => {
@@ -1087,6 +1691,7 @@ fn opt_zext(opt: &mut PassOpt, mut inst: ZExt) -> OptOutcome {
 mod test {
     use super::*;
     use crate::compile::j2::opt::fullopt::test::user_defined_opt_test;
+    use proptest::prelude::*;
 
     fn test_sf(mod_s: &str, ptn: &str) {
         user_defined_opt_test(
@@ -1372,6 +1977,104 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_ceil() {
+        test_sf(
+            "
+          %0: double = 2.5double
+          %1: double = ceil %0
+          blackbox %1
+          %3: double = -2.5double
+          %4: double = ceil %3
+          blackbox %4
+          %6: float = 2.5float
+          %7: float = ceil %6
+          blackbox %7
+        ",
+            "
+          ...
+          %1: double = 3
+          blackbox %1
+          ...
+          %4: double = -2
+          blackbox %4
+          ...
+          %7: float = 3
+          blackbox %7
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_copysign() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: double = 2.5double
+          %1: double = -1.0double
+          %2: double = copysign %0, %1
+          blackbox %2
+          %4: float = -2.5float
+          %5: float = 1.0float
+          %6: float = copysign %4, %5
+          blackbox %6
+        ",
+            "
+          ...
+          %2: double = -2.5
+          blackbox %2
+          ...
+          %6: float = 2.5
+          blackbox %6
+        ",
+        );
+
+        // `copysign x, +c` is `fabs x`.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = 1.0double
+          %2: double = copysign %0, %1
+          term [%2]
+        ",
+            "
+          %0: double = arg
+          ...
+          %2: double = fabs %0
+          term [%2]
+        ",
+        );
+
+        // `copysign x, -c` can't be simplified.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = -1.0double
+          %2: double = copysign %0, %1
+          term [%2]
+        ",
+            "
+          %0: double = arg
+          %1: double = -1
+          %2: double = copysign %0, %1
+          term [%2]
+        ",
+        );
+
+        // `copysign x, x` is `x`.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = copysign %0, %0
+          term [%1]
+        ",
+            "
+          %0: double = arg
+          term [%0]
+        ",
+        );
+    }
+
     #[test]
     fn opt_ctpop() {
         // Constant fold the number of set bits
@@ -1461,6 +2164,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_fabs() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: double = -2.5double
+          %1: double = fabs %0
+          blackbox %1
+          %3: float = 2.5float
+          %4: float = fabs %3
+          blackbox %4
+        ",
+            "
+          ...
+          %1: double = 2.5
+          blackbox %1
+          ...
+          %4: float = 2.5
+          blackbox %4
+        ",
+        );
+
+        // `fabs (fabs x)` is `fabs x`.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = fabs %0
+          %2: double = fabs %1
+          term [%2]
+        ",
+            "
+          %0: double = arg
+          %1: double = fabs %0
+          term [%1]
+        ",
+        );
+
+        // `fabs (fneg x)` is `fabs x`.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = fneg %0
+          %2: double = fabs %1
+          term [%2]
+        ",
+            "
+          %0: double = arg
+          %2: double = fabs %0
+          term [%2]
+        ",
+        );
+    }
+
     #[test]
     fn opt_fadd() {
         // Constant fold lhs float and rhs float
@@ -1494,6 +2250,66 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_fcmp() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: double = 1.5double
+          %1: double = 2.5double
+          %2: i1 = fcmp olt %0, %1
+          blackbox %2
+          %4: i1 = fcmp uge %0, %1
+          blackbox %4
+          %6: i1 = fcmp one %0, %1
+          blackbox %6
+          %8: float = 1.5float
+          %9: float = 2.5float
+          %10: i1 = fcmp ogt %8, %9
+          blackbox %10
+          %12: i1 = fcmp ord %8, %9
+          blackbox %12
+        ",
+            "
+          ...
+          %2: i1 = 1
+          blackbox %2
+          %4: i1 = 0
+          blackbox %4
+          %6: i1 = 1
+          blackbox %6
+          ...
+          %10: i1 = 0
+          blackbox %10
+          %12: i1 = 1
+          blackbox %12
+        ",
+        );
+
+        // `true` and `false` don't depend on their operands.
+        test_sf(
+            "
+          %0: double = arg [reg]
+          %1: double = arg [reg]
+          %2: i1 = fcmp true %0, %1
+          blackbox %2
+          %4: i1 = fcmp false %0, %1
+          blackbox %4
+          %6: i1 = fcmp oeq %0, %1
+          blackbox %6
+        ",
+            "
+          ...
+          %2: i1 = 1
+          blackbox %2
+          %4: i1 = 0
+          blackbox %4
+          %6: i1 = fcmp oeq %0, %1
+          blackbox %6
+        ",
+        );
+    }
+
     #[test]
     fn opt_fdiv() {
         // Constant fold lhs float and rhs float
@@ -1707,6 +2523,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_floor() {
+        test_sf(
+            "
+          %0: double = 2.5double
+          %1: double = floor %0
+          blackbox %1
+          %3: double = -2.5double
+          %4: double = floor %3
+          blackbox %4
+          %6: float = 2.5float
+          %7: float = floor %6
+          blackbox %7
+        ",
+            "
+          ...
+          %1: double = 2
+          blackbox %1
+          ...
+          %4: double = -3
+          blackbox %4
+          ...
+          %7: float = 2
+          blackbox %7
+        ",
+        );
+    }
+
     #[test]
     fn opt_fmul() {
         // Constant fold lhs float and rhs float
@@ -1741,68 +2585,282 @@ mod test {
     }
 
     #[test]
-    fn opt_fsub() {
-        // Constant fold lhs float and rhs float
+    fn opt_fneg() {
+        // Constant folding.
         test_sf(
             "
-          %0: float = 2.02float
-          %1: float = 1.01float
-          %2: float = fsub %0, %1
-          blackbox %2
+          %0: double = 2.5double
+          %1: double = fneg %0
+          blackbox %1
+          %3: float = -0.0float
+          %4: float = fneg %3
+          blackbox %4
         ",
             "
           ...
-          %2: float = 1.01
-          blackbox %2
+          %1: double = -2.5
+          blackbox %1
+          ...
+          %4: float = 0
+          blackbox %4
         ",
         );
 
-        // Constant fold lhs double and rhs double
+        // `fneg (fneg x)` is `x`.
         test_sf(
             "
-          %0: double = 2.02double
-          %1: double = 1.01double
-          %2: double = fsub %0, %1
-          blackbox %2
+          %0: double = arg [reg]
+          %1: double = fneg %0
+          %2: double = fneg %1
+          term [%2]
         ",
             "
-          ...
-          %2: double = 1.01
-          blackbox %2
+          %0: double = arg
+          term [%0]
         ",
         );
     }
 
     #[test]
-    fn opt_guard() {
-        // Guard referencing a constant
+    fn opt_fpclass() {
         test_sf(
             "
-          %0: i8 = arg [reg]
-          %1: i1 = 1
-          guard true, %1, []
-          term [%0]
+          %0: double = -1.5double
+          %1: i1 = fpclass %0, 8
+          blackbox %1
+          %3: i1 = fpclass %0, 256
+          blackbox %3
+          %5: double = 0.0double
+          %6: i1 = fpclass %5, 96
+          blackbox %6
+          %8: float = -0.0float
+          %9: i1 = fpclass %8, 32
+          blackbox %9
+          %11: i1 = fpclass %8, 64
+          blackbox %11
         ",
             "
-          %0: i8 = arg
-          term [%0]
+          ...
+          %1: i1 = 1
+          blackbox %1
+          %3: i1 = 0
+          blackbox %3
+          ...
+          %6: i1 = 1
+          blackbox %6
+          ...
+          %9: i1 = 1
+          blackbox %9
+          %11: i1 = 0
+          blackbox %11
         ",
         );
 
-        // Guard setting a range on IPred::Eq
+        // A `float` subnormal is a normal `double`, so it must be classified as a `float`.
         test_sf(
             "
-          %0: i8 = arg [reg]
-          %1: i8 = 4
-          %2: i1 = icmp eq %0, %1
-          guard true, %2, []
-          term [%0]
+          %0: float = 0.0000000000000000000000000000000000000001float
+          %1: i1 = fpclass %0, 128
+          blackbox %1
+          %3: i1 = fpclass %0, 256
+          blackbox %3
         ",
             "
-          %0: i8 = arg
-          %1: i8 = 4
-          %2: i1 = icmp eq %0, %1
-          guard true, %2, []
+          ...
+          %1: i1 = 1
+          blackbox %1
+          %3: i1 = 0
+          blackbox %3
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_fpext() {
+        test_sf(
+            "
+          %0: float = 2.5float
+          %1: double = fpext %0
+          blackbox %1
+        ",
+            "
+          ...
+          %1: double = 2.5
+          blackbox %1
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_fptosi() {
+        // Values are rounded towards zero.
+        test_sf(
+            "
+          %0: double = 2.5double
+          %1: i8 = fptosi %0
+          blackbox %1
+          %3: float = -2.5float
+          %4: i8 = fptosi %3
+          blackbox %4
+          %6: double = -128.9double
+          %7: i8 = fptosi %6
+          blackbox %7
+        ",
+            "
+          ...
+          %1: i8 = 2
+          blackbox %1
+          ...
+          %4: i8 = 254
+          blackbox %4
+          ...
+          %7: i8 = 128
+          blackbox %7
+        ",
+        );
+
+        // Values that can't be represented in the destination type produce poison, so aren't
+        // folded.
+        test_sf(
+            "
+          %0: double = 128.0double
+          %1: i8 = fptosi %0
+          blackbox %1
+          %3: double = -129.0double
+          %4: i8 = fptosi %3
+          blackbox %4
+        ",
+            "
+          ...
+          %1: i8 = fptosi %0
+          blackbox %1
+          ...
+          %4: i8 = fptosi %3
+          blackbox %4
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_freeze() {
+        test_sf(
+            "
+          %0: i8 = 3
+          %1: i8 = freeze %0
+          blackbox %1
+        ",
+            "
+          %0: i8 = 3
+          blackbox %0
+        ",
+        );
+
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = freeze %0
+          %2: i8 = freeze %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = freeze %0
+          term [%1]
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_fsub() {
+        // Constant fold lhs float and rhs float
+        test_sf(
+            "
+          %0: float = 2.02float
+          %1: float = 1.01float
+          %2: float = fsub %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: float = 1.01
+          blackbox %2
+        ",
+        );
+
+        // Constant fold lhs double and rhs double
+        test_sf(
+            "
+          %0: double = 2.02double
+          %1: double = 1.01double
+          %2: double = fsub %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: double = 1.01
+          blackbox %2
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_ftrunc() {
+        test_sf(
+            "
+          %0: double = 2.5double
+          %1: double = ftrunc %0
+          blackbox %1
+          %3: double = -2.5double
+          %4: double = ftrunc %3
+          blackbox %4
+          %6: float = -2.5float
+          %7: float = ftrunc %6
+          blackbox %7
+        ",
+            "
+          ...
+          %1: double = 2
+          blackbox %1
+          ...
+          %4: double = -2
+          blackbox %4
+          ...
+          %7: float = -2
+          blackbox %7
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_guard() {
+        // Guard referencing a constant
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i1 = 1
+          guard true, %1, []
+          term [%0]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+
+        // Guard setting a range on IPred::Eq
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 4
+          %2: i1 = icmp eq %0, %1
+          guard true, %2, []
+          term [%0]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 4
+          %2: i1 = icmp eq %0, %1
+          guard true, %2, []
           term [%1]
           ...
         ",
@@ -2934,16 +3992,110 @@ mod test {
           %0: i8 = arg [ reg ]
           %1: i8 = 255
           %2: i8 = or %0, %1
-          term [%1]
+          term [%2]
         ",
             "
-          ...
+          %0: i8 = arg
           %1: i8 = 255
           term [%1]
         ",
         );
     }
 
+    #[test]
+    fn opt_overflow() {
+        // Constant folding
+        test_sf(
+            "
+          %0: i8 = 100
+          %1: i8 = 27
+          %2: i1 = overflow sadd %0, %1
+          %3: i1 = overflow uadd %0, %1
+          %4: i8 = 28
+          %5: i1 = overflow sadd %0, %4
+          %6: i8 = -1
+          %7: i1 = overflow uadd %0, %6
+          %8: i1 = overflow ssub %1, %0
+          %9: i1 = overflow usub %1, %0
+          %10: i1 = overflow smul %0, %6
+          %11: i1 = overflow umul %1, %1
+          %12: i1 = overflow smul %1, %1
+          blackbox %2
+          blackbox %3
+          blackbox %5
+          blackbox %7
+          blackbox %8
+          blackbox %9
+          blackbox %10
+          blackbox %11
+          blackbox %12
+        ",
+            "
+          ...
+          %2: i1 = 0
+          %3: i1 = 0
+          %4: i8 = 28
+          %5: i1 = 1
+          %6: i8 = 255
+          %7: i1 = 1
+          %8: i1 = 0
+          %9: i1 = 1
+          %10: i1 = 0
+          %11: i1 = 1
+          %12: i1 = 1
+          ...
+        ",
+        );
+
+        // Operations which can never overflow
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 0
+          %2: i8 = 1
+          %3: i1 = overflow sadd %0, %1
+          %4: i1 = overflow usub %0, %1
+          %5: i1 = overflow umul %0, %2
+          %6: i1 = overflow smul %1, %0
+          %7: i1 = overflow ssub %0, %0
+          blackbox %3
+          blackbox %4
+          blackbox %5
+          blackbox %6
+          blackbox %7
+          term [%0]
+        ",
+            "
+          ...
+          %3: i1 = 0
+          %4: i1 = 0
+          %5: i1 = 0
+          %6: i1 = 0
+          %7: i1 = 0
+          ...
+        ",
+        );
+
+        // Operations which may overflow are left alone
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 2
+          %2: i1 = overflow uadd %1, %0
+          %3: i1 = overflow usub %1, %0
+          blackbox %2
+          blackbox %3
+          term [%0]
+        ",
+            "
+          ...
+          %2: i1 = overflow uadd %0, %1
+          %3: i1 = overflow usub %1, %0
+          ...
+        ",
+        );
+    }
+
     #[test]
     fn opt_ptradd() {
         // Constant folding
@@ -3025,6 +4177,59 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_sdiv() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -7
+          %1: i8 = 2
+          %2: i8 = sdiv %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 253
+          blackbox %2
+        ",
+        );
+
+        // Division by zero and `MIN / -1` are undefined behaviour and aren't folded.
+        test_sf(
+            "
+          %0: i8 = -128
+          %1: i8 = -1
+          %2: i8 = sdiv %0, %1
+          blackbox %2
+          %4: i8 = 0
+          %5: i8 = sdiv %0, %4
+          blackbox %5
+        ",
+            "
+          ...
+          %2: i8 = sdiv %0, %1
+          blackbox %2
+          %4: i8 = 0
+          %5: i8 = sdiv %0, %4
+          blackbox %5
+        ",
+        );
+
+        // Strength reduction of `x / 1`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 1
+          %2: i8 = sdiv %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+    }
+
     #[test]
     fn opt_select() {
         // Constant false
@@ -3221,7 +4426,204 @@ mod test {
           blackbox %1
         ",
         );
-    }
+
+        // Values which don't fit in an `i32`.
+        test_sf(
+            "
+          %0: i64 = 4294967297
+          %1: float = sitofp %0
+          blackbox %1
+          %3: i64 = -4294967297
+          %4: float = sitofp %3
+          blackbox %4
+        ",
+            "
+          ...
+          %1: float = 4294967300
+          blackbox %1
+          ...
+          %4: float = -4294967300
+          blackbox %4
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_smax() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -1
+          %1: i8 = 1
+          %2: i8 = smax %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 1
+          blackbox %2
+        ",
+        );
+
+        // `smax(x, x)` and `smax(x, MIN)` are `x`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = smax %0, %0
+          %2: i8 = -128
+          %3: i8 = smax %2, %1
+          term [%3]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+
+        // `smax(x, MAX)` is `MAX`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 127
+          %2: i8 = smax %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 127
+          term [%1]
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_smin() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -1
+          %1: i8 = 1
+          %2: i8 = smin %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 255
+          blackbox %2
+        ",
+        );
+
+        // `smin(x, MAX)` is `x`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 127
+          %2: i8 = smin %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+
+        // `smin(x, MIN)` is `MIN`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = -128
+          %2: i8 = smin %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 128
+          term [%1]
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_sqrt() {
+        test_sf(
+            "
+          %0: double = 2.25double
+          %1: double = sqrt %0
+          blackbox %1
+          %3: double = -1.0double
+          %4: double = sqrt %3
+          blackbox %4
+          %6: float = 16.0float
+          %7: float = sqrt %6
+          blackbox %7
+        ",
+            "
+          ...
+          %1: double = 1.5
+          blackbox %1
+          ...
+          %4: double = NaN
+          blackbox %4
+          ...
+          %7: float = 4
+          blackbox %7
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_srem() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -7
+          %1: i8 = 2
+          %2: i8 = srem %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 255
+          blackbox %2
+        ",
+        );
+
+        // Division by zero and `MIN % -1` are undefined behaviour and aren't folded.
+        test_sf(
+            "
+          %0: i8 = -128
+          %1: i8 = -1
+          %2: i8 = srem %0, %1
+          blackbox %2
+          %4: i8 = 0
+          %5: i8 = srem %0, %4
+          blackbox %5
+        ",
+            "
+          ...
+          %2: i8 = srem %0, %1
+          blackbox %2
+          %4: i8 = 0
+          %5: i8 = srem %0, %4
+          blackbox %5
+        ",
+        );
+
+        // Strength reduction of `x % 1`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 1
+          %2: i8 = srem %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %2: i8 = 0
+          term [%2]
+        ",
+        );
+    }
 
     #[test]
     fn opt_sub() {
@@ -3376,6 +4778,205 @@ mod test {
         );
     }
 
+    #[test]
+    fn opt_uitofp() {
+        // Precisely representable.
+        test_sf(
+            "
+          %0: i8 = 255
+          %1: double = uitofp %0
+          blackbox %1
+          %3: float = uitofp %0
+          blackbox %3
+        ",
+            "
+          ...
+          %1: double = 255
+          blackbox %1
+          %3: float = 255
+          blackbox %3
+        ",
+        );
+
+        // Rounded.
+        test_sf(
+            "
+          %0: i64 = 18446744073709551615
+          %1: double = uitofp %0
+          blackbox %1
+          %3: i32 = 16777217
+          %4: float = uitofp %3
+          blackbox %4
+        ",
+            "
+          ...
+          %1: double = 18446744073709552000
+          blackbox %1
+          ...
+          %4: float = 16777216
+          blackbox %4
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_umax() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -1
+          %1: i8 = 1
+          %2: i8 = umax %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 255
+          blackbox %2
+        ",
+        );
+
+        // `umax(x, 0)` is `x`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 0
+          %2: i8 = umax %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+
+        // `umax(x, MAX)` is `MAX`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 255
+          %2: i8 = umax %1, %0
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 255
+          term [%1]
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_umin() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = -1
+          %1: i8 = 1
+          %2: i8 = umin %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 1
+          blackbox %2
+        ",
+        );
+
+        // `umin(x, MAX)` is `x`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 255
+          %2: i8 = umin %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          term [%0]
+        ",
+        );
+
+        // `umin(x, 0)` is `0`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 0
+          %2: i8 = umin %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %1: i8 = 0
+          term [%1]
+        ",
+        );
+    }
+
+    #[test]
+    fn opt_urem() {
+        // Constant folding.
+        test_sf(
+            "
+          %0: i8 = 7
+          %1: i8 = 3
+          %2: i8 = urem %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = 1
+          blackbox %2
+        ",
+        );
+
+        // Division by zero is undefined behaviour and isn't folded.
+        test_sf(
+            "
+          %0: i8 = 7
+          %1: i8 = 0
+          %2: i8 = urem %0, %1
+          blackbox %2
+        ",
+            "
+          ...
+          %2: i8 = urem %0, %1
+          blackbox %2
+        ",
+        );
+
+        // Strength reduction of `x % 1`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 1
+          %2: i8 = urem %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %2: i8 = 0
+          term [%2]
+        ",
+        );
+
+        // Strength reduction of `x % 2^n`.
+        test_sf(
+            "
+          %0: i8 = arg [reg]
+          %1: i8 = 8
+          %2: i8 = urem %0, %1
+          term [%2]
+        ",
+            "
+          %0: i8 = arg
+          %2: i8 = 7
+          %3: i8 = and %0, %2
+          term [%3]
+        ",
+        );
+    }
+
     #[test]
     fn opt_xor() {
         // x ^ x == 0
@@ -3509,4 +5110,238 @@ mod test {
         ",
         );
     }
+
+    /// A reference evaluator for integer binary operations on `bitw` bit integers, written
+    /// independently of [ArbBitInt]. Returns `None` if the result is undefined behaviour or
+    /// poison in LLVM, in which case any folding is acceptable.
+    fn ref_int_binop(op: &str, bitw: u32, lhs: u64, rhs: u64) -> Option<u64> {
+        let mask = u64::MAX >> (64 - bitw);
+        let sext = |x: u64| i128::from(((x << (64 - bitw)) as i64) >> (64 - bitw));
+        let (ulhs, urhs) = (i128::from(lhs & mask), i128::from(rhs & mask));
+        let (slhs, srhs) = (sext(lhs), sext(rhs));
+        let smin = -(1i128 << (bitw - 1));
+        let v = match op {
+            "add" => ulhs + urhs,
+            "sub" => ulhs - urhs,
+            "mul" => ulhs.wrapping_mul(urhs),
+            "and" => ulhs & urhs,
+            "or" => ulhs | urhs,
+            "xor" => ulhs ^ urhs,
+            "shl" if urhs < i128::from(bitw) => ulhs << urhs,
+            "lshr" if urhs < i128::from(bitw) => ulhs >> urhs,
+            "ashr" if urhs < i128::from(bitw) => slhs >> urhs,
+            "udiv" if urhs != 0 => ulhs / urhs,
+            "urem" if urhs != 0 => ulhs % urhs,
+            "sdiv" if srhs != 0 && !(slhs == smin && srhs == -1) => slhs / srhs,
+            "srem" if srhs != 0 && !(slhs == smin && srhs == -1) => slhs % srhs,
+            "smax" => slhs.max(srhs),
+            "smin" => slhs.min(srhs),
+            "umax" => ulhs.max(urhs),
+            "umin" => ulhs.min(urhs),
+            _ => return None,
+        };
+        Some((v as u64) & mask)
+    }
+
+    proptest! {
+        #[test]
+        fn int_binop_folding(
+            op in prop::sample::select(vec![
+                "add", "sub", "mul", "and", "or", "xor", "shl", "lshr", "ashr", "udiv", "urem",
+                "sdiv", "srem", "smax", "smin", "umax", "umin",
+            ]),
+            bitw in prop::sample::select(vec![1u32, 8, 16, 32, 64]),
+            lhs in any::<u64>(),
+            // Small values make shifts and divisions more interesting.
+            rhs in prop_oneof![any::<u64>(), 0u64..=65],
+        ) {
+            let mask = u64::MAX >> (64 - bitw);
+            if let Some(v) = ref_int_binop(op, bitw, lhs, rhs) {
+                test_sf(
+                    &format!(
+                        "
+                      %0: i{bitw} = {}
+                      %1: i{bitw} = {}
+                      %2: i{bitw} = {op} %0, %1
+                      blackbox %2
+                    ",
+                        lhs & mask,
+                        rhs & mask
+                    ),
+                    &format!(
+                        "
+                      ...
+                      %2: i{bitw} = {v}
+                      blackbox %2
+                    "
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Format `x` as a HIR floating point constant of type `ty`.
+    fn fp_const<T: std::fmt::Display>(x: T, ty: &str) -> String {
+        let s = x.to_string();
+        if s.contains('.') {
+            format!("{s}{ty}")
+        } else {
+            format!("{s}.0{ty}")
+        }
+    }
+
+    /// Finite floating point values which are also finite when converted to `f32`.
+    fn fp_val() -> impl Strategy<Value = f64> {
+        prop_oneof![
+            -300.0f64..300.0,
+            -1e30f64..1e30,
+            -1e-40f64..1e-40,
+            prop::sample::select(vec![0.0, -0.0, 0.5, -0.5, 1.0, -1.0, 2.5, -2.5]),
+        ]
+    }
+
+    /// A reference evaluator for `fcmp`.
+    fn ref_fcmp(pred: &str, lhs: f64, rhs: f64) -> bool {
+        let uno = lhs.is_nan() || rhs.is_nan();
+        match pred {
+            "false" => false,
+            "oeq" => !uno && lhs == rhs,
+            "ogt" => !uno && lhs > rhs,
+            "oge" => !uno && lhs >= rhs,
+            "olt" => !uno && lhs < rhs,
+            "ole" => !uno && lhs <= rhs,
+            "one" => !uno && lhs != rhs,
+            "ord" => !uno,
+            "ueq" => uno || lhs == rhs,
+            "ugt" => uno || lhs > rhs,
+            "uge" => uno || lhs >= rhs,
+            "ult" => uno || lhs < rhs,
+            "ule" => uno || lhs <= rhs,
+            "une" => uno || lhs != rhs,
+            "uno" => uno,
+            "true" => true,
+            _ => unreachable!(),
+        }
+    }
+
+    /// A reference evaluator for floating point conversions and unary operations. `fty` is the
+    /// floating point type involved and `bitw` the width of the integer type involved (if any).
+    /// Returns `(operand type, operand, result type, result)`, where `result` is `None` if the
+    /// result is poison in LLVM, in which case the instruction must not be folded.
+    fn ref_fp_unop(
+        op: &str,
+        fty: &str,
+        bitw: u32,
+        fv: f64,
+        iv: u64,
+    ) -> (String, String, String, Option<String>) {
+        let mask = u64::MAX >> (64 - bitw);
+        let is_double = fty == "double";
+        let f = fv as f32;
+        let fconst = if is_double {
+            fp_const(fv, fty)
+        } else {
+            fp_const(f, fty)
+        };
+        let fv = if is_double { fv } else { f64::from(f) };
+        let (res_ty, res) = match op {
+            "fneg" if is_double => (fty.to_owned(), Some((-fv).to_string())),
+            "fneg" => (fty.to_owned(), Some((-f).to_string())),
+            "floor" if is_double => (fty.to_owned(), Some(fv.floor().to_string())),
+            "floor" => (fty.to_owned(), Some(f.floor().to_string())),
+            "sqrt" if is_double => (fty.to_owned(), Some(fv.sqrt().to_string())),
+            "sqrt" => (fty.to_owned(), Some(f.sqrt().to_string())),
+            "fpext" => ("double".to_owned(), Some(fv.to_string())),
+            "fptosi" => {
+                let t = fv.trunc();
+                let limit = 2f64.powi(i32::try_from(bitw).unwrap() - 1);
+                let res =
+                    (t >= -limit && t < limit).then(|| ((t as i128 as u64) & mask).to_string());
+                (format!("i{bitw}"), res)
+            }
+            "uitofp" => {
+                let v = iv & mask;
+                let res = if is_double {
+                    (v as f64).to_string()
+                } else {
+                    (v as f32).to_string()
+                };
+                return (format!("i{bitw}"), v.to_string(), fty.to_owned(), Some(res));
+            }
+            _ => unreachable!(),
+        };
+        (fty.to_owned(), fconst, res_ty, res)
+    }
+
+    proptest! {
+        #[test]
+        fn fcmp_folding(
+            pred in prop::sample::select(vec![
+                "false", "oeq", "ogt", "oge", "olt", "ole", "one", "ord", "ueq", "ugt", "uge",
+                "ult", "ule", "une", "uno", "true",
+            ]),
+            fty in prop::sample::select(vec!["float", "double"]),
+            lhs in fp_val(),
+            rhs in fp_val(),
+            same in any::<bool>(),
+        ) {
+            let rhs = if same { lhs } else { rhs };
+            let (lhs_c, rhs_c, lhs, rhs) = if fty == "double" {
+                (fp_const(lhs, fty), fp_const(rhs, fty), lhs, rhs)
+            } else {
+                let (lhs, rhs) = (lhs as f32, rhs as f32);
+                (fp_const(lhs, fty), fp_const(rhs, fty), f64::from(lhs), f64::from(rhs))
+            };
+            let v = u8::from(ref_fcmp(pred, lhs, rhs));
+            test_sf(
+                &format!(
+                    "
+                  %0: {fty} = {lhs_c}
+                  %1: {fty} = {rhs_c}
+                  %2: i1 = fcmp {pred} %0, %1
+                  blackbox %2
+                "
+                ),
+                &format!(
+                    "
+                  ...
+                  %2: i1 = {v}
+                  blackbox %2
+                "
+                ),
+            );
+        }
+
+        #[test]
+        fn fp_unop_folding(
+            op in prop::sample::select(vec![
+                "fneg", "floor", "sqrt", "fpext", "fptosi", "uitofp",
+            ]),
+            fty in prop::sample::select(vec!["float", "double"]),
+            bitw in prop::sample::select(vec![8u32, 16, 32, 64]),
+            fv in fp_val(),
+            iv in any::<u64>(),
+        ) {
+            // `fpext` can only extend `float` to `double`.
+            prop_assume!(op != "fpext" || fty == "float");
+            let (src_ty, src, res_ty, res) = ref_fp_unop(op, fty, bitw, fv, iv);
+            let res = res.unwrap_or_else(|| format!("{op} %0"));
+            test_sf(
+                &format!(
+                    "
+                  %0: {src_ty} = {src}
+                  %1: {res_ty} = {op} %0
+                  blackbox %1
+                "
+                ),
+                &format!(
+                    "
+                  ...
+                  %1: {res_ty} = {res}
+                  blackbox %1
+                "
+                ),
+            );
+        }
+    }
 }
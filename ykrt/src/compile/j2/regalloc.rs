@@ -57,7 +57,7 @@ use crate::compile::j2::hir::Ty;
 use crate::compile::{
     CompilationError,
     j2::{
-        hir::{Block, BlockLikeT, Const, ConstKind, Inst, InstIdx, Mod},
+        hir::{Alloca, Block, BlockLikeT, Const, ConstKind, Inst, InstIdx, Mod},
         hir_to_asm::HirToAsmBackend,
     },
 };
@@ -91,7 +91,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
         m: &'a Mod<AB::Reg>,
         b: &'a Block,
         args_vlocs: &'a [VarLocs<AB::Reg>],
-        stack_off: u32,
+        mut stack_off: u32,
     ) -> Self {
         // Before processing the main body of a trace, set the stack offset (if any) of entry
         // variables, so that we don't end up unnecessarily spilling them twice during execution.
@@ -118,6 +118,16 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
             }
         }
 
+        // Each `alloca` is given a fixed slot in the stack before any spills are allocated: its
+        // value is then a pointer into the stack, in the same way as an `arg` whose [VarLoc] is a
+        // [VarLoc::StackOff].
+        for (iidx, inst) in b.insts_iter(..) {
+            if let Inst::Alloca(Alloca { bytew, align }) = inst {
+                stack_off = AB::align_alloca(stack_off, *bytew, *align);
+                istates[iidx] = IState::StackOff(stack_off);
+            }
+        }
+
         Self {
             m,
             b,
@@ -166,7 +176,11 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
     }
 
     /// After processing the main body of a trace, set the [VarLocs]s of the entry variables.
-    pub(super) fn set_args_vlocs_at_start(&mut self, be: &mut AB, args_vlocs: &[VarLocs<AB::Reg>]) {
+    pub(super) fn set_args_vlocs_at_start(
+        &mut self,
+        be: &mut AB,
+        args_vlocs: &[VarLocs<AB::Reg>],
+    ) -> Result<(), CompilationError> {
         // In essence, this is a simple, special case of normal register allocation. First we work
         // out what the rstate after trace entry will be, diff that, and generate the appropriate
         // code.
@@ -182,7 +196,9 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                     if !in_rstate.iidxs(*reg).is_empty() {
                         let bitw = self.b.inst_bitw(self.m, iidx);
                         if bitw > iidxs_maxbitw(self.m, self.b, in_rstate.iidxs(*reg)) {
-                            todo!();
+                            unsupported!(
+                                "argument {iidx:?} shares a register with narrower arguments"
+                            );
                         }
                     }
                     in_rstate.set_fill_iidxs(*reg, *fill, smallvec![iidx]);
@@ -192,8 +208,8 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
 
         let mut ractions = RegActions::new();
         self.rstate_diff_to_action(be, &mut in_rstate, &mut ractions, false);
-        self.toposort_distinct_copies(&mut ractions).unwrap();
-        self.asm_ractions(be, &ractions).unwrap();
+        self.toposort_distinct_copies(&mut ractions)?;
+        self.asm_ractions(be, &ractions)?;
 
         // Because we are, in a sense, allocating registers for multiple instructions in one go, we
         // now need to find all `arg` instructions that we need to spill i.e. those where (1) they
@@ -213,9 +229,10 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                     panic!("{iidx:?} {vlocs:?}")
                 };
                 let bitw = self.b.inst_bitw(self.m, iidx);
-                be.spill(*reg, *fill, stack_off, bitw).unwrap();
+                be.spill(*reg, *fill, stack_off, bitw)?;
             }
         }
+        Ok(())
     }
 
     /// Set the [VarLocs] of a [super::hir::Term] instruction.
@@ -266,7 +283,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                     let bitw = self.b.inst_bitw(self.m, *iidx);
                     match vloc {
                         VarLoc::Stack(_) => (),
-                        VarLoc::StackOff(_) => todo!(),
+                        VarLoc::StackOff(_) => unsupported!("constant passed as a stack offset"),
                         VarLoc::Reg(reg, fill) => {
                             be.move_const(*reg, bitw, *fill, kind)?;
                         }
@@ -292,7 +309,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                             be.spill(find_tmp_reg(), RegFill::Zeroed, *stack_off, bitw)?;
                             be.move_const(find_tmp_reg(), bitw, RegFill::Zeroed, kind)?;
                         }
-                        VarLoc::StackOff(_) => todo!(),
+                        VarLoc::StackOff(_) => unsupported!("constant passed as a stack offset"),
                         VarLoc::Reg(_, _) => (),
                         VarLoc::Const(_) => (),
                     }
@@ -300,6 +317,20 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
             }
         }
 
+        // Pointers into the stack (e.g. `alloca`s) that need to be passed on the stack are
+        // treated similarly to constants (these may require one temporary register).
+        for (iidx, term_vlocs) in b.term_vars().iter().zip(all_term_vlocs.iter()) {
+            if let IState::StackOff(stack_off) = self.istates[*iidx] {
+                for vloc in term_vlocs.iter() {
+                    if let VarLoc::Stack(to_stack_off) = vloc {
+                        let bitw = self.b.inst_bitw(self.m, *iidx);
+                        be.spill(find_tmp_reg(), RegFill::Zeroed, *to_stack_off, bitw)?;
+                        be.move_stackoff(find_tmp_reg(), stack_off)?;
+                    }
+                }
+            }
+        }
+
         let mut moves = Vec::new();
         for (iidx, term_vlocs) in b.term_vars().iter().zip(all_term_vlocs.iter()) {
             if let Inst::Const(_) = self.b.inst(*iidx) {
@@ -314,7 +345,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                     .iter()
                     .any(|vloc| matches!(vloc, VarLoc::Const(_)))
                 {
-                    todo!();
+                    unsupported!("non-constant variable {iidx:?} passed as a constant");
                 }
                 match vloc {
                     VarLoc::Stack(to_stack_off) => {
@@ -340,7 +371,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                         if is_loop {
                             assert_eq!(self.istates[*iidx], IState::StackOff(*stack_off));
                         } else if self.istates[*iidx] != IState::StackOff(*stack_off) {
-                            todo!();
+                            unsupported!("stack offset {stack_off} of {iidx:?} differs at exit");
                         }
                     }
                     VarLoc::Reg(reg, fill) => {
@@ -358,7 +389,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
             if from_stack_off == to_stack_off {
                 continue;
             }
-            be.move_stack_val_at_term(bitw, from_stack_off, to_stack_off, find_tmp_reg());
+            be.move_stack_val_at_term(bitw, from_stack_off, to_stack_off, find_tmp_reg())?;
         }
 
         Ok(())
@@ -564,7 +595,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
             dst_fill,
         } in ractions.fill_changes.iter()
         {
-            be.arrange_fill(*dst_reg, *src_fill, *bitw, *dst_fill);
+            be.arrange_fill(*dst_reg, *src_fill, *bitw, *dst_fill)?;
         }
 
         for RegSpill { iidxs } in ractions.spills.iter().rev() {
@@ -752,7 +783,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                         } else if cnd_fills.has_undefined() {
                             RegFill::Undefined
                         } else {
-                            todo!();
+                            unsupported!("no suitable fill for output of {iidx:?}");
                         };
                         assert!(AnyOfFill::from_regfill(fill).intersects_with(cnd_fills));
                         *out_fill = RegCnstrFill::from_regfill(fill);
@@ -776,7 +807,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                             rtn_fills.push(out_fill);
                             output_reg = Some(reg);
                         } else {
-                            todo!()
+                            unsupported!("conflicting fills for cast {iidx:?}")
                         }
                     } else if n_out.iidxs(reg).contains(in_iidx) {
                         if n_out.iidxs(reg).len() == 1
@@ -789,7 +820,7 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
                             rtn_fills.push(out_fill);
                             output_reg = Some(reg);
                         } else {
-                            todo!()
+                            unsupported!("conflicting fills for cast {iidx:?}")
                         }
                     } else {
                         n_out.set_fill_iidxs(reg, out_fill, smallvec![iidx]);
@@ -1012,9 +1043,31 @@ impl<'a, AB: HirToAsmBackend> RegAlloc<'a, AB> {
         assert_matches!(self.b.inst(iidx), Inst::Const(_));
         // Constants should never be spilled.
         assert_eq!(self.istates[iidx], IState::None);
+        self.unspill_at_def(be, iidx)
+    }
 
-        // The constant might exist in multiple registers: we turn each into a [RegUnspill] and
-        // defer to [Self::asm_ractions] to choose how to do that unspilling.
+    /// For [Inst::Alloca] instructions only, allocate registers. This function should only be
+    /// called by [hir_to_asm]: backends should not call it.
+    ///
+    /// # Panics
+    ///
+    /// If this function is called on any other kind of instruction.
+    pub(super) fn alloc_alloca(
+        &mut self,
+        be: &mut AB,
+        iidx: InstIdx,
+    ) -> Result<(), CompilationError> {
+        assert_matches!(self.b.inst(iidx), Inst::Alloca(_));
+        // `alloca`s were given their stack slot in [Self::new] and are never spilled.
+        assert_matches!(self.istates[iidx], IState::StackOff(_));
+        self.unspill_at_def(be, iidx)
+    }
+
+    /// At the definition of `iidx`, which must be either a constant or an `alloca`, generate the
+    /// code to put its value into whichever registers it is expected to be in.
+    fn unspill_at_def(&mut self, be: &mut AB, iidx: InstIdx) -> Result<(), CompilationError> {
+        // The value might exist in multiple registers: we turn each into a [RegUnspill] and defer
+        // to [Self::asm_ractions] to choose how to do that unspilling.
         let mut unspills = Vec::new();
         for (reg, rstate) in self.rstates.iter_mut() {
             if rstate.iidxs.contains(&iidx) {
@@ -1518,7 +1571,8 @@ pub(super) trait PeelRegsBuilderT<Reg: RegT> {
 pub(super) trait TestRegIter<Reg: RegT> {
     /// Return a register which is suitable to hold instances of `ty`. The backend has freedom
     /// to interpret this: it might place all types in the same kinds of registers, or it may
-    /// do things like differentiate general-purpose and floating-point registers.
+    /// do things like differentiate general-purpose and floating-point registers. Returns `None`
+    /// if there are no registers left, or if no register can hold instances of `ty`.
     fn next_reg(&mut self, ty: &Ty) -> Option<Reg>;
 }
 
@@ -2103,13 +2157,8 @@ pub(crate) mod test {
             match ty {
                 Ty::Double | Ty::Float => self.fp_regs.next(),
                 Ty::Func(_func_ty) => todo!(),
-                Ty::Int(bitw) => {
-                    if *bitw <= 64 {
-                        self.gp_regs.next()
-                    } else {
-                        todo!()
-                    }
-                }
+                Ty::Int(bitw) if *bitw <= 64 => self.gp_regs.next(),
+                Ty::Int(_) => None,
                 Ty::Ptr(addrspace) => {
                     assert_eq!(*addrspace, 0);
                     self.gp_regs.next()
@@ -2242,10 +2291,11 @@ pub(crate) mod test {
             src_fill: RegFill,
             dst_bitw: u32,
             dst_fill: RegFill,
-        ) {
+        ) -> Result<(), CompilationError> {
             self.ra_log.push(format!(
                 "arrange_fill {reg:?} from={src_fill:?} dst_bitw={dst_bitw} to={dst_fill:?}"
             ));
+            Ok(())
         }
 
         fn copy_reg_with_fill(
@@ -2264,6 +2314,10 @@ pub(crate) mod test {
             stack_off + (bitw / 8).next_multiple_of(8)
         }
 
+        fn align_alloca(stack_off: u32, bytew: u32, align: u32) -> u32 {
+            (stack_off + bytew).next_multiple_of(align)
+        }
+
         fn spill(
             &mut self,
             reg: Self::Reg,
@@ -2296,10 +2350,11 @@ pub(crate) mod test {
             src_stack_off: u32,
             dst_stack_off: u32,
             tmp_reg: Self::Reg,
-        ) {
+        ) -> Result<(), CompilationError> {
             self.ra_log.push(format!(
                 "move_stack_val bitw={bitw} src_stack_off={src_stack_off} dst_stack_off={dst_stack_off} tmp_reg={tmp_reg:?}"
             ));
+            Ok(())
         }
 
         fn controlpoint_loop_end(&mut self) -> Result<Self::Label, CompilationError> {
@@ -2590,6 +2645,10 @@ pub(crate) mod test {
             tracecompilation_errors: 0,
             #[cfg(feature = "ykd")]
             debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
         }));
 
         let be = TestHirToAsm::new(&m);
@@ -2642,6 +2701,10 @@ pub(crate) mod test {
             tracecompilation_errors: 0,
             #[cfg(feature = "ykd")]
             debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
         }));
 
         let be = TestHirToAsm::new(&m);
@@ -164,6 +164,42 @@ pub(super) extern "C" fn __yk_j2_deopt(faddr: *mut u8, trid: u64, gid: u32) -> !
 
     let aot_smaps = AOT_STACKMAPS.as_ref().unwrap();
 
+    // Pointers into trace-local `alloca`s may be live in a different frame to the one which the
+    // `alloca` is materialised in, so before reconstructing any frame we work out where each
+    // materialised `alloca` will end up on the real stack.
+    let mut allocas = Vec::new();
+    {
+        let mut frame_addr = faddr;
+        let mut vars_off = 0;
+        for frame in &guard.deopt_frames {
+            let (smap, _) = aot_smaps.get(frame.pc_statepoint.smapidx);
+            let nlives = frame.pc_statepoint.lives.len();
+            for deopt_var in &guard.deopt_vars[vars_off..vars_off + nlives] {
+                let Some(bytew) = deopt_var.alloca_bytew else {
+                    continue;
+                };
+                let Some(VarLoc::StackOff(from_off)) = deopt_var.fromvlocs.iter().next() else {
+                    panic!()
+                };
+                if let Some(VarLoc::StackOff(to_off)) = deopt_var
+                    .tovlocs
+                    .iter()
+                    .find(|x| matches!(x, VarLoc::StackOff(_)))
+                {
+                    allocas.push(AllocaReloc {
+                        from: unsafe { faddr.byte_sub(usize::try_from(*from_off).unwrap()) }
+                            as usize,
+                        to: unsafe { frame_addr.byte_sub(usize::try_from(*to_off).unwrap()) }
+                            as usize,
+                        bytew: usize::try_from(bytew).unwrap(),
+                    });
+                }
+            }
+            vars_off += nlives;
+            frame_addr = unsafe { frame_addr.byte_sub(usize::try_from(smap.size).unwrap() + 8) };
+        }
+    }
+
     // We write to the buffer backwards, starting at `stkptr + stklen`. Eventually the contents of
     // this buffer (viewed from low to high address) will be:
     //
@@ -206,6 +242,7 @@ pub(super) extern "C" fn __yk_j2_deopt(faddr: *mut u8, trid: u64, gid: u32) -> !
             &guard.deopt_vars[0..frame.pc_statepoint.lives.len()],
             &mut gp_regs,
             &mut fp_regs,
+            &allocas,
             faddr,
             faddr,
         );
@@ -281,6 +318,7 @@ pub(super) extern "C" fn __yk_j2_deopt(faddr: *mut u8, trid: u64, gid: u32) -> !
             &guard.deopt_vars[deopt_vars_off..deopt_vars_off + frame.pc_statepoint.lives.len()],
             &mut gp_regs,
             &mut fp_regs,
+            &allocas,
             faddr,
             rbp,
         );
@@ -329,12 +367,32 @@ pub(super) extern "C" fn __yk_j2_deopt(faddr: *mut u8, trid: u64, gid: u32) -> !
     unsafe { __yk_j2_replace_stack(fdst, stkptr.byte_add(stklen).byte_sub(deoptlen), deoptlen) };
 }
 
+/// A trace-local `alloca` of `bytew` bytes at address `from`, which deopt materialises at address
+/// `to`.
+struct AllocaReloc {
+    from: usize,
+    to: usize,
+    bytew: usize,
+}
+
+/// Relocate `ptr`, which points into (or one past the end of) one of the trace-local `alloca`s in
+/// `allocas`, to point into the equivalent materialised `alloca`.
+fn relocate(allocas: &[AllocaReloc], ptr: u64) -> u64 {
+    let ptr = usize::try_from(ptr).unwrap();
+    let reloc = allocas
+        .iter()
+        .find(|x| ptr >= x.from && ptr <= x.from + x.bytew)
+        .unwrap();
+    u64::try_from(reloc.to + (ptr - reloc.from)).unwrap()
+}
+
 /// Reconstruct the stack for a frame, reading from the control point frame `cpfaddr` and writing
 /// to `toaddr`. Note: these two addresses can be the same.
 fn reconstruct(
     varlocs: &[DeoptVar<Reg>],
     gp_regs: &mut [u64; DeoptGpReg::COUNT],
     fp_regs: &mut [u64; DeoptFpReg::COUNT],
+    allocas: &[AllocaReloc],
     srcaddr: *const u8,
     tgtaddr: *mut u8,
 ) {
@@ -342,18 +400,44 @@ fn reconstruct(
         bitw,
         fromvlocs,
         tovlocs,
+        alloca_bytew,
+        alloca_ptr,
     } in varlocs.iter().filter(|x| !x.tovlocs.is_empty())
     {
         // FIXME: For now, we only deal with 1 fromvloc.
         assert_eq!(fromvlocs.len(), 1, "{fromvlocs:?}");
         let fromvloc = fromvlocs.iter().next().unwrap();
+        if let Some(alloca_bytew) = alloca_bytew {
+            // Materialise a trace-local `alloca` by copying its contents into the reconstructed
+            // frame's equivalent `alloca`.
+            let VarLoc::StackOff(from_off) = fromvloc else {
+                panic!("{fromvloc:?}")
+            };
+            for vloc in tovlocs.iter() {
+                let VarLoc::StackOff(to_off) = vloc else {
+                    continue;
+                };
+                unsafe {
+                    std::ptr::copy(
+                        srcaddr.byte_sub(usize::try_from(*from_off).unwrap()),
+                        tgtaddr.byte_sub(usize::try_from(*to_off).unwrap()),
+                        usize::try_from(*alloca_bytew).unwrap(),
+                    );
+                }
+            }
+            if !*alloca_ptr {
+                continue;
+            }
+        }
         match bitw {
             33..=64 => {
                 let v = match fromvloc {
                     VarLoc::Stack(off) => unsafe {
                         (srcaddr.byte_sub(usize::try_from(*off).unwrap()) as *const u64).read()
                     },
-                    VarLoc::StackOff(_) => todo!(),
+                    VarLoc::StackOff(off) => {
+                        unsafe { srcaddr.byte_sub(usize::try_from(*off).unwrap()) }.addr() as u64
+                    }
                     VarLoc::Reg(_, _) => todo!(),
                     VarLoc::Const(kind) => match kind {
                         ConstKind::Double(x) => x.to_bits(),
@@ -362,6 +446,7 @@ fn reconstruct(
                         ConstKind::Ptr(x) => u64::try_from(*x).unwrap(),
                     },
                 };
+                let v = if *alloca_ptr { relocate(allocas, v) } else { v };
 
                 for vloc in tovlocs.iter() {
                     match vloc {
@@ -476,7 +561,9 @@ fn reconstruct(
                     }
                 }
             }
-            x => todo!("{x}"),
+            // Wider values are rejected with a [crate::compile::CompilationError] when the guard
+            // is compiled.
+            x => unreachable!("{x}"),
         }
     }
 }
@@ -59,7 +59,13 @@ use array_concat::concat_arrays;
 use iced_x86::{Code, Instruction as IcedInst, MemoryOperand, Register as IcedReg};
 use index_type::{IndexType, vec::TypedVec};
 use smallvec::SmallVec;
-use std::{assert_matches, collections::HashMap, debug_assert_matches, ffi::c_void, sync::Arc};
+use std::{
+    assert_matches,
+    collections::HashMap,
+    debug_assert_matches,
+    ffi::c_void,
+    sync::{Arc, atomic::AtomicI64},
+};
 
 pub(in crate::compile::j2) struct X64HirToAsm<'a> {
     m: &'a Mod<Reg>,
@@ -138,7 +144,7 @@ impl<'a> X64HirToAsm<'a> {
                 ConstKind::Double(_) => unreachable!(),
                 ConstKind::Float(_) => unreachable!(),
                 ConstKind::Int(x) => x.to_zero_ext_u8().map(u32::from),
-                ConstKind::Ptr(_) => todo!(),
+                ConstKind::Ptr(x) => u8::try_from(*x).ok().map(u32::from),
             }
         } else {
             None
@@ -172,7 +178,7 @@ impl<'a> X64HirToAsm<'a> {
                 ConstKind::Int(x) => match bitw {
                     1..=32 => x.to_zero_ext_u32().map(|x| x.cast_signed()),
                     64 => x.to_sign_ext_i32(),
-                    x => todo!("{x}"),
+                    _ => None,
                 },
                 ConstKind::Ptr(x) => match bitw {
                     64 => {
@@ -350,6 +356,52 @@ impl<'a> X64HirToAsm<'a> {
         Ok(())
     }
 
+    /// Generate code for ceil/floor/ftrunc: `mode` is the rounding mode immediate passed to
+    /// `roundsd` / `roundss`.
+    fn i_fround(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        iidx: InstIdx,
+        tyidx: TyIdx,
+        val: InstIdx,
+        mode: i32,
+    ) -> Result<(), CompilationError> {
+        let [valr, outr] = ra.alloc(
+            self,
+            iidx,
+            [
+                RegCnstr::Input {
+                    in_iidx: val,
+                    in_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                    clobber: false,
+                },
+                RegCnstr::Output {
+                    out_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                    can_be_same_as_input: true,
+                },
+            ],
+        )?;
+        self.asm.push_inst(match self.m.ty(tyidx) {
+            Ty::Double => IcedInst::with3(
+                Code::Roundsd_xmm_xmmm64_imm8,
+                outr.to_xmm(),
+                valr.to_xmm(),
+                mode,
+            ),
+            Ty::Float => IcedInst::with3(
+                Code::Roundss_xmm_xmmm32_imm8,
+                outr.to_xmm(),
+                valr.to_xmm(),
+                mode,
+            ),
+            _ => panic!(),
+        });
+
+        Ok(())
+    }
+
     /// Generate code for a guard whose `cond` directly refers to an [ICmp].
     ///
     /// # Panics
@@ -449,7 +501,7 @@ impl<'a> X64HirToAsm<'a> {
             let label = self.asm.mk_label();
             self.asm
                 .push_reloc(IcedInst::with_branch(c, 0), RelocKind::NearWithLabel(label));
-            self.i_icmp_const(bitw, rmop, imm);
+            self.i_icmp_const(bitw, rmop, imm)?;
             Ok(label)
         } else {
             let (rmop, rhsr) =
@@ -499,19 +551,176 @@ impl<'a> X64HirToAsm<'a> {
             let label = self.asm.mk_label();
             self.asm
                 .push_reloc(IcedInst::with_branch(c, 0), RelocKind::NearWithLabel(label));
-            self.i_icmp_reg(bitw, rmop, rhsr);
+            self.i_icmp_reg(bitw, rmop, rhsr)?;
             Ok(label)
         }
     }
 
-    fn i_icmp_const(&mut self, bitw: u32, rmop: RegOrMemOp, rhs: i32) {
+    /// Generate code for a guard whose `cond` directly refers to an [Overflow]: the overflow
+    /// status is branched on directly from the flags rather than being materialised.
+    ///
+    /// # Panics
+    ///
+    /// If `cond` is not an [Overflow].
+    fn i_overflow_guard(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        Guard { expect, cond, .. }: &Guard,
+        exit_vars: &[InstIdx],
+    ) -> Result<LabelIdx, CompilationError> {
+        let Inst::Overflow(Overflow { op, lhs, rhs }) = b.inst(*cond) else {
+            panic!()
+        };
+
+        let bitw = b.inst_bitw(self.m, *lhs);
+        let [lhsr, rhsr, _] = self.overflow_alloc(
+            ra,
+            iidx,
+            *op,
+            *lhs,
+            *rhs,
+            RegCnstr::KeepAlive { iidxs: exit_vars },
+        )?;
+
+        // Signed overflow is reported in OF, unsigned overflow in CF.
+        let c = match (op.is_signed(), *expect) {
+            (true, true) => Code::Jno_rel32_64,
+            (true, false) => Code::Jo_rel32_64,
+            (false, true) => Code::Jae_rel32_64,
+            (false, false) => Code::Jb_rel32_64,
+        };
+        let label = self.asm.mk_label();
+        self.asm
+            .push_reloc(IcedInst::with_branch(c, 0), RelocKind::NearWithLabel(label));
+        self.overflow_arith(*op, bitw, lhsr, rhsr)?;
+        Ok(label)
+    }
+
+    /// Allocate registers for the operands of an [Overflow] instruction in the form
+    /// [Self::overflow_arith] requires, along with `extra`. Returns the registers for `lhs`, `rhs`,
+    /// and `extra` respectively.
+    fn overflow_alloc(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        iidx: InstIdx,
+        op: OverflowOp,
+        lhs: InstIdx,
+        rhs: InstIdx,
+        extra: RegCnstr<Reg>,
+    ) -> Result<[Reg; 3], CompilationError> {
+        let rhs_cnstr = RegCnstr::Input {
+            in_iidx: rhs,
+            in_fill: RegCnstrFill::Undefined,
+            regs: &NORMAL_GP_REGS,
+            clobber: false,
+        };
+        match op {
+            OverflowOp::SMul | OverflowOp::UMul => {
+                let [lhsr, rhsr, extrar, _] = ra.alloc(
+                    self,
+                    iidx,
+                    [
+                        RegCnstr::Input {
+                            in_iidx: lhs,
+                            in_fill: RegCnstrFill::Undefined,
+                            regs: &[Reg::RAX],
+                            clobber: true,
+                        },
+                        rhs_cnstr,
+                        extra,
+                        RegCnstr::Clobber { reg: Reg::RDX },
+                    ],
+                )?;
+                Ok([lhsr, rhsr, extrar])
+            }
+            _ => ra.alloc(
+                self,
+                iidx,
+                [
+                    RegCnstr::Input {
+                        in_iidx: lhs,
+                        in_fill: RegCnstrFill::Undefined,
+                        regs: &NORMAL_GP_REGS,
+                        clobber: true,
+                    },
+                    rhs_cnstr,
+                    extra,
+                ],
+            ),
+        }
+    }
+
+    /// Emit the arithmetic for an [Overflow] instruction, clobbering `lhsr` (which must be `RAX`
+    /// for multiplication, in which case `RDX` is also clobbered) and setting OF (for signed
+    /// operations) or CF (for unsigned operations) iff the operation overflows.
+    fn overflow_arith(
+        &mut self,
+        op: OverflowOp,
+        bitw: u32,
+        lhsr: Reg,
+        rhsr: Reg,
+    ) -> Result<(), CompilationError> {
+        let inst = match (op, bitw) {
+            (OverflowOp::SAdd | OverflowOp::UAdd, 8) => {
+                IcedInst::with2(Code::Add_rm8_r8, lhsr.to_reg8(), rhsr.to_reg8())
+            }
+            (OverflowOp::SAdd | OverflowOp::UAdd, 16) => {
+                IcedInst::with2(Code::Add_rm16_r16, lhsr.to_reg16(), rhsr.to_reg16())
+            }
+            (OverflowOp::SAdd | OverflowOp::UAdd, 32) => {
+                IcedInst::with2(Code::Add_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32())
+            }
+            (OverflowOp::SAdd | OverflowOp::UAdd, 64) => {
+                IcedInst::with2(Code::Add_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64())
+            }
+            (OverflowOp::SSub | OverflowOp::USub, 8) => {
+                IcedInst::with2(Code::Sub_rm8_r8, lhsr.to_reg8(), rhsr.to_reg8())
+            }
+            (OverflowOp::SSub | OverflowOp::USub, 16) => {
+                IcedInst::with2(Code::Sub_rm16_r16, lhsr.to_reg16(), rhsr.to_reg16())
+            }
+            (OverflowOp::SSub | OverflowOp::USub, 32) => {
+                IcedInst::with2(Code::Sub_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32())
+            }
+            (OverflowOp::SSub | OverflowOp::USub, 64) => {
+                IcedInst::with2(Code::Sub_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64())
+            }
+            (OverflowOp::SMul | OverflowOp::UMul, 8 | 16 | 32 | 64) => {
+                assert_eq!(lhsr, Reg::RAX);
+                // The one operand forms of `imul` and `mul` set OF and CF iff the upper half of
+                // the double-width result is not merely an extension of the lower half.
+                match (op, bitw) {
+                    (OverflowOp::SMul, 8) => IcedInst::with1(Code::Imul_rm8, rhsr.to_reg8()),
+                    (OverflowOp::SMul, 16) => IcedInst::with1(Code::Imul_rm16, rhsr.to_reg16()),
+                    (OverflowOp::SMul, 32) => IcedInst::with1(Code::Imul_rm32, rhsr.to_reg32()),
+                    (OverflowOp::SMul, 64) => IcedInst::with1(Code::Imul_rm64, rhsr.to_reg64()),
+                    (_, 8) => IcedInst::with1(Code::Mul_rm8, rhsr.to_reg8()),
+                    (_, 16) => IcedInst::with1(Code::Mul_rm16, rhsr.to_reg16()),
+                    (_, 32) => IcedInst::with1(Code::Mul_rm32, rhsr.to_reg32()),
+                    (_, _) => IcedInst::with1(Code::Mul_rm64, rhsr.to_reg64()),
+                }
+            }
+            (_, x) => unsupported!("{x}-bit overflow"),
+        };
+        self.asm.push_inst(inst);
+        Ok(())
+    }
+
+    fn i_icmp_const(
+        &mut self,
+        bitw: u32,
+        rmop: RegOrMemOp,
+        rhs: i32,
+    ) -> Result<(), CompilationError> {
         if rhs == 0
             && let RegOrMemOp::Reg(reg) = rmop
         {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Test_rm32_r32, reg.to_reg32(), reg.to_reg32()),
                 64 => IcedInst::with2(Code::Test_rm64_r64, reg.to_reg64(), reg.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             });
         } else {
             self.asm.push_inst(match bitw {
@@ -555,17 +764,23 @@ impl<'a> X64HirToAsm<'a> {
                         rhs,
                     ),
                 },
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             });
         }
+        Ok(())
     }
 
-    fn i_icmp_reg(&mut self, bitw: u32, rmop: RegOrMemOp, rhsr: Reg) {
+    fn i_icmp_reg(
+        &mut self,
+        bitw: u32,
+        rmop: RegOrMemOp,
+        rhsr: Reg,
+    ) -> Result<(), CompilationError> {
         self.asm.push_inst(match rmop {
             RegOrMemOp::Reg(lhsr) => match bitw {
                 1..=32 => IcedInst::with2(Code::Cmp_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::Cmp_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             },
             RegOrMemOp::MemOp(lhsr, disp) => match bitw {
                 32 => IcedInst::with2(
@@ -578,9 +793,10 @@ impl<'a> X64HirToAsm<'a> {
                     MemoryOperand::with_base_displ(lhsr.to_reg64(), disp),
                     rhsr.to_reg64(),
                 ),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             },
         });
+        Ok(())
     }
 
     fn _i_load_float(
@@ -696,7 +912,7 @@ impl<'a> X64HirToAsm<'a> {
                     }
                 }
                 64 => IcedInst::with2(Code::Mov_r64_rm64, outr.to_reg64(), memop),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit load"),
             },
             Ty::Ptr(_) => IcedInst::with2(Code::Mov_r64_rm64, outr.to_reg64(), memop),
             _ => unreachable!(),
@@ -814,7 +1030,7 @@ impl<'a> X64HirToAsm<'a> {
                     }
                     32 => IcedInst::with2(Code::Add_rm32_imm32, memop, imm),
                     64 => IcedInst::with2(Code::Add_rm64_imm32, memop, imm),
-                    x => todo!("{x}"),
+                    x => unsupported!("{x}-bit store"),
                 });
                 return Ok(());
             }
@@ -842,7 +1058,7 @@ impl<'a> X64HirToAsm<'a> {
                 16 => IcedInst::with2(Code::Mov_rm16_imm16, memop, imm),
                 32 => IcedInst::with2(Code::Mov_rm32_imm32, memop, imm),
                 64 => IcedInst::with2(Code::Mov_rm64_imm32, memop, imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit store"),
             });
         } else {
             let [ptrr, valr] = ra.alloc(
@@ -874,7 +1090,7 @@ impl<'a> X64HirToAsm<'a> {
                 16 => IcedInst::with2(Code::Mov_rm16_r16, memop, valr.to_reg16()),
                 32 => IcedInst::with2(Code::Mov_rm32_r32, memop, valr.to_reg32()),
                 64 => IcedInst::with2(Code::Mov_rm64_r64, memop, valr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit store"),
             });
         }
 
@@ -893,7 +1109,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
     fn smp_to_vloc(
         smp_locs: &SmallVec<[yksmp::Location; 1]>,
         reg_fill: RegFill,
-    ) -> VarLocs<Self::Reg> {
+    ) -> Result<VarLocs<Self::Reg>, CompilationError> {
         use yksmp::Location as L;
         assert_eq!(smp_locs.len(), 1, "Multi-locations not yet supported");
         match &smp_locs[0] {
@@ -910,19 +1126,17 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                         vlocs.push(VarLoc::Stack(u32::from(x.unsigned_abs())));
                     }
                 }
-                vlocs
+                Ok(vlocs)
             }
             L::Direct(6, off, _sz) => {
                 assert!(*off <= 0);
-                varlocs![VarLoc::StackOff(off.unsigned_abs())]
+                Ok(varlocs![VarLoc::StackOff(off.unsigned_abs())])
             }
             L::Indirect(6, off, _sz) => {
                 assert!(*off <= 0);
-                varlocs![VarLoc::Stack(off.unsigned_abs())]
-            }
-            x => {
-                todo!("{:?}", x);
+                Ok(varlocs![VarLoc::Stack(off.unsigned_abs())])
             }
+            x => unsupported!("stackmap location {x:?}"),
         }
     }
 
@@ -958,9 +1172,8 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
     fn iter_possible_regs(&self, b: &Block, iidx: InstIdx) -> impl Iterator<Item = Self::Reg> {
         match b.inst_ty(self.m, iidx) {
             Ty::Double | Ty::Float => ALL_XMM_REGS.iter().cloned(),
-            Ty::Func(_func_ty) => todo!(),
             Ty::Int(_) | Ty::Ptr(_) => NORMAL_GP_REGS.iter().cloned(),
-            Ty::Void => todo!(),
+            Ty::Func(_) | Ty::Void => unreachable!(),
         }
     }
 
@@ -1014,6 +1227,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                 Inst::Add(Add { lhs, .. })
                 | Inst::And(And { lhs, .. })
                 | Inst::AShr(AShr { lhs, .. })
+                | Inst::CopySign(CopySign { mag: lhs, .. })
                 | Inst::FAdd(FAdd { lhs, .. })
                 | Inst::FDiv(FDiv { lhs, .. })
                 | Inst::FMul(FMul { lhs, .. })
@@ -1053,7 +1267,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     let fty = self.m.func_ty(*func_tyidx);
                     self.reg_hints.push(match self.m.ty(fty.rtn_tyidx) {
                         Ty::Double | Ty::Float => Reg::XMM0,
-                        Ty::Func(_) => todo!(),
+                        Ty::Func(_) => unreachable!(),
                         Ty::Int(_) | Ty::Ptr(_) => Reg::RAX,
                         Ty::Void => Reg::Undefined,
                     });
@@ -1156,7 +1370,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                                 if let Some(x) = x.to_zero_ext_u32() {
                                     IcedInst::with2(Code::Mov_r32_imm32, reg.to_reg32(), x)
                                 } else {
-                                    todo!();
+                                    internal_error!("{tgt_bitw}-bit constant {x} too large");
                                 }
                             }
                             64 => {
@@ -1165,10 +1379,10 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                                 } else if let Some(x) = x.to_zero_ext_u64() {
                                     IcedInst::with2(Code::Mov_r64_imm64, reg.to_reg64(), x)
                                 } else {
-                                    todo!();
+                                    internal_error!("{tgt_bitw}-bit constant {x} too large");
                                 }
                             }
-                            x => todo!("{x}"),
+                            x => unsupported!("{x}-bit constant"),
                         }
                     } else {
                         assert_eq!(tgt_fill, RegFill::Signed);
@@ -1177,7 +1391,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                         } else if let Some(x) = x.to_sign_ext_i64() {
                             IcedInst::with2(Code::Mov_r64_imm64, reg.to_reg64(), x)
                         } else {
-                            todo!();
+                            internal_error!("{tgt_bitw}-bit constant {x} too large");
                         }
                     },
                 );
@@ -1204,7 +1418,13 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         Ok(())
     }
 
-    fn arrange_fill(&mut self, reg: Reg, src_fill: RegFill, dst_bitw: u32, dst_fill: RegFill) {
+    fn arrange_fill(
+        &mut self,
+        reg: Reg,
+        src_fill: RegFill,
+        dst_bitw: u32,
+        dst_fill: RegFill,
+    ) -> Result<(), CompilationError> {
         match (src_fill, dst_fill) {
             (RegFill::Undefined, RegFill::Undefined) => (),
             (RegFill::Undefined | RegFill::Signed, RegFill::Zeroed) => match dst_bitw {
@@ -1223,7 +1443,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     ));
                 }
                 64 => (),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit fill change"),
             },
             (RegFill::Zeroed, RegFill::Undefined) => (),
             (RegFill::Zeroed, RegFill::Zeroed) => (),
@@ -1250,11 +1470,12 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     reg.to_reg32(),
                 )),
                 64 => (),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit fill change"),
             },
             (RegFill::Signed, RegFill::Undefined) => (),
             (RegFill::Signed, RegFill::Signed) => (),
         }
+        Ok(())
     }
 
     fn copy_reg_with_fill(
@@ -1280,7 +1501,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                 src_reg.to_reg32(),
             ));
         } else {
-            self.arrange_fill(dst_reg, src_fill, dst_bitw, dst_fill);
+            self.arrange_fill(dst_reg, src_fill, dst_bitw, dst_fill)?;
             if src_reg.is_gp() {
                 self.asm.push_inst(IcedInst::with2(
                     Code::Mov_r64_rm64,
@@ -1305,10 +1526,16 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             9..=16 => (stack_off + 2).next_multiple_of(2),
             17..=32 => (stack_off + 4).next_multiple_of(4),
             33..=64 => (stack_off + 8).next_multiple_of(8),
-            x => todo!("{x}"),
+            x => unreachable!("{x}-bit values are rejected by aot_to_hir"),
         }
     }
 
+    fn align_alloca(stack_off: u32, bytew: u32, align: u32) -> u32 {
+        // RBP is 16-byte aligned, so any offset that is a multiple of `align` gives an address
+        // that is also a multiple of `align`.
+        (stack_off + bytew).next_multiple_of(align)
+    }
+
     fn spill(
         &mut self,
         reg: Reg,
@@ -1349,14 +1576,14 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     MemoryOperand::with_base_displ(IcedReg::RBP, -i64::from(stack_off)),
                     reg.to_reg64(),
                 )),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit spill"),
             }
         } else {
             assert!(reg.is_fp());
             self.asm.push_inst(match bitw {
                 64 => IcedInst::with2(Code::Movsd_xmmm64_xmm, mop, reg.to_xmm()),
                 32 => IcedInst::with2(Code::Movss_xmmm32_xmm, mop, reg.to_xmm()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit spill"),
             })
         }
         Ok(())
@@ -1378,7 +1605,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     }
                     RegFill::Signed => match bitw {
                         8 => IcedInst::with2(Code::Movsx_r64_rm8, reg.to_reg64(), memop),
-                        x => todo!("{x}"),
+                        x => unsupported!("{x}-bit unspill"),
                     },
                 },
                 16 => match out_fill {
@@ -1404,14 +1631,14 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                         IcedInst::with2(Code::Mov_r64_rm64, reg.to_reg64(), memop)
                     }
                 },
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit unspill"),
             });
         } else {
             assert!(reg.is_fp());
             self.asm.push_inst(match bitw {
                 64 => IcedInst::with2(Code::Movsd_xmm_xmmm64, reg.to_xmm(), memop),
                 32 => IcedInst::with2(Code::Movss_xmm_xmmm32, reg.to_xmm(), memop),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit unspill"),
             });
         }
         Ok(())
@@ -1423,7 +1650,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         src_stack_off: u32,
         dst_stack_off: u32,
         tmpr: Self::Reg,
-    ) {
+    ) -> Result<(), CompilationError> {
         match bitw {
             64 => {
                 self.asm.push_inst(IcedInst::with2(
@@ -1449,8 +1676,9 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     MemoryOperand::with_base_displ(IcedReg::RBP, -i64::from(src_stack_off)),
                 ));
             }
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit stack move"),
         }
+        Ok(())
     }
 
     fn controlpoint_coupler_or_return_start(
@@ -1487,13 +1715,63 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         self.asm.mk_label()
     }
 
-    fn controlpoint_loop_end(&mut self) -> Result<Self::Label, CompilationError> {
+    fn controlpoint_loop_end(
+        &mut self,
+        tier_up: Option<&AtomicI64>,
+    ) -> Result<(Self::Label, Self::Label, Self::Label), CompilationError> {
         let label = self.asm.mk_label();
+        let backedge_label = self.asm.mk_label();
+        let exit_label = self.asm.mk_label();
+        // `jmp rel32` is 5 bytes long, of which the displacement is the last 4 bytes. When the
+        // back-edge is patched, the displacement is overwritten while other threads may be
+        // executing it, so we align it to 4 bytes, which guarantees the write is atomic. The
+        // padding after the `jmp` is never executed.
+        let off = self.asm.buf_end_off() - 5;
+        self.asm.push_nops((off + 1) % 4);
         self.asm.push_reloc(
             IcedInst::with_branch(Code::Jmp_rel32_64, 0),
             RelocKind::NearWithLabel(label),
         );
-        Ok(label)
+        assert_eq!((self.asm.buf_end_off() + 1) % 4, 0);
+        self.asm.attach_label(backedge_label);
+        if let Some(tier_up) = tier_up {
+            // Before each back-edge, we emit:
+            //
+            // ```
+            // push rax
+            // mov rax, <tier_up>
+            // lock sub qword [rax], 1
+            // pop rax
+            // jz <exit_label>
+            // ```
+            //
+            // so that the loop is left (exactly once) when the counter reaches zero.
+            self.asm.push_reloc(
+                IcedInst::with_branch(Code::Je_rel32_64, 0),
+                RelocKind::NearWithLabel(exit_label.clone()),
+            );
+            self.asm
+                .push_inst(IcedInst::with1(Code::Pop_r64, IcedReg::RAX));
+            self.asm.push_inst(
+                IcedInst::with2(
+                    Code::Sub_rm64_imm8,
+                    MemoryOperand::with_base(IcedReg::RAX),
+                    1,
+                )
+                .map(|mut x| {
+                    x.set_has_lock_prefix(true);
+                    x
+                }),
+            );
+            self.asm.push_inst(IcedInst::with2(
+                Code::Mov_r64_imm64,
+                IcedReg::RAX,
+                u64::try_from(tier_up.as_ptr().addr()).unwrap(),
+            ));
+            self.asm
+                .push_inst(IcedInst::with1(Code::Push_r64, IcedReg::RAX));
+        }
+        Ok((label, backedge_label, exit_label))
     }
 
     fn star_return_end(
@@ -1553,9 +1831,8 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
 
         let callr = if let Some(ret_val) = ret_val {
             match b.inst_ty(self.m, ret_val) {
-                Ty::Double => todo!(),
-                Ty::Float => todo!(),
-                Ty::Func(_) => todo!(),
+                Ty::Double | Ty::Float => unsupported!("floating point return value"),
+                Ty::Func(_) => unreachable!(),
                 Ty::Int(_) | Ty::Ptr(_) => {
                     let bitw = b.inst_bitw(self.m, ret_val);
                     assert!(bitw <= 64);
@@ -1808,7 +2085,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Add_rm32_imm32, lhsr.to_reg32(), imm),
                 64 => IcedInst::with2(Code::Add_rm64_imm32, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit add"),
             });
         } else {
             let [lhsr, rhsr] = ra.alloc(
@@ -1832,7 +2109,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Add_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::Add_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit add"),
             });
         }
 
@@ -1862,7 +2139,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::And_rm32_imm32, lhsr.to_reg32(), imm),
                 64 => IcedInst::with2(Code::And_rm64_imm32, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit and"),
             });
         } else if bitw == 64
             && let Inst::Const(Const {
@@ -1913,7 +2190,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::And_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::And_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit and"),
             });
         }
 
@@ -2041,7 +2318,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     srcr.to_reg64(),
                 ));
             }
-            (x, y) => todo!("{x:?} {y:?}"),
+            (x, y) => unsupported!("bitcast from {x:?} to {y:?}"),
         }
         Ok(())
     }
@@ -2156,7 +2433,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     };
                     fp_args_off += 1;
                 }
-                Ty::Func(_) => todo!(),
+                Ty::Func(_) => unreachable!(),
                 Ty::Int(_) | Ty::Ptr(_) => {
                     let in_fill = match arg_ty {
                         Ty::Double | Ty::Float | Ty::Func(_) | Ty::Void => unreachable!(),
@@ -2226,7 +2503,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     }
                 }
             }
-            Ty::Func(_func_ty) => todo!(),
+            Ty::Func(_) => unreachable!(),
             Ty::Int(_) | Ty::Ptr(_) => {
                 debug_assert_matches!(gp_cnstrs[RAX_OFF], RegCnstr::Clobber { .. });
                 if fn_addr.is_none() {
@@ -2329,6 +2606,115 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         Ok(())
     }
 
+    fn i_ceil(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        _b: &Block,
+        iidx: InstIdx,
+        Ceil { tyidx, val }: &Ceil,
+    ) -> Result<(), CompilationError> {
+        self.i_fround(ra, iidx, *tyidx, *val, 2)
+    }
+
+    fn i_copysign(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        _b: &Block,
+        iidx: InstIdx,
+        CopySign { tyidx, mag, sign }: &CopySign,
+    ) -> Result<(), CompilationError> {
+        if mag == sign {
+            // `copysign(x, x) == x`. The optimiser normally removes such instructions, but if it
+            // hasn't, we only need the output to be in the same register as the input.
+            ra.alloc(
+                self,
+                iidx,
+                [RegCnstr::InputOutput {
+                    in_iidx: *mag,
+                    in_fill: RegCnstrFill::Undefined,
+                    out_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                }],
+            )?;
+            return Ok(());
+        }
+
+        let [outr, signr, tmpr] = ra.alloc(
+            self,
+            iidx,
+            [
+                RegCnstr::InputOutput {
+                    in_iidx: *mag,
+                    in_fill: RegCnstrFill::Undefined,
+                    out_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                },
+                RegCnstr::Input {
+                    in_iidx: *sign,
+                    in_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                    clobber: false,
+                },
+                RegCnstr::Temp {
+                    regs: &ALL_XMM_REGS,
+                },
+            ],
+        )?;
+        // We generate code (in execution order) of the form:
+        //
+        // ```
+        // movsd tmpr, [abs_mask]
+        // andpd outr, tmpr
+        // movsd tmpr, [sign_mask]
+        // andpd tmpr, signr
+        // orpd outr, tmpr
+        // ```
+        let (mov_code, and_code, or_code, abs_mask, sign_mask): (_, _, _, &[u8], &[u8]) =
+            match self.m.ty(*tyidx) {
+                Ty::Double => (
+                    Code::Movsd_xmm_xmmm64,
+                    Code::Andpd_xmm_xmmm128,
+                    Code::Orpd_xmm_xmmm128,
+                    &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F],
+                    &[0, 0, 0, 0, 0, 0, 0, 0x80],
+                ),
+                Ty::Float => (
+                    Code::Movss_xmm_xmmm32,
+                    Code::Andps_xmm_xmmm128,
+                    Code::Orps_xmm_xmmm128,
+                    &[0xFF, 0xFF, 0xFF, 0x7F],
+                    &[0, 0, 0, 0x80],
+                ),
+                _ => panic!(),
+            };
+        self.asm
+            .push_inst(IcedInst::with2(or_code, outr.to_xmm(), tmpr.to_xmm()));
+        self.asm
+            .push_inst(IcedInst::with2(and_code, tmpr.to_xmm(), signr.to_xmm()));
+        let lidx = self.push_data(16, sign_mask);
+        self.asm.push_reloc(
+            IcedInst::with2(
+                mov_code,
+                tmpr.to_xmm(),
+                MemoryOperand::with_base_displ(IcedReg::RIP, 0),
+            ),
+            RelocKind::NearWithLabel(lidx),
+        );
+        self.asm
+            .push_inst(IcedInst::with2(and_code, outr.to_xmm(), tmpr.to_xmm()));
+        let lidx = self.push_data(16, abs_mask);
+        self.asm.push_reloc(
+            IcedInst::with2(
+                mov_code,
+                tmpr.to_xmm(),
+                MemoryOperand::with_base_displ(IcedReg::RIP, 0),
+            ),
+            RelocKind::NearWithLabel(lidx),
+        );
+
+        Ok(())
+    }
+
     fn i_ctpop(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -2358,7 +2744,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         self.asm.push_inst(match bitw {
             32 => IcedInst::with2(Code::Popcnt_r32_rm32, outr.to_reg32(), inr.to_reg32()),
             64 => IcedInst::with2(Code::Popcnt_r64_rm64, outr.to_reg64(), inr.to_reg64()),
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit ctpop"),
         });
         Ok(())
     }
@@ -2392,7 +2778,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         self.asm.push_inst(match bitw {
             32 => IcedInst::with2(Code::Tzcnt_r32_rm32, outr.to_reg32(), inr.to_reg32()),
             64 => IcedInst::with2(Code::Tzcnt_r64_rm64, outr.to_reg64(), inr.to_reg64()),
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit cttz"),
         });
         Ok(())
     }
@@ -2488,6 +2874,67 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         Ok(())
     }
 
+    fn i_fabs(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        _b: &Block,
+        iidx: InstIdx,
+        FAbs { tyidx, val }: &FAbs,
+    ) -> Result<(), CompilationError> {
+        let [outr, tmpr] = ra.alloc(
+            self,
+            iidx,
+            [
+                RegCnstr::InputOutput {
+                    in_iidx: *val,
+                    in_fill: RegCnstrFill::Undefined,
+                    out_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                },
+                RegCnstr::Temp {
+                    regs: &ALL_XMM_REGS,
+                },
+            ],
+        )?;
+        match self.m.ty(*tyidx) {
+            Ty::Double => {
+                self.asm.push_inst(IcedInst::with2(
+                    Code::Andpd_xmm_xmmm128,
+                    outr.to_xmm(),
+                    tmpr.to_xmm(),
+                ));
+                let lidx = self.push_data(16, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]);
+                self.asm.push_reloc(
+                    IcedInst::with2(
+                        Code::Movsd_xmm_xmmm64,
+                        tmpr.to_xmm(),
+                        MemoryOperand::with_base_displ(IcedReg::RIP, 0),
+                    ),
+                    RelocKind::NearWithLabel(lidx),
+                );
+            }
+            Ty::Float => {
+                self.asm.push_inst(IcedInst::with2(
+                    Code::Andps_xmm_xmmm128,
+                    outr.to_xmm(),
+                    tmpr.to_xmm(),
+                ));
+                let lidx = self.push_data(16, &[0xFF, 0xFF, 0xFF, 0x7F]);
+                self.asm.push_reloc(
+                    IcedInst::with2(
+                        Code::Movss_xmm_xmmm32,
+                        tmpr.to_xmm(),
+                        MemoryOperand::with_base_displ(IcedReg::RIP, 0),
+                    ),
+                    RelocKind::NearWithLabel(lidx),
+                );
+            }
+            _ => panic!(),
+        }
+
+        Ok(())
+    }
+
     fn i_fadd(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -2618,13 +3065,15 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                 }
                 (lhsr, rhsr)
             }
-            FPred::False | FPred::Ord | FPred::Uno | FPred::True => todo!(),
+            FPred::False | FPred::Ord | FPred::Uno | FPred::True => {
+                unsupported!("fcmp predicate {pred:?}")
+            }
             FPred::Ugt | FPred::Uge | FPred::Olt | FPred::Ole => unreachable!(),
         };
         self.asm.push_inst(match bitw {
             64 => IcedInst::with2(Code::Ucomisd_xmm_xmmm64, lhsr.to_xmm(), rhsr.to_xmm()),
             32 => IcedInst::with2(Code::Ucomiss_xmm_xmmm32, lhsr.to_xmm(), rhsr.to_xmm()),
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit fcmp"),
         });
         Ok(())
     }
@@ -2655,35 +3104,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         iidx: InstIdx,
         Floor { tyidx, val }: &Floor,
     ) -> Result<(), CompilationError> {
-        let [valr, outr] = ra.alloc(
-            self,
-            iidx,
-            [
-                RegCnstr::Input {
-                    in_iidx: *val,
-                    in_fill: RegCnstrFill::Undefined,
-                    regs: &ALL_XMM_REGS,
-                    clobber: false,
-                },
-                RegCnstr::Output {
-                    out_fill: RegCnstrFill::Undefined,
-                    regs: &ALL_XMM_REGS,
-                    can_be_same_as_input: true,
-                },
-            ],
-        )?;
-        self.asm.push_inst(match self.m.ty(*tyidx) {
-            Ty::Float => todo!(),
-            Ty::Double => IcedInst::with3(
-                Code::Roundsd_xmm_xmmm64_imm8,
-                outr.to_xmm(),
-                valr.to_xmm(),
-                1,
-            ),
-            _ => panic!(),
-        });
-
-        Ok(())
+        self.i_fround(ra, iidx, *tyidx, *val, 1)
     }
 
     fn i_fmul(
@@ -2785,6 +3206,16 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         )
     }
 
+    fn i_ftrunc(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        _b: &Block,
+        iidx: InstIdx,
+        FTrunc { tyidx, val }: &FTrunc,
+    ) -> Result<(), CompilationError> {
+        self.i_fround(ra, iidx, *tyidx, *val, 3)
+    }
+
     fn i_fpclass(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -2850,11 +3281,11 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                         srcr.to_xmm(),
                     ));
                 }
-                Ty::Float => todo!(),
+                Ty::Float => unsupported!("float fpclass"),
                 _ => unreachable!(),
             }
         } else {
-            todo!("0b{test:b}");
+            unsupported!("fpclass test 0b{test:b}");
         }
         Ok(())
     }
@@ -2889,7 +3320,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                 (Ty::Float, Ty::Double) => {
                     IcedInst::with2(Code::Cvtss2sd_xmm_xmmm32, tgtr.to_xmm(), srcr.to_xmm())
                 }
-                _ => todo!(),
+                (x, y) => unsupported!("fpext from {x:?} to {y:?}"),
             });
 
         Ok(())
@@ -2932,7 +3363,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             (Ty::Float, 32) => {
                 IcedInst::with2(Code::Cvttss2si_r32_xmmm32, outr.to_reg32(), valr.to_xmm())
             }
-            x => todo!("{x:?}"),
+            (x, y) => unsupported!("fptosi from {x:?} to {y}-bit integer"),
         });
 
         Ok(())
@@ -2969,6 +3400,9 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         if let Inst::ICmp(ICmp { .. }) = b.inst(*cond) {
             return self.i_icmp_guard(ra, b, iidx, ginst, exit_vars);
         }
+        if let Inst::Overflow(Overflow { .. }) = b.inst(*cond) {
+            return self.i_overflow_guard(ra, b, iidx, ginst, exit_vars);
+        }
 
         let [cndr, _] = ra.alloc(
             self,
@@ -3060,7 +3494,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Cmp_rm32_imm32, lhsr.to_reg32(), imm),
                 64 => IcedInst::with2(Code::Cmp_rm64_imm32, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             });
         } else {
             let [lhsr, rhsr, outr] = ra.alloc(
@@ -3091,7 +3525,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 32 => IcedInst::with2(Code::Cmp_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::Cmp_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit icmp"),
             });
         }
 
@@ -3132,7 +3566,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
     ) -> Result<(), CompilationError> {
         match self.m.ty(*tyidx) {
             Ty::Double | Ty::Float => self._i_load_float(ra, b, iidx, inst),
-            Ty::Func(_) => todo!(),
+            Ty::Func(_) => unreachable!(),
             Ty::Int(_) | Ty::Ptr(_) => self._i_load_intptr(ra, b, iidx, inst),
             Ty::Void => unreachable!(),
         }
@@ -3172,7 +3606,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Shr_rm32_imm8, lhsr.to_reg32(), imm),
                 33..=64 => IcedInst::with2(Code::Shr_rm64_imm8, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit lshr"),
             });
         } else {
             let [lhsr, rhsr] = ra.alloc(
@@ -3196,7 +3630,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Shr_rm32_CL, lhsr.to_reg32(), rhsr.to_reg8()),
                 64 => IcedInst::with2(Code::Shr_rm64_CL, lhsr.to_reg64(), rhsr.to_reg8()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit lshr"),
             });
         }
         Ok(())
@@ -3344,12 +3778,32 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         self.asm.push_inst(match bitw {
             1..=32 => IcedInst::with1(Code::Mul_rm32, rhsr.to_reg32()),
             64 => IcedInst::with1(Code::Mul_rm64, rhsr.to_reg64()),
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit mul"),
         });
 
         Ok(())
     }
 
+    fn i_noalias(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        iidx: InstIdx,
+        NoAlias { ptr, .. }: &NoAlias,
+    ) -> Result<(), CompilationError> {
+        // A `noalias` is a copy of its operand.
+        ra.alloc(
+            self,
+            iidx,
+            [RegCnstr::InputOutput {
+                in_iidx: *ptr,
+                in_fill: RegCnstrFill::Undefined,
+                out_fill: RegCnstrFill::Undefined,
+                regs: &NORMAL_GP_REGS,
+            }],
+        )?;
+        Ok(())
+    }
+
     fn i_or(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -3385,7 +3839,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Or_rm32_imm32, lhsr.to_reg32(), imm),
                 64 => IcedInst::with2(Code::Or_rm64_imm32, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit or"),
             });
         } else {
             let out_fill = match bitw {
@@ -3413,13 +3867,42 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Or_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::Or_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit or"),
             });
         }
 
         Ok(())
     }
 
+    fn i_overflow(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        b: &Block,
+        iidx: InstIdx,
+        Overflow { op, lhs, rhs }: &Overflow,
+    ) -> Result<(), CompilationError> {
+        let bitw = b.inst_bitw(self.m, *lhs);
+        let [lhsr, rhsr, outr] = self.overflow_alloc(
+            ra,
+            iidx,
+            *op,
+            *lhs,
+            *rhs,
+            RegCnstr::Output {
+                out_fill: RegCnstrFill::Undefined,
+                regs: &NORMAL_GP_REGS,
+                can_be_same_as_input: true,
+            },
+        )?;
+
+        self.asm.push_inst(if op.is_signed() {
+            IcedInst::with1(Code::Seto_rm8, outr.to_reg8())
+        } else {
+            IcedInst::with1(Code::Setb_rm8, outr.to_reg8())
+        });
+        self.overflow_arith(*op, bitw, lhsr, rhsr)
+    }
+
     fn i_ptradd(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -3579,12 +4062,12 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                             0,
                         ));
                     }
-                    Ty::Float => todo!(),
+                    Ty::Float => unsupported!("float select"),
                     _ => unreachable!(),
                 }
                 Ok(())
             }
-            Ty::Func(_) => todo!(),
+            Ty::Func(_) => unreachable!(),
             Ty::Int(bitw) => {
                 if *bitw == 1
                     && let Some(c) = self.zero_ext_op_for_imm8(b, *truev)
@@ -3701,7 +4184,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                             truer.to_reg64(),
                             falser.to_reg64(),
                         ),
-                        x => todo!("{x}"),
+                        x => unsupported!("{x}-bit select"),
                     });
                     self.asm
                         .push_inst(IcedInst::with2(Code::Bt_rm32_imm8, condr.to_reg32(), 0));
@@ -3743,7 +4226,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     .push_inst(IcedInst::with2(Code::Bt_rm32_imm8, condr.to_reg32(), 0));
                 Ok(())
             }
-            Ty::Void => todo!(),
+            Ty::Void => unreachable!(),
         }
     }
 
@@ -3801,7 +4284,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 64 => IcedInst::with2(Code::Shl_rm64_imm8, lhsr.to_reg64(), imm),
                 8..=32 => IcedInst::with2(Code::Shl_rm32_imm8, lhsr.to_reg32(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit shl"),
             });
         } else {
             let [lhsr, rhsr] = ra.alloc(
@@ -3825,7 +4308,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Shl_rm32_CL, lhsr.to_reg32(), rhsr.to_reg8()),
                 64 => IcedInst::with2(Code::Shl_rm64_CL, lhsr.to_reg64(), rhsr.to_reg8()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit shl"),
             });
         }
         Ok(())
@@ -3860,7 +4343,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             Ty::Double => match b.inst_bitw(self.m, *val) {
                 32 => IcedInst::with2(Code::Cvtsi2sd_xmm_rm32, tgtr.to_xmm(), srcr.to_reg32()),
                 64 => IcedInst::with2(Code::Cvtsi2sd_xmm_rm64, tgtr.to_xmm(), srcr.to_reg64()),
-                _ => todo!(),
+                x => unsupported!("sitofp from {x}-bit integer"),
             },
             Ty::Float => {
                 assert_eq!(b.inst_bitw(self.m, *val), 32);
@@ -3953,6 +4436,39 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         Ok(())
     }
 
+    fn i_sqrt(
+        &mut self,
+        ra: &mut RegAlloc<Self>,
+        _b: &Block,
+        iidx: InstIdx,
+        Sqrt { tyidx, val }: &Sqrt,
+    ) -> Result<(), CompilationError> {
+        let [valr, outr] = ra.alloc(
+            self,
+            iidx,
+            [
+                RegCnstr::Input {
+                    in_iidx: *val,
+                    in_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                    clobber: false,
+                },
+                RegCnstr::Output {
+                    out_fill: RegCnstrFill::Undefined,
+                    regs: &ALL_XMM_REGS,
+                    can_be_same_as_input: true,
+                },
+            ],
+        )?;
+        self.asm.push_inst(match self.m.ty(*tyidx) {
+            Ty::Double => IcedInst::with2(Code::Sqrtsd_xmm_xmmm64, outr.to_xmm(), valr.to_xmm()),
+            Ty::Float => IcedInst::with2(Code::Sqrtss_xmm_xmmm32, outr.to_xmm(), valr.to_xmm()),
+            _ => panic!(),
+        });
+
+        Ok(())
+    }
+
     fn i_srem(
         &mut self,
         ra: &mut RegAlloc<Self>,
@@ -4002,7 +4518,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
     ) -> Result<(), CompilationError> {
         match b.inst_ty(self.m, *val) {
             Ty::Double | Ty::Float => self._i_store_float(ra, b, iidx, inst),
-            Ty::Func(_) => todo!(),
+            Ty::Func(_) => unreachable!(),
             Ty::Int(_) | Ty::Ptr(_) => self.i_store_intptr(ra, b, iidx, inst),
             Ty::Void => unreachable!(),
         }
@@ -4027,7 +4543,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
         assert_eq!(bitw, b.inst_bitw(self.m, *rhs));
         let in_fill = match bitw {
             32 | 64 => RegCnstrFill::Undefined,
-            x => todo!("{x}"),
+            x => unsupported!("{x}-bit sub"),
         };
         if let Some(0) = self.sign_ext_op_for_imm32(b, *lhs) {
             let [rhsr] = ra.alloc(
@@ -4262,7 +4778,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
                     srcr.to_reg64(),
                 ));
             }
-            Ty::Float => todo!(),
+            Ty::Float => unsupported!("uitofp to float"),
             _ => unreachable!(),
         }
         Ok(())
@@ -4421,7 +4937,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Xor_rm32_imm32, lhsr.to_reg32(), imm),
                 64 => IcedInst::with2(Code::Xor_rm64_imm32, lhsr.to_reg64(), imm),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit xor"),
             });
         } else {
             let [lhsr, rhsr] = ra.alloc(
@@ -4445,7 +4961,7 @@ impl HirToAsmBackend for X64HirToAsm<'_> {
             self.asm.push_inst(match bitw {
                 1..=32 => IcedInst::with2(Code::Xor_rm32_r32, lhsr.to_reg32(), rhsr.to_reg32()),
                 64 => IcedInst::with2(Code::Xor_rm64_r64, lhsr.to_reg64(), rhsr.to_reg64()),
-                x => todo!("{x}"),
+                x => unsupported!("{x}-bit xor"),
             });
         }
 
@@ -4688,6 +5204,10 @@ mod test {
             tracecompilation_errors: 0,
             #[cfg(feature = "ykd")]
             debug_str: None,
+            last_executed: 0,
+            hot_threshold: None,
+            stale: None,
+            tier_up: None,
         }));
         let be = X64HirToAsm::new(&m, CodeBufInProgress::new_testing(), true);
         let log = HirToAsm::new(&m, hl, be, true).build_test().unwrap();
@@ -5268,6 +5788,32 @@ mod test {
         );
     }
 
+    #[test]
+    fn alloca() {
+        codegen_and_test(
+            "
+              %0: i8 = arg [reg]
+              %1: ptr = alloca 16, 8
+              %2: ptr = alloca 4, 16
+              store %0, %1
+              store %0, %2
+              term [%0]
+            ",
+            &["
+              ...
+              ; %1: ptr = alloca 16, 8
+              lea r.64.x, [rbp-0x10]
+              ; %2: ptr = alloca 4, 16
+              lea r.64.y, [rbp-0x20]
+              ; store %0, %1
+              mov [r.64.x], r.8.z
+              ; store %0, %2
+              mov [r.64.y], r.8.z
+              ...
+            "],
+        );
+    }
+
     #[test]
     fn copy_with_fill() {
         codegen_and_test(
@@ -6021,6 +6567,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_ceil() {
+        // float
+        codegen_and_test(
+            "
+              %0: float = arg [reg]
+              %1: float = ceil %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: float = ceil %0
+              roundss fp.128._, fp.128._, 2
+              ...
+            "],
+        );
+
+        // double
+        codegen_and_test(
+            "
+              %0: double = arg [reg]
+              %1: double = ceil %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: double = ceil %0
+              roundsd fp.128._, fp.128._, 2
+              ...
+            "],
+        );
+    }
+
     #[test]
     fn cg_const() {
         // Integers
@@ -6252,6 +6831,89 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_copysign() {
+        // float
+        codegen_and_test(
+            "
+              %0: float = arg [reg]
+              %1: float = arg [reg]
+              %2: float = copysign %0, %1
+              term [%2, %1]
+            ",
+            &[
+                "
+              ; l{{1}}
+              db 0, 0, 0, 0x80
+              ; l{{2}}
+              db 0xFF, 0xFF, 0xFF, 0x7F
+              ...
+              ; %2: float = copysign %0, %1
+              movss fp.128.x, l{{2}}
+              andps fp.128.y, fp.128.x
+              movss fp.128.x, l{{1}}
+              andps fp.128.x, fp.128.z
+              orps fp.128.y, fp.128.x
+              ...
+            ",
+                "
+              ; l{{2}}
+              db 0xFF, 0xFF, 0xFF, 0x7F
+              ; l{{1}}
+              db 0, 0, 0, 0x80
+              ...
+              ; %2: float = copysign %0, %1
+              movss fp.128.x, l{{2}}
+              andps fp.128.y, fp.128.x
+              movss fp.128.x, l{{1}}
+              andps fp.128.x, fp.128.z
+              orps fp.128.y, fp.128.x
+              ...
+            ",
+            ],
+        );
+
+        // double
+        codegen_and_test(
+            "
+              %0: double = arg [reg]
+              %1: double = arg [reg]
+              %2: double = copysign %0, %1
+              term [%2, %1]
+            ",
+            &[
+                "
+              ; l{{1}}
+              db 0, 0, 0, 0, 0, 0, 0, 0x80
+              ; l{{2}}
+              db 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F
+              ...
+              ; %2: double = copysign %0, %1
+              movsd fp.128.x, l{{2}}
+              andpd fp.128.y, fp.128.x
+              movsd fp.128.x, l{{1}}
+              andpd fp.128.x, fp.128.z
+              orpd fp.128.y, fp.128.x
+              ...
+            ",
+                "
+              ; l{{2}}
+              db 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F
+              ; l{{1}}
+              db 0, 0, 0, 0, 0, 0, 0, 0x80
+              ...
+              ; %2: double = copysign %0, %1
+              movsd fp.128.x, l{{2}}
+              andpd fp.128.y, fp.128.x
+              movsd fp.128.x, l{{1}}
+              andpd fp.128.x, fp.128.z
+              orpd fp.128.y, fp.128.x
+              ...
+            ",
+            ],
+        );
+    }
+
     #[test]
     fn cg_ctpop() {
         // i32
@@ -6423,6 +7085,45 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_fabs() {
+        // float
+        codegen_and_test(
+            "
+              %0: float = arg [reg]
+              %1: float = fabs %0
+              term [%1]
+            ",
+            &["
+              ; l{{1}}
+              db 0xFF, 0xFF, 0xFF, 0x7F
+              ...
+              ; %1: float = fabs %0
+              movss fp.128.x, l{{1}}
+              andps fp.128.y, fp.128.x
+              ; term [%1]
+            "],
+        );
+
+        // double
+        codegen_and_test(
+            "
+              %0: double = arg [reg]
+              %1: double = fabs %0
+              term [%1]
+            ",
+            &["
+              ; l{{1}}
+              db 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F
+              ...
+              ; %1: double = fabs %0
+              movsd fp.128.x, l{{1}}
+              andpd fp.128.y, fp.128.x
+              ; term [%1]
+            "],
+        );
+    }
+
     #[test]
     fn cg_fadd() {
         codegen_and_test(
@@ -6724,6 +7425,21 @@ mod test {
 
     #[test]
     fn cg_floor() {
+        // float
+        codegen_and_test(
+            "
+              %0: float = arg [reg]
+              %1: float = floor %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: float = floor %0
+              roundss fp.128._, fp.128._, 1
+              ...
+            "],
+        );
+
         // double
         codegen_and_test(
             "
@@ -6917,6 +7633,23 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_ftrunc() {
+        codegen_and_test(
+            "
+              %0: double = arg [reg]
+              %1: double = ftrunc %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: double = ftrunc %0
+              roundsd fp.128._, fp.128._, 3
+              ...
+            "],
+        );
+    }
+
     #[test]
     fn cg_fpclass() {
         codegen_and_test(
@@ -8011,6 +8744,121 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_overflow() {
+        codegen_and_test(
+            "
+              %0: i32 = arg [reg]
+              %1: i32 = arg [reg]
+              %2: i1 = overflow sadd %0, %1
+              blackbox %2
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow sadd %0, %1
+              ...
+              add r.32.x, r.32._
+              seto r.8._
+              ...
+            "],
+        );
+
+        codegen_and_test(
+            "
+              %0: i16 = arg [reg]
+              %1: i16 = arg [reg]
+              %2: i1 = overflow usub %0, %1
+              blackbox %2
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow usub %0, %1
+              ...
+              sub r.16.x, r.16._
+              setb r.8._
+              ...
+            "],
+        );
+
+        codegen_and_test(
+            "
+              %0: i64 = arg [reg]
+              %1: i64 = arg [reg]
+              %2: i1 = overflow umul %0, %1
+              blackbox %2
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow umul %0, %1
+              ...
+              mul r.64._
+              setb r.8._
+              ...
+            "],
+        );
+
+        // Guards branch directly on the flags.
+        codegen_and_test(
+            "
+              %0: i64 = arg [reg]
+              %1: i64 = arg [reg]
+              %2: i1 = overflow sadd %0, %1
+              guard false, %2, []
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow sadd %0, %1
+              ; guard false, %2, []
+              ...
+              add r.64.x, r.64._
+              jo l{{1}}
+              ; term [%0, %1]
+            "],
+        );
+
+        codegen_and_test(
+            "
+              %0: i32 = arg [reg]
+              %1: i32 = arg [reg]
+              %2: i1 = overflow uadd %0, %1
+              guard true, %2, []
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow uadd %0, %1
+              ; guard true, %2, []
+              ...
+              add r.32.x, r.32._
+              jae l{{1}}
+              ; term [%0, %1]
+            "],
+        );
+
+        codegen_and_test(
+            "
+              %0: i32 = arg [reg]
+              %1: i32 = arg [reg]
+              %2: i1 = overflow smul %0, %1
+              guard false, %2, []
+              term [%0, %1]
+            ",
+            &["
+              ...
+              ; %2: i1 = overflow smul %0, %1
+              ; guard false, %2, []
+              ...
+              imul r.32._
+              jo l{{1}}
+              ; term [%0, %1]
+            "],
+        );
+    }
+
     #[test]
     fn cg_ptradd() {
         codegen_and_test(
@@ -8519,6 +9367,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn cg_sqrt() {
+        // float
+        codegen_and_test(
+            "
+              %0: float = arg [reg]
+              %1: float = sqrt %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: float = sqrt %0
+              sqrtss fp.128._, fp.128._
+              ...
+            "],
+        );
+
+        // double
+        codegen_and_test(
+            "
+              %0: double = arg [reg]
+              %1: double = sqrt %0
+              term [%1]
+            ",
+            &["
+              ...
+              ; %1: double = sqrt %0
+              sqrtsd fp.128._, fp.128._
+              ...
+            "],
+        );
+    }
+
     #[test]
     fn cg_srem() {
         // i64
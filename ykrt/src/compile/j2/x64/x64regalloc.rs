@@ -419,13 +419,8 @@ impl TestRegIter<Reg> for X64TestRegIter<Reg> {
         match ty {
             Ty::Double | Ty::Float => self.fp_regs.next(),
             Ty::Func(_func_ty) => todo!(),
-            Ty::Int(bitw) => {
-                if *bitw <= 64 {
-                    self.gp_regs.next()
-                } else {
-                    todo!()
-                }
-            }
+            Ty::Int(bitw) if *bitw <= 64 => self.gp_regs.next(),
+            Ty::Int(_) => None,
             Ty::Ptr(addrspace) => {
                 assert_eq!(*addrspace, 0);
                 self.gp_regs.next()
@@ -1107,6 +1107,9 @@ pub(crate) enum Inst {
     },
     #[deku(id = "23")]
     Freeze { tyidx: TyIdx, op: Operand },
+    /// As [Inst::LoadArg], but for an argument with LLVM's `noalias` attribute.
+    #[deku(id = "24")]
+    LoadNoAliasArg { arg_idx: usize, ty_idx: TyIdx },
     #[deku(id = "255")]
     Unimplemented {
         tyidx: TyIdx,
@@ -1179,7 +1182,9 @@ impl Inst {
             Self::Select {
                 cond: _, trueval, ..
             } => Some(trueval.type_(m)),
-            Self::LoadArg { arg_idx: _, ty_idx } => Some(m.type_(*ty_idx)),
+            Self::LoadArg { arg_idx: _, ty_idx } | Self::LoadNoAliasArg { arg_idx: _, ty_idx } => {
+                Some(m.type_(*ty_idx))
+            }
             Self::Unimplemented { tyidx, .. } => {
                 let ty = m.type_(*tyidx);
                 if ty != &Ty::Void { Some(ty) } else { None }
@@ -1474,6 +1479,7 @@ impl fmt::Display for DisplayableInst<'_> {
                 )
             }
             Inst::LoadArg { arg_idx, ty_idx: _ } => write!(f, "arg({arg_idx})",),
+            Inst::LoadNoAliasArg { arg_idx, ty_idx: _ } => write!(f, "arg({arg_idx}) noalias"),
             Inst::Unimplemented { llvm_inst_str, .. } => {
                 write!(f, "unimplemented <<{llvm_inst_str}>>")
             }
@@ -1593,9 +1599,16 @@ const FUNCFLAG_IDEMPOTENT: u8 = 1 << 1;
 const FUNCFLAG_INDIRECT_INLINE: u8 = 1 << 2;
 const FUNCFLAG_MEMORY_HI: u8 = 1 << 3;
 const FUNCFLAG_MEMORY_LO: u8 = 1 << 4;
+const FUNCFLAG_ALLOCATOR: u8 = 1 << 5;
+const FUNCFLAG_MEMORY_ARGMEM: u8 = 1 << 6;
+const FUNCFLAG_MEMORY_INACCESSIBLEMEM: u8 = 1 << 7;
 
 const FUNCFLAG_SHIFT_MEM: u8 = 3;
 
+/// Declarations of functions with these names are treated as allocators (see
+/// [Func::is_allocator]).
+const LIBC_ALLOCATORS: [&str; 2] = ["calloc", "malloc"];
+
 /// The memory capabilities of a function.
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) enum FuncMemory {
@@ -1609,17 +1622,37 @@ pub(crate) enum FuncMemory {
     ReadWrite = 0b11,
 }
 
-impl Display for FuncMemory {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl FuncMemory {
+    fn access_str(&self) -> &'static str {
         match self {
-            Self::None => write!(f, "memory(none)"),
-            Self::Read => write!(f, "memory(read)"),
-            Self::Write => write!(f, "memory(write)"),
-            Self::ReadWrite => write!(f, "memory(readwrite)"),
+            Self::None => "none",
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::ReadWrite => "readwrite",
         }
     }
 }
 
+impl Display for FuncMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "memory({})", self.access_str())
+    }
+}
+
+/// Which memory a function's [FuncMemory] capabilities apply to. These correspond to the
+/// locations in LLVM's `memory` attribute.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum FuncMemoryLoc {
+    /// Any memory.
+    Any,
+    /// Only memory reachable from the function's pointer arguments (LLVM's `argmem`) and,
+    /// possibly, memory that is not accessible to the caller.
+    ArgMem,
+    /// Only memory that is not accessible to the caller (LLVM's `inaccessiblemem`): e.g. an
+    /// allocator's internal state.
+    InaccessibleMem,
+}
+
 impl TryFrom<u8> for FuncMemory {
     type Error = &'static str;
     fn try_from(v: u8) -> Result<Self, Self::Error> {
@@ -1650,11 +1683,30 @@ impl Func {
         self.flags & FUNCFLAG_INDIRECT_INLINE != 0
     }
 
+    /// Is this function an allocator (i.e. does it return a pointer to fresh memory that cannot
+    /// alias any other pointer)? As well as functions flagged as such by `ykllvm`, the C standard
+    /// library's allocation functions are recognised by name.
+    pub(crate) fn is_allocator(&self) -> bool {
+        self.flags & FUNCFLAG_ALLOCATOR != 0
+            || (self.is_declaration() && LIBC_ALLOCATORS.contains(&self.name.as_str()))
+    }
+
     pub(crate) fn memory(&self) -> FuncMemory {
         let bits = (self.flags & (FUNCFLAG_MEMORY_HI | FUNCFLAG_MEMORY_LO)) >> FUNCFLAG_SHIFT_MEM;
         FuncMemory::try_from(bits).unwrap()
     }
 
+    /// Return the memory that this function's [Self::memory] capabilities apply to.
+    pub(crate) fn memory_loc(&self) -> FuncMemoryLoc {
+        if self.flags & FUNCFLAG_MEMORY_ARGMEM != 0 {
+            FuncMemoryLoc::ArgMem
+        } else if self.flags & FUNCFLAG_MEMORY_INACCESSIBLEMEM != 0 {
+            FuncMemoryLoc::InaccessibleMem
+        } else {
+            FuncMemoryLoc::Any
+        }
+    }
+
     /// Return the [BBlock] at the specified index.
     ///
     /// # Panics
@@ -1724,9 +1776,19 @@ impl fmt::Display for DisplayableFunc<'_> {
             if self.func_.is_indirect_inline() {
                 attrs.push("yk_indirect_inline");
             }
+            if self.func_.is_allocator() {
+                attrs.push("allocator");
+            }
             let mem = self.func_.memory();
-            let mem_s = mem.to_string();
-            if mem != FuncMemory::ReadWrite {
+            let mem_loc = self.func_.memory_loc();
+            let mem_s = match mem_loc {
+                FuncMemoryLoc::Any => mem.to_string(),
+                FuncMemoryLoc::ArgMem => format!("memory(argmem: {})", mem.access_str()),
+                FuncMemoryLoc::InaccessibleMem => {
+                    format!("memory(inaccessiblemem: {})", mem.access_str())
+                }
+            };
+            if mem != FuncMemory::ReadWrite || mem_loc != FuncMemoryLoc::Any {
                 attrs.push(&mem_s);
             }
             let attrs = if !attrs.is_empty() {
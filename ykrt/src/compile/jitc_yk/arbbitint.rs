@@ -246,6 +246,95 @@ impl ArbBitInt {
         }
     }
 
+    /// Return a new [ArbBitInt] that performs signed division of `self` by `other`, returning
+    /// `None` if `other` is 0 or if the division overflows (i.e. `MIN / -1`).
+    pub(crate) fn checked_sdiv(&self, other: &Self) -> Option<Self> {
+        debug_assert_eq!(self.bitw, other.bitw);
+        let (lhs, rhs) = self.checked_sdiv_operands(other)?;
+        Some(Self {
+            bitw: self.bitw,
+            val: (lhs / rhs).cast_unsigned(),
+        })
+    }
+
+    /// Return a new [ArbBitInt] that computes the signed remainder of `self` divided by `other`,
+    /// returning `None` if `other` is 0 or if the division overflows (i.e. `MIN % -1`).
+    pub(crate) fn checked_srem(&self, other: &Self) -> Option<Self> {
+        debug_assert_eq!(self.bitw, other.bitw);
+        let (lhs, rhs) = self.checked_sdiv_operands(other)?;
+        Some(Self {
+            bitw: self.bitw,
+            val: (lhs % rhs).cast_unsigned(),
+        })
+    }
+
+    /// Sign extend `self` and `other` to `i64`s suitable for signed division, returning `None` if
+    /// the division would be undefined at `self.bitw()` bits.
+    fn checked_sdiv_operands(&self, other: &Self) -> Option<(i64, i64)> {
+        let lhs = self.to_sign_ext_i64().unwrap();
+        let rhs = other.to_sign_ext_i64().unwrap();
+        if rhs == 0 || (rhs == -1 && lhs == (i64::MIN >> (64 - self.bitw))) {
+            None
+        } else {
+            Some((lhs, rhs))
+        }
+    }
+
+    /// Return a new [ArbBitInt] that computes the unsigned remainder of `self` divided by `other`,
+    /// returning `None` if `other` is 0.
+    pub(crate) fn checked_urem(&self, other: &Self) -> Option<Self> {
+        debug_assert_eq!(self.bitw, other.bitw);
+        let rhs = other.to_zero_ext_u64().unwrap();
+        if rhs == 0 {
+            None
+        } else {
+            Some(Self {
+                bitw: self.bitw,
+                val: self.val.truncate_to(self.bitw) % rhs,
+            })
+        }
+    }
+
+    /// Return whichever of `self` and `other` is greater when both are treated as signed.
+    pub(crate) fn smax(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.bitw, other.bitw);
+        if self.to_sign_ext_i64() >= other.to_sign_ext_i64() {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+
+    /// Return whichever of `self` and `other` is lesser when both are treated as signed.
+    pub(crate) fn smin(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.bitw, other.bitw);
+        if self.to_sign_ext_i64() <= other.to_sign_ext_i64() {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+
+    /// Return whichever of `self` and `other` is greater when both are treated as unsigned.
+    pub(crate) fn umax(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.bitw, other.bitw);
+        if self.to_zero_ext_u64() >= other.to_zero_ext_u64() {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+
+    /// Return whichever of `self` and `other` is lesser when both are treated as unsigned.
+    pub(crate) fn umin(&self, other: &Self) -> Self {
+        debug_assert_eq!(self.bitw, other.bitw);
+        if self.to_zero_ext_u64() <= other.to_zero_ext_u64() {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+
     /// Return a new [ArbBitInt] that left shifts `self` by `bits`s or `None` if `bits >=
     /// self.bitw()`.
     pub(crate) fn checked_shl(&self, bits: u32) -> Option<Self> {
@@ -1328,6 +1417,174 @@ mod tests {
                 x.checked_div(y)
             );
         }
+
+        #[test]
+        fn arbbitint_8bit_sdiv_srem(x in any::<i8>(), y in any::<i8>()) {
+            assert_eq!(
+                ArbBitInt::from_i64(8, x as i64)
+                    .checked_sdiv(&ArbBitInt::from_i64(8, y as i64))
+                    .map(|x| x.to_sign_ext_i8().unwrap()),
+                x.checked_div(y)
+            );
+            assert_eq!(
+                ArbBitInt::from_i64(8, x as i64)
+                    .checked_srem(&ArbBitInt::from_i64(8, y as i64))
+                    .map(|x| x.to_sign_ext_i8().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_8bit_urem(x in any::<u8>(), y in any::<u8>()) {
+            assert_eq!(
+                ArbBitInt::from_u64(8, x as u64)
+                    .checked_urem(&ArbBitInt::from_u64(8, y as u64))
+                    .map(|x| x.to_zero_ext_u8().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_8bit_min_max(x in any::<i8>(), y in any::<i8>()) {
+            let lhs = ArbBitInt::from_i64(8, x as i64);
+            let rhs = ArbBitInt::from_i64(8, y as i64);
+            assert_eq!(lhs.smax(&rhs).to_sign_ext_i8(), Some(x.max(y)));
+            assert_eq!(lhs.smin(&rhs).to_sign_ext_i8(), Some(x.min(y)));
+            assert_eq!(
+                lhs.umax(&rhs).to_zero_ext_u8(),
+                Some(x.cast_unsigned().max(y.cast_unsigned()))
+            );
+            assert_eq!(
+                lhs.umin(&rhs).to_zero_ext_u8(),
+                Some(x.cast_unsigned().min(y.cast_unsigned()))
+            );
+        }
+
+        #[test]
+        fn arbbitint_16bit_sdiv_srem(x in any::<i16>(), y in any::<i16>()) {
+            assert_eq!(
+                ArbBitInt::from_i64(16, x as i64)
+                    .checked_sdiv(&ArbBitInt::from_i64(16, y as i64))
+                    .map(|x| x.to_sign_ext_i16().unwrap()),
+                x.checked_div(y)
+            );
+            assert_eq!(
+                ArbBitInt::from_i64(16, x as i64)
+                    .checked_srem(&ArbBitInt::from_i64(16, y as i64))
+                    .map(|x| x.to_sign_ext_i16().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_16bit_urem(x in any::<u16>(), y in any::<u16>()) {
+            assert_eq!(
+                ArbBitInt::from_u64(16, x as u64)
+                    .checked_urem(&ArbBitInt::from_u64(16, y as u64))
+                    .map(|x| x.to_zero_ext_u16().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_16bit_min_max(x in any::<i16>(), y in any::<i16>()) {
+            let lhs = ArbBitInt::from_i64(16, x as i64);
+            let rhs = ArbBitInt::from_i64(16, y as i64);
+            assert_eq!(lhs.smax(&rhs).to_sign_ext_i16(), Some(x.max(y)));
+            assert_eq!(lhs.smin(&rhs).to_sign_ext_i16(), Some(x.min(y)));
+            assert_eq!(
+                lhs.umax(&rhs).to_zero_ext_u16(),
+                Some(x.cast_unsigned().max(y.cast_unsigned()))
+            );
+            assert_eq!(
+                lhs.umin(&rhs).to_zero_ext_u16(),
+                Some(x.cast_unsigned().min(y.cast_unsigned()))
+            );
+        }
+
+        #[test]
+        fn arbbitint_32bit_sdiv_srem(x in any::<i32>(), y in any::<i32>()) {
+            assert_eq!(
+                ArbBitInt::from_i64(32, x as i64)
+                    .checked_sdiv(&ArbBitInt::from_i64(32, y as i64))
+                    .map(|x| x.to_sign_ext_i32().unwrap()),
+                x.checked_div(y)
+            );
+            assert_eq!(
+                ArbBitInt::from_i64(32, x as i64)
+                    .checked_srem(&ArbBitInt::from_i64(32, y as i64))
+                    .map(|x| x.to_sign_ext_i32().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_32bit_urem(x in any::<u32>(), y in any::<u32>()) {
+            assert_eq!(
+                ArbBitInt::from_u64(32, x as u64)
+                    .checked_urem(&ArbBitInt::from_u64(32, y as u64))
+                    .map(|x| x.to_zero_ext_u32().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_32bit_min_max(x in any::<i32>(), y in any::<i32>()) {
+            let lhs = ArbBitInt::from_i64(32, x as i64);
+            let rhs = ArbBitInt::from_i64(32, y as i64);
+            assert_eq!(lhs.smax(&rhs).to_sign_ext_i32(), Some(x.max(y)));
+            assert_eq!(lhs.smin(&rhs).to_sign_ext_i32(), Some(x.min(y)));
+            assert_eq!(
+                lhs.umax(&rhs).to_zero_ext_u32(),
+                Some(x.cast_unsigned().max(y.cast_unsigned()))
+            );
+            assert_eq!(
+                lhs.umin(&rhs).to_zero_ext_u32(),
+                Some(x.cast_unsigned().min(y.cast_unsigned()))
+            );
+        }
+
+        #[test]
+        fn arbbitint_64bit_sdiv_srem(x in any::<i64>(), y in any::<i64>()) {
+            assert_eq!(
+                ArbBitInt::from_i64(64, x as i64)
+                    .checked_sdiv(&ArbBitInt::from_i64(64, y as i64))
+                    .map(|x| x.to_sign_ext_i64().unwrap()),
+                x.checked_div(y)
+            );
+            assert_eq!(
+                ArbBitInt::from_i64(64, x as i64)
+                    .checked_srem(&ArbBitInt::from_i64(64, y as i64))
+                    .map(|x| x.to_sign_ext_i64().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_64bit_urem(x in any::<u64>(), y in any::<u64>()) {
+            assert_eq!(
+                ArbBitInt::from_u64(64, x as u64)
+                    .checked_urem(&ArbBitInt::from_u64(64, y as u64))
+                    .map(|x| x.to_zero_ext_u64().unwrap()),
+                x.checked_rem(y)
+            );
+        }
+
+        #[test]
+        fn arbbitint_64bit_min_max(x in any::<i64>(), y in any::<i64>()) {
+            let lhs = ArbBitInt::from_i64(64, x as i64);
+            let rhs = ArbBitInt::from_i64(64, y as i64);
+            assert_eq!(lhs.smax(&rhs).to_sign_ext_i64(), Some(x.max(y)));
+            assert_eq!(lhs.smin(&rhs).to_sign_ext_i64(), Some(x.min(y)));
+            assert_eq!(
+                lhs.umax(&rhs).to_zero_ext_u64(),
+                Some(x.cast_unsigned().max(y.cast_unsigned()))
+            );
+            assert_eq!(
+                lhs.umin(&rhs).to_zero_ext_u64(),
+                Some(x.cast_unsigned().min(y.cast_unsigned()))
+            );
+        }
     }
 
     #[test]
@@ -27,9 +27,10 @@
 
 use crate::{
     compile::jitc_yk::aot_ir::Statepoint,
+    event::CompilationErrorKind,
     location::HotLocation,
-    mt::{MT, TraceId},
-    trace::AOTTraceIterator,
+    mt::{MT, TraceId, WatchId},
+    trace::{AOTTraceIterator, AOTTraceIteratorError, TraceAction},
 };
 use libc::c_void;
 use parking_lot::Mutex;
@@ -55,7 +56,6 @@ pub(crate) enum CompilationError {
     General(String),
     #[error("Internal error: {0}")]
     /// Something went wrong when compiling that is probably the result of a bug in yk.
-    #[allow(unused)]
     InternalError(String),
     #[error("Internal error: {0}")]
     /// A limit was exceeded (e.g. a pointer add that went beyond a struct). We try and check for
@@ -69,6 +69,18 @@ pub(crate) enum CompilationError {
     ResourceExhausted(Box<dyn Error>),
 }
 
+impl CompilationError {
+    /// Return the [CompilationErrorKind] of this error.
+    pub(crate) fn kind(&self) -> CompilationErrorKind {
+        match self {
+            CompilationError::General(_) => CompilationErrorKind::General,
+            CompilationError::InternalError(_) => CompilationErrorKind::InternalError,
+            CompilationError::LimitExceeded(_) => CompilationErrorKind::LimitExceeded,
+            CompilationError::ResourceExhausted(_) => CompilationErrorKind::ResourceExhausted,
+        }
+    }
+}
+
 /// The trait that every JIT compiler backend must implement.
 pub(crate) trait Compiler: Send + Sync {
     /// Compile the [Trace] `trace`.
@@ -97,8 +109,104 @@ pub(crate) struct Trace {
     pub(crate) ta_iter: Peekable<Box<dyn AOTTraceIterator>>,
     pub(crate) promotions: Box<[u8]>,
     pub(crate) debug_strs: Vec<String>,
+    /// The watchpoints this trace depends on, and how many times each had been fired when the
+    /// dependency was recorded.
+    pub(crate) watches: Vec<(WatchId, u64)>,
+    /// Which tier this trace is being compiled for.
+    pub(crate) tier: Tier,
+}
+
+impl Trace {
+    /// Consume this trace's [AOTTraceIterator], returning a [RecordedTrace] from which the trace
+    /// can later be recompiled. This trace's iterator is replaced with one which replays the
+    /// recorded [TraceAction]s, so this trace can still be compiled.
+    pub(crate) fn record(&mut self) -> Result<RecordedTrace, CompilationError> {
+        let actions = self
+            .ta_iter
+            .by_ref()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CompilationError::General(format!("{e:?}")))?;
+        let recorded = RecordedTrace {
+            trace_end: self.trace_end.clone(),
+            actions,
+            promotions: self.promotions.clone(),
+            debug_strs: self.debug_strs.clone(),
+            watches: self.watches.clone(),
+        };
+        self.ta_iter = recorded.replay();
+        Ok(recorded)
+    }
+}
+
+/// Which tier a trace is compiled for. By default, traces are compiled at the meta-tracer's
+/// optimisation level. If the meta-tracer has a recompile threshold (see
+/// [MT::recompile_threshold]), traces starting at a control point are first compiled without
+/// optimisation, and then recompiled once they have been executed often enough.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Tier {
+    /// Compile the trace at the meta-tracer's optimisation level.
+    Normal,
+    /// Compile the trace without optimisation, retaining its [RecordedTrace] so that it can later
+    /// be recompiled.
+    Baseline,
+    /// Recompile the [Tier::Baseline] trace with the given [TraceId] at the meta-tracer's
+    /// optimisation level. If recompilation succeeds, the new trace replaces the old in the
+    /// [HotLocation].
+    Recompile(TraceId),
+}
+
+/// The input needed to (re)compile a trace after its [AOTTraceIterator] has been consumed.
+pub(crate) struct RecordedTrace {
+    trace_end: TraceEnd,
+    actions: Vec<TraceAction>,
+    promotions: Box<[u8]>,
+    debug_strs: Vec<String>,
+    watches: Vec<(WatchId, u64)>,
+}
+
+impl RecordedTrace {
+    /// Convert this recorded trace into a [Trace], starting from `hl`, with the ID `ctrid`.
+    pub(crate) fn into_trace(
+        self,
+        hl: Arc<Mutex<HotLocation>>,
+        ctrid: TraceId,
+        tier: Tier,
+    ) -> Trace {
+        Trace {
+            trace_start: TraceStart::ControlPoint { hl },
+            trace_end: self.trace_end,
+            ctrid,
+            ta_iter: (Box::new(ReplayAOTTraceIterator(self.actions.into_iter()))
+                as Box<dyn AOTTraceIterator>)
+                .peekable(),
+            promotions: self.promotions,
+            debug_strs: self.debug_strs,
+            watches: self.watches,
+            tier,
+        }
+    }
+
+    /// Return an [AOTTraceIterator] which replays this trace's [TraceAction]s.
+    fn replay(&self) -> Peekable<Box<dyn AOTTraceIterator>> {
+        (Box::new(ReplayAOTTraceIterator(self.actions.clone().into_iter()))
+            as Box<dyn AOTTraceIterator>)
+            .peekable()
+    }
+}
+
+/// An [AOTTraceIterator] over previously recorded [TraceAction]s.
+struct ReplayAOTTraceIterator(std::vec::IntoIter<TraceAction>);
+
+impl Iterator for ReplayAOTTraceIterator {
+    type Item = Result<TraceAction, AOTTraceIteratorError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Ok)
+    }
 }
 
+impl AOTTraceIterator for ReplayAOTTraceIterator {}
+
 /// How a recorded trace started.
 #[derive(Clone)]
 pub(crate) enum TraceStart {
@@ -133,11 +241,39 @@ pub(crate) trait CompiledTrace: fmt::Debug + Send + Sync {
     /// upcasting in Rust is incomplete.
     fn as_any(self: Arc<Self>) -> Arc<dyn std::any::Any + Send + Sync + 'static>;
 
+    /// This trace should no longer be executed (e.g. because the [crate::Location] it was compiled
+    /// for has been dropped or reset). This informs the meta-tracer which compiled the trace so
+    /// that it can stop executing, and then free, the trace (see [MT::invalidate_trace]).
+    fn invalidate(self: Arc<Self>);
+
     /// Return a reference to the guard `id`.
     fn guard(&self, gid: GuardId) -> &Guard;
 
     fn patch_guard(&self, gid: GuardId, target: *const std::ffi::c_void);
 
+    /// Undo the effects of [Self::patch_guard] (if any), so that guard `gid` deopts when it fails.
+    fn unpatch_guard(&self, gid: GuardId);
+
+    /// If this trace is a loop, patch it so that threads executing it leave it (by deoptimising)
+    /// when they next reach the end of the loop, rather than starting another iteration. Threads
+    /// which have not yet reached the end of the loop continue executing normally until they do.
+    fn exit_loop(&self);
+
+    /// If this trace was compiled for [Tier::Baseline], count one execution of it towards it being
+    /// recompiled, returning `true` if it has now been executed at least as many times as the
+    /// [MT::recompile_threshold] in force when it was compiled. Every iteration of a looping trace
+    /// counts as an execution, and when the threshold is reached, threads executing the loop
+    /// leave it (as with [Self::exit_loop]) so that the trace can be recompiled. Returns `false`
+    /// for other traces.
+    fn count_execution(&self) -> bool;
+
+    /// Return the number of guards in this trace. Valid [GuardId]s are `0..num_guards()`.
+    fn num_guards(&self) -> usize;
+
+    /// If this trace ends by jumping to another trace (i.e. it is a coupler trace), return that
+    /// trace's [TraceId].
+    fn coupler(&self) -> Option<TraceId>;
+
     /// The pointer to this trace's executable code.
     fn entry(&self) -> *const c_void;
 
@@ -160,8 +296,9 @@ pub(crate) use compiled_trace_testing::*;
 mod compiled_trace_testing {
     use super::*;
 
-    /// A [CompiledTrace] implementation suitable only for testing: when any of its methods are
-    /// called it will `panic`.
+    /// A [CompiledTrace] implementation suitable only for testing: when any of its methods (other
+    /// than `invalidate`, `exit_loop`, and `count_execution`, which do nothing) are called it will
+    /// `panic`.
     #[derive(Debug)]
     pub(crate) struct CompiledTraceTestingMinimal;
 
@@ -180,6 +317,8 @@ mod compiled_trace_testing {
             panic!();
         }
 
+        fn invalidate(self: Arc<Self>) {}
+
         fn guard(&self, _gid: GuardId) -> &Guard {
             panic!();
         }
@@ -188,6 +327,24 @@ mod compiled_trace_testing {
             panic!();
         }
 
+        fn unpatch_guard(&self, _gid: GuardId) {
+            panic!();
+        }
+
+        fn exit_loop(&self) {}
+
+        fn count_execution(&self) -> bool {
+            false
+        }
+
+        fn num_guards(&self) -> usize {
+            panic!();
+        }
+
+        fn coupler(&self) -> Option<TraceId> {
+            panic!();
+        }
+
         fn entry(&self) -> *const c_void {
             panic!();
         }
@@ -206,31 +363,53 @@ mod compiled_trace_testing {
     }
 
     /// A [CompiledTrace] implementation suitable only for testing basic transitions. The `hl` method will return a
-    /// [HotLocation] but all other methods will `panic` if called.
+    /// [HotLocation], and `code` a dummy sequence of bytes, but all other methods will `panic` if
+    /// called. `invalidate` does nothing unless the trace was created with an `MT`.
     #[derive(Debug)]
     pub(crate) struct CompiledTraceTestingBasicTransitions {
+        ctrid: TraceId,
         guard: Guard,
         hl: Weak<Mutex<HotLocation>>,
+        mt: Option<Arc<MT>>,
     }
 
     impl CompiledTraceTestingBasicTransitions {
         pub(crate) fn new(hl: Weak<Mutex<HotLocation>>) -> Self {
+            Self::with_ctrid(hl, TraceId::testing(), None)
+        }
+
+        /// Create a trace with the ID `ctrid`. If `mt` is `Some`, `invalidate` informs that
+        /// meta-tracer, as a real trace would.
+        pub(crate) fn with_ctrid(
+            hl: Weak<Mutex<HotLocation>>,
+            ctrid: TraceId,
+            mt: Option<Arc<MT>>,
+        ) -> Self {
             Self {
+                ctrid,
                 guard: Guard::new(),
                 hl,
+                mt,
             }
         }
     }
 
     impl CompiledTrace for CompiledTraceTestingBasicTransitions {
         fn ctrid(&self) -> TraceId {
-            TraceId::testing()
+            self.ctrid
         }
 
         fn as_any(self: Arc<Self>) -> Arc<dyn std::any::Any + Send + Sync + 'static> {
             panic!();
         }
 
+        fn invalidate(self: Arc<Self>) {
+            if let Some(mt) = &self.mt {
+                let mt = Arc::clone(mt);
+                mt.invalidate_trace(self);
+            }
+        }
+
         fn guard(&self, gid: GuardId) -> &Guard {
             assert_eq!(usize::from(gid), 0);
             &self.guard
@@ -240,6 +419,24 @@ mod compiled_trace_testing {
             panic!();
         }
 
+        fn unpatch_guard(&self, _gid: GuardId) {
+            panic!();
+        }
+
+        fn exit_loop(&self) {}
+
+        fn count_execution(&self) -> bool {
+            false
+        }
+
+        fn num_guards(&self) -> usize {
+            1
+        }
+
+        fn coupler(&self) -> Option<TraceId> {
+            None
+        }
+
         fn entry(&self) -> *const c_void {
             panic!();
         }
@@ -249,7 +446,7 @@ mod compiled_trace_testing {
         }
 
         fn code(&self) -> &[u8] {
-            panic!()
+            &[0; 64]
         }
 
         fn name(&self) -> String {
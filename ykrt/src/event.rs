@@ -0,0 +1,100 @@
+//! Lifecycle events: a typed way for tools (e.g. IDE plugins and benchmark harnesses) to observe
+//! what the meta-tracer is doing (see [crate::MT::set_event_handler]).
+//!
+//! Trace IDs are the same `u64`s that appear in yk's logs.
+
+use std::fmt;
+
+/// A lifecycle event in the meta-tracer.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// Tracing started at a location.
+    StartTracing {
+        trid: u64,
+        /// The location's debug string, if one has been set.
+        debug_str: Option<String>,
+    },
+    /// Tracing started after guard `gid` in the trace `parent_trid` failed.
+    StartSideTracing {
+        trid: u64,
+        parent_trid: u64,
+        gid: usize,
+        /// The debug string of the location the parent trace was compiled for, if one has been
+        /// set.
+        debug_str: Option<String>,
+    },
+    /// Tracing stopped successfully: the trace has been queued for compilation.
+    StopTracing {
+        trid: u64,
+        debug_str: Option<String>,
+    },
+    /// Tracing was aborted.
+    AbortTracing {
+        trid: u64,
+        debug_str: Option<String>,
+        reason: AbortKind,
+    },
+    /// A trace was compiled successfully.
+    CompileOk { trid: u64 },
+    /// A trace failed to compile.
+    CompileErr {
+        trid: u64,
+        kind: CompilationErrorKind,
+        msg: String,
+    },
+    /// A thread started executing a trace at a location.
+    ExecuteTrace {
+        trid: u64,
+        debug_str: Option<String>,
+    },
+    /// Guard `gid` in the trace `trid` failed.
+    GuardFailure { trid: u64, gid: usize },
+    /// A thread executing the trace `trid` deoptimised back to the interpreter.
+    Deopt { trid: u64 },
+    /// A thread executing the trace `trid` returned from the function containing the trace's
+    /// location.
+    TraceReturn { trid: u64 },
+}
+
+/// Why did we abort tracing?
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbortKind {
+    /// While tracing we fell back from an interpreter to a JIT frame.
+    BackIntoExecution,
+    /// The JIT was disabled while tracing.
+    JitDisabled,
+    LongJmpEncountered,
+    /// While tracing an outer loop, we started unrolling an inner loop.
+    UnrolledInnerLoop,
+    /// The trace recorder could not produce a trace (e.g. because the trace was too long).
+    RecorderError,
+}
+
+impl fmt::Display for AbortKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AbortKind::BackIntoExecution => write!(f, "tracing continued into a JIT frame"),
+            AbortKind::JitDisabled => write!(f, "JIT disabled"),
+            AbortKind::LongJmpEncountered => write!(f, "longjmp encountered"),
+            AbortKind::UnrolledInnerLoop => write!(f, "unrolled inner loop"),
+            AbortKind::RecorderError => write!(f, "trace recorder error"),
+        }
+    }
+}
+
+/// The kind of a compilation error (see [Event::CompileErr]).
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompilationErrorKind {
+    /// Compilation failed for reasons that might be of interest to a programmer augmenting an
+    /// interpreter with yk.
+    General,
+    /// Something went wrong when compiling that is probably the result of a bug in yk.
+    InternalError,
+    /// A limit was exceeded (e.g. a pointer add that went beyond a struct).
+    LimitExceeded,
+    /// An external resource was exhausted.
+    ResourceExhausted,
+}
@@ -3,10 +3,11 @@
 
 use crate::mt::{MT, TraceId};
 use parking_lot::{Condvar, Mutex, MutexGuard};
+#[cfg(feature = "yk_testing")]
+use std::env;
 use std::{
     cmp,
     collections::VecDeque,
-    env,
     sync::{
         Arc,
         atomic::{AtomicUsize, Ordering},
@@ -74,19 +75,13 @@ pub(crate) struct JobQueue {
 }
 
 impl JobQueue {
-    pub(crate) fn new() -> Arc<Self> {
+    /// Create a new job queue which will run at most `max_worker_threads` worker threads. A value
+    /// of 0 is treated as 1.
+    pub(crate) fn new(max_worker_threads: usize) -> Arc<Self> {
         Arc::new(Self {
             queue: Arc::new((Condvar::new(), Mutex::new(VecDeque::new()))),
             failures: Mutex::new(Vec::new()),
-            max_worker_threads: AtomicUsize::new(cmp::max(
-                1,
-                match env::var("YK_JOBS") {
-                    Ok(x) => x
-                        .parse::<usize>()
-                        .unwrap_or_else(|x| panic!("Invalid value for YK_JOBS: {x}")),
-                    _ => num_cpus::get() - 1,
-                },
-            )),
+            max_worker_threads: AtomicUsize::new(cmp::max(1, max_worker_threads)),
             worker_threads: Mutex::new(Vec::new()),
             idle_worker_threads: AtomicUsize::new(0),
         })
@@ -295,7 +290,7 @@ mod tests {
 
     #[test]
     fn dependency_on_failure_fails() {
-        let jq = JobQueue::new();
+        let jq = JobQueue::new(1);
         let mt = MT::new().unwrap();
         let failure_trid = TraceId::from_u64(123);
         jq.notify_failure(&mt, failure_trid);
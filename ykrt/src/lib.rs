@@ -11,6 +11,7 @@
 
 pub(crate) mod aotsmp;
 pub mod compile;
+pub mod event;
 mod frame;
 mod job_queue;
 mod location;
@@ -24,8 +25,10 @@ pub mod trace;
 pub use thread_intercept::yk_init;
 pub use thread_intercept::{yk_foreach_shadowstack, yk_thread_shadowstack_bounds};
 
+pub use self::event::Event;
 pub use self::location::Location;
-pub use self::mt::{HotThreshold, MT, MTThread};
+pub use self::log::stats::StatsSnapshot;
+pub use self::mt::{HotThreshold, MT, MTConfig, MTThread, WatchId};
 pub use aotsmp::StackMapIdx;
 use std::ffi::{CStr, c_char};
 
@@ -37,6 +40,15 @@ pub unsafe extern "C" fn yk_debug_str(msg: *const c_char) {
     });
 }
 
+/// Record that the trace currently being recorded (if any) depends on the watchpoint `watch` (see
+/// [MT::watch_new]). Calls to this function are removed from compiled traces.
+#[unsafe(no_mangle)]
+pub extern "C" fn yk_watch_depend(watch: WatchId) {
+    if MTThread::is_tracing() {
+        MTThread::with_borrow_mut(|mtt| mtt.insert_watch(watch));
+    }
+}
+
 #[macro_export]
 macro_rules! varlocs {
     () => {
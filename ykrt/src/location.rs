@@ -9,20 +9,27 @@ use std::{
 };
 
 use crate::{
-    compile::CompiledTrace,
+    compile::{CompiledTrace, RecordedTrace},
     mt::{HotThreshold, MT, TraceCompilationErrorThreshold, TraceId},
 };
 use parking_lot::Mutex;
 
 #[cfg(target_pointer_width = "64")]
-const STATE_TAG_MASK: usize = 0b11; // All of the tag data must fit in this.
+const STATE_TAG_MASK: usize = 0b11; // All of the state tag data must fit in this.
+/// If set, this [Location] must never be traced (see [Location::set_dont_trace]). Unlike the state
+/// tag, this flag is sticky: once set, it is never unset.
 #[cfg(target_pointer_width = "64")]
-const STATE_NUM_BITS: usize = 2;
+const DONT_TRACE_FLAG: usize = 0b100;
+/// All of the non-payload bits.
+#[cfg(target_pointer_width = "64")]
+const NON_PAYLOAD_MASK: usize = STATE_TAG_MASK | DONT_TRACE_FLAG;
+#[cfg(target_pointer_width = "64")]
+const STATE_NUM_BITS: usize = 3;
 
 const STATE_NULL: usize = 0b00;
 /// The tag value for a not-yet-hot [Location]. Because null [Location]s have an inner value of 0,
 /// this value *must* be non-zero. To derive the count of a not-yet-hot [Location], we have to do
-/// `(inner & !1) >> STATE_NUM_BITS` to derive the count.
+/// `inner >> STATE_NUM_BITS` to derive the count.
 const STATE_NOT_HOT: usize = 0b01;
 /// The tag value for a hot [Location]; its [HotLocation] address will be contained in the non-tag
 /// bits.
@@ -105,9 +112,11 @@ pub struct Location {
     /// means that the `Counting` state is encoded in two separate ways: both with and without
     /// allocated memory.
     ///
-    /// The layout of a Location is as follows: bit 0 = <STATE_NOT_HOT|STATE_HOT>; bits 1..<machine
-    /// width> = payload. In the `STATE_NOT_HOT` state, the payload is an integer; in a `STATE_HOT`
-    /// state, the payload is a pointer from `Arc::into_raw::<Mutex<HotLocation>>()`.
+    /// The layout of a Location is as follows: bits 0..1 = <STATE_NOT_HOT|STATE_HOT>; bit 2 =
+    /// `DONT_TRACE_FLAG`; bits 3..<machine width> = payload. In the `STATE_NOT_HOT` state, the
+    /// payload is an integer; in a `STATE_HOT` state, the payload is a pointer from
+    /// `Arc::into_raw::<Mutex<HotLocation>>()`. `DONT_TRACE_FLAG` is independent of the state, and
+    /// is checked whenever a Location would otherwise transition to `Tracing`.
     inner: AtomicUsize,
 }
 
@@ -155,10 +164,11 @@ impl Location {
                     .is_some()
             );
 
+            let flag = x & DONT_TRACE_FLAG;
             self.inner
                 .compare_exchange_weak(
-                    ((old as usize) << STATE_NUM_BITS) | STATE_NOT_HOT,
-                    ((new as usize) << STATE_NUM_BITS) | STATE_NOT_HOT,
+                    ((old as usize) << STATE_NUM_BITS) | flag | STATE_NOT_HOT,
+                    ((new as usize) << STATE_NUM_BITS) | flag | STATE_NOT_HOT,
                     Ordering::Relaxed,
                     Ordering::Relaxed,
                 )
@@ -195,10 +205,13 @@ impl Location {
     ) -> Option<Arc<Mutex<HotLocation>>> {
         let hl = Arc::new(Mutex::new(hl));
         let cl: *const Mutex<HotLocation> = Arc::into_raw(Arc::clone(&hl));
-        debug_assert_eq!((cl as usize) & !STATE_TAG_MASK, cl as usize);
+        debug_assert_eq!((cl as usize) & !NON_PAYLOAD_MASK, cl as usize);
+        // If the flag is changed by another thread before the `compare_exchange`, we treat that as
+        // any other clash.
+        let flag = self.inner.load(Ordering::Relaxed) & DONT_TRACE_FLAG;
         match self.inner.compare_exchange(
-            ((old as usize) << STATE_NUM_BITS) | STATE_NOT_HOT,
-            (cl as usize) | STATE_HOT,
+            ((old as usize) << STATE_NUM_BITS) | flag | STATE_NOT_HOT,
+            (cl as usize) | flag | STATE_HOT,
             Ordering::Relaxed,
             Ordering::Relaxed,
         ) {
@@ -212,26 +225,98 @@ impl Location {
         }
     }
 
-    pub fn set_hl_debug_str(&self, s: String) {
-        match self.hot_location() {
-            Some(hl) => {
-                hl.lock().debug_str = Some(s);
+    /// Run `f` on `self`'s [HotLocation], creating a [HotLocation] (in the `Counting` state, with
+    /// `self`'s current count) first if `self` does not already have one.
+    ///
+    /// # Panics
+    ///
+    /// If `self` is a null location.
+    fn with_hot_location<F>(&self, f: F)
+    where
+        F: FnOnce(&mut HotLocation),
+    {
+        assert!(!self.is_null());
+        loop {
+            if let Some(hl) = self.hot_location() {
+                f(&mut hl.lock());
+                return;
             }
-            None => {
-                let Some(count) = self.count() else {
-                    // We clashed with another thread.
-                    todo!();
-                };
+            if let Some(count) = self.count() {
                 let hl = HotLocation {
                     kind: HotLocationKind::Counting(count),
                     tracecompilation_errors: 0,
-                    debug_str: Some(s),
+                    debug_str: None,
+                    last_executed: 0,
+                    hot_threshold: None,
+                    stale: None,
+                    tier_up: None,
                 };
-                if self.count_to_hot_location(count, hl).is_none() {
-                    // We clashed with another thread.
-                    todo!();
+                if let Some(hl) = self.count_to_hot_location(count, hl) {
+                    f(&mut hl.lock());
+                    return;
                 }
             }
+            // We clashed with another thread: try again.
+        }
+    }
+
+    pub fn set_hl_debug_str(&self, s: String) {
+        self.with_hot_location(|hl| hl.debug_str = Some(s));
+    }
+
+    /// Set the threshold at which this location is considered hot, overriding the meta-tracer's
+    /// hot threshold. If this location has already started tracing, this takes effect the next
+    /// time it returns to counting.
+    pub fn set_hot_threshold(&self, hot_threshold: HotThreshold) {
+        self.with_hot_location(|hl| hl.hot_threshold = Some(hot_threshold));
+    }
+
+    /// Stop this location from being traced in the future. If this location is currently being
+    /// traced or compiled, or already has a compiled trace, that trace is unaffected. This cannot
+    /// be undone: in particular, neither [Self::reset] nor tracing / compilation errors allow this
+    /// location to be traced again.
+    ///
+    /// # Panics
+    ///
+    /// If `self` is a null location.
+    pub fn set_dont_trace(&self) {
+        assert!(!self.is_null());
+        self.inner.fetch_or(DONT_TRACE_FLAG, Ordering::Relaxed);
+    }
+
+    /// Returns true if [Self::set_dont_trace] has been called on this location.
+    pub(crate) fn is_dont_trace(&self) -> bool {
+        self.inner.load(Ordering::Relaxed) & DONT_TRACE_FLAG != 0
+    }
+
+    /// Discard any compiled trace (and its side-traces) for this location, returning this location
+    /// to the `Counting` state with a count of zero. If a trace for this location is currently
+    /// being recorded or compiled, it will be thrown away when compilation completes. Note that
+    /// this forgets previous tracing / compilation errors, but does not undo
+    /// [Self::set_dont_trace].
+    ///
+    /// This should be called when the code that this location's trace(s) were recorded from has
+    /// changed (e.g. because the interpreter has reloaded a function).
+    pub fn reset(&self) {
+        let Some(hl) = self.hot_location() else {
+            // This location has never been hot, so there is nothing to invalidate.
+            return;
+        };
+        let mut lk = hl.lock();
+        lk.tracecompilation_errors = 0;
+        match lk.kind {
+            HotLocationKind::Compiled(ref ctr) => {
+                let ctr = Arc::clone(ctr);
+                lk.kind = HotLocationKind::Counting(0);
+                drop(lk);
+                ctr.invalidate();
+            }
+            HotLocationKind::Compiling(trid) | HotLocationKind::Tracing(trid) => {
+                lk.stale = Some(trid);
+            }
+            HotLocationKind::Counting(_) | HotLocationKind::DontTrace => {
+                lk.kind = HotLocationKind::Counting(0);
+            }
         }
     }
 
@@ -243,7 +328,7 @@ impl Location {
             // `Arc::into_raw::<Mutex<T>>` returns `*mut Mutex<T>` so the address we're wrapping is
             // a pointer to the `Mutex` itself. By returning a `&` reference we ensure that the
             // reference to the `Mutex` can't outlive this `Location`.
-            Some(unsafe { &*((x & !STATE_TAG_MASK) as *const _) })
+            Some(unsafe { &*((x & !NON_PAYLOAD_MASK) as *const _) })
         } else {
             None
         }
@@ -254,7 +339,7 @@ impl Location {
     pub(crate) fn hot_location_arc_clone(&self) -> Option<Arc<Mutex<HotLocation>>> {
         let x = self.inner.load(Ordering::Relaxed);
         if x & STATE_TAG_MASK == STATE_HOT {
-            let raw = unsafe { Arc::from_raw((x & !STATE_TAG_MASK) as *mut _) };
+            let raw = unsafe { Arc::from_raw((x & !NON_PAYLOAD_MASK) as *mut _) };
             let cl = Arc::clone(&raw);
             mem::forget(raw);
             Some(cl)
@@ -274,7 +359,25 @@ impl Drop for Location {
     fn drop(&mut self) {
         let x = self.inner.load(Ordering::Relaxed);
         if x & STATE_TAG_MASK == STATE_HOT {
-            drop(unsafe { Arc::from_raw((x & !STATE_TAG_MASK) as *mut Mutex<HotLocation>) });
+            let hl = unsafe { Arc::from_raw((x & !NON_PAYLOAD_MASK) as *mut Mutex<HotLocation>) };
+            let mut lk = hl.lock();
+            match lk.kind {
+                // If this location has a compiled trace, nothing can ever execute it directly
+                // again, so we give the meta-tracer the chance to free it (and any traces which
+                // depend on it).
+                HotLocationKind::Compiled(ref ctr) => {
+                    let ctr = Arc::clone(ctr);
+                    lk.kind = HotLocationKind::DontTrace;
+                    drop(lk);
+                    ctr.invalidate();
+                }
+                // If a trace for this location is still being recorded or compiled, it must not be
+                // installed when compilation completes.
+                HotLocationKind::Compiling(trid) | HotLocationKind::Tracing(trid) => {
+                    lk.stale = Some(trid);
+                }
+                HotLocationKind::Counting(_) | HotLocationKind::DontTrace => (),
+            }
         }
     }
 }
@@ -287,9 +390,36 @@ pub(crate) struct HotLocation {
     pub(crate) tracecompilation_errors: TraceCompilationErrorThreshold,
     /// An optional debug string for this hot location.
     pub(crate) debug_str: Option<String>,
+    /// When was this hot location's trace compiled or last executed? This is a logical time from
+    /// the meta-tracer's code cache clock and is only updated on execution if the meta-tracer has a
+    /// code cache limit.
+    pub(crate) last_executed: u64,
+    /// If `Some`, the hot threshold for this location, overriding the meta-tracer's hot
+    /// threshold.
+    pub(crate) hot_threshold: Option<HotThreshold>,
+    /// If `Some(trid)`, this location was reset (see [Location::reset]) while the trace `trid` was
+    /// being recorded or compiled: when compilation completes, that trace will be thrown away.
+    pub(crate) stale: Option<TraceId>,
+    /// If `Some`, a baseline (i.e. unoptimised) trace was compiled for this location, and will be
+    /// recompiled once it has been executed often enough. This is only meaningful while `kind`
+    /// is [HotLocationKind::Compiled] with the [TierUp]'s trace: otherwise it is ignored.
+    pub(crate) tier_up: Option<TierUp>,
+}
+
+/// A baseline trace awaiting recompilation (see [MT::recompile_threshold]).
+pub(crate) struct TierUp {
+    /// The [TraceId] of the baseline trace.
+    pub(crate) ctrid: TraceId,
+    /// The recorded trace from which the baseline trace was compiled.
+    pub(crate) trace: RecordedTrace,
 }
 
 impl HotLocation {
+    /// Return the threshold at which this location is considered hot.
+    pub(crate) fn hot_threshold(&self, mt: &Arc<MT>) -> HotThreshold {
+        self.hot_threshold.unwrap_or_else(|| mt.hot_threshold())
+    }
+
     /// A trace, or the compilation of a trace, starting at this [HotLocation] led to an error. The
     /// return value indicates whether further traces for this location should be generated or not.
     pub(crate) fn tracecompilation_error(&mut self, mt: &Arc<MT>) -> TraceFailed {
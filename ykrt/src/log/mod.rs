@@ -5,7 +5,7 @@
 
 #[cfg(feature = "ykd")]
 use parking_lot::Mutex;
-use std::{env, error::Error, fmt, fs::File, io::Write, path::PathBuf};
+use std::{error::Error, fmt, fs::File, io::Write, path::PathBuf};
 use strum::{EnumCount, FromRepr};
 
 #[cfg(feature = "ykd")]
@@ -38,39 +38,40 @@ pub(crate) struct Log {
 }
 
 impl Log {
-    pub(crate) fn new() -> Result<Self, Box<dyn Error>> {
-        match env::var("YKD_LOG") {
-            Ok(s) => {
-                let (path, level) = match s.split(':').collect::<Vec<_>>()[..] {
-                    [path, level] => {
-                        if path == "-" {
-                            (None, level)
-                        } else {
-                            let path = PathBuf::from(path);
-                            // If there's an existing log file, truncate (i.e. empty it), so that later
-                            // appends to the log aren't appending to a previous log run.
-                            File::create(&path).ok();
-                            (Some(path), level)
-                        }
-                    }
-                    [level] => (None, level),
-                    [..] => return Err("YKD_LOG must be of the format `[<path|->:]<level>".into()),
-                };
-                let level = level
-                    .parse::<u8>()
-                    .map_err(|e| format!("Invalid YKD_LOG level '{s}': {e}"))?;
-                // This unwrap can only fail dynamically if we've got the types wrong statically
-                // (i.e. it'll fail as soon as this code is executed for the first time).
-                let max_level = u8::try_from(Verbosity::COUNT).unwrap() - 1;
-                let level = Verbosity::from_repr(level)
-                    .ok_or_else(|| format!("YKD_LOG level {level} exceeds maximum {max_level}"))?;
-                Ok(Self { path, level })
-            }
-            Err(_) => Ok(Self {
-                path: None,
-                level: Verbosity::Error,
-            }),
+    /// Create a new logger which writes to `path` (or stderr if `None`) at verbosity `level`. If
+    /// `path` refers to an existing file, it is truncated.
+    pub(crate) fn new(path: Option<PathBuf>, level: u8) -> Result<Self, Box<dyn Error>> {
+        // This unwrap can only fail dynamically if we've got the types wrong statically (i.e.
+        // it'll fail as soon as this code is executed for the first time).
+        let max_level = u8::try_from(Verbosity::COUNT).unwrap() - 1;
+        let level = Verbosity::from_repr(level)
+            .ok_or_else(|| format!("Log level {level} exceeds maximum {max_level}"))?;
+        if let Some(path) = &path {
+            // If there's an existing log file, truncate (i.e. empty it), so that later appends to
+            // the log aren't appending to a previous log run.
+            File::create(path).ok();
         }
+        Ok(Self { path, level })
+    }
+
+    /// Parse a `YKD_LOG`-style string `[<path|->:]<level>` into a `(path, level)` pair suitable
+    /// for passing to [Log::new]. Note that `level` is not checked for validity.
+    pub(crate) fn parse_spec(s: &str) -> Result<(Option<PathBuf>, u8), Box<dyn Error>> {
+        let (path, level) = match s.split(':').collect::<Vec<_>>()[..] {
+            [path, level] => {
+                if path == "-" {
+                    (None, level)
+                } else {
+                    (Some(PathBuf::from(path)), level)
+                }
+            }
+            [level] => (None, level),
+            [..] => return Err("YKD_LOG must be of the format `[<path|->:]<level>".into()),
+        };
+        let level = level
+            .parse::<u8>()
+            .map_err(|e| format!("Invalid YKD_LOG level '{s}': {e}"))?;
+        Ok((path, level))
     }
 
     #[cfg(feature = "ykd")]
@@ -5,190 +5,222 @@
 
 #[cfg(feature = "yk_testing")]
 use crate::mt::MT;
-#[cfg(not(test))]
-use std::env;
 #[cfg(feature = "yk_testing")]
-use std::sync::Condvar;
+use std::sync::{Condvar, Mutex};
 use std::{
     cell::Cell,
     fs,
-    ops::DerefMut,
-    sync::Mutex,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 use strum::{Display, EnumCount, EnumIter, IntoEnumIterator};
 
-/// Record yk statistics if enabled. In non-testing mode, this is only enabled if the end user
-/// defines the environment variable `YKD_LOG_STATS`. In testing mode, this is always enabled, with
-/// output being sent to `stderr`.
+/// Record yk statistics. Statistics are always recorded (so that they can be queried with
+/// [crate::MT::stats_snapshot]), but they are only output at shutdown if the end user provides a
+/// stats output path (by default via the environment variable `YKD_LOG_STATS`).
 pub(crate) struct Stats {
-    // On most runs of yk we anticipate that the end user won't want to be recording JIT
-    // statistics, so we want to able to do the quickest possible check for "are any stats to be
-    // recorded?" The outer `Option` means thus becomes a simple `if (NULL) { return}` check: only
-    // if stats are to be recorded do we have to go to the expense of locking a `Mutex`.
-    inner: Option<Mutex<StatsInner>>,
-    // In `yk_testing`, this [CondVar] allows threads to wait until a certain set of events have
+    /// The path to write output to at shutdown, if any. If exactly equal to `-`, output will be
+    /// written to stderr.
+    output_path: Option<String>,
+    /// How many traces were recorded successfully?
+    traces_recorded_ok: AtomicU64,
+    /// How many traces were recorded unsuccessfully?
+    traces_recorded_err: AtomicU64,
+    /// How many traces were compiled successfully?
+    traces_compiled_ok: AtomicU64,
+    /// How many traces were compiled unsuccessfully?
+    traces_compiled_err: AtomicU64,
+    /// How many times have traces been executed? Note that the same trace can count arbitrarily
+    /// many times to this.
+    trace_executions: AtomicU64,
+    /// How many top-level traces have been evicted from the code cache?
+    trace_evictions: AtomicU64,
+    /// How many guards remained in traces after optimisation?
+    guards_compiled: AtomicU64,
+    /// How many guards did the optimiser remove from traces?
+    guards_removed: AtomicU64,
+    /// The time, in nanoseconds, spent in each [TimingState].
+    durations: [AtomicU64; TimingState::COUNT],
+    // In `yk_testing`, this [Condvar] allows threads to wait until a certain set of events have
     // happened with the [Self::wait_until] function.
     #[cfg(feature = "yk_testing")]
-    wait_until_condvar: Option<Condvar>,
+    wait_until_condvar: (Mutex<()>, Condvar),
 }
 
-struct StatsInner {
-    /// The path to write output. If exactly equal to `-`, output will be written to stderr.
-    output_path: String,
+/// A snapshot of yk's statistics (see [crate::MT::stats_snapshot]). Durations only include time
+/// up to each thread's most recent change of state (e.g. starting or stopping tracing).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatsSnapshot {
     /// How many traces were recorded successfully?
-    traces_recorded_ok: u64,
+    pub traces_recorded_ok: u64,
     /// How many traces were recorded unsuccessfully?
-    traces_recorded_err: u64,
+    pub traces_recorded_err: u64,
     /// How many traces were compiled successfully?
-    traces_compiled_ok: u64,
+    pub traces_compiled_ok: u64,
     /// How many traces were compiled unsuccessfully?
-    traces_compiled_err: u64,
+    pub traces_compiled_err: u64,
     /// How many times have traces been executed? Note that the same trace can count arbitrarily
     /// many times to this.
-    trace_executions: u64,
-    /// The time spent in each [TimingState].
-    durations: [Duration; TimingState::COUNT],
+    pub trace_executions: u64,
+    /// How many top-level traces have been evicted from the code cache?
+    pub trace_evictions: u64,
+    /// How many guards remained in traces after optimisation?
+    pub guards_compiled: u64,
+    /// How many guards did the optimiser remove from traces?
+    pub guards_removed: u64,
+    /// How long was spent tracing?
+    pub duration_tracing: Duration,
+    /// How long was spent compiling traces?
+    pub duration_compiling: Duration,
+    /// How long was spent deoptimising from failed guards?
+    pub duration_deopting: Duration,
+    /// How long was spent executing JIT compiled code?
+    pub duration_jit_executing: Duration,
+    /// How long was spent outside yk (roughly "in the interpreter")?
+    pub duration_outside_yk: Duration,
 }
 
 impl Stats {
-    #[cfg(not(test))]
-    pub fn new() -> Self {
-        if let Ok(p) = env::var("YKD_LOG_STATS") {
-            Self {
-                inner: Some(Mutex::new(StatsInner::new(p))),
-                #[cfg(feature = "yk_testing")]
-                wait_until_condvar: Some(Condvar::new()),
-            }
-        } else {
-            Self {
-                inner: None,
-                #[cfg(feature = "yk_testing")]
-                wait_until_condvar: None,
-            }
-        }
-    }
-
-    #[cfg(test)]
-    pub fn new() -> Self {
+    /// Create a new statistics recorder. If `output_path` is `Some`, statistics will be written to
+    /// that path (where `-` means stderr) at shutdown.
+    pub fn new(output_path: Option<String>) -> Self {
         Self {
-            inner: Some(Mutex::new(StatsInner::new("-".to_string()))),
+            output_path,
+            traces_recorded_ok: AtomicU64::new(0),
+            traces_recorded_err: AtomicU64::new(0),
+            traces_compiled_ok: AtomicU64::new(0),
+            traces_compiled_err: AtomicU64::new(0),
+            trace_executions: AtomicU64::new(0),
+            trace_evictions: AtomicU64::new(0),
+            guards_compiled: AtomicU64::new(0),
+            guards_removed: AtomicU64::new(0),
+            durations: [const { AtomicU64::new(0) }; TimingState::COUNT],
             #[cfg(feature = "yk_testing")]
-            wait_until_condvar: None,
+            wait_until_condvar: (Mutex::new(()), Condvar::new()),
         }
     }
 
-    /// If `YKD_LOG_STATS` was specified, update `inner` by running the function `f`, otherwise return
-    /// immediately without calling `f`.
-    fn update_with<F>(&self, f: F)
-    where
-        F: FnOnce(&mut StatsInner),
-    {
-        if let Some(mtx) = &self.inner {
-            let mut lk = mtx.lock().unwrap();
-            f(lk.deref_mut());
-            #[cfg(feature = "yk_testing")]
-            {
-                drop(lk);
-                if let Some(x) = self.wait_until_condvar.as_ref() {
-                    x.notify_all()
-                }
-            }
+    /// Add `n` to the counter `ctr`.
+    fn add(&self, ctr: &AtomicU64, n: u64) {
+        ctr.fetch_add(n, Ordering::Relaxed);
+        #[cfg(feature = "yk_testing")]
+        {
+            // Taking the lock ensures that a thread in [Self::wait_until] can't miss this update
+            // between testing the statistics and waiting.
+            drop(self.wait_until_condvar.0.lock().unwrap());
+            self.wait_until_condvar.1.notify_all();
         }
     }
 
-    /// Iff `YKD_LOG_STATS` is set, suspend this thread's execution until `test(StatsInner)` returns
-    /// true. Note that a lock is held on yk's statistics while `test` is called, so `test` should
-    /// not perform lengthy calculations (if it does, it may block other threads).
+    /// Suspend this thread's execution until `test(StatsSnapshot)` returns true.
     ///
     /// # Panics
     ///
-    /// If `YKD_LOG_STATS` is not set or if a worker thread dies whilst waiting.
+    /// If a worker thread dies whilst waiting.
     #[cfg(feature = "yk_testing")]
     fn wait_until<F>(&self, mt: &MT, test: F)
     where
-        F: Fn(&mut StatsInner) -> bool,
+        F: Fn(&StatsSnapshot) -> bool,
     {
-        match &self.inner {
-            Some(mtx) => {
-                let mut lk = mtx.lock().unwrap();
-                while !test(lk.deref_mut()) {
-                    lk = self
-                        .wait_until_condvar
-                        .as_ref()
-                        .expect("Can't call wait_until unless YKD_LOG_STATS is set")
-                        .wait_timeout(lk, Duration::from_secs(1))
-                        .unwrap()
-                        .0;
-                    mt.check_job_queue_integrity();
-                }
-            }
-            None => panic!("Can't call wait_until unless YKD_LOG_STATS is set"),
+        let (mtx, condvar) = &self.wait_until_condvar;
+        let mut lk = mtx.lock().unwrap();
+        while !test(&self.snapshot()) {
+            lk = condvar.wait_timeout(lk, Duration::from_secs(1)).unwrap().0;
+            mt.check_job_queue_integrity();
         }
     }
 
     /// Increment the "a trace has been recorded successfully" count.
     pub fn trace_recorded_ok(&self) {
-        self.update_with(|inner| inner.traces_recorded_ok += 1);
+        self.add(&self.traces_recorded_ok, 1);
     }
 
     /// Increment the "a trace has been recorded unsuccessfully" count.
     pub fn trace_recorded_err(&self) {
-        self.update_with(|inner| inner.traces_recorded_err += 1);
+        self.add(&self.traces_recorded_err, 1);
     }
 
     /// Increment the "a trace has been compiled successfully" count.
     pub fn trace_compiled_ok(&self) {
-        self.update_with(|inner| inner.traces_compiled_ok += 1);
+        self.add(&self.traces_compiled_ok, 1);
     }
 
     /// Increment the "a trace has been compiled unsuccessfully" count.
     pub fn trace_compiled_err(&self) {
-        self.update_with(|inner| inner.traces_compiled_err += 1);
+        self.add(&self.traces_compiled_err, 1);
     }
 
     /// Increment the "a compiled trace has started execution" count.
     pub fn trace_executed(&self) {
-        self.update_with(|inner| inner.trace_executions += 1);
+        self.add(&self.trace_executions, 1);
+    }
+
+    /// Add `n` to the "top-level traces evicted from the code cache" count.
+    pub fn traces_evicted(&self, n: u64) {
+        if n > 0 {
+            self.add(&self.trace_evictions, n);
+        }
+    }
+
+    /// Add `n` to the "guards in compiled traces" count.
+    pub fn guards_compiled(&self, n: u64) {
+        if n > 0 {
+            self.add(&self.guards_compiled, n);
+        }
+    }
+
+    /// Add `n` to the "guards removed by the optimiser" count.
+    pub fn guards_removed(&self, n: u64) {
+        if n > 0 {
+            self.add(&self.guards_removed, n);
+        }
     }
 
     /// Change the [TimingState] the current thread is in.
     pub fn timing_state(&self, new_state: TimingState) {
-        self.update_with(|inner| {
-            let now = Instant::now();
-            let (prev_state, then) = VM_STATE.replace((new_state, now));
-            let d = now.saturating_duration_since(then);
-            inner.durations[prev_state as usize] =
-                inner.durations[prev_state as usize].saturating_add(d);
-        });
+        let now = Instant::now();
+        let (prev_state, then) = VM_STATE.replace((new_state, now));
+        let d = now.saturating_duration_since(then);
+        self.durations[prev_state as usize].fetch_add(
+            u64::try_from(d.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
     }
 
-    /// Output these statistics to the appropriate output path.
-    pub(crate) fn output(&self) {
-        self.update_with(|inner| inner.output());
+    /// Return the time spent in `state`.
+    fn duration(&self, state: TimingState) -> Duration {
+        Duration::from_nanos(self.durations[state as usize].load(Ordering::Relaxed))
     }
-}
 
-impl StatsInner {
-    fn new(output_path: String) -> Self {
-        Self {
-            output_path,
-            traces_recorded_ok: 0,
-            traces_recorded_err: 0,
-            traces_compiled_ok: 0,
-            traces_compiled_err: 0,
-            trace_executions: 0,
-            durations: [Duration::new(0, 0); TimingState::COUNT],
+    /// Return a snapshot of the current statistics.
+    pub(crate) fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            traces_recorded_ok: self.traces_recorded_ok.load(Ordering::Relaxed),
+            traces_recorded_err: self.traces_recorded_err.load(Ordering::Relaxed),
+            traces_compiled_ok: self.traces_compiled_ok.load(Ordering::Relaxed),
+            traces_compiled_err: self.traces_compiled_err.load(Ordering::Relaxed),
+            trace_executions: self.trace_executions.load(Ordering::Relaxed),
+            trace_evictions: self.trace_evictions.load(Ordering::Relaxed),
+            guards_compiled: self.guards_compiled.load(Ordering::Relaxed),
+            guards_removed: self.guards_removed.load(Ordering::Relaxed),
+            duration_tracing: self.duration(TimingState::Tracing),
+            duration_compiling: self.duration(TimingState::Compiling),
+            duration_deopting: self.duration(TimingState::Deopting),
+            duration_jit_executing: self.duration(TimingState::JitExecuting),
+            duration_outside_yk: self.duration(TimingState::OutsideYk),
         }
     }
 
-    /// Output these statistics to the appropriate output path.
-    fn output(&self) {
+    /// If an output path was specified, output these statistics to it.
+    pub(crate) fn output(&self) {
+        let Some(output_path) = &self.output_path else {
+            return;
+        };
         let json = self.to_json();
-        if self.output_path == "-" {
+        if output_path == "-" {
             eprintln!("{json}");
         } else {
-            fs::write(&self.output_path, json).ok();
+            fs::write(output_path, json).ok();
         }
     }
 
@@ -199,32 +231,42 @@ impl StatsInner {
             format!("{}.{:03}", d.as_secs(), d.subsec_millis())
         }
 
+        let snap = self.snapshot();
         let mut fields = vec![
             (
                 "traces_recorded_ok".to_owned(),
-                self.traces_recorded_ok.to_string(),
+                snap.traces_recorded_ok.to_string(),
             ),
             (
                 "traces_recorded_err".to_owned(),
-                self.traces_recorded_err.to_string(),
+                snap.traces_recorded_err.to_string(),
             ),
             (
                 "traces_compiled_ok".to_owned(),
-                self.traces_compiled_ok.to_string(),
+                snap.traces_compiled_ok.to_string(),
             ),
             (
                 "traces_compiled_err".to_owned(),
-                self.traces_compiled_err.to_string(),
+                snap.traces_compiled_err.to_string(),
             ),
             (
                 "trace_executions".to_owned(),
-                self.trace_executions.to_string(),
+                snap.trace_executions.to_string(),
             ),
+            (
+                "trace_evictions".to_owned(),
+                snap.trace_evictions.to_string(),
+            ),
+            (
+                "guards_compiled".to_owned(),
+                snap.guards_compiled.to_string(),
+            ),
+            ("guards_removed".to_owned(), snap.guards_removed.to_string()),
         ];
         for v in TimingState::iter() {
             let s = v.to_string();
             if !s.is_empty() {
-                fields.push((s, fmt_duration(self.durations[v as usize])));
+                fields.push((s, fmt_duration(self.duration(v))));
             }
         }
         // We sort the output fields so that tests can match the output with a simple text match.
@@ -300,16 +342,13 @@ mod yk_testing {
         test: unsafe extern "C" fn(YkCStats) -> bool,
     ) {
         let mt = unsafe { &*mt };
-        if mt.stats.inner.is_none() {
-            panic!("Statistics collection not enabled");
-        }
-        mt.stats.wait_until(mt, |inner| {
+        mt.stats.wait_until(mt, |snap| {
             let cstats = YkCStats {
-                traces_recorded_ok: inner.traces_recorded_ok,
-                traces_recorded_err: inner.traces_recorded_err,
-                traces_compiled_ok: inner.traces_compiled_ok,
-                traces_compiled_err: inner.traces_compiled_err,
-                trace_executions: inner.trace_executions,
+                traces_recorded_ok: snap.traces_recorded_ok,
+                traces_recorded_err: snap.traces_recorded_err,
+                traces_compiled_ok: snap.traces_compiled_ok,
+                traces_compiled_err: snap.traces_compiled_err,
+                trace_executions: snap.trace_executions,
             };
             unsafe { test(cstats) }
         });
@@ -3,15 +3,18 @@
 use std::{
     assert_matches,
     cell::RefCell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     debug_assert_matches, env,
     error::Error,
     ffi::c_void,
     marker::PhantomData,
+    path::PathBuf,
     sync::{
         Arc,
-        atomic::{AtomicBool, AtomicU8, AtomicU16, AtomicU32, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU8, AtomicU16, AtomicU32, AtomicU64, AtomicUsize, Ordering},
     },
+    thread,
+    time::Duration,
 };
 
 use parking_lot::Mutex;
@@ -21,15 +24,16 @@ use parking_lot_core::SpinWait;
 use crate::{
     aotsmp::{AOT_STACKMAPS, StackMapIdx, load_aot_stackmaps},
     compile::{
-        CompilationError, CompiledTrace, Compiler, GuardId, Trace, TraceEnd, TraceStart,
-        default_compiler,
+        CompilationError, CompiledTrace, Compiler, GuardId, Tier, Trace, TraceEnd, TraceStart,
+        default_compiler, j2::OptConfig,
     },
+    event::{AbortKind, Event},
     frame::{FrameRelationship, frame_relationship},
     job_queue::{Job, JobQueue},
-    location::{HotLocation, HotLocationKind, Location, SeenHotLocations, TraceFailed},
+    location::{HotLocation, HotLocationKind, Location, SeenHotLocations, TierUp, TraceFailed},
     log::{
         Log, Verbosity,
-        stats::{Stats, TimingState},
+        stats::{Stats, StatsSnapshot, TimingState},
     },
     profile::{PlatformTraceProfiler, profiler_for_current_platform},
     trace::{TraceRecorder, Tracer, default_tracer},
@@ -55,6 +59,8 @@ macro_rules! yklog {
 // error.
 #[cfg(target_pointer_width = "64")]
 pub type HotThreshold = u32;
+/// The identifier of a watchpoint (see [MT::watch_new]).
+pub type WatchId = u64;
 #[cfg(target_pointer_width = "64")]
 type AtomicHotThreshold = AtomicU32;
 
@@ -90,6 +96,194 @@ static __yk_thread_tracing_state: AtomicU8 = const { AtomicU8::new(IsTracing::No
 /// This optimisation avoids `__tls_get_addr` overhead when not tracing.
 static TRACING_THREAD_COUNT: AtomicU32 = AtomicU32::new(0);
 
+/// The configuration of a new [MT] instance.
+///
+/// [MTConfig::from_env] creates a configuration whose values are taken from the `YK_*` / `YKD_*`
+/// environment variables where they are set (and built-in defaults otherwise): each value can then
+/// be overridden before being passed to [MT::with_config]. [MTConfig::default] ignores the
+/// environment entirely. For example:
+///
+/// ```ignore
+/// let mut config = MTConfig::from_env()?;
+/// config.hot_threshold(50).jobs(2);
+/// let mt = MT::with_config(config)?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct MTConfig {
+    hot_threshold: HotThreshold,
+    sidetrace_threshold: HotThreshold,
+    recompile_threshold: HotThreshold,
+    trace_failure_threshold: TraceCompilationErrorThreshold,
+    opt_level: u8,
+    opt_config: OptConfig,
+    jobs: usize,
+    jit_enabled: bool,
+    log_path: Option<PathBuf>,
+    log_level: u8,
+    stats_path: Option<String>,
+    trace_collection_interval: Option<Duration>,
+    code_cache_limit: usize,
+}
+
+impl MTConfig {
+    /// Create a configuration from the `YK_HOT_THRESHOLD`, `YK_SIDETRACE_THRESHOLD`,
+    /// `YK_RECOMPILE_THRESHOLD`, `YK_JOBS`, `YK_JITC`, `YK_TRACE_COLLECTION_INTERVAL`,
+    /// `YK_CODE_CACHE_LIMIT`, `YKD_OPT`, `YKD_OPT_PASSES`, `YKD_OPT_FUEL`, `YKD_LOG`, and
+    /// `YKD_LOG_STATS` environment variables, using built-in defaults for any that are not set.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let mut config = Self::default();
+        if let Ok(s) = env::var("YKD_OPT") {
+            config.opt_level = s
+                .parse::<u8>()
+                .map_err(|e| format!("Invalid optimisation level '{s}': {e}"))?;
+        }
+        config.opt_config = OptConfig::from_env()?;
+        if let Ok(s) = env::var("YK_HOT_THRESHOLD") {
+            config.hot_threshold = s
+                .parse::<HotThreshold>()
+                .map_err(|e| format!("Invalid hot threshold '{s}': {e}"))?;
+        }
+        if let Ok(s) = env::var("YK_SIDETRACE_THRESHOLD") {
+            config.sidetrace_threshold = s
+                .parse::<HotThreshold>()
+                .map_err(|e| format!("Invalid sidetrace threshold '{s}': {e}"))?;
+        }
+        if let Ok(s) = env::var("YK_RECOMPILE_THRESHOLD") {
+            config.recompile_threshold = s
+                .parse::<HotThreshold>()
+                .map_err(|e| format!("Invalid recompile threshold '{s}': {e}"))?;
+        }
+        if let Ok(s) = env::var("YK_JOBS") {
+            config.jobs = s
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid value for YK_JOBS '{s}': {e}"))?;
+        }
+        if let Ok(s) = env::var("YK_JITC") {
+            config.jit_enabled = s != "none";
+        }
+        if let Ok(s) = env::var("YK_TRACE_COLLECTION_INTERVAL") {
+            let ms = s
+                .parse::<u64>()
+                .map_err(|e| format!("Invalid trace collection interval '{s}': {e}"))?;
+            config.trace_collection_interval = (ms > 0).then(|| Duration::from_millis(ms));
+        }
+        if let Ok(s) = env::var("YK_CODE_CACHE_LIMIT") {
+            config.code_cache_limit = s
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid code cache limit '{s}': {e}"))?;
+        }
+        if let Ok(s) = env::var("YKD_LOG") {
+            let (path, level) = Log::parse_spec(&s)?;
+            config.log_path = path;
+            config.log_level = level;
+        }
+        if let Ok(s) = env::var("YKD_LOG_STATS") {
+            config.stats_path = Some(s);
+        }
+        Ok(config)
+    }
+
+    /// Set the threshold at which `Location`s are considered hot.
+    pub fn hot_threshold(&mut self, hot_threshold: HotThreshold) -> &mut Self {
+        self.hot_threshold = hot_threshold;
+        self
+    }
+
+    /// Set the threshold at which guard failures are considered hot and side-tracing should start.
+    pub fn sidetrace_threshold(&mut self, sidetrace_threshold: HotThreshold) -> &mut Self {
+        self.sidetrace_threshold = sidetrace_threshold;
+        self
+    }
+
+    /// Set the number of executions after which a trace compiled without optimisation is
+    /// recompiled with optimisation. 0 disables tiered compilation. See [MT::recompile_threshold].
+    pub fn recompile_threshold(&mut self, recompile_threshold: HotThreshold) -> &mut Self {
+        self.recompile_threshold = recompile_threshold;
+        self
+    }
+
+    /// Set the threshold at which a `Location` from which tracing has failed multiple times is
+    /// marked as "do not try tracing again". Must be >= 1.
+    pub fn trace_failure_threshold(
+        &mut self,
+        trace_failure_threshold: TraceCompilationErrorThreshold,
+    ) -> &mut Self {
+        self.trace_failure_threshold = trace_failure_threshold;
+        self
+    }
+
+    /// Set the trace optimisation level, where 0 is the lowest possible level.
+    pub fn opt_level(&mut self, opt_level: u8) -> &mut Self {
+        self.opt_level = opt_level;
+        self
+    }
+
+    /// Set the maximum number of compilation worker threads. A value of 0 is treated as 1. If
+    /// `YKD_OPT_FUEL` specifies fuel shared between all traces, only 1 thread is used.
+    pub fn jobs(&mut self, jobs: usize) -> &mut Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Set whether JIT compilation is enabled.
+    pub fn jit_enabled(&mut self, jit_enabled: bool) -> &mut Self {
+        self.jit_enabled = jit_enabled;
+        self
+    }
+
+    /// Set the path that yk will log to. `None` means stderr.
+    pub fn log_path(&mut self, log_path: Option<PathBuf>) -> &mut Self {
+        self.log_path = log_path;
+        self
+    }
+
+    /// Set the verbosity of yk's logging. See `YKD_LOG` for the meaning of each level.
+    pub fn log_level(&mut self, log_level: u8) -> &mut Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// Set the path that statistics will be written to at shutdown, where `-` means stderr. `None`
+    /// disables statistics collection.
+    pub fn stats_path(&mut self, stats_path: Option<String>) -> &mut Self {
+        self.stats_path = stats_path;
+        self
+    }
+
+    /// Set how often unreachable compiled traces are freed by a background thread (see
+    /// [MT::collect_traces]). `None` disables periodic collection.
+    pub fn trace_collection_interval(&mut self, interval: Option<Duration>) -> &mut Self {
+        self.trace_collection_interval = interval;
+        self
+    }
+
+    /// Set the maximum size, in bytes, of the code of all compiled traces. 0 means unlimited.
+    pub fn code_cache_limit(&mut self, code_cache_limit: usize) -> &mut Self {
+        self.code_cache_limit = code_cache_limit;
+        self
+    }
+}
+
+impl Default for MTConfig {
+    fn default() -> Self {
+        Self {
+            hot_threshold: DEFAULT_HOT_THRESHOLD,
+            sidetrace_threshold: DEFAULT_SIDETRACE_THRESHOLD,
+            recompile_threshold: 0,
+            trace_failure_threshold: DEFAULT_TRACECOMPILATION_ERROR_THRESHOLD,
+            opt_level: 1,
+            opt_config: OptConfig::default(),
+            jobs: num_cpus::get() - 1,
+            jit_enabled: true,
+            log_path: None,
+            log_level: Verbosity::Error as u8,
+            stats_path: None,
+            trace_collection_interval: None,
+            code_cache_limit: 0,
+        }
+    }
+}
+
 /// A meta-tracer. This is always passed around stored in an [Arc].
 ///
 /// When you are finished with this meta-tracer, it is best to explicitly call [MT::shutdown] to
@@ -104,10 +298,18 @@ pub struct MT {
     shutdown: AtomicBool,
     hot_threshold: AtomicHotThreshold,
     sidetrace_threshold: AtomicHotThreshold,
+    /// If non-zero, traces starting at a control point are first compiled without optimisation,
+    /// and then recompiled with optimisation after they have been executed this many times.
+    recompile_threshold: AtomicHotThreshold,
     trace_failure_threshold: AtomicTraceCompilationErrorThreshold,
     /// The requested optimisation level, where 0 is the lowest possible level. How these levels
     /// are interpreted is up to a given optimiser. Default: 1.
     opt_level: AtomicU8,
+    /// Which optimisation passes are run, and with how much fuel. Only meaningful if `opt_level`
+    /// is non-zero.
+    opt_config: OptConfig,
+    /// The fuel remaining if `opt_config` specifies fuel shared between all traces.
+    opt_fuel: AtomicU64,
     /// The queue of compiling jobs.
     job_queue: Arc<JobQueue>,
     /// The [Tracer] that should be used for creating future traces. Note that this might not be
@@ -118,16 +320,45 @@ pub struct MT {
     compiler: Mutex<Arc<dyn Compiler>>,
     /// A monotonically increasing integer that uniquely identifies each compiled trace.
     compiled_trace_id: AtomicU64,
+    /// Traces whose [TraceId] is less than this value were invalidated by [MT::invalidate_all]:
+    /// if they are still being recorded or compiled, they will be thrown away when compilation
+    /// completes.
+    invalidated_before: AtomicU64,
     /// The currently available compiled traces. This is a [HashMap] because it is potentially a
-    /// sparse mapping due to (1) (one day!) we might garbage collect traces (2) some
-    /// [TraceId]s that we hand out are "lost" because a trace failed to compile.
+    /// sparse mapping due to (1) traces being freed by [MT::collect_traces] (2) some [TraceId]s
+    /// that we hand out are "lost" because a trace failed to compile.
     pub(crate) compiled_traces: Mutex<HashMap<TraceId, Arc<dyn CompiledTrace>>>,
+    /// How many threads are currently executing JIT code produced by this meta-tracer? A thread
+    /// that has returned from a trace is only removed from this count once it is guaranteed to
+    /// have left JIT code (see [MTThread::left_jit_code]).
+    executing_threads: AtomicUsize,
+    /// Has [MT::collect_traces] been unable to free traces because a thread was executing JIT
+    /// code? If so, it is retried by [MT::collect_pending_traces].
+    collect_pending: AtomicBool,
+    /// The maximum size, in bytes, of the code in `compiled_traces`. 0 means unlimited.
+    code_cache_limit: AtomicUsize,
+    /// The current size, in bytes, of the code in `compiled_traces`.
+    code_cache_size: AtomicUsize,
+    /// A logical clock used to determine which traces were least recently executed.
+    code_cache_clock: AtomicU64,
+    /// The ID of the next watchpoint created by [MT::watch_new].
+    watch_id: AtomicU64,
+    /// The watchpoints created by [MT::watch_new]. Watchpoints are never removed from this map.
+    watches: Mutex<HashMap<WatchId, Watch>>,
+    /// The handler set by [MT::set_event_handler], if any.
+    event_handler: Mutex<Option<Arc<dyn Fn(&Event) + Send + Sync>>>,
+    /// Is `event_handler` `Some`? This allows [MT::emit_event] to avoid locking `event_handler`
+    /// in the common case where no handler is set.
+    has_event_handler: AtomicBool,
     pub(crate) log: Log,
     pub(crate) stats: Stats,
     /// The trace profiler implementation to use.
     trace_profiler: Arc<dyn PlatformTraceProfiler>,
-    /// Whether JIT compilation is enabled. Can be disabled with YK_JITC=none.
+    /// Whether JIT compilation is enabled. Can be disabled with YK_JITC=none,
+    /// [MTConfig::jit_enabled], or (at run-time) [MT::set_jit_enabled].
     jit_enabled: AtomicBool,
+    /// Whether already compiled traces should be executed. Default: true.
+    execute_compiled_traces: AtomicBool,
 }
 
 impl std::fmt::Debug for MT {
@@ -143,48 +374,74 @@ impl MT {
 
     // Create a new meta-tracer instance. Arbitrarily many of these can be created, though there
     // are no guarantees as to whether they will share resources effectively or fairly.
+    //
+    // The meta-tracer's configuration is taken from environment variables: see
+    // [MTConfig::from_env].
     pub fn new() -> Result<Arc<Self>, Box<dyn Error>> {
+        Self::with_config(MTConfig::from_env()?)
+    }
+
+    /// Create a new meta-tracer instance with the configuration `config`.
+    pub fn with_config(config: MTConfig) -> Result<Arc<Self>, Box<dyn Error>> {
+        if config.trace_failure_threshold < 1 {
+            return Err("Trace failure threshold must be >= 1.".into());
+        }
         load_aot_stackmaps();
-        let opt_level = match env::var("YKD_OPT") {
-            Ok(s) => s
-                .parse::<u8>()
-                .map_err(|e| format!("Invalid optimisation level '{s}': {e}"))?,
-            Err(_) => 1,
-        };
-        let hot_threshold = match env::var("YK_HOT_THRESHOLD") {
-            Ok(s) => s
-                .parse::<HotThreshold>()
-                .map_err(|e| format!("Invalid hot threshold '{s}': {e}"))?,
-            Err(_) => DEFAULT_HOT_THRESHOLD,
-        };
-        let sidetrace_threshold = match env::var("YK_SIDETRACE_THRESHOLD") {
-            Ok(s) => s
-                .parse::<HotThreshold>()
-                .map_err(|e| format!("Invalid sidetrace threshold '{s}': {e}"))?,
-            Err(_) => DEFAULT_SIDETRACE_THRESHOLD,
-        };
-        let jit_enabled = match env::var("YK_JITC") {
-            Ok(s) => s != "none",
-            Err(_) => true, // Default to enabled
-        };
-        Ok(Arc::new(Self {
+        let mt = Arc::new(Self {
             shutdown: AtomicBool::new(false),
-            hot_threshold: AtomicHotThreshold::new(hot_threshold),
-            sidetrace_threshold: AtomicHotThreshold::new(sidetrace_threshold),
+            hot_threshold: AtomicHotThreshold::new(config.hot_threshold),
+            sidetrace_threshold: AtomicHotThreshold::new(config.sidetrace_threshold),
+            recompile_threshold: AtomicHotThreshold::new(config.recompile_threshold),
             trace_failure_threshold: AtomicTraceCompilationErrorThreshold::new(
-                DEFAULT_TRACECOMPILATION_ERROR_THRESHOLD,
+                config.trace_failure_threshold,
             ),
-            opt_level: AtomicU8::new(opt_level),
-            job_queue: JobQueue::new(),
+            opt_level: AtomicU8::new(config.opt_level),
+            opt_fuel: AtomicU64::new(config.opt_config.total_fuel().unwrap_or(0)),
+            // Fuel shared between traces is consumed in whatever order they are compiled in: to
+            // make that order as predictable as possible, traces are then compiled one at a time.
+            job_queue: JobQueue::new(if config.opt_config.total_fuel().is_some() {
+                1
+            } else {
+                config.jobs
+            }),
+            opt_config: config.opt_config,
             tracer: Mutex::new(default_tracer()?),
             compiler: Mutex::new(default_compiler()?),
             compiled_trace_id: AtomicU64::new(0),
+            invalidated_before: AtomicU64::new(0),
             compiled_traces: Mutex::new(HashMap::new()),
-            log: Log::new()?,
-            stats: Stats::new(),
+            executing_threads: AtomicUsize::new(0),
+            collect_pending: AtomicBool::new(false),
+            code_cache_limit: AtomicUsize::new(config.code_cache_limit),
+            code_cache_size: AtomicUsize::new(0),
+            code_cache_clock: AtomicU64::new(0),
+            watch_id: AtomicU64::new(0),
+            watches: Mutex::new(HashMap::new()),
+            event_handler: Mutex::new(None),
+            has_event_handler: AtomicBool::new(false),
+            log: Log::new(config.log_path, config.log_level)?,
+            stats: Stats::new(config.stats_path),
             trace_profiler: profiler_for_current_platform(),
-            jit_enabled: AtomicBool::new(jit_enabled),
-        }))
+            jit_enabled: AtomicBool::new(config.jit_enabled),
+            execute_compiled_traces: AtomicBool::new(true),
+        });
+        if let Some(interval) = config.trace_collection_interval {
+            // We only keep a weak reference alive to `MT`, as otherwise the collection thread
+            // would cause it to never be dropped.
+            let mt_wk = Arc::downgrade(&mt);
+            thread::spawn(move || {
+                loop {
+                    thread::sleep(interval);
+                    match mt_wk.upgrade() {
+                        Some(mt) if !mt.shutdown.load(Ordering::Relaxed) => {
+                            mt.collect_traces();
+                        }
+                        _ => return,
+                    }
+                }
+            });
+        }
+        Ok(mt)
     }
 
     /// Put this meta-tracer into shutdown mode, panicking if any problems are discovered. This
@@ -205,6 +462,13 @@ impl MT {
         }
     }
 
+    /// Return a snapshot of this meta-tracer's statistics. This is cheap enough to be called
+    /// frequently, and works whether or not statistics are output at shutdown (see
+    /// [MTConfig::stats_path]).
+    pub fn stats_snapshot(&self) -> StatsSnapshot {
+        self.stats.snapshot()
+    }
+
     /// Check the integrity of the job queue: if any job queue thread has panicked, this function
     /// will itself panic. This should only be used for testing purposes.
     #[cfg(feature = "yk_testing")]
@@ -235,6 +499,26 @@ impl MT {
             .store(hot_threshold, Ordering::Relaxed);
     }
 
+    /// Return this `MT` instance's current recompile threshold. Notice that this value can be
+    /// changed by other threads and is thus potentially stale as soon as it is read.
+    ///
+    /// If non-zero, tiered compilation is enabled: a trace starting at a control point is first
+    /// compiled quickly without optimisation. Once it has been executed this many times (where
+    /// each iteration of a looping trace counts as an execution), it is recompiled with
+    /// optimisation, and the new trace replaces the old. Side-traces of the old trace are retired
+    /// along with it. If 0, traces are compiled once, with optimisation.
+    pub fn recompile_threshold(self: &Arc<Self>) -> HotThreshold {
+        self.recompile_threshold.load(Ordering::Relaxed)
+    }
+
+    /// Set the recompile threshold (see [Self::recompile_threshold]). Traces already compiled
+    /// without optimisation will still be recompiled, once they have been executed as many times
+    /// as the recompile threshold in force when they were compiled.
+    pub fn set_recompile_threshold(self: &Arc<Self>, recompile_threshold: HotThreshold) {
+        self.recompile_threshold
+            .store(recompile_threshold, Ordering::Relaxed);
+    }
+
     /// Return this `MT` instance's current trace failure threshold. Notice that this value can be
     /// changed by other threads and is thus potentially stale as soon as it is read.
     pub fn trace_failure_threshold(self: &Arc<Self>) -> TraceCompilationErrorThreshold {
@@ -260,11 +544,76 @@ impl MT {
         self.opt_level.load(Ordering::Relaxed)
     }
 
-    /// Return whether JIT compilation is enabled. Can be controlled with YK_JITC.
-    pub(crate) fn jit_enabled(self: &Arc<Self>) -> bool {
+    /// Return the optimiser configuration.
+    pub(crate) fn opt_config(&self) -> &OptConfig {
+        &self.opt_config
+    }
+
+    /// Try to take one unit of the optimisation fuel shared between all traces, returning the
+    /// amount of fuel there was before the unit was taken, or `None` if no fuel remains.
+    pub(crate) fn take_opt_fuel(&self) -> Option<u64> {
+        self.opt_fuel
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| x.checked_sub(1))
+            .ok()
+    }
+
+    /// Return a unit of fuel taken by [Self::take_opt_fuel] that turned out not to be needed.
+    pub(crate) fn return_opt_fuel(&self) {
+        self.opt_fuel.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Return whether JIT compilation is enabled. Notice that this value can be changed by other
+    /// threads and is thus potentially stale as soon as it is read.
+    pub fn jit_enabled(self: &Arc<Self>) -> bool {
         self.jit_enabled.load(Ordering::Relaxed)
     }
 
+    /// Enable or disable JIT compilation. While disabled, `Location`s do not count towards
+    /// hotness and no new tracing (including side-tracing) is started. If the calling thread is
+    /// recording a trace, that trace is abandoned immediately; other threads abandon their
+    /// recordings at their next control point. Traces which are already compiled (or which finish
+    /// compiling later) continue to be executed unless [Self::set_execute_compiled_traces] is used
+    /// to prevent this.
+    pub fn set_jit_enabled(self: &Arc<Self>, jit_enabled: bool) {
+        self.jit_enabled.store(jit_enabled, Ordering::Relaxed);
+        if !jit_enabled && MTThread::is_tracing() {
+            self.abandon_tracing();
+        }
+    }
+
+    /// Return whether compiled traces are executed. Notice that this value can be changed by other
+    /// threads and is thus potentially stale as soon as it is read.
+    pub fn execute_compiled_traces(self: &Arc<Self>) -> bool {
+        self.execute_compiled_traces.load(Ordering::Relaxed)
+    }
+
+    /// Set whether control points should enter already compiled traces. When set to `false`,
+    /// threads which are currently executing a trace continue to do so until they next leave it.
+    pub fn set_execute_compiled_traces(self: &Arc<Self>, execute: bool) {
+        self.execute_compiled_traces
+            .store(execute, Ordering::Relaxed);
+    }
+
+    /// Return the maximum size, in bytes, of the code of all compiled traces, where 0 means
+    /// unlimited. Notice that this value can be changed by other threads and is thus potentially
+    /// stale as soon as it is read.
+    pub fn code_cache_limit(self: &Arc<Self>) -> usize {
+        self.code_cache_limit.load(Ordering::Relaxed)
+    }
+
+    /// Set the maximum size, in bytes, of the code of all compiled traces, where 0 means
+    /// unlimited. If the code cache is currently larger than `limit`, traces are evicted
+    /// immediately.
+    pub fn set_code_cache_limit(self: &Arc<Self>, limit: usize) {
+        self.code_cache_limit.store(limit, Ordering::Relaxed);
+        self.evict_traces();
+    }
+
+    /// Return the current value of the code cache's logical clock, and advance the clock.
+    fn code_cache_tick(&self) -> u64 {
+        self.code_cache_clock.fetch_add(1, Ordering::Relaxed)
+    }
+
     /// Return a reference to the [CompiledTrace] with ID `ctrid`.
     ///
     /// # Panics
@@ -274,6 +623,445 @@ impl MT {
         Arc::clone(&self.compiled_traces.lock()[&trid])
     }
 
+    /// Return a reference to the [CompiledTrace] with ID `ctrid` or `None` if there is no such
+    /// trace (e.g. because it has been freed by [Self::collect_traces]).
+    pub(crate) fn try_compiled_trace(
+        self: &Arc<Self>,
+        trid: TraceId,
+    ) -> Option<Arc<dyn CompiledTrace>> {
+        self.compiled_traces.lock().get(&trid).cloned()
+    }
+
+    /// Free compiled traces which can no longer be executed, returning the number of traces freed.
+    ///
+    /// A trace can be executed if it is reachable from a [HotLocation] in the `Compiled` state,
+    /// either directly, or indirectly via a chain of guards (i.e. side-traces) and couplers. Since
+    /// threads executing JIT code do not record which traces they have jumped into, traces can
+    /// only be freed when no thread is executing JIT code: if any thread is, this function frees
+    /// nothing and returns 0, and collection is retried once no thread is executing JIT code (see
+    /// [Self::collect_pending_traces]).
+    pub fn collect_traces(self: &Arc<Self>) -> usize {
+        let mut ct_lk = self.compiled_traces.lock();
+        if self.executing_threads.load(Ordering::Acquire) > 0 {
+            self.collect_pending.store(true, Ordering::Release);
+            return 0;
+        }
+
+        // Mark phase.
+        let reachable = Self::live_traces(&ct_lk);
+        // A thread enters JIT code by incrementing `executing_threads` while holding the lock on
+        // the [HotLocation] whose trace it is about to execute. It may have done so after we
+        // checked `executing_threads` above but before the mark phase locked that [HotLocation]:
+        // since the mark phase acquired every such lock, checking again now is guaranteed to see
+        // the increment.
+        if self.executing_threads.load(Ordering::Acquire) > 0 {
+            self.collect_pending.store(true, Ordering::Release);
+            return 0;
+        }
+
+        // Sweep phase.
+        let freed = self.remove_traces(&mut ct_lk, |trid| !reachable.contains(&trid));
+        drop(ct_lk);
+        let num_freed = freed.len();
+        if num_freed > 0 {
+            self.log.log(Verbosity::Tracing, |log| {
+                write!(
+                    log,
+                    "collect-traces {{\"trids\": [{}]}}",
+                    freed
+                        .iter()
+                        .map(|x| format!("\"{}\"", x.ctrid().as_u64()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            });
+        }
+        // Dropping the last reference to a trace frees its memory, including its executable code.
+        drop(freed);
+        num_freed
+    }
+
+    /// `ctr` must no longer be executed, e.g. because the [Location] it was compiled for has been
+    /// dropped or reset, or because a watchpoint it depends on has been fired. Stop `ctr`, and any
+    /// trace which (transitively) couples to it, from being executed: compile jobs which couple to
+    /// `ctr` are cancelled; [HotLocation]s and guards which lead to such traces are reset; and the
+    /// side-traces of `ctr`'s guards are retired. A thread that is currently executing one of these
+    /// traces leaves JIT code the next time one of the trace's guards fails, the trace returns, or
+    /// it reaches the end of a looping trace (see [CompiledTrace::exit_loop]). The traces' memory
+    /// is then freed as soon as possible (see [Self::collect_traces]).
+    pub(crate) fn invalidate_trace(self: &Arc<Self>, ctr: Arc<dyn CompiledTrace>) {
+        let trid = ctr.ctrid();
+        drop(ctr);
+        // Any job that was waiting for `trid` to be compiled can no longer usefully do anything.
+        self.job_queue.notify_failure(self, trid);
+
+        let ct_lk = self.compiled_traces.lock();
+        // Find all the traces which couple, directly or indirectly, to `trid`.
+        let mut doomed = HashSet::from([trid]);
+        loop {
+            let len = doomed.len();
+            for ctr in ct_lk.values() {
+                if let Some(x) = ctr.coupler()
+                    && doomed.contains(&x)
+                {
+                    doomed.insert(ctr.ctrid());
+                }
+            }
+            if doomed.len() == len {
+                break;
+            }
+        }
+
+        for ctr in ct_lk.values() {
+            if doomed.contains(&ctr.ctrid()) {
+                // If this is a trace which starts from a [HotLocation], stop that [HotLocation]
+                // executing it.
+                if let Some(hl) = ctr.hl().upgrade() {
+                    let mut lk = hl.lock();
+                    if let HotLocationKind::Compiled(ref x) = lk.kind
+                        && x.ctrid() == ctr.ctrid()
+                    {
+                        lk.kind = HotLocationKind::Counting(0);
+                    }
+                }
+                // A thread looping in this trace would otherwise never leave it.
+                ctr.exit_loop();
+            }
+            // Unpatch guards which jump to a doomed side-trace, as well as all guards in the
+            // trace that was compiled for the dropped [Location].
+            for gid in 0..ctr.num_guards() {
+                let gid = GuardId::from(gid);
+                let g = ctr.guard(gid);
+                if let Some(x) = g.sidetrace()
+                    && (ctr.ctrid() == trid || doomed.contains(&x))
+                {
+                    g.retire_ctr(&**ctr, gid);
+                }
+            }
+        }
+        drop(ct_lk);
+
+        self.log.log(Verbosity::Tracing, |log| {
+            write!(log, "trace-invalidated {{\"trid\": \"{}\"}}", trid.as_u64())
+        });
+        self.collect_traces();
+    }
+
+    /// Invalidate every trace this meta-tracer has compiled, or is currently recording or
+    /// compiling. This should be called when the interpreter's code has changed in a way that
+    /// could make any trace incorrect (e.g. a global has been redefined).
+    ///
+    /// All [HotLocation]s with compiled traces go back to counting, and all guards are unpatched
+    /// so that a guard failure always returns to the interpreter (rather than jumping to a
+    /// side-trace). Traces still being recorded or compiled are thrown away when compilation
+    /// completes. A thread that is currently executing a trace leaves JIT code the next time one
+    /// of the trace's guards fails, the trace returns, or it reaches the end of a looping trace
+    /// (see [CompiledTrace::exit_loop]). The traces' memory is freed as soon as no thread is
+    /// executing JIT code (see [Self::collect_traces]).
+    pub fn invalidate_all(self: &Arc<Self>) {
+        self.invalidated_before.store(
+            self.compiled_trace_id.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        let ct_lk = self.compiled_traces.lock();
+        let mut trids = Vec::with_capacity(ct_lk.len());
+        for ctr in ct_lk.values() {
+            if let Some(hl) = ctr.hl().upgrade() {
+                let mut lk = hl.lock();
+                if let HotLocationKind::Compiled(ref x) = lk.kind
+                    && x.ctrid() == ctr.ctrid()
+                {
+                    lk.kind = HotLocationKind::Counting(0);
+                }
+            }
+            for gid in 0..ctr.num_guards() {
+                let gid = GuardId::from(gid);
+                let g = ctr.guard(gid);
+                if g.sidetrace().is_some() {
+                    g.retire_ctr(&**ctr, gid);
+                }
+            }
+            // A thread looping in this trace would otherwise never leave it.
+            ctr.exit_loop();
+            trids.push(ctr.ctrid());
+        }
+        drop(ct_lk);
+
+        for trid in &trids {
+            self.job_queue.notify_failure(self, *trid);
+        }
+        self.log.log(Verbosity::Tracing, |log| {
+            write!(
+                log,
+                "traces-invalidated {{\"trids\": [{}]}}",
+                trids
+                    .iter()
+                    .map(|x| format!("\"{}\"", x.as_u64()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        });
+        self.collect_traces();
+    }
+
+    /// Has the trace `ctrid`, started from `trace_start`, been invalidated (by
+    /// [Self::invalidate_all] or [Location::reset]) since it started being recorded?
+    fn trace_invalidated(&self, ctrid: TraceId, trace_start: &TraceStart) -> bool {
+        if ctrid.as_u64() < self.invalidated_before.load(Ordering::Relaxed) {
+            return true;
+        }
+        match trace_start {
+            TraceStart::ControlPoint { hl } => hl.lock().stale == Some(ctrid),
+            TraceStart::Guard { .. } => false,
+        }
+    }
+
+    /// Set (or, if `handler` is `None`, remove) the handler which is called for each lifecycle
+    /// [Event] (tracing starting / stopping / aborting, compilation succeeding / failing, traces
+    /// being executed, guards failing etc.). The handler may be called from any thread, including
+    /// compilation threads; it must not call back into the meta-tracer.
+    pub fn set_event_handler(&self, handler: Option<Box<dyn Fn(&Event) + Send + Sync>>) {
+        let mut lk = self.event_handler.lock();
+        self.has_event_handler
+            .store(handler.is_some(), Ordering::Relaxed);
+        *lk = handler.map(Arc::from);
+    }
+
+    /// If an event handler is set, call it with the [Event] produced by `f`.
+    pub(crate) fn emit_event<F>(&self, f: F)
+    where
+        F: FnOnce() -> Event,
+    {
+        if !self.has_event_handler.load(Ordering::Relaxed) {
+            return;
+        }
+        // We don't call the handler while holding the lock so that one thread's handler can't
+        // block another's.
+        let handler = self.event_handler.lock().clone();
+        if let Some(handler) = handler {
+            handler(&f());
+        }
+    }
+
+    /// Create a new watchpoint. While recording a trace, an interpreter can record that the trace
+    /// depends on a watchpoint with [crate::yk_watch_depend]. Firing the watchpoint with
+    /// [Self::watch_fire] then invalidates every such trace. This allows an interpreter to treat
+    /// values which rarely change (e.g. global tables or method caches) as constants in traces
+    /// (e.g. by reading them via `yk_idempotent` functions) without needing guards.
+    pub fn watch_new(&self) -> WatchId {
+        let watch = self.watch_id.fetch_add(1, Ordering::Relaxed);
+        self.watches.lock().insert(watch, Watch::default());
+        watch
+    }
+
+    /// Fire the watchpoint `watch`, invalidating every trace which depends on it, including traces
+    /// which are currently being recorded or compiled. The watchpoint can continue to be used
+    /// after it is fired.
+    pub fn watch_fire(self: &Arc<Self>, watch: WatchId) {
+        let trids = match self.watches.lock().get_mut(&watch) {
+            Some(x) => {
+                x.fired += 1;
+                std::mem::take(&mut x.trids)
+            }
+            None => return,
+        };
+        self.log.log(Verbosity::Tracing, |log| {
+            write!(log, "watch-fired {{\"watch\": \"{watch}\"}}")
+        });
+        for trid in trids {
+            if let Some(ctr) = self.try_compiled_trace(trid) {
+                self.invalidate_trace(ctr);
+            }
+        }
+    }
+
+    /// If `watch` exists, return how many times it has been fired.
+    fn watch_fired(&self, watch: WatchId) -> Option<u64> {
+        self.watches.lock().get(&watch).map(|x| x.fired)
+    }
+
+    /// If the code cache is larger than its limit, evict the least recently executed top-level
+    /// traces (i.e. those started from a [HotLocation]), and their side-traces, until the code
+    /// cache is within its limit. The [HotLocation]s of evicted traces go back to counting.
+    ///
+    /// Evicted traces are not executed again, but their memory can only be freed when no thread is
+    /// executing JIT code: if that is not currently the case, their memory will be freed once the
+    /// last thread has left JIT code (see [Self::collect_pending_traces]). Until then, traces which
+    /// have already been evicted do not count towards the code cache's size, so that they do not
+    /// cause further live traces to be evicted.
+    fn evict_traces(self: &Arc<Self>) {
+        let limit = self.code_cache_limit.load(Ordering::Relaxed);
+        if limit == 0 {
+            return;
+        }
+        let ct_lk = self.compiled_traces.lock();
+        let live = Self::live_traces(&ct_lk);
+        let unfreed = ct_lk
+            .iter()
+            .filter(|(trid, _)| !live.contains(trid))
+            .map(|(_, ctr)| ctr.code().len())
+            .sum::<usize>();
+        let size = self.code_cache_size.load(Ordering::Relaxed) - unfreed;
+        if size <= limit {
+            return;
+        }
+
+        let mut roots = Vec::new();
+        for ctr in ct_lk.values() {
+            let Some(hl) = ctr.hl().upgrade() else {
+                continue;
+            };
+            // If another thread holds the lock, the [HotLocation] is in use, so it isn't a good
+            // candidate for eviction anyway.
+            if let Some(lk) = hl.try_lock()
+                && let HotLocationKind::Compiled(ref x) = lk.kind
+                && x.ctrid() == ctr.ctrid()
+            {
+                roots.push((lk.last_executed, ctr.ctrid(), Arc::clone(&hl)));
+            }
+        }
+        roots.sort_unstable_by_key(|(last_executed, _, _)| *last_executed);
+
+        let mut excess = size - limit;
+        let mut num_evicted = 0;
+        for (_, trid, hl) in roots {
+            if excess == 0 {
+                break;
+            }
+            let Some(mut lk) = hl.try_lock() else {
+                continue;
+            };
+            if !matches!(lk.kind, HotLocationKind::Compiled(ref x) if x.ctrid() == trid) {
+                continue;
+            }
+            lk.kind = HotLocationKind::Counting(0);
+            drop(lk);
+            // Side-traces can be shared between top-level traces (e.g. via couplers), so this may
+            // overestimate how much memory evicting this trace will free.
+            let evicted_size = Self::reachable_traces(&ct_lk, vec![trid])
+                .iter()
+                .filter_map(|x| ct_lk.get(x))
+                .map(|x| x.code().len())
+                .sum::<usize>();
+            excess = excess.saturating_sub(evicted_size);
+            num_evicted += 1;
+            yklog!(
+                self.log,
+                Verbosity::Tracing,
+                |log| write!(log, "trace-evicted {{\"trid\": \"{}\"}}", trid.as_u64()),
+                Some(&*hl)
+            );
+        }
+        drop(ct_lk);
+        self.stats.traces_evicted(num_evicted);
+        if num_evicted > 0 {
+            self.collect_traces();
+        }
+    }
+
+    /// If [Self::collect_traces] was previously unable to free traces because a thread was
+    /// executing JIT code, and no thread now is, collect them. This is called from the control
+    /// point, since that is the first point at which a thread that has left JIT code is guaranteed
+    /// not to return into it (e.g. [MTThread::trace_returned] is called from the trace itself).
+    fn collect_pending_traces(self: &Arc<Self>) {
+        if self.collect_pending.load(Ordering::Relaxed)
+            && self.executing_threads.load(Ordering::Acquire) == 0
+            && self.collect_pending.swap(false, Ordering::AcqRel)
+        {
+            self.collect_traces();
+        }
+    }
+
+    /// Return the [TraceId]s of every trace in `compiled_traces` which may still be executed i.e.
+    /// which is reachable from a [HotLocation] in the `Compiled` state.
+    fn live_traces(compiled_traces: &HashMap<TraceId, Arc<dyn CompiledTrace>>) -> HashSet<TraceId> {
+        // Find the roots (i.e. traces directly referenced by a [HotLocation])...
+        let mut roots = Vec::new();
+        for ctr in compiled_traces.values() {
+            let Some(hl) = ctr.hl().upgrade() else {
+                continue;
+            };
+            match hl.try_lock() {
+                Some(lk) => {
+                    if let HotLocationKind::Compiled(ref x) = lk.kind
+                        && x.ctrid() == ctr.ctrid()
+                    {
+                        roots.push(ctr.ctrid());
+                    }
+                }
+                // Another thread holds the lock. We can't tell what state the [HotLocation] is in,
+                // so we conservatively assume that this trace is reachable.
+                None => roots.push(ctr.ctrid()),
+            }
+        }
+        // ...and then everything reachable from them.
+        Self::reachable_traces(compiled_traces, roots)
+    }
+
+    /// Return the [TraceId]s of `roots` and every trace reachable from them via couplers and
+    /// guards.
+    fn reachable_traces(
+        compiled_traces: &HashMap<TraceId, Arc<dyn CompiledTrace>>,
+        mut roots: Vec<TraceId>,
+    ) -> HashSet<TraceId> {
+        let mut reachable = HashSet::new();
+        while let Some(trid) = roots.pop() {
+            if !reachable.insert(trid) {
+                continue;
+            }
+            if let Some(ctr) = compiled_traces.get(&trid) {
+                roots.extend(ctr.coupler());
+                for gid in 0..ctr.num_guards() {
+                    roots.extend(ctr.guard(GuardId::from(gid)).sidetrace());
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Remove from `compiled_traces` every trace for which `remove` returns true, returning the
+    /// removed traces. Any guard in a remaining trace that jumps to a removed side-trace is
+    /// unpatched so that it deopts instead.
+    ///
+    /// The caller must guarantee that no thread is executing any of the removed traces.
+    fn remove_traces<F>(
+        &self,
+        compiled_traces: &mut HashMap<TraceId, Arc<dyn CompiledTrace>>,
+        remove: F,
+    ) -> Vec<Arc<dyn CompiledTrace>>
+    where
+        F: Fn(TraceId) -> bool,
+    {
+        let mut removed = Vec::new();
+        compiled_traces.retain(|trid, ctr| {
+            if remove(*trid) {
+                self.code_cache_size
+                    .fetch_sub(ctr.code().len(), Ordering::Relaxed);
+                removed.push(Arc::clone(ctr));
+                false
+            } else {
+                true
+            }
+        });
+        if !removed.is_empty() {
+            for x in self.watches.lock().values_mut() {
+                x.trids.retain(|trid| !remove(*trid));
+            }
+            for ctr in compiled_traces.values() {
+                for gid in 0..ctr.num_guards() {
+                    let gid = GuardId::from(gid);
+                    let g = ctr.guard(gid);
+                    if let Some(trid) = g.sidetrace()
+                        && remove(trid)
+                    {
+                        g.retire_ctr(&**ctr, gid);
+                    }
+                }
+            }
+        }
+        removed
+    }
+
     /// Return the unique ID for the next trace.
     pub(crate) fn next_trace_id(self: &Arc<Self>) -> TraceId {
         // Note: fetch_add is documented to wrap on overflow.
@@ -288,7 +1076,10 @@ impl MT {
 
     /// Add `trace` to the compile queue.
     fn queue_compile_job(self: &Arc<Self>, mut trace: Trace) {
-        self.stats.trace_recorded_ok();
+        let tier = trace.tier;
+        if !matches!(tier, Tier::Recompile(_)) {
+            self.stats.trace_recorded_ok();
+        }
 
         let (coupler_tid, failure): (_, Box<dyn FnOnce() + Send>) = {
             let mt = Arc::clone(self);
@@ -297,6 +1088,11 @@ impl MT {
                     let hl = Arc::clone(hl);
                     let ctrid = trace.ctrid;
                     let failure = move || {
+                        if let Tier::Recompile(_) = tier {
+                            // The baseline trace remains in place.
+                            mt.job_queue.notify_failure(&mt, ctrid);
+                            return;
+                        }
                         let mut lk = hl.lock();
                         debug_assert_matches!(lk.kind, HotLocationKind::Compiling(_));
                         if let TraceFailed::DontTrace = lk.tracecompilation_error(&mt) {
@@ -335,29 +1131,130 @@ impl MT {
             };
             let ctrid = trace.ctrid;
             let trace_start = trace.trace_start.clone();
-
-            match compiler.compile(Arc::clone(&mt), &mut trace) {
+            let watches = std::mem::take(&mut trace.watches);
+
+            // If this is a baseline trace, we need to retain the recorded trace so that we can
+            // recompile it later.
+            let (rtn, recorded) = match tier {
+                Tier::Baseline => match trace.record() {
+                    Ok(x) => (compiler.compile(Arc::clone(&mt), &mut trace), Some(x)),
+                    Err(e) => (Err(e), None),
+                },
+                Tier::Normal | Tier::Recompile(_) => {
+                    (compiler.compile(Arc::clone(&mt), &mut trace), None)
+                }
+            };
+            // We keep `compiled_traces` locked until the new trace is reachable from its
+            // [HotLocation] or guard, so that [MT::collect_traces] can't free it in the interim.
+            let mut ct_lk = mt.compiled_traces.lock();
+            // Similarly, we keep `watches` locked until the new trace is recorded as depending on
+            // its watchpoints, so that [MT::watch_fire] can't miss it.
+            let mut watches_lk = mt.watches.lock();
+            let rtn = rtn.and_then(|ctr| match ctr.coupler() {
+                Some(tgt) if !ct_lk.contains_key(&tgt) => Err(CompilationError::General(format!(
+                    "Coupler target trace {} was freed during compilation",
+                    tgt.as_u64()
+                ))),
+                _ => Ok(ctr),
+            });
+            match rtn {
+                Ok(_)
+                    if mt.trace_invalidated(ctrid, &trace_start)
+                        || watches
+                            .iter()
+                            .any(|(watch, fired)| watches_lk[watch].fired != *fired) =>
+                {
+                    drop(watches_lk);
+                    drop(ct_lk);
+                    // The code this trace was recorded from has changed since the trace started,
+                    // so we throw the trace away. This isn't the trace's fault, so it doesn't
+                    // count as an error.
+                    match trace_start {
+                        // Whatever invalidated the trace will also have dealt with the baseline
+                        // trace.
+                        TraceStart::ControlPoint { .. } if matches!(tier, Tier::Recompile(_)) => {}
+                        TraceStart::ControlPoint { hl } => {
+                            let mut lk = hl.lock();
+                            assert_matches!(lk.kind, HotLocationKind::Compiling(_));
+                            lk.kind = HotLocationKind::Counting(0);
+                            lk.stale = None;
+                            drop(lk);
+                            mt.job_queue.notify_failure(&mt, ctrid);
+                        }
+                        TraceStart::Guard { parent_ctr, gid } => {
+                            parent_ctr.guard(gid).trace_abandoned();
+                        }
+                    }
+                }
                 Ok(ctr) => {
                     assert_eq!(ctr.ctrid(), ctrid);
-                    mt.compiled_traces
-                        .lock()
-                        .insert(ctr.ctrid(), Arc::clone(&ctr));
+                    mt.code_cache_size
+                        .fetch_add(ctr.code().len(), Ordering::Relaxed);
+                    ct_lk.insert(ctr.ctrid(), Arc::clone(&ctr));
+                    for (watch, _) in &watches {
+                        watches_lk.get_mut(watch).unwrap().trids.push(ctrid);
+                    }
+                    drop(watches_lk);
                     match trace_start {
                         TraceStart::ControlPoint { hl } => {
                             let mut lk = hl.lock();
-                            assert_matches!(lk.kind, HotLocationKind::Compiling(_));
-                            lk.kind = HotLocationKind::Compiled(ctr);
+                            match tier {
+                                Tier::Recompile(old_ctrid) => {
+                                    // If the baseline trace has been invalidated or evicted while
+                                    // we were recompiling it, the new trace is unreachable, and
+                                    // will be freed by [MT::collect_traces]. Otherwise, the new
+                                    // trace replaces the old, and the old trace (and its
+                                    // side-traces) will be freed when nothing can reach them.
+                                    if let HotLocationKind::Compiled(ref x) = lk.kind
+                                        && x.ctrid() == old_ctrid
+                                    {
+                                        // Threads still looping in the baseline trace must leave
+                                        // it so that they can start executing the new trace.
+                                        x.exit_loop();
+                                        lk.kind = HotLocationKind::Compiled(ctr);
+                                        mt.log.log(Verbosity::Tracing, |log| {
+                                            write!(
+                                                log,
+                                                "trace-recompiled {{\"trid\": \"{}\"}}",
+                                                ctrid.as_u64()
+                                            )
+                                        });
+                                    }
+                                }
+                                Tier::Normal | Tier::Baseline => {
+                                    assert_matches!(lk.kind, HotLocationKind::Compiling(_));
+                                    lk.kind = HotLocationKind::Compiled(ctr);
+                                    lk.tier_up = recorded.map(|trace| TierUp { ctrid, trace });
+                                }
+                            }
+                            lk.last_executed = mt.code_cache_tick();
                             drop(lk);
+                            drop(ct_lk);
                             mt.job_queue.notify_success(ctrid);
+                            if let Tier::Recompile(_) = tier {
+                                mt.collect_traces();
+                            }
                         }
                         TraceStart::Guard { parent_ctr, gid } => {
                             parent_ctr.guard(gid).set_ctr(ctr, &parent_ctr, gid);
+                            drop(ct_lk);
                         }
                     }
                     mt.stats.trace_compiled_ok();
+                    mt.emit_event(|| Event::CompileOk {
+                        trid: ctrid.as_u64(),
+                    });
+                    mt.evict_traces();
                 }
                 Err(e) => {
+                    drop(watches_lk);
+                    drop(ct_lk);
                     mt.stats.trace_compiled_err();
+                    mt.emit_event(|| Event::CompileErr {
+                        trid: ctrid.as_u64(),
+                        kind: e.kind(),
+                        msg: e.to_string(),
+                    });
                     match e {
                         CompilationError::General(e) | CompilationError::LimitExceeded(e) => {
                             mt.log.log(Verbosity::Warning, |log| {
@@ -381,6 +1278,10 @@ impl MT {
                         }
                     }
                     match trace_start {
+                        // The baseline trace remains in place.
+                        TraceStart::ControlPoint { .. } if matches!(tier, Tier::Recompile(_)) => {
+                            mt.job_queue.notify_failure(&mt, ctrid);
+                        }
                         TraceStart::ControlPoint { hl } => {
                             let mut lk = hl.lock();
                             assert_matches!(lk.kind, HotLocationKind::Compiling(_));
@@ -412,6 +1313,12 @@ impl MT {
         frameaddr: *mut c_void,
         smapidx: StackMapIdx,
     ) {
+        MTThread::left_jit_code();
+        self.collect_pending_traces();
+        if MTThread::is_tracing() && !self.jit_enabled() {
+            // The JIT was disabled (by another thread) while this thread was tracing.
+            self.abandon_tracing();
+        }
         match self.transition_control_point(loc, frameaddr) {
             TransitionControlPoint::NoAction => (),
             TransitionControlPoint::AbortTracing => {
@@ -436,6 +1343,11 @@ impl MT {
                     |log| write!(log, "abort-tracing"),
                     loc.hot_location()
                 );
+                self.emit_event(|| Event::AbortTracing {
+                    trid: trid.as_u64(),
+                    debug_str: hl_debug_str(loc.hot_location()),
+                    reason: AbortKind::UnrolledInnerLoop,
+                });
                 self.stats.trace_recorded_err();
             }
             TransitionControlPoint::Execute(ctr) => {
@@ -449,6 +1361,10 @@ impl MT {
                     ),
                     loc.hot_location()
                 );
+                self.emit_event(|| Event::ExecuteTrace {
+                    trid: ctr.ctrid().as_u64(),
+                    debug_str: hl_debug_str(loc.hot_location()),
+                });
                 self.stats.trace_executed();
 
                 // Compute the rsp of the control_point frame.
@@ -489,7 +1405,7 @@ impl MT {
                 yklog!(
                     self.log,
                     Verbosity::Warning,
-                    |log| write!(log, "tracing-aborted: unrolled inner loop"),
+                    |log| write!(log, "tracing-aborted: {}", AbortKind::UnrolledInnerLoop),
                     loc.hot_location()
                 );
                 let (trid, thread_tracer) = MTThread::with_borrow_mut(|mtt| {
@@ -506,6 +1422,11 @@ impl MT {
                 });
                 let _ = thread_tracer.stop();
                 self.job_queue.notify_failure(self, trid);
+                self.emit_event(|| Event::AbortTracing {
+                    trid: trid.as_u64(),
+                    debug_str: hl_debug_str(loc.hot_location()),
+                    reason: AbortKind::UnrolledInnerLoop,
+                });
                 self.stats.trace_recorded_err();
                 self.start_tracing(frameaddr, loc, inner_hl, unroll_tid);
             }
@@ -518,7 +1439,7 @@ impl MT {
             } => {
                 // Assuming no bugs elsewhere, the `unwrap`s cannot fail, because
                 // `StartSideTracing` will have put a `Some` in the `Rc`.
-                let (thread_tracer, promotions, debug_strs) =
+                let (thread_tracer, promotions, debug_strs, watches) =
                     MTThread::with_borrow_mut(|mtt| match mtt.pop_tstate() {
                         MTThreadState::Tracing {
                             mt: _,
@@ -527,12 +1448,13 @@ impl MT {
                             thread_tracer,
                             promotions,
                             debug_strs,
+                            watches,
                             frameaddr: _,
                             seen_hls: _,
                             gtrace: _,
                         } => {
                             assert!(Arc::ptr_eq(&hl, &parent_ctr.hl().upgrade().unwrap()));
-                            (thread_tracer, promotions, debug_strs)
+                            (thread_tracer, promotions, debug_strs, watches)
                         }
                         _ => unreachable!(),
                     });
@@ -546,6 +1468,10 @@ impl MT {
                             |log| write!(log, "stop-tracing"),
                             loc.hot_location()
                         );
+                        self.emit_event(|| Event::StopTracing {
+                            trid: trid.as_u64(),
+                            debug_str: hl_debug_str(loc.hot_location()),
+                        });
                         let trace_end = match coupler_tid {
                             Some(x) => TraceEnd::Coupler(x),
                             None => TraceEnd::Return,
@@ -557,6 +1483,8 @@ impl MT {
                             ta_iter: ta_iter.peekable(),
                             promotions: promotions.into_boxed_slice(),
                             debug_strs,
+                            watches,
+                            tier: Tier::Normal,
                         });
                         if start {
                             self.start_tracing(
@@ -578,6 +1506,11 @@ impl MT {
                             |log| write!(log, "stop-tracing-aborted: {e}"),
                             loc.hot_location()
                         );
+                        self.emit_event(|| Event::AbortTracing {
+                            trid: trid.as_u64(),
+                            debug_str: hl_debug_str(loc.hot_location()),
+                            reason: AbortKind::RecorderError,
+                        });
                         self.stats.timing_state(TimingState::None);
                     }
                 }
@@ -603,6 +1536,10 @@ impl MT {
             |log| write!(log, "start-tracing"),
             _loc.hot_location()
         );
+        self.emit_event(|| Event::StartTracing {
+            trid: trid.as_u64(),
+            debug_str: hl_debug_str(Some(&*hl)),
+        });
         let tracer = {
             let lk = self.tracer.lock();
             Arc::clone(&*lk)
@@ -618,6 +1555,7 @@ impl MT {
                         thread_tracer: tt,
                         promotions: Vec::new(),
                         debug_strs: Vec::new(),
+                        watches: Vec::new(),
                         frameaddr,
                         seen_hls: SeenHotLocations::new(hl),
                         gtrace: None,
@@ -641,7 +1579,7 @@ impl MT {
         let _loc = loc; // Only used in `cfg(test)`.
         // Assuming no bugs elsewhere, the `unwrap`s cannot fail, because `StartTracing`
         // will have put a `Some` in the `Rc`.
-        let (hl, thread_tracer, promotions, debug_strs) =
+        let (hl, thread_tracer, promotions, debug_strs, watches) =
             MTThread::with_borrow_mut(|mtt| match mtt.pop_tstate() {
                 MTThreadState::Tracing {
                     mt: _,
@@ -650,10 +1588,11 @@ impl MT {
                     thread_tracer,
                     promotions,
                     debug_strs,
+                    watches,
                     frameaddr: _,
                     seen_hls: _,
                     gtrace: _,
-                } => (hl, thread_tracer, promotions, debug_strs),
+                } => (hl, thread_tracer, promotions, debug_strs, watches),
                 _ => unreachable!(),
             });
         match thread_tracer.stop() {
@@ -666,6 +1605,15 @@ impl MT {
                     |log| write!(log, "stop-tracing"),
                     _loc.hot_location()
                 );
+                self.emit_event(|| Event::StopTracing {
+                    trid: ctrid.as_u64(),
+                    debug_str: hl_debug_str(Some(&*hl)),
+                });
+                let tier = if self.recompile_threshold() > 0 && self.opt_level() > 0 {
+                    Tier::Baseline
+                } else {
+                    Tier::Normal
+                };
                 self.queue_compile_job(Trace {
                     trace_start: TraceStart::ControlPoint { hl },
                     trace_end,
@@ -673,6 +1621,8 @@ impl MT {
                     ta_iter: ta_iter.peekable(),
                     promotions: promotions.into_boxed_slice(),
                     debug_strs,
+                    watches,
+                    tier,
                 });
             }
             Err(e) => {
@@ -697,6 +1647,11 @@ impl MT {
                     |log| write!(log, "stop-tracing-aborted: {e}"),
                     _loc.hot_location()
                 );
+                self.emit_event(|| Event::AbortTracing {
+                    trid: ctrid.as_u64(),
+                    debug_str: hl_debug_str(Some(&*hl)),
+                    reason: AbortKind::RecorderError,
+                });
             }
         }
         self.stats.timing_state(TimingState::OutsideYk);
@@ -724,10 +1679,9 @@ impl MT {
         self: &Arc<Self>,
         loc: &Location,
     ) -> TransitionControlPoint {
-        // If JIT is disabled, don't increment the location count.
-        if !self.jit_enabled() {
-            return TransitionControlPoint::NoAction;
-        }
+        // If JIT is disabled, don't increment the location count, though previously compiled traces
+        // can still be executed.
+        let jit_enabled = self.jit_enabled();
         match loc.hot_location() {
             Some(hl) => {
                 let mut lk;
@@ -755,13 +1709,51 @@ impl MT {
 
                 match lk.kind {
                     HotLocationKind::Compiled(ref ctr) => {
-                        TransitionControlPoint::Execute(Arc::clone(ctr))
+                        if self.execute_compiled_traces() {
+                            let ctr = Arc::clone(ctr);
+                            if self.code_cache_limit.load(Ordering::Relaxed) > 0 {
+                                lk.last_executed = self.code_cache_tick();
+                            }
+                            let recompile = if jit_enabled
+                                && let Some(tier_up) = &lk.tier_up
+                                && tier_up.ctrid == ctr.ctrid()
+                                && ctr.count_execution()
+                            {
+                                lk.tier_up.take()
+                            } else {
+                                None
+                            };
+                            // We count this thread as executing JIT code while we still hold the
+                            // lock on the [HotLocation], so that [MT::collect_traces] can't free
+                            // `ctr` before this thread has jumped into it.
+                            self.executing_threads.fetch_add(1, Ordering::AcqRel);
+                            // The compile job may lock the [HotLocation] (and, when compilation
+                            // is serialised, runs before `queue_compile_job` returns) so we must
+                            // release our lock before queueing it.
+                            drop(lk);
+                            if let Some(TierUp { ctrid, trace, .. }) = recompile {
+                                let hl = loc.hot_location_arc_clone().unwrap();
+                                let trid = self.next_trace_id();
+                                self.queue_compile_job(trace.into_trace(
+                                    hl,
+                                    trid,
+                                    Tier::Recompile(ctrid),
+                                ));
+                            }
+                            TransitionControlPoint::Execute(ctr)
+                        } else {
+                            TransitionControlPoint::NoAction
+                        }
                     }
                     HotLocationKind::Compiling(_) => TransitionControlPoint::NoAction,
+                    _ if !jit_enabled => TransitionControlPoint::NoAction,
                     HotLocationKind::Counting(c) => {
-                        if c < self.hot_threshold() {
+                        if c < lk.hot_threshold(self) {
                             lk.kind = HotLocationKind::Counting(c + 1);
                             TransitionControlPoint::NoAction
+                        } else if loc.is_dont_trace() {
+                            lk.kind = HotLocationKind::DontTrace;
+                            TransitionControlPoint::NoAction
                         } else {
                             let hl = loc.hot_location_arc_clone().unwrap();
                             let trid = self.next_trace_id();
@@ -779,12 +1771,12 @@ impl MT {
                             self.stats.trace_recorded_err();
                             self.job_queue.notify_failure(self, trid);
                             match lk.tracecompilation_error(self) {
-                                TraceFailed::KeepTrying => {
+                                TraceFailed::KeepTrying if !loc.is_dont_trace() => {
                                     let trid = self.next_trace_id();
                                     lk.kind = HotLocationKind::Tracing(trid);
                                     TransitionControlPoint::StartTracing(hl, trid)
                                 }
-                                TraceFailed::DontTrace => {
+                                TraceFailed::KeepTrying | TraceFailed::DontTrace => {
                                     // FIXME: This is stupidly brutal.
                                     lk.kind = HotLocationKind::DontTrace;
                                     TransitionControlPoint::NoAction
@@ -798,6 +1790,7 @@ impl MT {
                     HotLocationKind::DontTrace => TransitionControlPoint::NoAction,
                 }
             }
+            None if !jit_enabled || loc.is_dont_trace() => TransitionControlPoint::NoAction,
             None => {
                 match loc.inc_count() {
                     Some(x) => {
@@ -810,6 +1803,10 @@ impl MT {
                                 kind: HotLocationKind::Tracing(trid),
                                 tracecompilation_errors: 0,
                                 debug_str: None,
+                                last_executed: 0,
+                                hot_threshold: None,
+                                stale: None,
+                                tier_up: None,
                             };
                             if let Some(hl) = loc.count_to_hot_location(x, hl) {
                                 TransitionControlPoint::StartTracing(hl, trid)
@@ -879,23 +1876,26 @@ impl MT {
 
                         assert!(!Arc::ptr_eq(&hl, tracing_hl));
                         // We now have a potential race in the inner loop, so only if it's in the
-                        // Counting state do we restart tracing.
-                        if let HotLocationKind::Counting(_) = lk.kind {
+                        // Counting state (and can be traced) do we restart tracing.
+                        if let HotLocationKind::Counting(_) = lk.kind
+                            && !loc.is_dont_trace()
+                        {
                             let unroll_tid = self.next_trace_id();
                             lk.kind = HotLocationKind::Tracing(unroll_tid);
                             drop(lk);
                             let mut lk = tracing_hl.lock();
-                            lk.kind = HotLocationKind::Counting(self.hot_threshold());
+                            lk.kind = HotLocationKind::Counting(lk.hot_threshold(self));
                             return TransitionControlPoint::StopUnrollTracing {
                                 inner_hl: hl,
                                 unroll_tid,
                             };
                         } else {
-                            // We raced with something: all we can do is throw away the trace, and
-                            // put the outer loop back in counting mode.
+                            // We raced with something, or the inner loop must not be traced: all
+                            // we can do is throw away the trace, and put the outer loop back in
+                            // counting mode.
                             drop(lk);
                             let mut lk = tracing_hl.lock();
-                            lk.kind = HotLocationKind::Counting(self.hot_threshold());
+                            lk.kind = HotLocationKind::Counting(lk.hot_threshold(self));
                             return TransitionControlPoint::AbortTracing;
                         }
                     }
@@ -928,6 +1928,10 @@ impl MT {
                             kind: HotLocationKind::Counting(count),
                             tracecompilation_errors: 0,
                             debug_str: None,
+                            last_executed: 0,
+                            hot_threshold: None,
+                            stale: None,
+                            tier_up: None,
                         };
                         loc.count_to_hot_location(count, hl)
                     }
@@ -1009,7 +2013,7 @@ impl MT {
                             start: false,
                         }
                     }
-                    HotLocationKind::Counting(_) => {
+                    HotLocationKind::Counting(_) if !loc.is_dont_trace() => {
                         let next_tid = self.next_trace_id();
                         lk.kind = HotLocationKind::Tracing(next_tid);
                         drop(lk);
@@ -1026,16 +2030,24 @@ impl MT {
                             start: true,
                         }
                     }
-                    HotLocationKind::DontTrace => TransitionControlPoint::NoAction,
+                    HotLocationKind::Counting(_) | HotLocationKind::DontTrace => {
+                        TransitionControlPoint::NoAction
+                    }
                 }
             }
             None => {
-                if let Some(x) = loc.count() {
+                if !loc.is_dont_trace()
+                    && let Some(x) = loc.count()
+                {
                     let next_trid = self.next_trace_id();
                     let hl = HotLocation {
                         kind: HotLocationKind::Tracing(next_trid),
                         tracecompilation_errors: 0,
                         debug_str: None,
+                        last_executed: 0,
+                        hot_threshold: None,
+                        stale: None,
+                        tier_up: None,
                     };
                     if let Some(_hl) = loc.count_to_hot_location(x, hl) {
                         let Some((parent_ctr, gid)) = gtrace else {
@@ -1052,8 +2064,8 @@ impl MT {
                         };
                     }
                 }
-                // We raced with another thread which has started tracing this
-                // location. We leave it to do the tracing.
+                // Either this location must not be traced, or we raced with another thread which
+                // has started tracing this location (and we leave it to do the tracing).
                 TransitionControlPoint::NoAction
             }
         }
@@ -1065,6 +2077,9 @@ impl MT {
         parent_ctr: Arc<dyn CompiledTrace>,
         gid: GuardId,
     ) -> TransitionGuardFailure {
+        if !self.jit_enabled() {
+            return TransitionGuardFailure::NoAction;
+        }
         if parent_ctr.guard(gid).inc_failed(self) {
             if let Some(hl) = parent_ctr.hl().upgrade() {
                 // This thread should not be tracing anything.
@@ -1084,39 +2099,29 @@ impl MT {
     /// [MTThreadState]s.
     pub(crate) fn deopt(self: &Arc<Self>) {
         loop {
-            let st = MTThread::with_borrow_mut(|mtt| mtt.pop_tstate());
+            let st = MTThread::with_borrow_mut(|mtt| match mtt.peek_mut_tstate() {
+                MTThreadState::Interpreting => None,
+                _ => Some(mtt.pop_tstate()),
+            });
             match st {
-                MTThreadState::Interpreting => todo!(),
-                MTThreadState::Tracing {
+                None => {
+                    // There was no trace executing for us to deopt from: there is nothing to
+                    // update, so continue interpreting.
+                    return;
+                }
+                Some(MTThreadState::Interpreting) => unreachable!(),
+                Some(MTThreadState::Tracing {
                     trid,
                     hl,
                     thread_tracer,
                     gtrace,
                     ..
-                } => {
+                }) => {
                     let mut lk = hl.lock();
                     match &lk.kind {
-                        HotLocationKind::Compiled(_) => {
-                            if let Some((parent_ctr, gidx)) = gtrace {
-                                // An inner trace has started side-tracing, then returned to the
-                                // outer trace, which deopts.
-                                let mt = Arc::clone(self);
-                                parent_ctr.guard(gidx).trace_or_compile_failed(&mt);
-                                mt.stats.trace_recorded_err();
-                                self.job_queue.notify_failure(self, trid);
-                            } else {
-                                todo!();
-                            }
-                        }
-                        HotLocationKind::Compiling(_) => {
-                            todo!();
-                        }
-                        HotLocationKind::Counting(_) => {
-                            todo!();
-                        }
-                        HotLocationKind::DontTrace => {}
-                        HotLocationKind::Tracing(trid) => {
-                            let trid = *trid;
+                        HotLocationKind::Tracing(hl_trid)
+                            if *hl_trid == trid && gtrace.is_none() =>
+                        {
                             match lk.tracecompilation_error(self) {
                                 TraceFailed::KeepTrying => {
                                     lk.kind = HotLocationKind::Counting(0);
@@ -1125,9 +2130,21 @@ impl MT {
                                     lk.kind = HotLocationKind::DontTrace;
                                 }
                             }
-                            self.job_queue.notify_failure(self, trid);
+                        }
+                        _ => {
+                            // Either an inner trace has started side-tracing, then returned to
+                            // the outer trace, which deopts; or the [HotLocation] we were tracing
+                            // from has changed state since tracing started (e.g. it has been reset,
+                            // or another thread has compiled a trace for it). Either way, we
+                            // abandon the side-trace or trace without penalising the
+                            // [HotLocation].
+                            if let Some((parent_ctr, gidx)) = gtrace {
+                                parent_ctr.guard(gidx).trace_or_compile_failed(self);
+                                self.stats.trace_recorded_err();
+                            }
                         }
                     }
+                    self.job_queue.notify_failure(self, trid);
                     drop(lk);
                     thread_tracer.stop().ok();
                     MTThread::set_tracing(IsTracing::None);
@@ -1137,17 +2154,84 @@ impl MT {
                         |log| write!(log, "tracing-aborted: {}", AbortKind::BackIntoExecution),
                         Some(&*hl)
                     );
+                    self.emit_event(|| Event::AbortTracing {
+                        trid: trid.as_u64(),
+                        debug_str: hl_debug_str(Some(&*hl)),
+                        reason: AbortKind::BackIntoExecution,
+                    });
+                }
+                Some(MTThreadState::Executing { trid, .. }) => {
+                    self.executing_threads.fetch_sub(1, Ordering::Release);
+                    self.emit_event(|| Event::Deopt {
+                        trid: trid.as_u64(),
+                    });
+                    return;
+                }
+            }
+        }
+    }
+
+    /// If the current thread is recording a trace for this meta-tracer, abandon it. Unlike other
+    /// tracing aborts, this does not count as an error against the [HotLocation] or guard that
+    /// tracing started from, as the trace itself was not at fault.
+    fn abandon_tracing(self: &Arc<Self>) {
+        let tstate = MTThread::with_borrow_mut(|mtt| {
+            if matches!(mtt.peek_mut_tstate(), MTThreadState::Tracing { mt, .. } if Arc::ptr_eq(mt, self))
+            {
+                Some(mtt.pop_tstate())
+            } else {
+                None
+            }
+        });
+        let Some(MTThreadState::Tracing {
+            trid,
+            hl,
+            thread_tracer,
+            gtrace,
+            ..
+        }) = tstate
+        else {
+            return;
+        };
+        thread_tracer.stop().ok();
+        MTThread::set_tracing(IsTracing::None);
+        match gtrace {
+            Some((parent_ctr, gid)) => parent_ctr.guard(gid).trace_abandoned(),
+            None => {
+                let mut lk = hl.lock();
+                // We could have raced with another thread which has changed the state from
+                // `Tracing`.
+                if let HotLocationKind::Tracing(x) = lk.kind
+                    && x == trid
+                {
+                    lk.kind = HotLocationKind::Counting(0);
                 }
-                MTThreadState::Executing { .. } => return,
             }
         }
+        self.job_queue.notify_failure(self, trid);
+        self.stats.trace_recorded_err();
+        self.stats.timing_state(TimingState::OutsideYk);
+        yklog!(
+            self.log,
+            Verbosity::Warning,
+            |log| write!(log, "tracing-aborted: {}", AbortKind::JitDisabled),
+            Some(&*hl)
+        );
+        self.emit_event(|| Event::AbortTracing {
+            trid: trid.as_u64(),
+            debug_str: hl_debug_str(Some(&*hl)),
+            reason: AbortKind::JitDisabled,
+        });
     }
 
     /// Deal with `longjmp` while tracing. Note: the caller _must_ have checked that the current
     /// thread is tracing before calling this function.
     fn longjmp_encountered(self: &Arc<Self>) {
         let MTThreadState::Tracing {
-            thread_tracer, hl, ..
+            trid,
+            thread_tracer,
+            hl,
+            ..
         } = MTThread::with_borrow_mut(|mtt| mtt.pop_tstate())
         else {
             panic!()
@@ -1166,6 +2250,11 @@ impl MT {
             |log| write!(log, "tracing-aborted: {}", AbortKind::LongJmpEncountered),
             Some(&*hl)
         );
+        self.emit_event(|| Event::AbortTracing {
+            trid: trid.as_u64(),
+            debug_str: hl_debug_str(Some(&*hl)),
+            reason: AbortKind::LongJmpEncountered,
+        });
         self.stats.trace_recorded_err();
     }
 
@@ -1180,6 +2269,10 @@ impl MT {
         gid: GuardId,
         frameaddr: *mut c_void,
     ) {
+        self.emit_event(|| Event::GuardFailure {
+            trid: parent.ctrid().as_u64(),
+            gid: usize::from(gid),
+        });
         match self.transition_guard_failure(Arc::clone(&parent), gid) {
             TransitionGuardFailure::NoAction => {
                 self.stats
@@ -1194,6 +2287,12 @@ impl MT {
                     |log| write!(log, "start-side-tracing"),
                     Some(&*hl)
                 );
+                self.emit_event(|| Event::StartSideTracing {
+                    trid: trid.as_u64(),
+                    parent_trid: parent.ctrid().as_u64(),
+                    gid: usize::from(gid),
+                    debug_str: hl_debug_str(Some(&*hl)),
+                });
                 let tracer = {
                     let lk = self.tracer.lock();
                     Arc::clone(&*lk)
@@ -1207,6 +2306,7 @@ impl MT {
                         thread_tracer: tt,
                         promotions: Vec::new(),
                         debug_strs: Vec::new(),
+                        watches: Vec::new(),
                         frameaddr,
                         seen_hls: SeenHotLocations::new(hl),
                         gtrace: Some((parent, gid)),
@@ -1266,6 +2366,9 @@ enum MTThreadState {
         promotions: Vec<u8>,
         /// Records the content of data recorded via `yk_debug_str`.
         debug_strs: Vec<String>,
+        /// Records the watchpoints passed to `yk_watch_depend`, and how many times each had been
+        /// fired at that point.
+        watches: Vec<(WatchId, u64)>,
         /// The `frameaddr` when tracing started. This allows us to tell if we're finishing tracing
         /// at the same point that we started.
         frameaddr: *mut c_void,
@@ -1296,18 +2399,45 @@ pub struct MTThread {
     /// Where in the "interpreting/tracing/executing" is this thread? This `Vec` always has at
     /// least 1 element in it. It should not be access directly: use the `*_tstate` methods.
     tstate: Vec<MTThreadState>,
+    /// The [MT]s whose traces this thread has returned from, but which still count this thread in
+    /// their `executing_threads`. When [Self::trace_returned] is called, this thread is still
+    /// executing the trace's epilogue, so the trace's code must not yet be freed: this thread is
+    /// only removed from `executing_threads` once it is guaranteed to have left JIT code (see
+    /// [Self::left_jit_code]).
+    returned_from: Vec<Arc<MT>>,
     // Raw pointers are neither send nor sync.
     _dont_send_or_sync_me: PhantomData<*mut ()>,
 }
 
+impl Drop for MTThread {
+    fn drop(&mut self) {
+        // A thread which is exiting has necessarily left JIT code.
+        for mt in self.returned_from.drain(..) {
+            mt.executing_threads.fetch_sub(1, Ordering::Release);
+        }
+    }
+}
+
 impl MTThread {
     fn new() -> Self {
         MTThread {
             tstate: vec![MTThreadState::Interpreting],
+            returned_from: Vec::new(),
             _dont_send_or_sync_me: PhantomData,
         }
     }
 
+    /// This thread is guaranteed to have left any trace it previously returned from: remove it
+    /// from the `executing_threads` of the relevant [MT]s. This must only be called when no JIT
+    /// code is on this thread's stack, e.g. at the start of a control point call.
+    fn left_jit_code() {
+        let returned_from =
+            THREAD_MTTHREAD.with_borrow_mut(|mtt| std::mem::take(&mut mtt.returned_from));
+        for mt in returned_from {
+            mt.executing_threads.fetch_sub(1, Ordering::Release);
+        }
+    }
+
     /// Is this thread currently tracing something?
     ///
     /// This function is optimised for the common case (not tracing): it first checks a global
@@ -1390,7 +2520,15 @@ impl MTThread {
                     |log| write!(log, "return {{\"trid\": \"{}\"}}", trid.as_u64()),
                     None
                 );
-                mtt.pop_tstate();
+                mt.emit_event(|| Event::TraceReturn {
+                    trid: trid.as_u64(),
+                });
+                // We are still executing the trace's epilogue, so we can't yet decrement
+                // `executing_threads`: that is done when we next reach a control point.
+                let MTThreadState::Executing { mt, .. } = mtt.pop_tstate() else {
+                    unreachable!()
+                };
+                mtt.returned_from.push(mt);
             }
             MTThreadState::Tracing { mt, trid, .. } => {
                 // We could consider stopping tracing at this point, as we've got an "early return"
@@ -1604,6 +2742,21 @@ impl MTThread {
         }
         true
     }
+
+    /// Record that the trace currently being recorded depends on the watchpoint `watch`.
+    ///
+    /// # Panics
+    ///
+    /// If the stack is empty. There should always be at least one element on the stack, so a panic
+    /// here means that something has gone wrong elsewhere.
+    pub(crate) fn insert_watch(&mut self, watch: WatchId) {
+        if let MTThreadState::Tracing { mt, watches, .. } = self.peek_mut_tstate()
+            && let Some(fired) = mt.watch_fired(watch)
+            && !watches.iter().any(|(x, _)| *x == watch)
+        {
+            watches.push((watch, fired));
+        }
+    }
 }
 
 /// The current thread's tracing state.
@@ -1673,21 +2826,9 @@ enum TransitionControlPoint {
     },
 }
 
-/// Why did we abort tracing?
-#[derive(Debug)]
-enum AbortKind {
-    /// While tracing we fell back from an interpreter to a JIT frame.
-    BackIntoExecution,
-    LongJmpEncountered,
-}
-
-impl std::fmt::Display for AbortKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            AbortKind::BackIntoExecution => write!(f, "tracing continued into a JIT frame"),
-            AbortKind::LongJmpEncountered => write!(f, "longjmp encountered"),
-        }
-    }
+/// Return a copy of `hl`'s debug string, if it has one.
+fn hl_debug_str(hl: Option<&Mutex<HotLocation>>) -> Option<String> {
+    hl.and_then(|hl| hl.lock().debug_str.clone())
 }
 
 /// What action should a caller of [MT::transition_guard_failure] take?
@@ -1697,6 +2838,15 @@ pub(crate) enum TransitionGuardFailure {
     StartSideTracing(Arc<Mutex<HotLocation>>, TraceId),
 }
 
+/// A watchpoint: see [MT::watch_new].
+#[derive(Debug, Default)]
+struct Watch {
+    /// How many times has this watchpoint been fired?
+    fired: u64,
+    /// The compiled traces which depend on this watchpoint.
+    trids: Vec<TraceId>,
+}
+
 /// The unique identifier of a trace.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct TraceId(u64);
@@ -1761,7 +2911,7 @@ mod tests {
 
     impl TraceRecorder for DummyTraceRecorder {
         fn stop(self: Box<Self>) -> Result<Box<dyn AOTTraceIterator>, TraceRecorderError> {
-            todo!();
+            Err(TraceRecorderError::TraceEmpty)
         }
     }
 
@@ -1780,6 +2930,7 @@ mod tests {
                 thread_tracer: Box::new(DummyTraceRecorder),
                 promotions: Vec::new(),
                 debug_strs: Vec::new(),
+                watches: Vec::new(),
                 frameaddr: ptr::null_mut(),
                 seen_hls: SeenHotLocations::new(hl),
                 gtrace: None,
@@ -1814,6 +2965,7 @@ mod tests {
                 thread_tracer: Box::new(DummyTraceRecorder),
                 promotions: Vec::new(),
                 debug_strs: Vec::new(),
+                watches: Vec::new(),
                 frameaddr: ptr::null_mut(),
                 seen_hls: SeenHotLocations::new(hl),
                 gtrace: Some((ctr, GuardId::from(0))),
@@ -1944,6 +3096,7 @@ mod tests {
                             thread_tracer: Box::new(DummyTraceRecorder),
                             promotions: Vec::new(),
                             debug_strs: Vec::new(),
+                            watches: Vec::new(),
                             frameaddr: ptr::null_mut(),
                             seen_hls: SeenHotLocations::new(hl),
                             gtrace: None,
@@ -2184,6 +3337,7 @@ mod tests {
                                     thread_tracer: Box::new(DummyTraceRecorder),
                                     promotions: Vec::new(),
                                     debug_strs: Vec::new(),
+                                    watches: Vec::new(),
                                     frameaddr: ptr::null_mut(),
                                     seen_hls: SeenHotLocations::new(hl),
                                     gtrace: None,
@@ -2392,6 +3546,517 @@ mod tests {
         MTThread::set_tracing(IsTracing::None);
     }
 
+    #[test]
+    fn disabling_jit_abandons_tracing() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let loc = Location::new();
+        expect_start_tracing(&mt, &loc);
+        assert!(MTThread::is_tracing());
+        mt.set_jit_enabled(false);
+        assert!(!MTThread::is_tracing());
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        // Abandoning a trace is not the location's fault.
+        assert_eq!(
+            loc.hot_location().unwrap().lock().tracecompilation_errors,
+            0
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+
+        // Compiled traces are still executed unless we ask otherwise.
+        let ctr = Arc::new(CompiledTraceTestingBasicTransitions::new(Arc::downgrade(
+            &loc.hot_location_arc_clone().unwrap(),
+        )));
+        loc.hot_location().unwrap().lock().kind = HotLocationKind::Compiled(ctr);
+        assert_matches!(
+            mt.transition_control_point(&loc, ptr::null_mut()),
+            TransitionControlPoint::Execute(_)
+        );
+        mt.set_execute_compiled_traces(false);
+        assert_eq!(
+            mt.transition_control_point(&loc, ptr::null_mut()),
+            TransitionControlPoint::NoAction
+        );
+
+        // Locations that haven't yet become hot don't count while the JIT is disabled.
+        let loc = Location::new();
+        assert_eq!(
+            mt.transition_control_point(&loc, ptr::null_mut()),
+            TransitionControlPoint::NoAction
+        );
+        assert_eq!(loc.count(), Some(0));
+        mt.set_jit_enabled(true);
+        expect_start_tracing(&mt, &loc);
+        mt.set_jit_enabled(false);
+    }
+
+    /// Trace `loc` and pretend that the trace has been compiled, returning the compiled trace.
+    /// `mt`'s hot threshold must be 0.
+    fn expect_compiled(mt: &Arc<MT>, loc: &Location) -> Arc<dyn CompiledTrace> {
+        expect_compiled_with(mt, loc, None)
+    }
+
+    /// As [expect_compiled], but if `ctr_mt` is `Some`, the compiled trace informs that
+    /// meta-tracer when it is invalidated (e.g. because `loc` is dropped).
+    fn expect_compiled_with(
+        mt: &Arc<MT>,
+        loc: &Location,
+        ctr_mt: Option<Arc<MT>>,
+    ) -> Arc<dyn CompiledTrace> {
+        expect_start_tracing(mt, loc);
+        expect_stop_loop_tracing(mt, loc);
+        let hl = loc.hot_location_arc_clone().unwrap();
+        let ctr: Arc<dyn CompiledTrace> =
+            Arc::new(CompiledTraceTestingBasicTransitions::with_ctrid(
+                Arc::downgrade(&hl),
+                mt.next_trace_id(),
+                ctr_mt,
+            ));
+        let mut ct_lk = mt.compiled_traces.lock();
+        mt.code_cache_size
+            .fetch_add(ctr.code().len(), Ordering::Relaxed);
+        ct_lk.insert(ctr.ctrid(), Arc::clone(&ctr));
+        let mut lk = hl.lock();
+        lk.kind = HotLocationKind::Compiled(Arc::clone(&ctr));
+        lk.last_executed = mt.code_cache_tick();
+        ctr
+    }
+
+    #[test]
+    fn location_hot_threshold() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(10);
+        let loc = Location::new();
+        loc.set_hot_threshold(2);
+        for _ in 0..2 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        expect_start_tracing(&mt, &loc);
+        expect_stop_loop_tracing(&mt, &loc);
+
+        // A location that has already counted past its new threshold becomes hot immediately.
+        let loc = Location::new();
+        for _ in 0..5 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        loc.set_hot_threshold(3);
+        expect_start_tracing(&mt, &loc);
+        expect_stop_loop_tracing(&mt, &loc);
+    }
+
+    #[test]
+    fn location_dont_trace() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let loc = Location::new();
+        loc.set_dont_trace();
+        for _ in 0..10 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        assert!(loc.hot_location().is_none());
+    }
+
+    #[test]
+    fn location_dont_trace_is_sticky() {
+        // Neither a tracing abort nor a reset allows a location marked as "don't trace" to be
+        // traced again.
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let loc = Location::new();
+        expect_start_tracing(&mt, &loc);
+        loc.set_dont_trace();
+        mt.longjmp_encountered();
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::DontTrace
+        );
+
+        loc.reset();
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        for _ in 0..10 {
+            assert_eq!(
+                mt.transition_control_point(&loc, ptr::null_mut()),
+                TransitionControlPoint::NoAction
+            );
+        }
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::DontTrace
+        );
+    }
+
+    #[test]
+    fn location_reset() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let loc = Location::new();
+        // Give `loc` a [HotLocation].
+        loc.set_hot_threshold(0);
+        loc.reset();
+        assert_matches!(
+            loc.hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+
+        // Resetting a location which is being traced marks that trace as stale.
+        expect_start_tracing(&mt, &loc);
+        loc.reset();
+        let HotLocationKind::Tracing(trid) = loc.hot_location().unwrap().lock().kind else {
+            panic!()
+        };
+        assert_eq!(loc.hot_location().unwrap().lock().stale, Some(trid));
+        let hl = loc.hot_location_arc_clone().unwrap();
+        assert!(mt.trace_invalidated(trid, &TraceStart::ControlPoint { hl }));
+    }
+
+    #[test]
+    fn invalidate_all() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let locs = [Location::new(), Location::new()];
+        for loc in &locs {
+            expect_compiled(&mt, loc);
+        }
+        let loc = Location::new();
+        expect_start_tracing(&mt, &loc);
+        let HotLocationKind::Tracing(trid) = loc.hot_location().unwrap().lock().kind else {
+            panic!()
+        };
+        mt.invalidate_all();
+        for loc in &locs {
+            assert_matches!(
+                loc.hot_location().unwrap().lock().kind,
+                HotLocationKind::Counting(0)
+            );
+        }
+        assert!(mt.compiled_traces.lock().is_empty());
+        assert_eq!(mt.code_cache_size.load(Ordering::Relaxed), 0);
+        // The trace which was being recorded will be thrown away, but later traces won't be.
+        let hl = loc.hot_location_arc_clone().unwrap();
+        assert!(mt.trace_invalidated(
+            trid,
+            &TraceStart::ControlPoint {
+                hl: Arc::clone(&hl)
+            }
+        ));
+        assert!(!mt.trace_invalidated(mt.next_trace_id(), &TraceStart::ControlPoint { hl }));
+    }
+
+    #[test]
+    fn watch() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let w1 = mt.watch_new();
+        let w2 = mt.watch_new();
+        assert_ne!(w1, w2);
+
+        // Dependencies are only recorded while tracing, and only once per watchpoint.
+        let loc = Location::new();
+        expect_start_tracing(&mt, &loc);
+        MTThread::with_borrow_mut(|mtt| {
+            mtt.insert_watch(w1);
+            mtt.insert_watch(w1);
+            mtt.insert_watch(w1 + w2 + 1);
+            let MTThreadState::Tracing { watches, .. } = mtt.peek_mut_tstate() else {
+                panic!()
+            };
+            assert_eq!(watches, &vec![(w1, 0)]);
+        });
+        MTThread::with_borrow_mut(|mtt| mtt.pop_tstate());
+        MTThread::set_tracing(IsTracing::None);
+
+        let locs = [Location::new(), Location::new()];
+        let ctrs = locs
+            .iter()
+            .map(|loc| expect_compiled(&mt, loc))
+            .collect::<Vec<_>>();
+        mt.watches
+            .lock()
+            .get_mut(&w1)
+            .unwrap()
+            .trids
+            .push(ctrs[0].ctrid());
+        drop(ctrs);
+        mt.watch_fire(w2);
+        assert_eq!(mt.compiled_traces.lock().len(), 2);
+        mt.watch_fire(w1);
+        assert_eq!(mt.watch_fired(w1), Some(1));
+        assert_matches!(
+            locs[0].hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        assert_matches!(
+            locs[1].hot_location().unwrap().lock().kind,
+            HotLocationKind::Compiled(_)
+        );
+        assert_eq!(mt.compiled_traces.lock().len(), 1);
+    }
+
+    #[test]
+    fn event_handler() {
+        let mt = MT::new().unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        // Without a handler, events aren't even constructed.
+        mt.emit_event(|| unreachable!());
+        let events_cl = Arc::clone(&events);
+        mt.set_event_handler(Some(Box::new(move |e: &Event| {
+            events_cl.lock().push(e.clone())
+        })));
+        mt.emit_event(|| Event::CompileOk { trid: 3 });
+        let loc = Location::new();
+        loc.set_hl_debug_str("abc".to_owned());
+        mt.emit_event(|| Event::AbortTracing {
+            trid: 4,
+            debug_str: hl_debug_str(loc.hot_location()),
+            reason: AbortKind::JitDisabled,
+        });
+        mt.set_event_handler(None);
+        mt.emit_event(|| unreachable!());
+        assert_eq!(
+            *events.lock(),
+            vec![
+                Event::CompileOk { trid: 3 },
+                Event::AbortTracing {
+                    trid: 4,
+                    debug_str: Some("abc".to_owned()),
+                    reason: AbortKind::JitDisabled
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_snapshot() {
+        let mt = MT::new().unwrap();
+        let before = mt.stats_snapshot();
+        mt.stats.trace_recorded_ok();
+        mt.stats.trace_executed();
+        mt.stats.trace_executed();
+        mt.stats.guards_compiled(3);
+        mt.stats.guards_removed(0);
+        mt.stats.timing_state(TimingState::Tracing);
+        thread::sleep(Duration::from_millis(1));
+        mt.stats.timing_state(TimingState::None);
+        let after = mt.stats_snapshot();
+        assert_eq!(after.traces_recorded_ok, before.traces_recorded_ok + 1);
+        assert_eq!(after.trace_executions, before.trace_executions + 2);
+        assert_eq!(after.traces_compiled_ok, before.traces_compiled_ok);
+        assert_eq!(after.guards_compiled, before.guards_compiled + 3);
+        assert_eq!(after.guards_removed, before.guards_removed);
+        assert!(after.duration_tracing >= before.duration_tracing + Duration::from_millis(1));
+    }
+
+    #[test]
+    fn collect_traces() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let loc = Location::new();
+        expect_compiled(&mt, &loc);
+        // The trace is reachable from a `HotLocation`, so it can't be collected.
+        assert_eq!(mt.collect_traces(), 0);
+        assert_eq!(mt.compiled_traces.lock().len(), 1);
+
+        loc.hot_location().unwrap().lock().kind = HotLocationKind::Counting(0);
+        // Nothing can be collected while a thread is executing JIT code.
+        mt.executing_threads.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(mt.collect_traces(), 0);
+        mt.executing_threads.fetch_sub(1, Ordering::Relaxed);
+        assert_eq!(mt.collect_traces(), 1);
+        assert!(mt.compiled_traces.lock().is_empty());
+        assert_eq!(mt.collect_traces(), 0);
+    }
+
+    #[test]
+    fn code_cache_eviction() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let mut locs = Vec::new();
+        let mut trids = Vec::new();
+        for _ in 0..3 {
+            let loc = Location::new();
+            trids.push(expect_compiled(&mt, &loc).ctrid());
+            locs.push(loc);
+        }
+        let size = mt.code_cache_size.load(Ordering::Relaxed);
+
+        // Everything fits in the code cache, so nothing should be evicted.
+        mt.set_code_cache_limit(size);
+        assert_eq!(mt.compiled_traces.lock().len(), 3);
+
+        // Executing the first trace means that the second is now the least recently executed.
+        assert_matches!(
+            mt.transition_control_point(&locs[0], ptr::null_mut()),
+            TransitionControlPoint::Execute(_)
+        );
+        // We didn't really execute the trace, so we need to undo the bookkeeping that would
+        // otherwise have been done when leaving the trace.
+        mt.executing_threads.fetch_sub(1, Ordering::Relaxed);
+
+        mt.set_code_cache_limit(size - 1);
+        assert_matches!(
+            locs[1].hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        let ct_lk = mt.compiled_traces.lock();
+        assert_eq!(ct_lk.len(), 2);
+        assert!(ct_lk.contains_key(&trids[0]));
+        assert!(ct_lk.contains_key(&trids[2]));
+        drop(ct_lk);
+        assert!(mt.code_cache_size.load(Ordering::Relaxed) < size);
+    }
+
+    #[test]
+    fn code_cache_eviction_while_executing() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        let mut locs = Vec::new();
+        let mut trids = Vec::new();
+        for _ in 0..3 {
+            let loc = Location::new();
+            trids.push(expect_compiled(&mt, &loc).ctrid());
+            locs.push(loc);
+        }
+        let size = mt.code_cache_size.load(Ordering::Relaxed);
+
+        // Enter the first trace, and stay "inside" it for the rest of the test: the second trace is
+        // then the least recently executed.
+        assert_matches!(
+            mt.transition_control_point(&locs[0], ptr::null_mut()),
+            TransitionControlPoint::Execute(_)
+        );
+        mt.set_code_cache_limit(size - 1);
+        assert_matches!(
+            locs[1].hot_location().unwrap().lock().kind,
+            HotLocationKind::Counting(0)
+        );
+        // The evicted trace can't be freed while a thread is executing JIT code...
+        assert_eq!(mt.compiled_traces.lock().len(), 3);
+        assert_eq!(mt.code_cache_size.load(Ordering::Relaxed), size);
+        // ...but it no longer counts towards the code cache's size, so further evictions don't
+        // evict the remaining live traces.
+        mt.set_code_cache_limit(size - 1);
+        assert_matches!(
+            locs[0].hot_location().unwrap().lock().kind,
+            HotLocationKind::Compiled(_)
+        );
+        assert_matches!(
+            locs[2].hot_location().unwrap().lock().kind,
+            HotLocationKind::Compiled(_)
+        );
+
+        // Until the thread has left JIT code, retrying does nothing.
+        mt.collect_pending_traces();
+        assert_eq!(mt.compiled_traces.lock().len(), 3);
+        mt.executing_threads.fetch_sub(1, Ordering::Relaxed);
+        mt.collect_pending_traces();
+        let ct_lk = mt.compiled_traces.lock();
+        assert_eq!(ct_lk.len(), 2);
+        assert!(ct_lk.contains_key(&trids[0]));
+        assert!(ct_lk.contains_key(&trids[2]));
+        drop(ct_lk);
+        assert!(mt.code_cache_size.load(Ordering::Relaxed) < size);
+    }
+
+    #[test]
+    fn location_dropped() {
+        let mt = MT::new().unwrap();
+        mt.set_hot_threshold(0);
+        // Dropping a location whose trace is compiled frees that trace.
+        let loc = Location::new();
+        let trid = expect_compiled_with(&mt, &loc, Some(Arc::clone(&mt))).ctrid();
+        let hl = loc.hot_location_arc_clone().unwrap();
+        drop(loc);
+        assert_matches!(hl.lock().kind, HotLocationKind::DontTrace);
+        assert!(mt.compiled_traces.lock().is_empty());
+        assert_eq!(mt.code_cache_size.load(Ordering::Relaxed), 0);
+
+        // A compile job coupling to the dropped location's trace can never run.
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled_cl = Arc::clone(&cancelled);
+        mt.job_queue.push(
+            &mt,
+            Job::new(
+                Box::new(|| unreachable!()),
+                Some(trid),
+                Box::new(move || cancelled_cl.store(true, Ordering::Relaxed)),
+            ),
+        );
+        assert!(cancelled.load(Ordering::Relaxed));
+
+        // Dropping a location whose trace is still being compiled means that the trace will be
+        // thrown away, rather than installed, when compilation completes.
+        let loc = Location::new();
+        expect_start_tracing(&mt, &loc);
+        expect_stop_loop_tracing(&mt, &loc);
+        let hl = loc.hot_location_arc_clone().unwrap();
+        let HotLocationKind::Compiling(trid) = hl.lock().kind else {
+            panic!()
+        };
+        drop(loc);
+        assert_eq!(hl.lock().stale, Some(trid));
+        assert!(mt.trace_invalidated(trid, &TraceStart::ControlPoint { hl }));
+    }
+
+    #[test]
+    fn config_overrides_defaults() {
+        let mut config = MTConfig::default();
+        config
+            .hot_threshold(7)
+            .sidetrace_threshold(3)
+            .recompile_threshold(9)
+            .trace_failure_threshold(2)
+            .opt_level(0)
+            .jit_enabled(false);
+        let mt = MT::with_config(config).unwrap();
+        assert_eq!(mt.hot_threshold(), 7);
+        assert_eq!(mt.sidetrace_threshold(), 3);
+        assert_eq!(mt.recompile_threshold(), 9);
+        assert_eq!(mt.trace_failure_threshold(), 2);
+        assert_eq!(mt.opt_level(), 0);
+        assert!(!mt.jit_enabled());
+
+        let mut config = MTConfig::default();
+        config.trace_failure_threshold(0);
+        assert!(MT::with_config(config).is_err());
+        let mut config = MTConfig::default();
+        config.log_level(u8::MAX);
+        assert!(MT::with_config(config).is_err());
+    }
+
     #[test]
     fn tracing_thread_count_tracks_tracing_threads() {
         // Note: We can only assert `> 0` while tracing, not exact values, because other tests